@@ -0,0 +1,165 @@
+use crate::config::Config;
+use crate::http::{AppError, AppState};
+use crate::limits::IngestLimits;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// api key and rate limit checks, the api key only when configured; the rate
+/// limit is always installed as the admin api can turn it on at runtime, and
+/// comes from `state` so every listener counts against the same budget
+pub fn layer<S: Clone + Send + Sync + 'static>(router: Router<S>, state: &AppState) -> Router<S> {
+    let limiter = state.rate_limiter.clone();
+    let mut router = router.layer(middleware::from_fn_with_state(limiter, rate_limit));
+
+    if !state.config.api_keys.is_empty() {
+        let keys: Arc<Vec<_>> = Arc::new(
+            state
+                .config
+                .api_keys
+                .iter()
+                .map(|key| Sha256::digest(key.as_bytes()))
+                .collect(),
+        );
+        router = router.layer(middleware::from_fn_with_state(keys, require_api_key));
+    }
+
+    router
+}
+
+/// health checks stay reachable for load balancers
+fn is_exempt(request: &Request) -> bool {
+    request.uri().path() == "/health"
+}
+
+/// X-Api-Key header, or ?api_key= for websocket clients that cannot set headers
+async fn require_api_key(
+    State(keys): State<Arc<Vec<sha2::digest::Output<Sha256>>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if is_exempt(&request) {
+        return next.run(request).await;
+    }
+
+    let from_query = request.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(name, _)| name == "api_key")
+            .map(|(_, value)| value.into_owned())
+    });
+    let provided = request
+        .headers()
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or(from_query);
+
+    // digests so the comparison does not leak key lengths or prefixes
+    match provided {
+        Some(key) if keys.contains(&Sha256::digest(key.as_bytes())) => next.run(request).await,
+        _ => AppError::InvalidApiKey.into_response(),
+    }
+}
+
+/// fixed one minute windows per client address
+pub struct RateLimiter {
+    /// holds the current requests per minute, 0 = off
+    limits: IngestLimits,
+    trust_forwarded_for: bool,
+    windows: Mutex<HashMap<IpAddr, (u64, u32)>>,
+}
+
+/// the connected peer, or the first X-Forwarded-For address when the server
+/// sits behind a trusted proxy
+pub(crate) fn client_ip(
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+    trust_forwarded_for: bool,
+) -> Option<IpAddr> {
+    if trust_forwarded_for {
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+    peer.map(|addr| addr.ip())
+}
+
+impl RateLimiter {
+    pub fn new(config: &Config, limits: &IngestLimits) -> Self {
+        Self {
+            limits: limits.clone(),
+            trust_forwarded_for: config.trust_forwarded_for,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn client(&self, request: &Request) -> Option<IpAddr> {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+        client_ip(request.headers(), peer, self.trust_forwarded_for)
+    }
+
+    /// seconds until the window resets when the client is over its limit
+    fn check(&self, client: IpAddr, per_minute: u32) -> Result<(), u64> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let minute = now / 60;
+
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() >= MAX_TRACKED_CLIENTS {
+            windows.retain(|_, (window, _)| *window == minute);
+        }
+
+        let (window, count) = windows.entry(client).or_insert((minute, 0));
+        if *window != minute {
+            *window = minute;
+            *count = 0;
+        }
+
+        if *count >= per_minute {
+            return Err(60 - now % 60);
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let per_minute = limiter.limits.rate_limit_per_minute();
+    if per_minute == 0 || is_exempt(&request) {
+        return next.run(request).await;
+    }
+
+    let Some(client) = limiter.client(&request) else {
+        return next.run(request).await;
+    };
+
+    match limiter.check(client, per_minute) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after_secs) => AppError::RateLimited { retry_after_secs }.into_response(),
+    }
+}
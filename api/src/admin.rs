@@ -0,0 +1,195 @@
+use crate::config::Config;
+use crate::http::{AppError, AppState};
+use axum::{
+    extract::{Path, Request, State},
+    http::header,
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get},
+    Router,
+};
+use githem_core::{validate_github_name, CacheManager};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// admin routes, only mounted when ADMIN_TOKEN is set
+pub fn router(config: &Config) -> Router<AppState> {
+    let Some(token) = config.admin_token.clone() else {
+        return Router::new();
+    };
+
+    Router::new()
+        .route("/admin/cache", delete(purge_cache))
+        .route("/admin/cache/{owner}/{repo}", delete(invalidate_repository))
+        .route("/admin/metrics/export", get(export_metrics))
+        .route("/admin/limits", get(get_limits).post(update_limits))
+        .route("/admin/config", get(get_config))
+        .route_layer(middleware::from_fn_with_state(
+            Arc::new(token),
+            require_admin_token,
+        ))
+}
+
+/// compares digests so the check does not leak the token length or prefix
+fn token_matches(provided: &str, expected: &str) -> bool {
+    Sha256::digest(provided.as_bytes()) == Sha256::digest(expected.as_bytes())
+}
+
+async fn require_admin_token(
+    State(token): State<Arc<String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match provided {
+        Some(provided) if token_matches(provided, &token) => next.run(request).await,
+        _ => AppError::Unauthorized.into_response(),
+    }
+}
+
+async fn purge_cache(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let repositories = state.repo_cache.clear().await;
+    let diffs = state.diff_cache.clear().await;
+
+    tokio::task::spawn_blocking(CacheManager::clear_cache)
+        .await
+        .map_err(|e| AppError::InternalError(format!("Failed to clear disk cache: {}", e)))?
+        .map_err(|e| AppError::InternalError(format!("Failed to clear disk cache: {}", e)))?;
+
+    info!(
+        "Admin purged the cache ({} repositories, {} diffs)",
+        repositories, diffs
+    );
+
+    Ok(Json(serde_json::json!({
+        "repositories": repositories,
+        "diffs": diffs,
+        "disk": "cleared",
+    })))
+}
+
+async fn invalidate_repository(
+    State(state): State<AppState>,
+    Path((owner, repo)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    if !validate_github_name(&owner) || !validate_github_name(&repo) {
+        return Err(AppError::InvalidRequest(
+            "Invalid owner or repo name".to_string(),
+        ));
+    }
+
+    let url = format!("https://github.com/{owner}/{repo}");
+    let repositories = state.repo_cache.invalidate_repository(&url).await;
+    let diffs = state
+        .diff_cache
+        .invalidate_repository(&format!("{owner}/{repo}"))
+        .await;
+
+    let disk_url = url.clone();
+    let disk = tokio::task::spawn_blocking(move || CacheManager::remove_repository(&disk_url))
+        .await
+        .map_err(|e| AppError::InternalError(format!("Failed to clear disk cache: {}", e)))?
+        .map_err(|e| AppError::InternalError(format!("Failed to clear disk cache: {}", e)))?;
+
+    info!(
+        "Admin invalidated {} ({} repositories, {} diffs, {} disk entries)",
+        url, repositories, diffs, disk
+    );
+
+    Ok(Json(serde_json::json!({
+        "repository": format!("{owner}/{repo}"),
+        "repositories": repositories,
+        "diffs": diffs,
+        "disk": disk,
+    })))
+}
+
+/// everything the collector knows, including per-repository counters
+async fn export_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let body = Json(serde_json::json!({
+        "exported_at": timestamp,
+        "metrics": state.metrics.get_metrics().await,
+        "repo_cache": state.repo_cache.stats().await,
+        "diff_cache": state.diff_cache.stats().await,
+    }));
+
+    (
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"githem-metrics-{timestamp}.json\""),
+        )],
+        body,
+    )
+}
+
+fn limits_json(state: &AppState) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "ingest_timeout_secs": state.limits.timeout().as_secs(),
+        "max_repo_size_mb": state.limits.max_repo_size_mb(),
+        "rate_limit_per_minute": state.limits.rate_limit_per_minute(),
+    }))
+}
+
+async fn get_limits(State(state): State<AppState>) -> impl IntoResponse {
+    limits_json(&state)
+}
+
+#[derive(Deserialize)]
+struct LimitsUpdate {
+    ingest_timeout_secs: Option<u64>,
+    /// 0 disables the size check
+    max_repo_size_mb: Option<u64>,
+    /// per client, 0 disables rate limiting
+    rate_limit_per_minute: Option<u32>,
+}
+
+async fn update_limits(
+    State(state): State<AppState>,
+    Json(update): Json<LimitsUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    if let Some(secs) = update.ingest_timeout_secs {
+        if secs == 0 {
+            return Err(AppError::InvalidRequest(
+                "ingest_timeout_secs must be positive".to_string(),
+            ));
+        }
+        state.limits.set_timeout(Duration::from_secs(secs));
+    }
+    if let Some(max_repo_size_mb) = update.max_repo_size_mb {
+        state.limits.set_max_repo_size_mb(max_repo_size_mb);
+    }
+    if let Some(per_minute) = update.rate_limit_per_minute {
+        state.limits.set_rate_limit_per_minute(per_minute);
+    }
+
+    info!(
+        "Admin set limits: timeout {}s, max repository size {} MB, {} requests per minute",
+        state.limits.timeout().as_secs(),
+        state.limits.max_repo_size_mb(),
+        state.limits.rate_limit_per_minute()
+    );
+
+    Ok(limits_json(&state))
+}
+
+/// effective configuration with secrets masked, limits as currently set
+async fn get_config(State(state): State<AppState>) -> impl IntoResponse {
+    let mut config = state.config.redacted();
+    config["ingest_timeout_secs"] = state.limits.timeout().as_secs().into();
+    config["max_repo_size_mb"] = state.limits.max_repo_size_mb().into();
+    config["rate_limit_per_minute"] = state.limits.rate_limit_per_minute().into();
+    Json(config)
+}
@@ -1,3 +1,4 @@
+use githem_core::CacheBackend;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
@@ -6,10 +7,8 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
 // cache timing constants
-const CACHE_FRESH_SECS: u64 = 300;      // 5 min - serve immediately without validation
 #[allow(dead_code)]
 const CACHE_VALIDATE_SECS: u64 = 86400; // 24h - validate commit hash before serving
-const CACHE_EXPIRE_SECS: u64 = 604800;  // 7 days - hard expiry
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CachedRepository {
@@ -18,42 +17,107 @@ pub struct CachedRepository {
     pub branch: Option<String>,
     pub commit_hash: String,
     pub result: crate::ingestion::IngestionResult,
-    pub created_at: u64,       // unix timestamp
-    pub last_accessed: u64,    // unix timestamp
-    pub last_validated: u64,   // last time we checked commit hash
+    pub created_at: u64,     // unix timestamp
+    pub last_accessed: u64,  // unix timestamp
+    pub last_validated: u64, // last time we checked commit hash
     pub access_count: u64,
+    /// size of the compressed content actually held in memory
     pub size_bytes: usize,
+    pub uncompressed_bytes: usize,
+    /// zstd-compressed `result.content`; the result itself is stored with empty content
+    #[serde(skip)]
+    compressed_content: Vec<u8>,
+    /// parameters that produced this entry, so it can be rebuilt in the background
+    pub params: crate::ingestion::IngestionParams,
+}
+
+impl CachedRepository {
+    /// the entry as the backend stores it: the length of its json, the
+    /// json without the content, then the compressed content
+    fn encode(&self) -> serde_json::Result<Vec<u8>> {
+        let json = serde_json::to_vec(self)?;
+        let mut data = Vec::with_capacity(8 + json.len() + self.compressed_content.len());
+        data.extend_from_slice(&(json.len() as u64).to_le_bytes());
+        data.extend_from_slice(&json);
+        data.extend_from_slice(&self.compressed_content);
+        Ok(data)
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        let len = u64::from_le_bytes(data.get(..8)?.try_into().ok()?) as usize;
+        let json = data.get(8..8usize.checked_add(len)?)?;
+        let mut entry: Self = serde_json::from_slice(json).ok()?;
+        entry.compressed_content = data[8 + len..].to_vec();
+        Some(entry)
+    }
+}
+
+/// what the background refresher needs to rebuild an entry
+#[derive(Clone, Debug)]
+pub struct RefreshTarget {
+    pub key: String,
+    pub branch: Option<String>,
+    pub commit_hash: String,
+    pub params: crate::ingestion::IngestionParams,
 }
 
 #[derive(Debug, PartialEq)]
 #[allow(dead_code)]
 pub enum CacheStatus {
-    Fresh,              // < 5 min old, serve immediately
-    Valid,              // validated commit hash matches
-    Stale,              // commit hash changed, needs refresh
-    Expired,            // > 7 days old
-    Miss,               // not in cache
+    Fresh,   // < 5 min old, serve immediately
+    Valid,   // validated commit hash matches
+    Stale,   // commit hash changed, needs refresh
+    Expired, // > 7 days old
+    Miss,    // not in cache
 }
 
 pub struct RepositoryCache {
     cache: Arc<RwLock<HashMap<String, CachedRepository>>>,
     max_size: usize,
+    /// serve immediately without validation below this age
+    fresh_secs: u64,
+    /// hard expiry
+    expire_secs: u64,
     metrics: Arc<crate::metrics::MetricsCollector>,
+    /// entries are written through to it and read back after a restart
+    backend: Option<Arc<dyn CacheBackend>>,
+    /// default branch of each url as its ingestions resolved it
+    default_branches: RwLock<HashMap<String, String>>,
+    /// ids of the cached results, so they resolve after a restart
+    store: Option<crate::store::Store>,
 }
 
 impl RepositoryCache {
     pub fn new(
         max_size: usize,
-        _ttl: Duration, // kept for API compat but we use constants now
+        fresh: Duration,
+        expire: Duration,
         metrics: Arc<crate::metrics::MetricsCollector>,
     ) -> Self {
         Self {
             cache: Arc::new(RwLock::new(HashMap::new())),
             max_size,
+            fresh_secs: fresh.as_secs(),
+            expire_secs: expire.as_secs(),
             metrics,
+            backend: None,
+            default_branches: RwLock::new(HashMap::new()),
+            store: None,
         }
     }
 
+    /// keep entries in `backend` as well as in memory
+    pub fn with_backend(mut self, backend: Arc<dyn CacheBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// record the id of every result in `store`
+    pub fn with_store(mut self, store: crate::store::Store) -> Self {
+        self.store = Some(store);
+        self
+    }
+
     fn current_timestamp() -> u64 {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -61,31 +125,103 @@ impl RepositoryCache {
             .as_secs()
     }
 
+    /// the same keys as the core cache, see `generate_variant_key`
     pub fn generate_key(
         url: &str,
         branch: Option<&str>,
         preset: Option<&str>,
         path: Option<&str>,
     ) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(url.as_bytes());
-        if let Some(branch) = branch {
-            hasher.update(b":");
-            hasher.update(branch.as_bytes());
+        githem_core::RepositoryCache::generate_variant_key(url, branch, preset, path)
+    }
+
+    /// `branch` as cache keys spell it: None for the default branch of
+    /// `url`, so asking for it by name shares the entry of asking for none
+    pub async fn key_branch<'a>(&self, url: &str, branch: Option<&'a str>) -> Option<&'a str> {
+        let branch = branch?;
+        let default_branches = self.default_branches.read().await;
+        (default_branches.get(url).map(String::as_str) != Some(branch)).then_some(branch)
+    }
+
+    async fn remember_default_branch(&self, url: &str, result: &crate::ingestion::IngestionResult) {
+        let branch = &result.metadata.default_branch;
+        if branch != "HEAD" {
+            self.default_branches
+                .write()
+                .await
+                .insert(url.to_string(), branch.clone());
+        }
+    }
+
+    /// run `f` on the backend from a blocking thread, None without one
+    async fn with_backend_blocking<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&dyn CacheBackend) -> anyhow::Result<T> + Send + 'static,
+    ) -> Option<anyhow::Result<T>> {
+        let backend = self.backend.clone()?;
+        match tokio::task::spawn_blocking(move || f(backend.as_ref())).await {
+            Ok(result) => Some(result),
+            Err(e) => Some(Err(anyhow::anyhow!("Cache backend task failed: {e}"))),
         }
-        if let Some(preset) = preset {
-            hasher.update(b":");
-            hasher.update(preset.as_bytes());
+    }
+
+    /// load an entry the backend has but memory does not, e.g. after a
+    /// restart; expired and unreadable ones are dropped from the backend
+    async fn restore(&self, key: &str) {
+        if self.backend.is_none() || self.cache.read().await.contains_key(key) {
+            return;
         }
-        if let Some(path) = path {
-            hasher.update(b":");
-            hasher.update(path.as_bytes());
+        let owned = key.to_string();
+        let data = match self
+            .with_backend_blocking(move |backend| backend.load(&owned))
+            .await
+        {
+            Some(Ok(Some(data))) => data,
+            Some(Err(e)) => {
+                tracing::warn!("Reading cache entry {} failed: {}", key, e);
+                return;
+            }
+            _ => return,
+        };
+
+        let now = Self::current_timestamp();
+        let entry =
+            CachedRepository::decode(&data).filter(|e| now - e.created_at <= self.expire_secs);
+        let Some(entry) = entry else {
+            self.remove_persisted(key).await;
+            return;
+        };
+        self.remember_default_branch(&entry.url, &entry.result)
+            .await;
+        let mut cache = self.cache.write().await;
+        if self.calculate_size(&cache) + entry.size_bytes <= self.max_size {
+            cache.entry(key.to_string()).or_insert(entry);
+        }
+    }
+
+    async fn persist(&self, key: &str, data: Vec<u8>) {
+        let owned = key.to_string();
+        if let Some(Err(e)) = self
+            .with_backend_blocking(move |backend| backend.store(&owned, &data))
+            .await
+        {
+            tracing::warn!("Persisting cache entry {} failed: {}", key, e);
+        }
+    }
+
+    async fn remove_persisted(&self, key: &str) {
+        let owned = key.to_string();
+        if let Some(Err(e)) = self
+            .with_backend_blocking(move |backend| backend.remove(&owned))
+            .await
+        {
+            tracing::warn!("Removing cache entry {} failed: {}", key, e);
         }
-        format!("{:x}", hasher.finalize())
     }
 
     /// check cache status without returning content
     pub async fn check_status(&self, key: &str) -> (CacheStatus, Option<String>) {
+        self.restore(key).await;
         let cache = self.cache.read().await;
         let now = Self::current_timestamp();
 
@@ -93,11 +229,11 @@ impl RepositoryCache {
             let age = now - entry.created_at;
             let since_validation = now - entry.last_validated;
 
-            if age > CACHE_EXPIRE_SECS {
+            if age > self.expire_secs {
                 return (CacheStatus::Expired, None);
             }
 
-            if since_validation < CACHE_FRESH_SECS {
+            if since_validation < self.fresh_secs {
                 return (CacheStatus::Fresh, Some(entry.commit_hash.clone()));
             }
 
@@ -110,6 +246,7 @@ impl RepositoryCache {
 
     /// get cached entry if fresh or validated
     pub async fn get(&self, key: &str) -> Option<CachedRepository> {
+        self.restore(key).await;
         let mut cache = self.cache.write().await;
         let now = Self::current_timestamp();
 
@@ -117,23 +254,77 @@ impl RepositoryCache {
             let age = now - entry.created_at;
 
             // hard expiry
-            if age > CACHE_EXPIRE_SECS {
+            if age > self.expire_secs {
                 cache.remove(key);
+                drop(cache);
+                self.remove_persisted(key).await;
                 self.metrics.record_cache_miss().await;
                 return None;
             }
 
+            let content = match githem_core::cache::decompress(&entry.compressed_content)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| String::from_utf8(bytes).map_err(|e| e.to_string()))
+            {
+                Ok(content) => content,
+                Err(e) => {
+                    tracing::warn!("Dropping unreadable cache entry {}: {}", key, e);
+                    cache.remove(key);
+                    drop(cache);
+                    self.remove_persisted(key).await;
+                    self.metrics.record_cache_miss().await;
+                    return None;
+                }
+            };
+
             entry.last_accessed = now;
             entry.access_count += 1;
             self.metrics.record_cache_hit().await;
 
-            Some(entry.clone())
+            let mut entry = entry.clone();
+            entry.result.content = content;
+            Some(entry)
         } else {
             self.metrics.record_cache_miss().await;
             None
         }
     }
 
+    /// the cached entry of an ingestion id, as returned by /api/ingest;
+    /// ids not in memory are looked up in the store
+    pub async fn get_by_id(&self, id: &str) -> Option<CachedRepository> {
+        let key = {
+            let cache = self.cache.read().await;
+            cache
+                .iter()
+                .find(|(_, entry)| entry.result.id == id)
+                .map(|(key, _)| key.clone())
+        };
+        if let Some(key) = key {
+            return self.get(&key).await;
+        }
+
+        let store = self.store.as_ref()?;
+        let record = match store.result(id).await {
+            Ok(record) => record?,
+            Err(e) => {
+                tracing::warn!("Looking up result {} failed: {:#}", id, e);
+                return None;
+            }
+        };
+        // the entry may have been refreshed or evicted since
+        let entry = self
+            .get(&record.cache_key)
+            .await
+            .filter(|entry| entry.result.id == id);
+        if entry.is_none() {
+            if let Err(e) = store.remove_result(id).await {
+                tracing::warn!("Forgetting result {} failed: {:#}", id, e);
+            }
+        }
+        entry
+    }
+
     /// mark entry as validated (commit hash confirmed current)
     pub async fn mark_validated(&self, key: &str) {
         let mut cache = self.cache.write().await;
@@ -146,8 +337,56 @@ impl RepositoryCache {
 
     /// invalidate entry (commit hash changed)
     pub async fn invalidate(&self, key: &str) {
-        let mut cache = self.cache.write().await;
-        cache.remove(key);
+        self.cache.write().await.remove(key);
+        self.remove_persisted(key).await;
+    }
+
+    /// drop every entry of one repository, returns how many were removed
+    /// from memory; the backend loses the ones it has as well
+    pub async fn invalidate_repository(&self, url: &str) -> usize {
+        let removed = {
+            let mut cache = self.cache.write().await;
+            let before = cache.len();
+            cache.retain(|_, entry| entry.url != url);
+            before - cache.len()
+        };
+        let url = url.to_string();
+        let purged = self
+            .with_backend_blocking(move |backend| {
+                for key in backend.keys()? {
+                    let data = backend.load(&key)?.unwrap_or_default();
+                    if CachedRepository::decode(&data).is_none_or(|entry| entry.url == url) {
+                        backend.remove(&key)?;
+                    }
+                }
+                Ok(())
+            })
+            .await;
+        if let Some(Err(e)) = purged {
+            tracing::warn!("Purging persisted cache entries failed: {}", e);
+        }
+        removed
+    }
+
+    pub async fn clear(&self) -> usize {
+        let removed = {
+            let mut cache = self.cache.write().await;
+            let removed = cache.len();
+            cache.clear();
+            removed
+        };
+        let cleared = self
+            .with_backend_blocking(|backend| {
+                backend
+                    .keys()?
+                    .iter()
+                    .try_for_each(|key| backend.remove(key))
+            })
+            .await;
+        if let Some(Err(e)) = cleared {
+            tracing::warn!("Clearing persisted cache entries failed: {}", e);
+        }
+        removed
     }
 
     pub async fn put(
@@ -156,10 +395,27 @@ impl RepositoryCache {
         url: String,
         branch: Option<String>,
         commit_hash: String,
-        result: crate::ingestion::IngestionResult,
+        mut result: crate::ingestion::IngestionResult,
+        params: crate::ingestion::IngestionParams,
     ) {
-        let size_bytes = result.content.len();
+        let uncompressed_bytes = result.content.len();
+        let compressed_content = match githem_core::cache::compress(result.content.as_bytes()) {
+            Ok(compressed) => compressed,
+            Err(e) => {
+                tracing::warn!("Not caching {}: compression failed: {}", url, e);
+                return;
+            }
+        };
+        result.content = String::new();
+        let size_bytes = compressed_content.len();
         let now = Self::current_timestamp();
+        self.remember_default_branch(&url, &result).await;
+
+        let mut cache = self.cache.write().await;
+
+        // a background refresh replaces the entry but keeps its popularity
+        let access_count = cache.get(&key).map(|e| e.access_count).unwrap_or(1);
+        cache.remove(&key);
 
         let entry = CachedRepository {
             key: key.clone(),
@@ -170,12 +426,13 @@ impl RepositoryCache {
             created_at: now,
             last_accessed: now,
             last_validated: now,
-            access_count: 1,
+            access_count,
             size_bytes,
+            uncompressed_bytes,
+            compressed_content,
+            params,
         };
 
-        let mut cache = self.cache.write().await;
-
         // enforce size limit with lru eviction
         while self.calculate_size(&cache) + size_bytes > self.max_size && !cache.is_empty() {
             // find least recently used
@@ -189,7 +446,47 @@ impl RepositoryCache {
             }
         }
 
-        cache.insert(key, entry);
+        let persisted = self.backend.as_ref().map(|_| entry.encode());
+        let record = self.store.as_ref().map(|_| crate::store::ResultRecord {
+            id: entry.result.id.clone(),
+            cache_key: key.clone(),
+            url: entry.url.clone(),
+            branch: entry.branch.clone(),
+            commit_hash: entry.result.commit.clone(),
+            files: entry.result.summary.files_analyzed,
+            total_size: entry.result.summary.total_size,
+            created_at: now,
+        });
+        cache.insert(key.clone(), entry);
+        drop(cache);
+
+        if let (Some(store), Some(record)) = (&self.store, record) {
+            if let Err(e) = store.put_result(record).await {
+                tracing::warn!("Recording result of {} failed: {:#}", key, e);
+            }
+        }
+
+        // evicted entries stay in the backend, restored on their next use
+        match persisted {
+            Some(Ok(data)) => self.persist(&key, data).await,
+            Some(Err(e)) => tracing::warn!("Not persisting cache entry {}: {}", key, e),
+            None => {}
+        }
+    }
+
+    /// every cached variant (branch, preset, path) of a repository url
+    pub async fn refresh_targets(&self, url: &str) -> Vec<RefreshTarget> {
+        let cache = self.cache.read().await;
+        cache
+            .values()
+            .filter(|e| e.url == url)
+            .map(|e| RefreshTarget {
+                key: e.key.clone(),
+                branch: e.branch.clone(),
+                commit_hash: e.commit_hash.clone(),
+                params: e.params.clone(),
+            })
+            .collect()
     }
 
     pub async fn stats(&self) -> CacheStats {
@@ -198,6 +495,7 @@ impl RepositoryCache {
         CacheStats {
             entries: cache.len(),
             total_size: self.calculate_size(&cache),
+            uncompressed_size: cache.values().map(|e| e.uncompressed_bytes).sum(),
             max_size: self.max_size,
             hit_rate: self.calculate_hit_rate(&cache),
             top_accessed: self.get_top_accessed(&cache, 10),
@@ -241,7 +539,9 @@ impl RepositoryCache {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CacheStats {
     pub entries: usize,
+    /// bytes held after compression
     pub total_size: usize,
+    pub uncompressed_size: usize,
     pub max_size: usize,
     pub hit_rate: f64,
     pub top_accessed: Vec<(String, u64)>,
@@ -257,7 +557,11 @@ pub struct DiffCache {
 #[derive(Clone)]
 #[allow(dead_code)]
 pub struct CachedDiff {
+    /// `owner/repo`, for targeted invalidation
+    pub repository: String,
     pub content: String,
+    /// full id of the commit the diff or snapshot leads to, when known
+    pub commit: Option<String>,
     pub created_at: u64,
     pub access_count: u64,
 }
@@ -289,18 +593,24 @@ impl DiffCache {
         format!("{:x}", hasher.finalize())
     }
 
-    pub async fn get(&self, key: &str) -> Option<String> {
+    pub async fn get(&self, key: &str) -> Option<CachedDiff> {
         let mut cache = self.cache.write().await;
 
         if let Some(entry) = cache.get_mut(key) {
             entry.access_count += 1;
-            Some(entry.content.clone())
+            Some(entry.clone())
         } else {
             None
         }
     }
 
-    pub async fn put(&self, key: String, content: String) {
+    pub async fn put(
+        &self,
+        key: String,
+        repository: String,
+        content: String,
+        commit: Option<String>,
+    ) {
         let mut cache = self.cache.write().await;
 
         // evict least accessed if at capacity
@@ -318,13 +628,30 @@ impl DiffCache {
         cache.insert(
             key,
             CachedDiff {
+                repository,
                 content,
+                commit,
                 created_at: Self::current_timestamp(),
                 access_count: 1,
             },
         );
     }
 
+    /// drop every diff of `owner/repo`, returns how many were removed
+    pub async fn invalidate_repository(&self, repository: &str) -> usize {
+        let mut cache = self.cache.write().await;
+        let before = cache.len();
+        cache.retain(|_, entry| entry.repository != repository);
+        before - cache.len()
+    }
+
+    pub async fn clear(&self) -> usize {
+        let mut cache = self.cache.write().await;
+        let removed = cache.len();
+        cache.clear();
+        removed
+    }
+
     pub async fn stats(&self) -> DiffCacheStats {
         let cache = self.cache.read().await;
         DiffCacheStats {
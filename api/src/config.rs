@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// every server setting, read once at startup from upper-case environment
+/// variables named after the fields (HTTP_PORT, CACHE_MAX_MB, ...)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub http_port: u16,
+    /// legacy websocket listener, 0 disables it (/ws on the http port stays)
+    pub ws_port: u16,
+    /// gRPC listener of builds with the grpc feature, 0 disables it
+    pub grpc_port: u16,
+    /// in-memory repository cache budget
+    pub cache_max_mb: u64,
+    /// entries younger than this are served without checking the remote head
+    pub cache_fresh_secs: u64,
+    /// hard expiry of repository cache entries
+    pub cache_expire_secs: u64,
+    /// also keep repository results on disk next to the cli's cache, so
+    /// they survive restarts
+    pub cache_persist: bool,
+    pub diff_cache_entries: usize,
+    /// number of top repositories kept warm, 0 disables the refresher
+    pub cache_refresh_top_n: usize,
+    pub cache_refresh_interval_secs: u64,
+    pub ingest_timeout_secs: u64,
+    /// 0 disables the size check
+    pub max_repo_size_mb: u64,
+    /// ingestions running at once, 0 for no limit
+    pub max_concurrent_ingestions: usize,
+    /// ingestions stop before their output exceeds this, 0 disables the limit
+    pub max_output_mb: u64,
+    /// the same in estimated tokens
+    pub max_output_tokens: usize,
+    /// holds all mutable state (githem.db, cache/, scratch/) instead of
+    /// HOME, XDG_CACHE_HOME and the system temp dir, also `--state-dir`
+    pub state_dir: Option<PathBuf>,
+    /// parent of the per-ingestion clone directories, defaults to the system temp dir
+    pub scratch_dir: Option<PathBuf>,
+    /// download limit of a single clone, 0 disables it
+    pub scratch_max_mb: u64,
+    /// further attempts at clones and fetches failing on a network error
+    pub clone_retries: u32,
+    /// seconds between disk clean-ups, 0 disables them
+    pub janitor_interval_secs: u64,
+    /// persistent checkouts and their indexes, least recently used evicted
+    /// above this, 0 for no limit
+    pub disk_cache_max_mb: u64,
+    /// the same for stored results (see cache_persist)
+    pub disk_results_max_mb: u64,
+    /// hosts repositories may be fetched from, empty allows every supported host
+    pub allowed_hosts: Vec<String>,
+    /// requests per minute per client address, 0 disables rate limiting
+    pub rate_limit_per_minute: u32,
+    /// take the client address from X-Forwarded-For (only behind a proxy)
+    pub trust_forwarded_for: bool,
+    /// bearer token for the /admin routes, which are not mounted without it
+    pub admin_token: Option<String>,
+    /// when set, every request except /health needs one of these keys
+    pub api_keys: Vec<String>,
+    /// allowed browser origins, empty or `*` allows any
+    pub cors_origins: Vec<String>,
+    /// allowed request methods, empty or `*` allows any
+    pub cors_methods: Vec<String>,
+    /// allowed request headers, empty or `*` allows any
+    pub cors_headers: Vec<String>,
+    /// let listed origins send cookies and authorization headers
+    pub cors_allow_credentials: bool,
+    /// sqlite database of result ids and share links, defaults to
+    /// githem.db next to the repository cache
+    pub database_path: Option<PathBuf>,
+    /// share files of older versions, imported into the database at startup
+    pub share_dir: Option<PathBuf>,
+    /// base of the links handed out by /api/share, defaults to the Host header
+    pub public_url: Option<String>,
+    /// websocket ping period, 0 disables heartbeats
+    pub ws_ping_interval_secs: u64,
+    /// websockets without a client message for this long are closed, 0 disables it
+    pub ws_idle_timeout_secs: u64,
+    /// open websockets per client address, 0 for no limit
+    pub ws_max_connections_per_ip: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            http_port: 42069,
+            ws_port: 42070,
+            grpc_port: 42071,
+            cache_max_mb: 5 * 1024,
+            cache_fresh_secs: 300,
+            cache_expire_secs: 7 * 86400,
+            cache_persist: true,
+            diff_cache_entries: 10_000,
+            cache_refresh_top_n: 20,
+            // below the fresh window so hot entries never leave it
+            cache_refresh_interval_secs: 240,
+            ingest_timeout_secs: 300,
+            max_repo_size_mb: 2048,
+            max_concurrent_ingestions: 0,
+            max_output_mb: 100,
+            max_output_tokens: 0,
+            state_dir: None,
+            scratch_dir: None,
+            scratch_max_mb: 4096,
+            clone_retries: 2,
+            janitor_interval_secs: 600,
+            disk_cache_max_mb: 10 * 1024,
+            disk_results_max_mb: 2048,
+            allowed_hosts: Vec::new(),
+            rate_limit_per_minute: 0,
+            trust_forwarded_for: false,
+            admin_token: None,
+            api_keys: Vec::new(),
+            cors_origins: Vec::new(),
+            cors_methods: Vec::new(),
+            cors_headers: Vec::new(),
+            cors_allow_credentials: false,
+            database_path: None,
+            share_dir: None,
+            public_url: None,
+            ws_ping_interval_secs: 30,
+            ws_idle_timeout_secs: 600,
+            ws_max_connections_per_ip: 8,
+        }
+    }
+}
+
+impl Config {
+    /// lists are comma separated, unknown variables are ignored
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut config: Config =
+            envy::from_env().map_err(|e| anyhow::anyhow!("Invalid configuration: {}", e))?;
+
+        for list in [
+            &mut config.allowed_hosts,
+            &mut config.api_keys,
+            &mut config.cors_origins,
+            &mut config.cors_methods,
+            &mut config.cors_headers,
+        ] {
+            list.iter_mut()
+                .for_each(|item| *item = item.trim().to_string());
+            list.retain(|item| !item.is_empty());
+        }
+        config
+            .allowed_hosts
+            .iter_mut()
+            .for_each(|host| host.make_ascii_lowercase());
+        config.admin_token = config.admin_token.filter(|token| !token.is_empty());
+        config.cache_refresh_interval_secs = config.cache_refresh_interval_secs.max(10);
+
+        if let Some(dir) = state_dir_arg(std::env::args().skip(1))? {
+            config.state_dir = Some(dir);
+        }
+        if let Some(ref state) = config.state_dir {
+            config
+                .scratch_dir
+                .get_or_insert_with(|| state.join("scratch"));
+            config
+                .database_path
+                .get_or_insert_with(|| state.join("githem.db"));
+            config.share_dir.get_or_insert_with(|| state.join("shares"));
+        }
+
+        config.validate_cors()?;
+
+        if config.ingest_timeout_secs == 0 {
+            anyhow::bail!("INGEST_TIMEOUT_SECS must be positive");
+        }
+
+        Ok(config)
+    }
+
+    /// bad values fail at startup instead of silently opening or closing the api
+    fn validate_cors(&self) -> anyhow::Result<()> {
+        use axum::http::{HeaderName, HeaderValue, Method};
+
+        for origin in self.cors_origins.iter().filter(|o| *o != "*") {
+            if !origin.starts_with("http://") && !origin.starts_with("https://") {
+                anyhow::bail!(
+                    "CORS_ORIGINS entry '{}' must start with http:// or https://",
+                    origin
+                );
+            }
+            HeaderValue::from_str(origin)
+                .map_err(|_| anyhow::anyhow!("Invalid CORS_ORIGINS entry '{}'", origin))?;
+        }
+        for method in self.cors_methods.iter().filter(|m| *m != "*") {
+            Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                .map_err(|_| anyhow::anyhow!("Invalid CORS_METHODS entry '{}'", method))?;
+        }
+        for name in self.cors_headers.iter().filter(|h| *h != "*") {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| anyhow::anyhow!("Invalid CORS_HEADERS entry '{}'", name))?;
+        }
+
+        if self.cors_allow_credentials && Self::allows_any(&self.cors_origins) {
+            anyhow::bail!("CORS_ALLOW_CREDENTIALS needs explicit CORS_ORIGINS");
+        }
+
+        Ok(())
+    }
+
+    /// empty lists and `*` mean no restriction
+    pub fn allows_any(list: &[String]) -> bool {
+        list.is_empty() || list.iter().any(|item| item == "*")
+    }
+
+    /// the repository and result cache below `state_dir`, None for the
+    /// user's cache directory
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.state_dir.as_ref().map(|state| state.join("cache"))
+    }
+
+    pub fn cache_max_bytes(&self) -> usize {
+        (self.cache_max_mb as usize).saturating_mul(1024 * 1024)
+    }
+
+    pub fn cache_refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.cache_refresh_interval_secs)
+    }
+
+    /// the configuration with secrets masked, for the admin api
+    pub fn redacted(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if self.admin_token.is_some() {
+            value["admin_token"] = "<redacted>".into();
+        }
+        value["api_keys"] = serde_json::json!(vec!["<redacted>"; self.api_keys.len()]);
+        value
+    }
+}
+
+/// `--state-dir <dir>` or `--state-dir=<dir>`, the server's only flag
+fn state_dir_arg(mut args: impl Iterator<Item = String>) -> anyhow::Result<Option<PathBuf>> {
+    while let Some(arg) = args.next() {
+        if let Some(dir) = arg.strip_prefix("--state-dir=") {
+            return Ok(Some(PathBuf::from(dir)));
+        }
+        if arg == "--state-dir" {
+            let dir = args
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("--state-dir needs a directory"))?;
+            return Ok(Some(PathBuf::from(dir)));
+        }
+    }
+    Ok(None)
+}
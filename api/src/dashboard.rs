@@ -0,0 +1,207 @@
+use crate::http::AppState;
+use crate::metrics::{HourlyStats, Metrics, RepoMetrics};
+use axum::{extract::State, response::Html};
+use html_escape::encode_text;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TOP_REPOSITORIES: usize = 20;
+
+const STYLE: &str = "
+.stats { display: grid; grid-template-columns: repeat(auto-fit, minmax(9rem, 1fr)); gap: 1rem; margin: 1.5rem 0; }
+.stat { padding: 0.75rem; border: 1px solid var(--gray-700); border-radius: 6px; }
+.stat b { display: block; font-size: 1.4rem; }
+.chart { display: flex; align-items: flex-end; gap: 3px; height: 10rem; margin: 1rem 0 0.25rem; }
+.hour { flex: 1; display: flex; flex-direction: column-reverse; height: 100%; }
+.hour span { display: block; min-height: 0; }
+.ingested { background: var(--primary); }
+.cached { background: var(--success); }
+.errors { background: var(--error); }
+.legend span { margin-right: 1rem; }
+.legend i { display: inline-block; width: 0.7rem; height: 0.7rem; margin-right: 0.3rem; }
+table { width: 100%; border-collapse: collapse; margin-top: 1rem; }
+th, td { text-align: left; padding: 0.35rem 0.5rem; border-bottom: 1px solid var(--gray-700); }
+td.n { text-align: right; }
+";
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1} GB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1u64 << 10) as f64),
+        b => format!("{b} B"),
+    }
+}
+
+fn ago(secs: u64) -> String {
+    match secs {
+        s if s < 60 => "just now".to_string(),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s if s < 86400 => format!("{}h ago", s / 3600),
+        s => format!("{}d ago", s / 86400),
+    }
+}
+
+fn percent(part: u64, total: u64) -> String {
+    if total == 0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", part as f64 * 100.0 / total as f64)
+}
+
+/// one stacked bar per hour of the last day, oldest first
+fn chart(hourly: &[HourlyStats], now: u64) -> String {
+    let current = now / 3600;
+    let hours: Vec<Option<&HourlyStats>> = (0..24)
+        .rev()
+        .map(|back| hourly.iter().find(|s| s.hour == current - back))
+        .collect();
+    let peak = hours
+        .iter()
+        .flatten()
+        .map(|s| s.requests + s.cache_hits + s.errors)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let mut html = String::from("<div class=\"chart\">");
+    for (back, stat) in hours.iter().enumerate().map(|(i, s)| (23 - i, s)) {
+        let (ingested, cached, errors) =
+            stat.map_or((0, 0, 0), |s| (s.requests, s.cache_hits, s.errors));
+        let _ = write!(
+            html,
+            "<div class=\"hour\" title=\"{}: {} ingested, {} cached, {} errors\">",
+            if back == 0 {
+                "this hour".to_string()
+            } else {
+                format!("{back}h ago")
+            },
+            ingested,
+            cached,
+            errors
+        );
+        for (class, count) in [
+            ("ingested", ingested),
+            ("cached", cached),
+            ("errors", errors),
+        ] {
+            let _ = write!(
+                html,
+                "<span class=\"{class}\" style=\"height: {:.1}%\"></span>",
+                count as f64 * 100.0 / peak as f64
+            );
+        }
+        html.push_str("</div>");
+    }
+    html.push_str("</div>");
+    html
+}
+
+fn top_table(repos: &[RepoMetrics], now: u64) -> String {
+    if repos.is_empty() {
+        return "<p>nothing ingested yet</p>".to_string();
+    }
+    let mut html = String::from(
+        "<table><tr><th>repository</th><th>requests</th><th>files</th><th>size</th><th>last request</th></tr>",
+    );
+    for repo in repos {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td>{}</td></tr>",
+            encode_text(&repo.url),
+            repo.request_count,
+            repo.file_count,
+            size(repo.size_bytes),
+            ago(now.saturating_sub(repo.last_accessed))
+        );
+    }
+    html.push_str("</table>");
+    html
+}
+
+fn render(metrics: &Metrics, top: &[RepoMetrics]) -> String {
+    let now = now();
+    let lookups = metrics.cache_hits + metrics.cache_misses;
+    let day: (u64, u64, u64) = metrics.hourly_stats.iter().fold((0, 0, 0), |(i, c, e), s| {
+        (i + s.requests, c + s.cache_hits, e + s.errors)
+    });
+
+    let stats = [
+        ("requests", metrics.total_requests.to_string()),
+        ("ingestions", metrics.total_ingestions.to_string()),
+        ("cache hit rate", percent(metrics.cache_hits, lookups)),
+        (
+            "error rate",
+            percent(metrics.errors, metrics.total_requests),
+        ),
+        ("errors, last day", day.2.to_string()),
+        (
+            "avg response",
+            format!("{} ms", metrics.average_response_time_ms),
+        ),
+        ("processed", size(metrics.total_bytes_processed)),
+    ];
+    let mut cards = String::new();
+    for (label, value) in stats {
+        let _ = write!(cards, "<div class=\"stat\"><b>{value}</b>{label}</div>");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <meta http-equiv="refresh" content="60">
+    <title>githem stats</title>
+    <link rel="stylesheet" href="styles.css?v=3">
+    <style>{STYLE}</style>
+</head>
+<body>
+    <div class="container">
+        <nav class="help-nav"><a href="index.html" class="back-link">&larr; back</a></nav>
+        <header class="help-header">
+            <h1>githem stats</h1>
+            <p class="tagline">usage of this instance since its last restart</p>
+        </header>
+        <section>
+            <div class="stats">{cards}</div>
+        </section>
+        <section>
+            <h2>last 24 hours</h2>
+            {chart}
+            <p class="legend">
+                <span><i class="ingested"></i>ingested {ingested}</span>
+                <span><i class="cached"></i>cached {cached}</span>
+                <span><i class="errors"></i>errors {errors}</span>
+            </p>
+        </section>
+        <section>
+            <h2>top repositories</h2>
+            {table}
+        </section>
+    </div>
+</body>
+</html>
+"#,
+        chart = chart(&metrics.hourly_stats, now),
+        ingested = day.0,
+        cached = day.1,
+        errors = day.2,
+        table = top_table(top, now),
+    )
+}
+
+/// usage at a glance for operators: totals, the last day by hour and the
+/// most requested repositories, from the same counters as /metrics
+pub async fn stats_page(State(state): State<AppState>) -> Html<String> {
+    let metrics = state.metrics.get_metrics().await;
+    let top = state.metrics.get_top_repositories(TOP_REPOSITORIES).await;
+    Html(render(&metrics, &top))
+}
@@ -0,0 +1,428 @@
+use crate::http::{AppError, AppState};
+use crate::ingestion::{IngestionResult, IngestionService};
+use crate::limits::IngestLimits;
+use crate::tls::TlsConfig;
+use crate::validation::{Validate, MAX_CONTEXT_LINES};
+use anyhow::Result;
+use githem_core::{is_remote_url, split_files, validate_ref_name, HeaderDetail, Transform};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::time::timeout;
+use tonic::body::Body;
+use tonic::codegen::{http, Service};
+use tonic::server::{Grpc, NamedService};
+use tonic::{Request, Response, Status};
+use tonic_prost::ProstCodec;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IngestRequest {
+    #[prost(string, tag = "1")]
+    pub url: String,
+    #[prost(string, optional, tag = "2")]
+    pub branch: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub path_prefix: Option<String>,
+    #[prost(string, repeated, tag = "4")]
+    pub include_patterns: Vec<String>,
+    #[prost(string, repeated, tag = "5")]
+    pub exclude_patterns: Vec<String>,
+    #[prost(string, repeated, tag = "6")]
+    pub extensions: Vec<String>,
+    #[prost(string, repeated, tag = "7")]
+    pub transforms: Vec<String>,
+    #[prost(string, optional, tag = "8")]
+    pub header_detail: Option<String>,
+    #[prost(uint64, optional, tag = "9")]
+    pub max_file_size: Option<u64>,
+    #[prost(uint64, tag = "10")]
+    pub min_file_size: u64,
+    #[prost(uint64, optional, tag = "11")]
+    pub max_lines: Option<u64>,
+    #[prost(string, optional, tag = "12")]
+    pub filter_preset: Option<String>,
+    #[prost(bool, tag = "13")]
+    pub raw: bool,
+    #[prost(uint64, optional, tag = "14")]
+    pub max_files: Option<u64>,
+    #[prost(string, repeated, tag = "15")]
+    pub branches: Vec<String>,
+    #[prost(string, repeated, tag = "16")]
+    pub paths: Vec<String>,
+    #[prost(string, repeated, tag = "17")]
+    pub exclude_paths: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Summary {
+    #[prost(string, tag = "1")]
+    pub repository: String,
+    #[prost(string, tag = "2")]
+    pub branch: String,
+    #[prost(uint64, tag = "3")]
+    pub files_analyzed: u64,
+    #[prost(uint64, tag = "4")]
+    pub total_size: u64,
+    #[prost(uint64, tag = "5")]
+    pub estimated_tokens: u64,
+    #[prost(string, tag = "6")]
+    pub filter_preset: String,
+    #[prost(uint64, tag = "7")]
+    pub skipped_files: u64,
+    #[prost(uint64, tag = "8")]
+    pub omitted_files: u64,
+    #[prost(string, tag = "9")]
+    pub commit: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IngestResponse {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(message, optional, tag = "2")]
+    pub summary: Option<Summary>,
+    #[prost(string, tag = "3")]
+    pub tree: String,
+    #[prost(string, tag = "4")]
+    pub content: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct File {
+    #[prost(string, tag = "1")]
+    pub path: String,
+    #[prost(string, tag = "2")]
+    pub content: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IngestEvent {
+    #[prost(oneof = "ingest_event::Event", tags = "1, 2")]
+    pub event: Option<ingest_event::Event>,
+}
+
+pub mod ingest_event {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        File(super::File),
+        #[prost(message, tag = "2")]
+        Summary(super::Summary),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DiffRequest {
+    #[prost(string, tag = "1")]
+    pub url: String,
+    #[prost(string, tag = "2")]
+    pub base: String,
+    #[prost(string, tag = "3")]
+    pub head: String,
+    #[prost(uint32, optional, tag = "4")]
+    pub context_lines: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DiffResponse {
+    #[prost(string, tag = "1")]
+    pub diff: String,
+    #[prost(string, tag = "2")]
+    pub commit: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListRefsRequest {
+    #[prost(string, tag = "1")]
+    pub url: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Ref {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub commit: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListRefsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub branches: Vec<Ref>,
+    #[prost(message, repeated, tag = "2")]
+    pub tags: Vec<Ref>,
+    #[prost(string, tag = "3")]
+    pub default_branch: String,
+}
+
+fn status(error: AppError) -> Status {
+    match error {
+        AppError::InvalidRequest(message) => Status::invalid_argument(message),
+        AppError::InvalidParameter { field, message } => {
+            Status::invalid_argument(format!("{field} {message}"))
+        }
+        AppError::NotFound => Status::not_found("resource not found"),
+        AppError::RefNotFound(missing) => Status::not_found(missing.to_string()),
+        AppError::Unauthorized | AppError::InvalidApiKey => {
+            Status::unauthenticated("missing or invalid api key")
+        }
+        AppError::Timeout => Status::deadline_exceeded("request timed out"),
+        AppError::TooLarge { size_mb, limit_mb } => Status::resource_exhausted(format!(
+            "repository is {size_mb} MB, above this server's {limit_mb} MB limit"
+        )),
+        AppError::RateLimited { .. } | AppError::TooManyConnections { .. } => {
+            Status::resource_exhausted("too many requests")
+        }
+        AppError::InternalError(message) => Status::internal(message),
+    }
+}
+
+/// the http request of the same shape, so both go through one validation
+fn ingestion_request(request: IngestRequest) -> Result<crate::http::IngestRequest, Status> {
+    let transforms = request
+        .transforms
+        .iter()
+        .map(|name| {
+            Transform::parse(name)
+                .ok_or_else(|| Status::invalid_argument(format!("unknown transform {name}")))
+        })
+        .collect::<Result<_, _>>()?;
+    let header_detail = match request.header_detail.as_deref() {
+        Some(name) => HeaderDetail::parse(name)
+            .ok_or_else(|| Status::invalid_argument(format!("unknown header detail {name}")))?,
+        None => HeaderDetail::default(),
+    };
+
+    let mut request = crate::http::IngestRequest {
+        url: request.url,
+        branch: request.branch,
+        branches: request.branches,
+        subpath: None,
+        path_prefix: request.path_prefix,
+        paths: request.paths,
+        exclude_paths: request.exclude_paths,
+        include_patterns: request.include_patterns,
+        exclude_patterns: request.exclude_patterns,
+        extensions: request.extensions,
+        transforms,
+        header_detail,
+        max_file_size: request
+            .max_file_size
+            .map_or_else(crate::http::default_max_file_size, |size| size as usize),
+        min_file_size: request.min_file_size as usize,
+        max_lines: request.max_lines.map(|lines| lines as usize),
+        max_files: request.max_files.map(|files| files as usize),
+        filter_preset: request.filter_preset,
+        raw: request.raw,
+        refresh: false,
+    };
+    request.validate().map_err(status)?;
+    Ok(request)
+}
+
+fn summary(result: &IngestionResult) -> Summary {
+    Summary {
+        repository: result.summary.repository.clone(),
+        branch: result.summary.branch.clone(),
+        files_analyzed: result.summary.files_analyzed as u64,
+        total_size: result.summary.total_size as u64,
+        estimated_tokens: result.summary.estimated_tokens as u64,
+        filter_preset: result.summary.filter_preset.clone(),
+        skipped_files: result.summary.skipped_files as u64,
+        omitted_files: result.summary.omitted_files as u64,
+        commit: result.commit.clone().unwrap_or_default(),
+    }
+}
+
+/// the `githem.Githem` service of proto/githem.proto, requests are bounded
+/// by the limits of the http routes, including changes made at runtime
+#[derive(Clone)]
+pub struct GithemService {
+    limits: IngestLimits,
+}
+
+impl GithemService {
+    pub fn new(limits: IngestLimits) -> Self {
+        Self { limits }
+    }
+
+    async fn run(&self, request: IngestRequest) -> Result<IngestionResult, Status> {
+        let params = ingestion_request(request)?.params();
+        self.limits.check(&params).await.map_err(status)?;
+
+        match timeout(self.limits.timeout(), IngestionService::ingest(params)).await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(e)) => Err(status(AppError::ingestion_failed(e))),
+            Err(_) => Err(status(AppError::Timeout)),
+        }
+    }
+
+    async fn ingest(
+        self,
+        request: Request<IngestRequest>,
+    ) -> Result<Response<IngestResponse>, Status> {
+        let result = self.run(request.into_inner()).await?;
+        Ok(Response::new(IngestResponse {
+            summary: Some(summary(&result)),
+            id: result.id,
+            tree: result.tree,
+            content: result.content,
+        }))
+    }
+
+    async fn ingest_stream(
+        self,
+        request: Request<IngestRequest>,
+    ) -> Result<Response<IngestEvents>, Status> {
+        let result = self.run(request.into_inner()).await?;
+        let mut events: Vec<Result<IngestEvent, Status>> = split_files(&result.content)
+            .into_iter()
+            .map(|(path, content)| {
+                let file = File {
+                    path: path.to_string(),
+                    content: content.to_string(),
+                };
+                Ok(IngestEvent {
+                    event: Some(ingest_event::Event::File(file)),
+                })
+            })
+            .collect();
+        events.push(Ok(IngestEvent {
+            event: Some(ingest_event::Event::Summary(summary(&result))),
+        }));
+        Ok(Response::new(tokio_stream::iter(events)))
+    }
+
+    async fn generate_diff(
+        self,
+        request: Request<DiffRequest>,
+    ) -> Result<Response<DiffResponse>, Status> {
+        let request = request.into_inner();
+        for (field, name) in [("base", &request.base), ("head", &request.head)] {
+            validate_ref_name(name)
+                .map_err(|e| Status::invalid_argument(format!("{field} {e}")))?;
+        }
+        if request
+            .context_lines
+            .is_some_and(|lines| lines > MAX_CONTEXT_LINES)
+        {
+            return Err(Status::invalid_argument(format!(
+                "context_lines must be at most {MAX_CONTEXT_LINES}"
+            )));
+        }
+
+        let diff = IngestionService::generate_diff(
+            &request.url,
+            &request.base,
+            &request.head,
+            None,
+            None,
+            request.context_lines,
+        );
+        match timeout(self.limits.timeout(), diff).await {
+            Ok(Ok(diff)) => Ok(Response::new(DiffResponse {
+                diff: diff.content,
+                commit: diff.commit.unwrap_or_default(),
+            })),
+            Ok(Err(e)) => Err(Status::internal(format!("Diff generation failed: {e}"))),
+            Err(_) => Err(status(AppError::Timeout)),
+        }
+    }
+
+    async fn list_refs(
+        self,
+        request: Request<ListRefsRequest>,
+    ) -> Result<Response<ListRefsResponse>, Status> {
+        let url = request.into_inner().url;
+        if !is_remote_url(&url) {
+            return Err(Status::invalid_argument("url must be a remote repository"));
+        }
+        let refs = IngestionService::list_refs(&url)
+            .await
+            .map_err(|e| Status::unavailable(format!("Listing refs failed: {e}")))?;
+
+        let mut response = ListRefsResponse {
+            default_branch: githem_core::default_branch_of(&refs).unwrap_or_default(),
+            ..Default::default()
+        };
+        for remote_ref in refs {
+            if remote_ref.name == "HEAD" {
+                continue;
+            }
+            let entry = Ref {
+                name: remote_ref.name,
+                commit: remote_ref.oid,
+            };
+            if entry.name.starts_with("refs/heads/") {
+                response.branches.push(entry);
+            } else if entry.name.starts_with("refs/tags/") && !entry.name.ends_with("^{}") {
+                response.tags.push(entry);
+            }
+        }
+        Ok(Response::new(response))
+    }
+}
+
+type IngestEvents = tokio_stream::Iter<std::vec::IntoIter<Result<IngestEvent, Status>>>;
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+impl NamedService for GithemService {
+    const NAME: &'static str = "githem.Githem";
+}
+
+/// what tonic-build would generate: each method path decoded with prost and
+/// handed to the matching method above
+impl Service<http::Request<Body>> for GithemService {
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Self::Response, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let service = self.clone();
+        Box::pin(async move {
+            let response = match request.uri().path() {
+                "/githem.Githem/Ingest" => {
+                    let method = tower::service_fn(move |r| service.clone().ingest(r));
+                    Grpc::new(ProstCodec::default())
+                        .unary(method, request)
+                        .await
+                }
+                "/githem.Githem/IngestStream" => {
+                    let method = tower::service_fn(move |r| service.clone().ingest_stream(r));
+                    Grpc::new(ProstCodec::default())
+                        .server_streaming(method, request)
+                        .await
+                }
+                "/githem.Githem/GenerateDiff" => {
+                    let method = tower::service_fn(move |r| service.clone().generate_diff(r));
+                    Grpc::new(ProstCodec::default())
+                        .unary(method, request)
+                        .await
+                }
+                "/githem.Githem/ListRefs" => {
+                    let method = tower::service_fn(move |r| service.clone().list_refs(r));
+                    Grpc::new(ProstCodec::default())
+                        .unary(method, request)
+                        .await
+                }
+                _ => Status::unimplemented("unknown method").into_http(),
+            };
+            Ok(response)
+        })
+    }
+}
+
+/// plaintext h2c unless TLS is configured, api keys go in the x-api-key metadata
+pub async fn serve(addr: SocketAddr, tls: Option<TlsConfig>, state: AppState) -> Result<()> {
+    let router =
+        tonic::service::Routes::new(GithemService::new(state.limits.clone())).into_axum_router();
+    let app = crate::access::layer(router, &state);
+
+    crate::tls::serve(addr, app, tls).await
+}
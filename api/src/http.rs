@@ -1,12 +1,23 @@
-use crate::cache::{CacheStatus, DiffCache, RepositoryCache};
-use crate::ingestion::{IngestionParams, IngestionService};
+use crate::access::RateLimiter;
+use crate::cache::{CacheStatus, CachedRepository, DiffCache, RepositoryCache};
+use crate::config::Config;
+use crate::ingestion::{IngestionParams, IngestionResult, IngestionService};
+use crate::janitor::DiskStats;
+use crate::limits::IngestLimits;
 use crate::metrics::MetricsCollector;
-use githem_core::validate_github_name;
+use crate::openapi::ApiDoc;
+use crate::share::ShareStore;
+use crate::store::Store;
+use crate::validation::{ValidJson, ValidQuery};
+use githem_core::{
+    parse_compare_spec, search_output, validate_github_name, validate_path_prefix,
+    validate_ref_name, HeaderDetail, RefNotFound, SearchOptions, Transform,
+};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Path, State},
     http::{header, HeaderMap, StatusCode},
     response::{Html, IntoResponse, Json, Response},
     routing::{get, post},
@@ -16,67 +27,175 @@ use serde::{Deserialize, Serialize};
 use tokio::time::timeout;
 use tower::ServiceBuilder;
 use tower_http::{
-    compression::CompressionLayer, cors::CorsLayer, set_header::SetResponseHeaderLayer,
+    compression::CompressionLayer,
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    set_header::SetResponseHeaderLayer,
+    trace::{DefaultOnResponse, TraceLayer},
 };
-
-const INGEST_TIMEOUT: Duration = Duration::from_secs(300);
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
 
 #[derive(Clone)]
 pub struct AppState {
+    pub config: Arc<Config>,
     pub repo_cache: Arc<RepositoryCache>,
     pub diff_cache: Arc<DiffCache>,
     pub metrics: Arc<MetricsCollector>,
+    pub shares: Arc<ShareStore>,
+    pub store: Store,
+    pub limits: IngestLimits,
+    /// one for all listeners, a client's budget is not per protocol
+    pub rate_limiter: Arc<RateLimiter>,
+    pub disk: Arc<tokio::sync::RwLock<DiskStats>>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
-        Self::new()
+        Self::new(Arc::new(Config::default()))
     }
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    pub fn new(config: Arc<Config>) -> Self {
         let metrics = Arc::new(MetricsCollector::new());
+        let store = Store::open_or_memory(
+            &config
+                .database_path
+                .clone()
+                .unwrap_or_else(Store::default_path),
+        );
+        let mut repo_cache = RepositoryCache::new(
+            config.cache_max_bytes(),
+            Duration::from_secs(config.cache_fresh_secs),
+            Duration::from_secs(config.cache_expire_secs),
+            metrics.clone(),
+        )
+        .with_store(store.clone());
+        if config.cache_persist {
+            match githem_core::DiskBackend::shared() {
+                Ok(backend) => repo_cache = repo_cache.with_backend(Arc::new(backend)),
+                Err(e) => tracing::warn!("Repository cache kept in memory only: {}", e),
+            }
+        }
+        let limits = IngestLimits::new(&config);
         Self {
-            repo_cache: Arc::new(RepositoryCache::new(
-                5 * 1024 * 1024 * 1024,    // 5GB
-                Duration::from_secs(3600), // 1 hour TTL
-                metrics.clone(),
-            )),
-            diff_cache: Arc::new(DiffCache::new(10000)), // 10k diff entries
+            repo_cache: Arc::new(repo_cache),
+            diff_cache: Arc::new(DiffCache::new(config.diff_cache_entries)),
             metrics,
+            shares: Arc::new(ShareStore::new(store.clone())),
+            store,
+            rate_limiter: Arc::new(RateLimiter::new(&config, &limits)),
+            limits,
+            disk: Arc::default(),
+            config,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct IngestRequest {
     pub url: String,
     pub branch: Option<String>,
+    /// several branches into one output, one section each, instead of `branch`
+    #[serde(default)]
+    pub branches: Vec<String>,
     pub subpath: Option<String>,
     pub path_prefix: Option<String>,
+    /// more subtrees next to `path_prefix`, e.g. `["src/core", "proto"]`
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// subtrees left out entirely, e.g. `["vendor/legacy"]`
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
     #[serde(default)]
     pub include_patterns: Vec<String>,
     #[serde(default)]
     pub exclude_patterns: Vec<String>,
+    /// only files with these extensions, e.g. `["rs", "toml"]`
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// content transforms: strip-comments, redact-secrets, notebooks, line-numbers
+    #[serde(default)]
+    #[schema(value_type = Vec<String>)]
+    pub transforms: Vec<Transform>,
+    /// metadata next to each file path: path, basic or full
+    #[serde(default)]
+    #[schema(value_type = String)]
+    pub header_detail: HeaderDetail,
     #[serde(default = "default_max_file_size")]
     pub max_file_size: usize,
+    /// skip files smaller than this many bytes
+    #[serde(default)]
+    pub min_file_size: usize,
+    /// cut files off after this many lines
+    pub max_lines: Option<usize>,
+    /// keep only this many files, the most important ones
+    pub max_files: Option<usize>,
     pub filter_preset: Option<String>,
     #[serde(default)]
     pub raw: bool,
+    /// check a cached result against the remote head even when fresh
+    #[serde(default)]
+    pub refresh: bool,
 }
 
-fn default_max_file_size() -> usize {
+pub(crate) fn default_max_file_size() -> usize {
     10 * 1024 * 1024
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl IngestRequest {
+    /// the ingestion of this request, at the branch head
+    pub fn params(&self) -> IngestionParams {
+        IngestionParams {
+            url: self.url.clone(),
+            subpath: self.subpath.clone(),
+            branch: self.branch.clone(),
+            branches: self.branches.clone(),
+            path_prefix: self.path_prefix.clone().or(self.subpath.clone()),
+            paths: self.paths.clone(),
+            exclude_paths: self.exclude_paths.clone(),
+            include_patterns: self.include_patterns.clone(),
+            exclude_patterns: self.exclude_patterns.clone(),
+            extensions: self.extensions.clone(),
+            transforms: self.transforms.clone(),
+            header_detail: self.header_detail,
+            max_file_size: self.max_file_size,
+            min_file_size: self.min_file_size,
+            max_lines: self.max_lines,
+            max_files: self.max_files,
+            filter_preset: self.filter_preset.clone(),
+            raw: self.raw,
+            commit: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct IngestResponse {
     pub id: String,
     pub status: String,
+    /// fresh, validated or miss, like the x-githem-cache header
+    pub cache: String,
+    /// head commit ingested, when known
+    pub commit: Option<String>,
+    /// unix time the result was cached, None on a miss
+    pub cached_at: Option<u64>,
+}
+
+impl IngestResponse {
+    fn new(id: String, stats: &RepoStats) -> Self {
+        Self {
+            id,
+            status: "completed".to_string(),
+            cache: stats.cache.to_string(),
+            commit: stats.commit.clone(),
+            cached_at: stats.cached_at,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ErrorResponse {
     pub error: String,
     pub code: String,
@@ -84,19 +203,52 @@ pub struct ErrorResponse {
     pub hint: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub docs: Option<String>,
+    /// the offending parameter of a 400
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// matches the x-request-id response header and the server logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 #[derive(Debug)]
 pub enum AppError {
     InvalidRequest(String),
+    InvalidParameter {
+        field: &'static str,
+        message: String,
+    },
     NotFound,
+    RefNotFound(RefNotFound),
+    Unauthorized,
     Timeout,
+    TooLarge {
+        size_mb: u64,
+        limit_mb: u64,
+    },
+    InvalidApiKey,
+    RateLimited {
+        retry_after_secs: u64,
+    },
+    TooManyConnections {
+        limit: usize,
+    },
     InternalError(String),
 }
 
+impl AppError {
+    /// a failed ingestion, a 404 when the requested ref is missing
+    pub fn ingestion_failed(error: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        match error.downcast::<RefNotFound>() {
+            Ok(missing) => AppError::RefNotFound(*missing),
+            Err(error) => AppError::InternalError(format!("Ingestion failed: {error}")),
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
-        let (status, error_response) = match self {
+        let (status, mut error_response) = match self {
             AppError::InvalidRequest(msg) => (
                 StatusCode::BAD_REQUEST,
                 ErrorResponse {
@@ -104,6 +256,19 @@ impl IntoResponse for AppError {
                     code: "INVALID_REQUEST".to_string(),
                     hint: Some("check the url format: /{owner}/{repo} or /{owner}/{repo}/tree/{branch}".to_string()),
                     docs: Some("https://githem.com/help.html".to_string()),
+                    field: None,
+                    request_id: None,
+                },
+            ),
+            AppError::InvalidParameter { field, message } => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    error: format!("{field} {message}"),
+                    code: "INVALID_PARAMETER".to_string(),
+                    hint: None,
+                    docs: Some("https://githem.com/api/docs".to_string()),
+                    field: Some(field.to_string()),
+                    request_id: None,
                 },
             ),
             AppError::NotFound => (
@@ -113,6 +278,30 @@ impl IntoResponse for AppError {
                     code: "NOT_FOUND".to_string(),
                     hint: Some("valid formats: /{owner}/{repo}, /{owner}/{repo}/tree/{branch}, /{owner}/{repo}/commit/{sha}".to_string()),
                     docs: Some("https://githem.com/help.html".to_string()),
+                    field: None,
+                    request_id: None,
+                },
+            ),
+            AppError::RefNotFound(missing) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    error: missing.to_string(),
+                    code: "REF_NOT_FOUND".to_string(),
+                    hint: Some("tags are listed at /{owner}/{repo}/releases".to_string()),
+                    docs: Some("https://githem.com/help.html".to_string()),
+                    field: None,
+                    request_id: None,
+                },
+            ),
+            AppError::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                ErrorResponse {
+                    error: "missing or invalid admin token".to_string(),
+                    code: "UNAUTHORIZED".to_string(),
+                    hint: Some("send Authorization: Bearer $ADMIN_TOKEN".to_string()),
+                    docs: None,
+                    field: None,
+                    request_id: None,
                 },
             ),
             AppError::Timeout => (
@@ -122,6 +311,52 @@ impl IntoResponse for AppError {
                     code: "TIMEOUT".to_string(),
                     hint: Some("try using ?include=src/ to limit scope, or ?preset=code-only".to_string()),
                     docs: Some("https://githem.com/help.html".to_string()),
+                    field: None,
+                    request_id: None,
+                },
+            ),
+            AppError::TooLarge { size_mb, limit_mb } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorResponse {
+                    error: format!("repository is {size_mb} MB, above this server's {limit_mb} MB limit"),
+                    code: "REPOSITORY_TOO_LARGE".to_string(),
+                    hint: Some("run the githem cli locally, narrowing the request does not shrink the clone".to_string()),
+                    docs: Some("https://githem.com/help.html".to_string()),
+                    field: None,
+                    request_id: None,
+                },
+            ),
+            AppError::InvalidApiKey => (
+                StatusCode::UNAUTHORIZED,
+                ErrorResponse {
+                    error: "missing or invalid api key".to_string(),
+                    code: "INVALID_API_KEY".to_string(),
+                    hint: Some("send X-Api-Key: $KEY (or ?api_key= for websockets)".to_string()),
+                    docs: None,
+                    field: None,
+                    request_id: None,
+                },
+            ),
+            AppError::RateLimited { retry_after_secs } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorResponse {
+                    error: "too many requests".to_string(),
+                    code: "RATE_LIMITED".to_string(),
+                    hint: Some(format!("retry in {retry_after_secs}s")),
+                    docs: None,
+                    field: None,
+                    request_id: None,
+                },
+            ),
+            AppError::TooManyConnections { limit } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorResponse {
+                    error: format!("at most {limit} websockets per client are allowed"),
+                    code: "TOO_MANY_CONNECTIONS".to_string(),
+                    hint: Some("close an open websocket before opening another".to_string()),
+                    docs: None,
+                    field: None,
+                    request_id: None,
                 },
             ),
             AppError::InternalError(msg) => (
@@ -131,28 +366,95 @@ impl IntoResponse for AppError {
                     code: "INTERNAL_ERROR".to_string(),
                     hint: None,
                     docs: Some("https://github.com/rotkonetworks/githem/issues".to_string()),
+                    field: None,
+                    request_id: None,
                 },
             ),
         };
+        error_response.request_id = crate::telemetry::current_request_id();
+        if status.is_server_error() {
+            tracing::error!(code = %error_response.code, "{}", error_response.error);
+        }
 
         (status, Json(error_response)).into_response()
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
 pub struct QueryParams {
     pub branch: Option<String>,
+    /// comma-separated branches, ingested into one output with a section each
+    pub branches: Option<String>,
     pub subpath: Option<String>,
     pub include: Option<String>,
     pub exclude: Option<String>,
+    /// comma-separated extensions, e.g. `rs,toml,proto`
+    pub ext: Option<String>,
+    /// comma-separated transforms: strip-comments, redact-secrets, notebooks, line-numbers
+    pub transform: Option<String>,
+    /// file header detail: path, basic (language, size, tokens) or full
+    pub header: Option<String>,
     pub max_size: Option<usize>,
+    /// skip files smaller than this many bytes
+    pub min_size: Option<usize>,
+    /// cut files off after this many lines
+    pub max_lines: Option<usize>,
+    /// keep only this many files, the most important ones
+    pub max_files: Option<usize>,
     pub preset: Option<String>,
     pub raw: Option<bool>,
     pub path: Option<String>,
+    /// comma-separated subtrees to ingest together, e.g. `src/core,proto`
+    pub paths: Option<String>,
+    /// comma-separated subtrees to leave out, e.g. `vendor/legacy`
+    pub exclude_paths: Option<String>,
+    /// check a cached result against the remote head even when fresh
+    pub refresh: Option<bool>,
     /// diff context lines (like git diff -U), defaults to 3
     pub ctx: Option<u32>,
 }
 
+impl QueryParams {
+    /// the `branches` parameter split into names
+    pub fn branch_list(&self) -> Vec<String> {
+        split_list(self.branches.as_deref())
+    }
+
+    /// the `paths` parameter split into paths
+    pub fn path_list(&self) -> Vec<String> {
+        split_list(self.paths.as_deref())
+    }
+
+    /// the `exclude_paths` parameter split into paths
+    pub fn exclude_path_list(&self) -> Vec<String> {
+        split_list(self.exclude_paths.as_deref())
+    }
+}
+
+fn split_list(list: Option<&str>) -> Vec<String> {
+    let items = list.unwrap_or_default().split(',').map(str::trim);
+    items
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchParams {
+    pub q: String,
+    pub regex: Option<bool>,
+    pub case_sensitive: Option<bool>,
+    /// lines of context around each match, defaults to 2
+    pub context: Option<usize>,
+    pub branch: Option<String>,
+    pub preset: Option<String>,
+    pub path: Option<String>,
+}
+
+const MAX_SEARCH_CONTEXT: usize = 20;
+
 // Serve static files
 async fn serve_static_file(filename: &str) -> Response {
     let (content, content_type) = match filename {
@@ -216,6 +518,12 @@ async fn install_ps1() -> Response {
     serve_static_file("install.ps1").await
 }
 
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "status",
+    responses((status = 200, description = "service status and cache summary", body = serde_json::Value))
+)]
 async fn health(State(state): State<AppState>) -> impl IntoResponse {
     let timestamp = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -224,6 +532,7 @@ async fn health(State(state): State<AppState>) -> impl IntoResponse {
 
     let repo_cache_stats = state.repo_cache.stats().await;
     let diff_cache_stats = state.diff_cache.stats().await;
+    let disk = state.disk.read().await.clone();
 
     Json(serde_json::json!({
         "status": "ok",
@@ -237,10 +546,17 @@ async fn health(State(state): State<AppState>) -> impl IntoResponse {
         "diff_cache": {
             "entries": diff_cache_stats.entries,
             "size_kb": diff_cache_stats.total_size / 1024
-        }
+        },
+        "disk": disk
     }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api",
+    tag = "status",
+    responses((status = 200, description = "available endpoints and query parameters", body = serde_json::Value))
+)]
 async fn api_info() -> impl IntoResponse {
     Json(serde_json::json!({
         "name": "githem",
@@ -251,14 +567,35 @@ async fn api_info() -> impl IntoResponse {
             "branch": "/{owner}/{repo}/tree/{branch}",
             "path": "/{owner}/{repo}/tree/{branch}/{path}",
             "commit": "/{owner}/{repo}/commit/{sha}",
+            "snapshot": "/{owner}/{repo}/at/{sha}",
+            "releases": "/{owner}/{repo}/releases, tags with their commit and release date",
+            "release": "/{owner}/{repo}/releases/tag/{tag}",
+            "share": "POST /api/share, then GET /s/{id}",
+            "websocket": "/ws?url={repository}, then {\"type\":\"refilter\",\"include\":...} to filter again without refetching",
+            "websocket_binary": "/ws?url={repository}&binary=true&deflate=true, files as [u32 BE path length][path][content] frames; deflate compresses each frame with raw DEFLATE, githem's own framing rather than permessage-deflate, so clients inflate frames themselves",
             "compare": "/{owner}/{repo}/compare/{base}...{head}",
-            "pull_request": "/{owner}/{repo}/pull/{number}"
+            "pull_request": "/{owner}/{repo}/pull/{number}",
+            "search": "/{owner}/{repo}/search?q={pattern}&regex=true",
+            "preflight": "HEAD /{owner}/{repo}, X-Githem-* headers without the content",
+            "stats": "/stats, usage dashboard: top repositories and the last day by hour",
+            "openapi": "/api/openapi.json",
+            "swagger_ui": "/api/docs"
         },
         "query_params": {
-            "preset": ["raw", "standard", "code-only", "minimal"],
+            "preset": ["raw", "standard", "code-only", "minimal", "docs-only", "tests-only"],
             "include": "comma-separated patterns (e.g. src/,lib/)",
             "exclude": "comma-separated patterns (e.g. tests/,*.md)",
-            "branch": "branch name (alternative to /tree/{branch})"
+            "ext": "comma-separated extensions (e.g. rs,toml,proto)",
+            "transform": "comma-separated transforms (strip-comments, redact-secrets, notebooks, line-numbers)",
+            "header": "file header detail (path, basic, full)",
+            "min_size": "skip files smaller than this many bytes",
+            "max_lines": "cut files off after this many lines",
+            "max_files": "keep only this many files, the most important ones",
+            "refresh": "true to check a cached result against the remote head even when fresh",
+            "branch": "branch name (alternative to /tree/{branch})",
+            "branches": "comma-separated branches, one section each (e.g. main,develop)",
+            "paths": "comma-separated subtrees to ingest together (e.g. src/core,proto)",
+            "exclude_paths": "comma-separated subtrees to leave out (e.g. vendor/legacy)"
         },
         "examples": [
             "https://githem.com/owner/repo",
@@ -284,50 +621,72 @@ async fn version() -> impl IntoResponse {
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/ingest",
+    tag = "ingest",
+    request_body = IngestRequest,
+    responses(
+        (status = 200, description = "ingestion finished", body = IngestResponse),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+        (status = 413, description = "repository above the size limit", body = ErrorResponse),
+    )
+)]
 async fn ingest_repository(
     State(state): State<AppState>,
-    Json(request): Json<IngestRequest>,
+    ValidJson(request): ValidJson<IngestRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     state.metrics.record_request().await;
     let start = Instant::now();
 
     // Check cache first
+    let branch = cache_branch(request.branch.as_deref(), &request.branches);
     let cache_key = RepositoryCache::generate_key(
         &request.url,
-        request.branch.as_deref(),
+        state
+            .repo_cache
+            .key_branch(&request.url, branch.as_deref())
+            .await,
         request.filter_preset.as_deref(),
-        request.path_prefix.as_deref(),
+        cache_path(
+            request.path_prefix.as_deref(),
+            &request.paths,
+            &request.exclude_paths,
+        )
+        .as_deref(),
     );
 
-    if let Some(cached) = state.repo_cache.get(&cache_key).await {
+    let cached = cached_repo(
+        &state,
+        &cache_key,
+        &request.url,
+        branch.as_deref(),
+        request.refresh,
+    )
+    .await;
+    if let Some((cached, cache)) = cached {
         state.metrics.record_response_time(start.elapsed()).await;
-        return Ok(Json(IngestResponse {
-            id: cached.result.id.clone(),
-            status: "completed".to_string(),
-        }));
-    }
-
-    let params = IngestionParams {
-        url: request.url.clone(),
-        subpath: request.subpath.clone(),
-        branch: request.branch.clone(),
-        path_prefix: request.path_prefix.or(request.subpath),
-        include_patterns: request.include_patterns,
-        exclude_patterns: request.exclude_patterns,
-        max_file_size: request.max_file_size,
-        filter_preset: request.filter_preset.clone(),
-        raw: request.raw,
-    };
+        let stats = RepoStats::cached(&cached, cache);
+        return Ok((
+            stats.headers(),
+            Json(IngestResponse::new(cached.result.id, &stats)),
+        ));
+    }
+
+    let params = request.params();
+    state.limits.check(&params).await?;
 
-    let ingestion_result = match timeout(INGEST_TIMEOUT, async {
-        IngestionService::ingest(params).await
+    let ingestion_result = match timeout(state.limits.timeout(), async {
+        IngestionService::ingest(params.clone()).await
     })
     .await
     {
         Ok(Ok(result)) => result,
         Ok(Err(e)) => {
             state.metrics.record_error().await;
-            return Err(AppError::InternalError(format!("Ingestion failed: {}", e)));
+            return Err(AppError::ingestion_failed(e));
         }
         Err(_) => {
             state.metrics.record_error().await;
@@ -345,8 +704,7 @@ async fn ingest_repository(
         )
         .await;
 
-    // Get commit hash (simplified - would need actual implementation)
-    let commit_hash = ingestion_result.metadata.url.clone();
+    let commit_hash = ingestion_result.commit.clone().unwrap_or_default();
 
     // Cache the result
     state
@@ -354,69 +712,254 @@ async fn ingest_repository(
         .put(
             cache_key,
             request.url,
-            request.branch,
+            branch,
             commit_hash,
             ingestion_result.clone(),
+            params,
         )
         .await;
 
     state.metrics.record_response_time(start.elapsed()).await;
 
-    Ok(Json(IngestResponse {
-        id: ingestion_result.id.clone(),
-        status: "completed".to_string(),
-    }))
+    let stats = RepoStats::new(&ingestion_result, "miss");
+    Ok((
+        stats.headers(),
+        Json(IngestResponse::new(ingestion_result.id, &stats)),
+    ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/result/{id}",
+    tag = "ingest",
+    params(("id" = String, Path, description = "ingestion id")),
+    responses(
+        (status = 200, description = "ingestion result: summary, tree, content and repository metadata", body = serde_json::Value),
+        (status = 404, description = "unknown or expired id", body = ErrorResponse),
+    )
+)]
 async fn get_result(
     State(state): State<AppState>,
-    Path(_id): Path<String>,
+    Path(id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     state.metrics.record_request().await;
 
-    // Check all cache entries for matching ID
-    // This is a simplified approach - in production you'd want a separate ID index
-    Err::<Json<()>, AppError>(AppError::NotFound)
+    let cached = state
+        .repo_cache
+        .get_by_id(&id)
+        .await
+        .ok_or(AppError::NotFound)?;
+    let stats = RepoStats::cached(&cached, "fresh");
+    Ok((stats.headers(), Json(cached.result)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/download/{id}",
+    tag = "ingest",
+    params(("id" = String, Path, description = "ingestion id")),
+    description = "accepts `Range: bytes=...` to resume an interrupted download",
+    responses(
+        (status = 200, description = "ingested content", body = String, content_type = "text/plain"),
+        (status = 206, description = "the requested byte range", body = String, content_type = "text/plain"),
+        (status = 404, description = "unknown or expired id", body = ErrorResponse),
+        (status = 416, description = "range outside the content"),
+    )
+)]
 async fn download_content(
     State(state): State<AppState>,
-    Path(_id): Path<String>,
+    Path(id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     state.metrics.record_request().await;
 
-    // Similar to get_result but returns as download
-    Err::<String, AppError>(AppError::NotFound)
+    let cached = state
+        .repo_cache
+        .get_by_id(&id)
+        .await
+        .ok_or(AppError::NotFound)?;
+    let content = RepoContent::cached(cached, "fresh");
+
+    let mut headers = content.stats.headers();
+    if let Ok(value) = format!("attachment; filename=\"githem-{id}.txt\"").parse() {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    Ok((headers, content.content))
 }
 
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}",
+    tag = "ingest",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "repository content on the default branch", body = String, content_type = "text/plain", headers(
+            ("x-githem-files" = usize, description = "files ingested"),
+            ("x-githem-tokens" = usize, description = "estimated tokens"),
+            ("x-githem-size" = usize, description = "bytes of file content"),
+            ("x-githem-commit" = String, description = "head commit ingested, when known"),
+            ("x-githem-cache" = String, description = "fresh, validated or miss"),
+            ("x-githem-cached-at" = u64, description = "unix time the result was cached, absent on a miss"),
+        )),
+        (status = 206, description = "the `Range: bytes=...` requested, also on the other content routes", body = String, content_type = "text/plain"),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+        (status = 413, description = "repository above the size limit", body = ErrorResponse),
+    )
+)]
 async fn handle_repo(
     State(state): State<AppState>,
     Path((owner, repo)): Path<(String, String)>,
-    Query(params): Query<QueryParams>,
+    ValidQuery(params): ValidQuery<QueryParams>,
 ) -> Result<impl IntoResponse, AppError> {
     ingest_github_repo(state, owner, repo, None, None, params).await
 }
 
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/tree/{branch}",
+    tag = "ingest",
+    description = "also served at `/blob/{branch}`, `/-/tree/{branch}` and `/-/blob/{branch}`",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        ("branch" = String, Path, description = "branch name"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "repository content on the branch", body = String, content_type = "text/plain", headers(
+            ("x-githem-files" = usize, description = "files ingested"),
+            ("x-githem-tokens" = usize, description = "estimated tokens"),
+            ("x-githem-size" = usize, description = "bytes of file content"),
+            ("x-githem-commit" = String, description = "head commit ingested, when known"),
+            ("x-githem-cache" = String, description = "fresh, validated or miss"),
+            ("x-githem-cached-at" = u64, description = "unix time the result was cached, absent on a miss"),
+        )),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+        (status = 413, description = "repository above the size limit", body = ErrorResponse),
+    )
+)]
 async fn handle_repo_branch(
     State(state): State<AppState>,
     Path((owner, repo, branch)): Path<(String, String, String)>,
-    Query(params): Query<QueryParams>,
+    ValidQuery(params): ValidQuery<QueryParams>,
 ) -> Result<impl IntoResponse, AppError> {
     ingest_github_repo(state, owner, repo, Some(branch), None, params).await
 }
 
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/tree/{branch}/{path}",
+    tag = "ingest",
+    description = "also served under `/blob/`, `/-/tree/` and `/-/blob/`",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        ("branch" = String, Path, description = "branch name"),
+        ("path" = String, Path, description = "subdirectory or file to ingest"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "content below the path", body = String, content_type = "text/plain", headers(
+            ("x-githem-files" = usize, description = "files ingested"),
+            ("x-githem-tokens" = usize, description = "estimated tokens"),
+            ("x-githem-size" = usize, description = "bytes of file content"),
+            ("x-githem-commit" = String, description = "head commit ingested, when known"),
+            ("x-githem-cache" = String, description = "fresh, validated or miss"),
+            ("x-githem-cached-at" = u64, description = "unix time the result was cached, absent on a miss"),
+        )),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+        (status = 413, description = "repository above the size limit", body = ErrorResponse),
+    )
+)]
 async fn handle_repo_path(
     State(state): State<AppState>,
     Path((owner, repo, branch, path)): Path<(String, String, String, String)>,
-    Query(params): Query<QueryParams>,
+    ValidQuery(params): ValidQuery<QueryParams>,
 ) -> Result<impl IntoResponse, AppError> {
     ingest_github_repo(state, owner, repo, Some(branch), Some(path), params).await
 }
 
+#[utoipa::path(
+    head,
+    path = "/{owner}/{repo}",
+    tag = "ingest",
+    description = "the headers of the GET response without ingesting, from the cache or estimated from the \
+                   size github reports (`x-githem-cache: estimate`, no file count); also served for the \
+                   `/tree/` and `/blob/` routes",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "metadata headers only", headers(
+            ("x-githem-files" = usize, description = "files ingested, absent for an estimate"),
+            ("x-githem-tokens" = usize, description = "estimated tokens, when known"),
+            ("x-githem-size" = usize, description = "bytes of file content, when known"),
+            ("x-githem-commit" = String, description = "head commit, when known"),
+            ("x-githem-cache" = String, description = "fresh, validated or estimate"),
+            ("x-githem-cached-at" = u64, description = "unix time the result was cached, absent for an estimate"),
+        )),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 413, description = "repository above the size limit", body = ErrorResponse),
+    )
+)]
+async fn head_repo(
+    State(state): State<AppState>,
+    Path((owner, repo)): Path<(String, String)>,
+    ValidQuery(params): ValidQuery<QueryParams>,
+) -> Result<Response, AppError> {
+    preflight_repo(state, owner, repo, None, None, params).await
+}
+
+async fn head_repo_branch(
+    State(state): State<AppState>,
+    Path((owner, repo, branch)): Path<(String, String, String)>,
+    ValidQuery(params): ValidQuery<QueryParams>,
+) -> Result<Response, AppError> {
+    preflight_repo(state, owner, repo, Some(branch), None, params).await
+}
+
+async fn head_repo_path(
+    State(state): State<AppState>,
+    Path((owner, repo, branch, path)): Path<(String, String, String, String)>,
+    ValidQuery(params): ValidQuery<QueryParams>,
+) -> Result<Response, AppError> {
+    preflight_repo(state, owner, repo, Some(branch), Some(path), params).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/pull/{pr_number}",
+    tag = "diff",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        ("pr_number" = u64, Path, description = "pull request number"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "pull request diff", body = String, content_type = "text/plain", headers(
+            ("x-githem-commit" = String, description = "head commit of the pull request, when known"),
+        )),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+    )
+)]
 async fn handle_pr(
     State(state): State<AppState>,
     Path((owner, repo, pr_number)): Path<(String, String, String)>,
-    Query(params): Query<QueryParams>,
+    ValidQuery(params): ValidQuery<QueryParams>,
 ) -> Result<impl IntoResponse, AppError> {
     if !validate_github_name(&owner) || !validate_github_name(&repo) {
         return Err(AppError::InvalidRequest(
@@ -424,27 +967,27 @@ async fn handle_pr(
         ));
     }
 
-    let pr_num = pr_number.parse::<u32>().map_err(|_| {
-        AppError::InvalidRequest("Invalid PR number".to_string())
-    })?;
+    let pr_num = pr_number
+        .parse::<u32>()
+        .map_err(|_| AppError::InvalidRequest("Invalid PR number".to_string()))?;
 
     state.metrics.record_request().await;
 
     // check cache - PRs can change but cache for a short time
     let context_suffix = params.ctx.map(|c| format!(":ctx{}", c)).unwrap_or_default();
-    let cache_key = DiffCache::generate_key("pr", &owner, &repo, &format!("{}{}", pr_number, context_suffix));
+    let cache_key = DiffCache::generate_key(
+        "pr",
+        &owner,
+        &repo,
+        &format!("{}{}", pr_number, context_suffix),
+    );
     if let Some(cached) = state.diff_cache.get(&cache_key).await {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "content-type",
-            "text/plain; charset=utf-8".parse().unwrap(),
-        );
-        return Ok((headers, cached));
+        return Ok((text_headers(cached.commit.as_deref()), cached.content));
     }
 
     let url = format!("https://github.com/{owner}/{repo}");
 
-    let diff_content = timeout(INGEST_TIMEOUT, async {
+    let diff = timeout(state.limits.timeout(), async {
         IngestionService::generate_pr_diff(
             &url,
             pr_num,
@@ -458,32 +1001,116 @@ async fn handle_pr(
     .map_err(|_| AppError::Timeout)?
     .map_err(|e| AppError::InternalError(format!("Failed to generate PR diff: {}", e)))?;
 
-    state.diff_cache.put(cache_key, diff_content.clone()).await;
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "content-type",
-        "text/plain; charset=utf-8"
-            .parse()
-            .map_err(|e| AppError::InternalError(format!("Header parse error: {}", e)))?,
-    );
+    state
+        .diff_cache
+        .put(
+            cache_key,
+            format!("{owner}/{repo}"),
+            diff.content.clone(),
+            diff.commit.clone(),
+        )
+        .await;
 
-    Ok((headers, diff_content))
+    Ok((text_headers(diff.commit.as_deref()), diff.content))
 }
 
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/releases/tag/{tag}",
+    tag = "ingest",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        ("tag" = String, Path, description = "tag name"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "repository content at the tag", body = String, content_type = "text/plain", headers(
+            ("x-githem-files" = usize, description = "files ingested"),
+            ("x-githem-tokens" = usize, description = "estimated tokens"),
+            ("x-githem-size" = usize, description = "bytes of file content"),
+            ("x-githem-commit" = String, description = "head commit ingested, when known"),
+            ("x-githem-cache" = String, description = "fresh, validated or miss"),
+            ("x-githem-cached-at" = u64, description = "unix time the result was cached, absent on a miss"),
+        )),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+        (status = 413, description = "repository above the size limit", body = ErrorResponse),
+    )
+)]
 async fn handle_repo_tag(
     State(state): State<AppState>,
     Path((owner, repo, tag)): Path<(String, String, String)>,
-    Query(params): Query<QueryParams>,
+    ValidQuery(params): ValidQuery<QueryParams>,
 ) -> Result<impl IntoResponse, AppError> {
     // tag works just like a branch
     ingest_github_repo(state, owner, repo, Some(tag), None, params).await
 }
 
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/releases",
+    tag = "ingest",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+    ),
+    responses(
+        (status = 200, description = "tags with their commit and release date, newest release first", body = serde_json::Value),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 408, description = "listing timed out", body = ErrorResponse),
+    )
+)]
+async fn list_releases(
+    State(state): State<AppState>,
+    Path((owner, repo)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    state.metrics.record_request().await;
+    if !validate_github_name(&owner) || !validate_github_name(&repo) {
+        return Err(AppError::InvalidRequest(
+            "Invalid owner or repo name".to_string(),
+        ));
+    }
+
+    let url = format!("https://github.com/{owner}/{repo}");
+    let releases = timeout(
+        state.limits.timeout(),
+        IngestionService::list_releases(&url),
+    )
+    .await
+    .map_err(|_| AppError::Timeout)?
+    .map_err(|e| AppError::InternalError(format!("Failed to list tags: {}", e)))?;
+
+    Ok(Json(serde_json::json!({
+        "repository": url,
+        "tags": releases,
+    })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/-/merge_requests/{mr_number}",
+    tag = "diff",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        ("mr_number" = u64, Path, description = "merge request number"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "merge request diff", body = String, content_type = "text/plain", headers(
+            ("x-githem-commit" = String, description = "head commit of the merge request, when known"),
+        )),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+    )
+)]
 async fn handle_mr(
     State(state): State<AppState>,
     Path((owner, repo, mr_number)): Path<(String, String, String)>,
-    Query(params): Query<QueryParams>,
+    ValidQuery(params): ValidQuery<QueryParams>,
 ) -> Result<impl IntoResponse, AppError> {
     if !validate_github_name(&owner) || !validate_github_name(&repo) {
         return Err(AppError::InvalidRequest(
@@ -491,27 +1118,27 @@ async fn handle_mr(
         ));
     }
 
-    let mr_num = mr_number.parse::<u32>().map_err(|_| {
-        AppError::InvalidRequest("Invalid MR number".to_string())
-    })?;
+    let mr_num = mr_number
+        .parse::<u32>()
+        .map_err(|_| AppError::InvalidRequest("Invalid MR number".to_string()))?;
 
     state.metrics.record_request().await;
 
     // check cache
     let context_suffix = params.ctx.map(|c| format!(":ctx{}", c)).unwrap_or_default();
-    let cache_key = DiffCache::generate_key("mr", &owner, &repo, &format!("{}{}", mr_number, context_suffix));
+    let cache_key = DiffCache::generate_key(
+        "mr",
+        &owner,
+        &repo,
+        &format!("{}{}", mr_number, context_suffix),
+    );
     if let Some(cached) = state.diff_cache.get(&cache_key).await {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "content-type",
-            "text/plain; charset=utf-8".parse().unwrap(),
-        );
-        return Ok((headers, cached));
+        return Ok((text_headers(cached.commit.as_deref()), cached.content));
     }
 
     let url = format!("https://gitlab.com/{owner}/{repo}");
 
-    let diff_content = timeout(INGEST_TIMEOUT, async {
+    let diff = timeout(state.limits.timeout(), async {
         IngestionService::generate_mr_diff(
             &url,
             mr_num,
@@ -525,23 +1152,43 @@ async fn handle_mr(
     .map_err(|_| AppError::Timeout)?
     .map_err(|e| AppError::InternalError(format!("Failed to generate MR diff: {}", e)))?;
 
-    state.diff_cache.put(cache_key, diff_content.clone()).await;
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "content-type",
-        "text/plain; charset=utf-8"
-            .parse()
-            .map_err(|e| AppError::InternalError(format!("Header parse error: {}", e)))?,
-    );
+    state
+        .diff_cache
+        .put(
+            cache_key,
+            format!("{owner}/{repo}"),
+            diff.content.clone(),
+            diff.commit.clone(),
+        )
+        .await;
 
-    Ok((headers, diff_content))
+    Ok((text_headers(diff.commit.as_deref()), diff.content))
 }
 
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/commit/{commit_sha}",
+    tag = "diff",
+    description = "also served at `/-/commit/{commit_sha}`",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        ("commit_sha" = String, Path, description = "commit sha"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "commit diff", body = String, content_type = "text/plain", headers(
+            ("x-githem-commit" = String, description = "full id of the commit, when known"),
+        )),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+    )
+)]
 async fn handle_commit(
     State(state): State<AppState>,
     Path((owner, repo, commit_sha)): Path<(String, String, String)>,
-    Query(params): Query<QueryParams>,
+    ValidQuery(params): ValidQuery<QueryParams>,
 ) -> Result<impl IntoResponse, AppError> {
     if !validate_github_name(&owner) || !validate_github_name(&repo) {
         return Err(AppError::InvalidRequest(
@@ -550,7 +1197,10 @@ async fn handle_commit(
     }
 
     // validate commit sha format (7-40 hex chars)
-    if commit_sha.len() < 7 || commit_sha.len() > 40 || !commit_sha.chars().all(|c| c.is_ascii_hexdigit()) {
+    if commit_sha.len() < 7
+        || commit_sha.len() > 40
+        || !commit_sha.chars().all(|c| c.is_ascii_hexdigit())
+    {
         return Err(AppError::InvalidRequest(
             "Invalid commit SHA format".to_string(),
         ));
@@ -560,19 +1210,19 @@ async fn handle_commit(
 
     // check cache first - commits are immutable, but context param matters
     let context_suffix = params.ctx.map(|c| format!(":ctx{}", c)).unwrap_or_default();
-    let cache_key = DiffCache::generate_key("commit", &owner, &repo, &format!("{}{}", commit_sha, context_suffix));
+    let cache_key = DiffCache::generate_key(
+        "commit",
+        &owner,
+        &repo,
+        &format!("{}{}", commit_sha, context_suffix),
+    );
     if let Some(cached) = state.diff_cache.get(&cache_key).await {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "content-type",
-            "text/plain; charset=utf-8".parse().unwrap(),
-        );
-        return Ok((headers, cached));
+        return Ok((text_headers(cached.commit.as_deref()), cached.content));
     }
 
     let url = format!("https://github.com/{owner}/{repo}");
 
-    let diff_content = timeout(INGEST_TIMEOUT, async {
+    let diff = timeout(state.limits.timeout(), async {
         IngestionService::generate_commit_diff(
             &url,
             &commit_sha,
@@ -587,23 +1237,313 @@ async fn handle_commit(
     .map_err(|e| AppError::InternalError(format!("Failed to generate commit diff: {}", e)))?;
 
     // cache the result
-    state.diff_cache.put(cache_key, diff_content.clone()).await;
+    state
+        .diff_cache
+        .put(
+            cache_key,
+            format!("{owner}/{repo}"),
+            diff.content.clone(),
+            diff.commit.clone(),
+        )
+        .await;
+
+    Ok((text_headers(diff.commit.as_deref()), diff.content))
+}
 
+/// headers of a plain text diff or snapshot, `x-githem-commit` the commit
+/// it leads to
+fn text_headers(commit: Option<&str>) -> HeaderMap {
     let mut headers = HeaderMap::new();
     headers.insert(
-        "content-type",
-        "text/plain; charset=utf-8"
-            .parse()
-            .map_err(|e| AppError::InternalError(format!("Header parse error: {}", e)))?,
+        header::CONTENT_TYPE,
+        "text/plain; charset=utf-8".parse().unwrap(),
     );
+    if let Some(commit) = commit.and_then(|commit| commit.parse().ok()) {
+        headers.insert("x-githem-commit", commit);
+    }
+    headers
+}
+
+/// is `sha` an abbreviated or full commit id
+fn is_commit_sha(sha: &str) -> bool {
+    (7..=40).contains(&sha.len()) && sha.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/at/{commit_sha}",
+    tag = "ingest",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        ("commit_sha" = String, Path, description = "commit sha, 7 to 40 hex characters"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "repository content pinned to the commit, cacheable forever for a full 40 character sha", body = String, content_type = "text/plain", headers(
+            ("x-githem-commit" = String, description = "full id of the commit, when known"),
+        )),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+        (status = 413, description = "repository above the size limit", body = ErrorResponse),
+    )
+)]
+async fn handle_repo_at(
+    State(state): State<AppState>,
+    Path((owner, repo, commit_sha)): Path<(String, String, String)>,
+    ValidQuery(params): ValidQuery<QueryParams>,
+) -> Result<impl IntoResponse, AppError> {
+    fetch_repo_at(state, owner, repo, commit_sha, None, params).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/at/{commit_sha}/{path}",
+    tag = "ingest",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        ("commit_sha" = String, Path, description = "commit sha, 7 to 40 hex characters"),
+        ("path" = String, Path, description = "subdirectory or file to ingest"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "content below the path, pinned to the commit", body = String, content_type = "text/plain"),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+        (status = 413, description = "repository above the size limit", body = ErrorResponse),
+    )
+)]
+async fn handle_repo_at_path(
+    State(state): State<AppState>,
+    Path((owner, repo, commit_sha, path)): Path<(String, String, String, String)>,
+    ValidQuery(params): ValidQuery<QueryParams>,
+) -> Result<impl IntoResponse, AppError> {
+    fetch_repo_at(state, owner, repo, commit_sha, Some(path), params).await
+}
+
+/// snapshot of a repository at a commit; immutable, so cached without expiry
+async fn fetch_repo_at(
+    state: AppState,
+    owner: String,
+    repo: String,
+    commit_sha: String,
+    path_prefix: Option<String>,
+    params: QueryParams,
+) -> Result<impl IntoResponse, AppError> {
+    if !validate_github_name(&owner) || !validate_github_name(&repo) {
+        return Err(AppError::InvalidRequest(
+            "Invalid owner or repo name".to_string(),
+        ));
+    }
+
+    if !is_commit_sha(&commit_sha) {
+        return Err(AppError::InvalidRequest(
+            "Invalid commit SHA format".to_string(),
+        ));
+    }
+
+    state.metrics.record_request().await;
+
+    let url = format!("https://github.com/{owner}/{repo}");
+    let mut ingestion_params = build_ingestion_params(url, None, path_prefix, &params);
+    ingestion_params.commit = Some(commit_sha.to_lowercase());
+
+    serve_snapshot(&state, &owner, &repo, ingestion_params).await
+}
+
+/// ingest (or replay from cache) parameters pinned to a commit
+async fn serve_snapshot(
+    state: &AppState,
+    owner: &str,
+    repo: &str,
+    ingestion_params: IngestionParams,
+) -> Result<(HeaderMap, String), AppError> {
+    let start = Instant::now();
+    let pinned = ingestion_params.commit.clone().unwrap_or_default();
+
+    let identifier = serde_json::to_string(&ingestion_params)
+        .map_err(|e| AppError::InternalError(format!("Failed to build cache key: {}", e)))?;
+    let cache_key = DiffCache::generate_key("at", owner, repo, &identifier);
+
+    let (content, commit) = match state.diff_cache.get(&cache_key).await {
+        Some(cached) => (cached.content, cached.commit),
+        None => {
+            state.limits.check(&ingestion_params).await?;
+            let result = match timeout(state.limits.timeout(), async {
+                IngestionService::ingest(ingestion_params).await
+            })
+            .await
+            {
+                Ok(Ok(result)) => result,
+                Ok(Err(e)) => {
+                    state.metrics.record_error().await;
+                    return Err(AppError::ingestion_failed(e));
+                }
+                Err(_) => {
+                    state.metrics.record_error().await;
+                    return Err(AppError::Timeout);
+                }
+            };
+
+            state
+                .diff_cache
+                .put(
+                    cache_key,
+                    format!("{owner}/{repo}"),
+                    result.content.clone(),
+                    result.commit.clone(),
+                )
+                .await;
+            (result.content, result.commit)
+        }
+    };
+
+    state.metrics.record_response_time(start.elapsed()).await;
+
+    // an abbreviated sha can become ambiguous as the repository grows
+    let cache_control = if pinned.len() == 40 {
+        "public, max-age=31536000, immutable"
+    } else {
+        "public, max-age=86400"
+    };
+    let mut headers = text_headers(commit.as_deref());
+    headers.insert(header::CACHE_CONTROL, cache_control.parse().unwrap());
+
+    Ok((headers, content))
+}
 
-    Ok((headers, diff_content))
+#[derive(Deserialize, ToSchema)]
+pub struct ShareRequest {
+    pub owner: String,
+    pub repo: String,
+    /// pin to this commit; defaults to the current head of `branch`
+    pub commit: Option<String>,
+    /// seconds until the link stops working, at most a year
+    pub expires_in: Option<u64>,
+    #[serde(flatten)]
+    pub options: QueryParams,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/share",
+    tag = "share",
+    request_body = ShareRequest,
+    responses(
+        (status = 201, description = "share link created", body = serde_json::Value),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+    )
+)]
+async fn create_share(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidJson(request): ValidJson<ShareRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    state.metrics.record_request().await;
+
+    if !validate_github_name(&request.owner) || !validate_github_name(&request.repo) {
+        return Err(AppError::InvalidRequest(
+            "Invalid owner or repo name".to_string(),
+        ));
+    }
+
+    let url = format!("https://github.com/{}/{}", request.owner, request.repo);
+
+    let commit = match request.commit {
+        Some(commit) if is_commit_sha(&commit) => commit.to_lowercase(),
+        Some(_) => {
+            return Err(AppError::InvalidRequest(
+                "Invalid commit SHA format".to_string(),
+            ))
+        }
+        None => {
+            let remote_url = url.clone();
+            let branch = request.options.branch.clone();
+            tokio::task::spawn_blocking(move || {
+                githem_core::get_remote_head(&remote_url, branch.as_deref())
+            })
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to resolve head: {}", e)))?
+            .map_err(|e| AppError::InvalidRequest(format!("Failed to resolve head: {}", e)))?
+        }
+    };
+
+    let mut params = build_ingestion_params(url, None, None, &request.options);
+    params.commit = Some(commit.clone());
+
+    let snapshot = state
+        .shares
+        .create(request.owner, request.repo, params, request.expires_in)
+        .await
+        .map_err(|e| AppError::InternalError(format!("Failed to store share: {}", e)))?;
+
+    let base = state.config.public_url.clone().unwrap_or_else(|| {
+        let host = headers
+            .get(header::HOST)
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or("githem.com");
+        format!("https://{host}")
+    });
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "id": snapshot.id,
+            "url": format!("{}/s/{}", base.trim_end_matches('/'), snapshot.id),
+            "commit": commit,
+            "expires_at": snapshot.expires_at,
+        })),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/s/{id}",
+    tag = "share",
+    params(("id" = String, Path, description = "share id")),
+    responses(
+        (status = 200, description = "the shared snapshot", body = String, content_type = "text/plain"),
+        (status = 404, description = "unknown or expired share", body = ErrorResponse),
+    )
+)]
+async fn handle_share(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let snapshot = state.shares.get(&id).await.ok_or(AppError::NotFound)?;
+    state.metrics.record_request().await;
+    serve_snapshot(&state, &snapshot.owner, &snapshot.repo, snapshot.params).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/compare/{compare_spec}",
+    tag = "diff",
+    description = "also served at `/-/compare/{compare_spec}`",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        ("compare_spec" = String, Path, description = "`base...head` or `base..head`"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "diff between the two refs", body = String, content_type = "text/plain", headers(
+            ("x-githem-commit" = String, description = "commit the head ref resolved to, when known"),
+        )),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+    )
+)]
 async fn handle_repo_compare(
     State(state): State<AppState>,
     Path((owner, repo, compare_spec)): Path<(String, String, String)>,
-    Query(params): Query<QueryParams>,
+    ValidQuery(params): ValidQuery<QueryParams>,
 ) -> Result<impl IntoResponse, AppError> {
     if !validate_github_name(&owner) || !validate_github_name(&repo) {
         return Err(AppError::InvalidRequest(
@@ -611,29 +1551,29 @@ async fn handle_repo_compare(
         ));
     }
 
-    let (base, head) = parse_compare_spec(&compare_spec).ok_or_else(|| {
-        AppError::InvalidRequest(
-            "Invalid compare format. Use 'base...head' or 'base..head'".to_string(),
-        )
-    })?;
+    let (base, head) =
+        parse_compare_spec(&compare_spec).map_err(|message| AppError::InvalidParameter {
+            field: "compare",
+            message,
+        })?;
 
     state.metrics.record_request().await;
 
     // check cache
     let context_suffix = params.ctx.map(|c| format!(":ctx{}", c)).unwrap_or_default();
-    let cache_key = DiffCache::generate_key("compare", &owner, &repo, &format!("{}{}", compare_spec, context_suffix));
+    let cache_key = DiffCache::generate_key(
+        "compare",
+        &owner,
+        &repo,
+        &format!("{}{}", compare_spec, context_suffix),
+    );
     if let Some(cached) = state.diff_cache.get(&cache_key).await {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "content-type",
-            "text/plain; charset=utf-8".parse().unwrap(),
-        );
-        return Ok((headers, cached));
+        return Ok((text_headers(cached.commit.as_deref()), cached.content));
     }
 
     let url = format!("https://github.com/{owner}/{repo}");
 
-    let diff_content = timeout(INGEST_TIMEOUT, async {
+    let diff = timeout(state.limits.timeout(), async {
         IngestionService::generate_diff(
             &url,
             &base,
@@ -648,141 +1588,498 @@ async fn handle_repo_compare(
     .map_err(|_| AppError::Timeout)?
     .map_err(|e| AppError::InternalError(format!("Failed to generate diff: {}", e)))?;
 
-    state.diff_cache.put(cache_key, diff_content.clone()).await;
+    state
+        .diff_cache
+        .put(
+            cache_key,
+            format!("{owner}/{repo}"),
+            diff.content.clone(),
+            diff.commit.clone(),
+        )
+        .await;
 
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "content-type",
-        "text/plain; charset=utf-8"
-            .parse()
-            .map_err(|e| AppError::InternalError(format!("Header parse error: {}", e)))?,
-    );
+    Ok((text_headers(diff.commit.as_deref()), diff.content))
+}
 
-    Ok((headers, diff_content))
+#[utoipa::path(
+    get,
+    path = "/{owner}/{repo}/search",
+    tag = "ingest",
+    params(
+        ("owner" = String, Path, description = "repository owner"),
+        ("repo" = String, Path, description = "repository name"),
+        SearchParams
+    ),
+    responses(
+        (status = 200, description = "matches grouped by file", body = serde_json::Value),
+        (status = 400, description = "invalid request", body = ErrorResponse),
+        (status = 404, description = "repository or ref not found", body = ErrorResponse),
+        (status = 408, description = "ingestion timed out", body = ErrorResponse),
+        (status = 413, description = "repository above the size limit", body = ErrorResponse),
+    )
+)]
+async fn handle_search(
+    State(state): State<AppState>,
+    Path((owner, repo)): Path<(String, String)>,
+    ValidQuery(params): ValidQuery<SearchParams>,
+) -> Result<impl IntoResponse, AppError> {
+    if params.q.trim().is_empty() {
+        return Err(AppError::InvalidRequest(
+            "Search query (?q=) is required".to_string(),
+        ));
+    }
+
+    let query_params = QueryParams {
+        branch: params.branch,
+        branches: None,
+        subpath: None,
+        include: None,
+        exclude: None,
+        ext: None,
+        transform: None,
+        header: None,
+        max_size: None,
+        min_size: None,
+        max_lines: None,
+        max_files: None,
+        preset: params.preset,
+        raw: None,
+        path: params.path,
+        paths: None,
+        exclude_paths: None,
+        refresh: None,
+        ctx: None,
+    };
+
+    // reuses the cached ingestion when available
+    let RepoContent { content, stats } =
+        fetch_repo_content(state, owner, repo, None, None, query_params).await?;
+
+    let options = SearchOptions {
+        query: params.q.clone(),
+        regex: params.regex.unwrap_or(false),
+        case_sensitive: params.case_sensitive.unwrap_or(false),
+        context_lines: params.context.unwrap_or(2).min(MAX_SEARCH_CONTEXT),
+        ..Default::default()
+    };
+
+    let files = search_output(&content, &options)
+        .map_err(|e| AppError::InvalidRequest(format!("Invalid search pattern: {}", e)))?;
+    let total_matches: usize = files.iter().map(|f| f.matches.len()).sum();
+
+    Ok((
+        stats.headers(),
+        Json(serde_json::json!({
+            "query": params.q,
+            "regex": options.regex,
+            "total_matches": total_matches,
+            "files": files,
+            "cache": stats.cache,
+            "commit": stats.commit,
+            "cached_at": stats.cached_at,
+        })),
+    ))
 }
 
-fn parse_compare_spec(spec: &str) -> Option<(String, String)> {
-    if let Some((base, head)) = spec.split_once("...") {
-        if !base.is_empty() && !head.is_empty() {
-            Some((base.to_string(), head.to_string()))
-        } else {
-            None
+async fn ingest_github_repo(
+    state: AppState,
+    owner: String,
+    repo: String,
+    branch: Option<String>,
+    path_prefix: Option<String>,
+    params: QueryParams,
+) -> Result<impl IntoResponse, AppError> {
+    fetch_repo_content(state, owner, repo, branch, path_prefix, params).await
+}
+
+/// provenance of an ingestion, sent as `X-Githem-*` headers so scripted
+/// clients need neither the body nor JSON to log it
+struct RepoStats {
+    /// None for an estimate
+    files: Option<usize>,
+    /// None when not even an estimate is available
+    tokens: Option<usize>,
+    size: Option<usize>,
+    /// None when the head commit could not be resolved
+    commit: Option<String>,
+    /// `fresh`, `validated`, `miss` or `estimate`
+    cache: &'static str,
+    /// unix time the served result was cached, None unless from the cache
+    cached_at: Option<u64>,
+}
+
+impl RepoStats {
+    fn new(result: &IngestionResult, cache: &'static str) -> Self {
+        Self {
+            files: Some(result.summary.files_analyzed),
+            tokens: Some(result.summary.estimated_tokens),
+            size: Some(result.summary.total_size),
+            commit: result.commit.clone(),
+            cache,
+            cached_at: None,
+        }
+    }
+
+    fn cached(cached: &CachedRepository, cache: &'static str) -> Self {
+        let stats = Self::new(&cached.result, cache);
+        Self {
+            // results cached before they recorded their commit; those of
+            // failed head lookups hold the url instead
+            commit: stats
+                .commit
+                .or_else(|| Some(cached.commit_hash.clone()).filter(|c| is_commit_sha(c))),
+            cached_at: Some(cached.created_at),
+            ..stats
+        }
+    }
+
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let mut set = |name: &'static str, value: String| {
+            if let Ok(value) = value.parse() {
+                headers.insert(name, value);
+            }
+        };
+        if let Some(files) = self.files {
+            set("x-githem-files", files.to_string());
+        }
+        if let Some(tokens) = self.tokens {
+            set("x-githem-tokens", tokens.to_string());
+        }
+        if let Some(size) = self.size {
+            set("x-githem-size", size.to_string());
+        }
+        set("x-githem-cache", self.cache.to_string());
+        if let Some(ref commit) = self.commit {
+            set("x-githem-commit", commit.clone());
         }
-    } else if let Some((base, head)) = spec.split_once("..") {
-        if !base.is_empty() && !head.is_empty() {
-            Some((base.to_string(), head.to_string()))
-        } else {
-            None
+        if let Some(cached_at) = self.cached_at {
+            set("x-githem-cached-at", cached_at.to_string());
         }
+        headers
+    }
+}
+
+/// an ingested repository with its `X-Githem-*` headers
+struct RepoContent {
+    content: String,
+    stats: RepoStats,
+}
+
+impl RepoContent {
+    fn new(result: IngestionResult, cache: &'static str) -> Self {
+        Self {
+            stats: RepoStats::new(&result, cache),
+            content: result.content,
+        }
+    }
+
+    fn cached(cached: CachedRepository, cache: &'static str) -> Self {
+        Self {
+            stats: RepoStats::cached(&cached, cache),
+            content: cached.result.content,
+        }
+    }
+}
+
+impl IntoResponse for RepoContent {
+    fn into_response(self) -> Response {
+        (self.stats.headers(), self.content).into_response()
+    }
+}
+
+/// head commit of the branch on the remote, None when it can't be resolved
+async fn remote_head(url: &str, branch: Option<&str>) -> Option<String> {
+    let url = url.to_string();
+    let branch = branch.map(str::to_string);
+    tokio::task::spawn_blocking(move || githem_core::get_remote_head(&url, branch.as_deref()).ok())
+        .await
+        .ok()
+        .flatten()
+}
+
+/// the cached entry under `key` and whether it was served `fresh` or
+/// `validated`. past the fresh window, or always with `refresh`, the
+/// entry is only served once ls-remote shows the head still at its commit
+async fn cached_repo(
+    state: &AppState,
+    key: &str,
+    url: &str,
+    branch: Option<&str>,
+    refresh: bool,
+) -> Option<(CachedRepository, &'static str)> {
+    let (cache_status, cached_commit) = state.repo_cache.check_status(key).await;
+    let cache = match cache_status {
+        CacheStatus::Fresh if !refresh => "fresh",
+        CacheStatus::Fresh | CacheStatus::Valid => {
+            // several branches have no single head to compare
+            let cached_commit = cached_commit.filter(|commit| !commit.is_empty())?;
+            // if ls-remote fails, fall through to full fetch
+            let current_commit = remote_head(url, branch).await?;
+            if current_commit != cached_commit {
+                state.repo_cache.invalidate(key).await;
+                return None;
+            }
+            state.repo_cache.mark_validated(key).await;
+            "validated"
+        }
+        CacheStatus::Expired | CacheStatus::Stale | CacheStatus::Miss => return None,
+    };
+    let cached = state.repo_cache.get(key).await?;
+    Some((cached, cache))
+}
+
+/// the branch of a cache entry, several branches joined by a space, which
+/// no ref name contains
+fn cache_branch(branch: Option<&str>, branches: &[String]) -> Option<String> {
+    if branches.is_empty() {
+        branch.map(str::to_string)
     } else {
-        None
+        Some(branches.join(" "))
     }
 }
 
-async fn ingest_github_repo(
+/// the path of a cache entry, several joined by a newline, which no valid
+/// path contains, excluded ones marked by a leading `-`, which no valid
+/// path starts with
+fn cache_path(path: Option<&str>, paths: &[String], exclude_paths: &[String]) -> Option<String> {
+    if paths.is_empty() && exclude_paths.is_empty() {
+        return path.map(str::to_string);
+    }
+    let mut all: Vec<String> = path
+        .into_iter()
+        .chain(paths.iter().map(String::as_str))
+        .map(str::to_string)
+        .collect();
+    all.extend(exclude_paths.iter().map(|excluded| format!("-{excluded}")));
+    Some(all.join("\n"))
+}
+
+/// cache key of a repository route, shared by GET and HEAD
+async fn repo_cache_key(
+    state: &AppState,
+    url: &str,
+    branch: Option<&str>,
+    path_prefix: Option<&str>,
+    params: &QueryParams,
+) -> String {
+    let path = path_prefix
+        .or(params.path.as_deref())
+        .or(params.subpath.as_deref());
+    RepositoryCache::generate_key(
+        url,
+        state.repo_cache.key_branch(url, branch).await,
+        params.preset.as_deref(),
+        cache_path(path, &params.path_list(), &params.exclude_path_list()).as_deref(),
+    )
+}
+
+/// the `X-Githem-*` headers of a repository route without its body: from
+/// the cache when the result is there, otherwise estimated from the size
+/// the hosting api reports, which is what GET would check against the
+/// size limit before cloning
+async fn preflight_repo(
     state: AppState,
     owner: String,
     repo: String,
     branch: Option<String>,
     path_prefix: Option<String>,
     params: QueryParams,
-) -> Result<impl IntoResponse, AppError> {
-    state.metrics.record_request().await;
-    let start = Instant::now();
-
+) -> Result<Response, AppError> {
     if !validate_github_name(&owner) || !validate_github_name(&repo) {
-        state.metrics.record_error().await;
         return Err(AppError::InvalidRequest(
             "Invalid owner or repo name".to_string(),
         ));
     }
+    if let Some(ref branch) = branch {
+        validate_ref_name(branch).map_err(|message| AppError::InvalidParameter {
+            field: "branch",
+            message,
+        })?;
+        if params.branches.is_some() {
+            return Err(AppError::InvalidParameter {
+                field: "branches",
+                message: "cannot be combined with /tree/{branch}".to_string(),
+            });
+        }
+    }
+    if let Some(ref path) = path_prefix {
+        validate_path_prefix(path).map_err(|message| AppError::InvalidParameter {
+            field: "path",
+            message,
+        })?;
+    }
 
     let url = format!("https://github.com/{owner}/{repo}");
     let effective_branch = branch.clone().or(params.branch.clone());
-
-    // Check cache with smart validation
-    let cache_key = RepositoryCache::generate_key(
+    let key_branch = cache_branch(effective_branch.as_deref(), &params.branch_list());
+    let cache_key = repo_cache_key(
+        &state,
         &url,
-        effective_branch.as_deref(),
-        params.preset.as_deref(),
-        path_prefix
-            .as_ref()
-            .or(params.path.as_ref())
-            .or(params.subpath.as_ref())
-            .map(|s| s.as_str()),
-    );
-
-    let (cache_status, cached_commit) = state.repo_cache.check_status(&cache_key).await;
+        key_branch.as_deref(),
+        path_prefix.as_deref(),
+        &params,
+    )
+    .await;
 
-    match cache_status {
-        CacheStatus::Fresh => {
-            // < 5 min old, serve immediately
-            if let Some(cached) = state.repo_cache.get(&cache_key).await {
-                state.metrics.record_response_time(start.elapsed()).await;
-                return Ok(cached.result.content);
-            }
-        }
-        CacheStatus::Valid => {
-            // 5min-24h old, validate commit hash
-            if let Some(cached_hash) = cached_commit {
-                // quick ls-remote check
-                if let Ok(current_hash) = githem_core::get_remote_head(&url, effective_branch.as_deref()) {
-                    if current_hash == cached_hash {
-                        // commit unchanged, serve cached and update validation time
-                        state.repo_cache.mark_validated(&cache_key).await;
-                        if let Some(cached) = state.repo_cache.get(&cache_key).await {
-                            state.metrics.record_response_time(start.elapsed()).await;
-                            return Ok(cached.result.content);
-                        }
-                    } else {
-                        // commit changed, invalidate cache
-                        state.repo_cache.invalidate(&cache_key).await;
-                    }
-                }
-                // if ls-remote fails, fall through to full fetch
-            }
-        }
-        CacheStatus::Expired | CacheStatus::Stale | CacheStatus::Miss => {
-            // need fresh fetch
-        }
+    let refresh = params.refresh.unwrap_or(false);
+    let cached = cached_repo(&state, &cache_key, &url, key_branch.as_deref(), refresh).await;
+    if let Some((cached, cache)) = cached {
+        let mut headers = RepoStats::cached(&cached, cache).headers();
+        headers.insert(header::CONTENT_LENGTH, cached.uncompressed_bytes.into());
+        headers.insert(
+            header::CONTENT_TYPE,
+            "text/plain; charset=utf-8".parse().unwrap(),
+        );
+        return Ok((headers, ()).into_response());
     }
 
-    let ingestion_params = IngestionParams {
-        url: url.clone(),
+    let ingestion_params =
+        build_ingestion_params(url.clone(), effective_branch.clone(), path_prefix, &params);
+    state.limits.check(&ingestion_params).await?;
+
+    let size_kb = state.limits.repository_size_kb(&url).await;
+    let commit = match params.branches {
+        Some(_) => None,
+        None => remote_head(&url, effective_branch.as_deref()).await,
+    };
+
+    // the packed size of the whole history, an upper bound for the files
+    let size = size_kb.map(|kb| kb as usize * 1024);
+    let stats = RepoStats {
+        files: None,
+        tokens: size.map(|size| size / 4),
+        size,
+        commit,
+        cache: "estimate",
+        cached_at: None,
+    };
+    let mut headers = stats.headers();
+    headers.insert(
+        header::CONTENT_TYPE,
+        "text/plain; charset=utf-8".parse().unwrap(),
+    );
+    Ok((headers, ()).into_response())
+}
+
+/// ingestion parameters for a repository route plus its query string
+fn build_ingestion_params(
+    url: String,
+    branch: Option<String>,
+    path_prefix: Option<String>,
+    params: &QueryParams,
+) -> IngestionParams {
+    let split_patterns = |patterns: &Option<String>| split_list(patterns.as_deref());
+
+    IngestionParams {
+        url,
         subpath: params.subpath.clone(),
-        branch: branch.clone().or(params.branch.clone()),
+        branch,
+        branches: params.branch_list(),
         path_prefix: path_prefix
             .or(params.path.clone())
             .or(params.subpath.clone())
-            .filter(|p| !p.contains("..") && !p.starts_with('/')),
-        include_patterns: params
-            .include
-            .unwrap_or_default()
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect(),
-        exclude_patterns: params
-            .exclude
-            .unwrap_or_default()
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
+            .filter(|p| validate_path_prefix(p).is_ok()),
+        paths: params.path_list(),
+        exclude_paths: params.exclude_path_list(),
+        include_patterns: split_patterns(&params.include),
+        exclude_patterns: split_patterns(&params.exclude),
+        extensions: split_patterns(&params.ext),
+        transforms: split_patterns(&params.transform)
+            .iter()
+            .filter_map(|name| Transform::parse(name))
             .collect(),
+        header_detail: params
+            .header
+            .as_deref()
+            .and_then(HeaderDetail::parse)
+            .unwrap_or_default(),
         max_file_size: params.max_size.unwrap_or(10 * 1024 * 1024),
+        min_file_size: params.min_size.unwrap_or(0),
+        max_lines: params.max_lines,
+        max_files: params.max_files,
         filter_preset: params.preset.clone(),
         raw: params.raw.unwrap_or(false),
-    };
+        commit: None,
+    }
+}
+
+async fn fetch_repo_content(
+    state: AppState,
+    owner: String,
+    repo: String,
+    branch: Option<String>,
+    path_prefix: Option<String>,
+    params: QueryParams,
+) -> Result<RepoContent, AppError> {
+    state.metrics.record_request().await;
+    let start = Instant::now();
+
+    if !validate_github_name(&owner) || !validate_github_name(&repo) {
+        state.metrics.record_error().await;
+        return Err(AppError::InvalidRequest(
+            "Invalid owner or repo name".to_string(),
+        ));
+    }
+    if let Some(ref branch) = branch {
+        validate_ref_name(branch).map_err(|message| AppError::InvalidParameter {
+            field: "branch",
+            message,
+        })?;
+        if params.branches.is_some() {
+            return Err(AppError::InvalidParameter {
+                field: "branches",
+                message: "cannot be combined with /tree/{branch}".to_string(),
+            });
+        }
+    }
+    if let Some(ref path) = path_prefix {
+        validate_path_prefix(path).map_err(|message| AppError::InvalidParameter {
+            field: "path",
+            message,
+        })?;
+    }
+
+    let url = format!("https://github.com/{owner}/{repo}");
+    let effective_branch = branch.clone().or(params.branch.clone());
+    let key_branch = cache_branch(effective_branch.as_deref(), &params.branch_list());
+
+    // Check cache with smart validation
+    let cache_key = repo_cache_key(
+        &state,
+        &url,
+        key_branch.as_deref(),
+        path_prefix.as_deref(),
+        &params,
+    )
+    .await;
+    let refresh = params.refresh.unwrap_or(false);
+
+    if let Some((cached, cache)) =
+        cached_repo(&state, &cache_key, &url, key_branch.as_deref(), refresh).await
+    {
+        state.metrics.record_repo_hit(&url).await;
+        state.metrics.record_response_time(start.elapsed()).await;
+        return Ok(RepoContent::cached(cached, cache));
+    }
 
-    let result = match timeout(INGEST_TIMEOUT, async {
-        IngestionService::ingest(ingestion_params).await
+    let ingestion_params = build_ingestion_params(
+        url.clone(),
+        branch.or(params.branch.clone()),
+        path_prefix,
+        &params,
+    );
+    state.limits.check(&ingestion_params).await?;
+
+    let result = match timeout(state.limits.timeout(), async {
+        IngestionService::ingest(ingestion_params.clone()).await
     })
     .await
     {
         Ok(Ok(result)) => result,
         Ok(Err(e)) => {
             state.metrics.record_error().await;
-            return Err(AppError::InternalError(format!("Ingestion failed: {}", e)));
+            return Err(AppError::ingestion_failed(e));
         }
         Err(_) => {
             state.metrics.record_error().await;
@@ -801,36 +2098,65 @@ async fn ingest_github_repo(
         .await;
 
     // Cache the result with commit hash
-    // TODO: get actual commit hash from ingestion result
-    let commit_hash = githem_core::get_remote_head(&url, effective_branch.as_deref())
-        .unwrap_or_else(|_| result.metadata.url.clone());
+    let commit_hash = result.commit.clone().unwrap_or_default();
     state
         .repo_cache
-        .put(cache_key, url, effective_branch, commit_hash, result.clone())
+        .put(
+            cache_key,
+            url,
+            key_branch,
+            commit_hash,
+            result.clone(),
+            ingestion_params,
+        )
         .await;
 
     state.metrics.record_response_time(start.elapsed()).await;
 
-    Ok(result.content)
+    Ok(RepoContent::new(result, "miss"))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/metrics/top",
+    tag = "status",
+    responses((status = 200, description = "most requested repositories", body = serde_json::Value))
+)]
 async fn get_top_repos(State(state): State<AppState>) -> impl IntoResponse {
     let repos = state.metrics.get_top_repositories(10).await;
     Json(repos)
 }
 
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "status",
+    responses((status = 200, description = "request and ingestion counters", body = serde_json::Value))
+)]
 async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
     let metrics = state.metrics.get_metrics().await;
     Json(metrics)
 }
 
+#[utoipa::path(
+    get,
+    path = "/cache/stats",
+    tag = "status",
+    responses((status = 200, description = "repository and diff cache statistics", body = serde_json::Value))
+)]
 async fn get_cache_stats(State(state): State<AppState>) -> impl IntoResponse {
     let stats = state.repo_cache.stats().await;
     Json(stats)
 }
 
-pub fn create_router() -> Router {
-    let state = AppState::new();
+pub fn create_router(state: AppState) -> Router {
+    let config = state.config.clone();
+    crate::refresh::spawn_cache_refresher(state.clone());
+    crate::janitor::spawn_janitor(state.clone());
+    crate::share::spawn_import(state.shares.clone(), config.share_dir.clone());
+
+    // Range requests on the routes that answer with ingested content
+    let ranged = || axum::middleware::from_fn(crate::range::byte_ranges);
 
     let router = Router::new()
         // Landing page and static assets
@@ -846,48 +2172,90 @@ pub fn create_router() -> Router {
         .route("/metrics", get(get_metrics))
         .route("/api/metrics/top", get(get_top_repos))
         .route("/cache/stats", get(get_cache_stats))
+        .route("/stats", get(crate::dashboard::stats_page))
         .route("/api/ingest", post(ingest_repository))
         .route("/api/result/{id}", get(get_result))
-        .route("/api/download/{id}", get(download_content))
+        .route("/api/download/{id}", get(download_content).layer(ranged()))
+        .route("/api/share", post(create_share))
+        .route("/s/{id}", get(handle_share))
+        .route("/ws", get(crate::websocket::websocket_handler))
+        // diffs under /api for the frontend, which owns the repository paths
+        .route(
+            "/api/{owner}/{repo}/compare/{compare_spec}",
+            get(handle_repo_compare),
+        )
+        .route("/api/{owner}/{repo}/pull/{pr_number}", get(handle_pr))
+        .merge(crate::admin::router(&config))
+        .merge(SwaggerUi::new("/api/docs").url("/api/openapi.json", ApiDoc::openapi()))
         // GitHub repository routes
-        .route("/{owner}/{repo}", get(handle_repo))
+        .route(
+            "/{owner}/{repo}",
+            get(handle_repo).head(head_repo).layer(ranged()),
+        )
+        .route("/{owner}/{repo}/search", get(handle_search))
         .route("/{owner}/{repo}/pull/{pr_number}", get(handle_pr))
         .route("/{owner}/{repo}/commit/{commit_sha}", get(handle_commit))
+        .route(
+            "/{owner}/{repo}/at/{commit_sha}",
+            get(handle_repo_at).layer(ranged()),
+        )
+        .route(
+            "/{owner}/{repo}/at/{commit_sha}/{*path}",
+            get(handle_repo_at_path).layer(ranged()),
+        )
         .route(
             "/{owner}/{repo}/compare/{compare_spec}",
             get(handle_repo_compare),
         )
-        .route("/{owner}/{repo}/tree/{branch}", get(handle_repo_branch))
+        .route(
+            "/{owner}/{repo}/tree/{branch}",
+            get(handle_repo_branch)
+                .head(head_repo_branch)
+                .layer(ranged()),
+        )
         .route(
             "/{owner}/{repo}/tree/{branch}/{*path}",
-            get(handle_repo_path),
+            get(handle_repo_path).head(head_repo_path).layer(ranged()),
         )
         // blob routes (same as tree, just different github url pattern)
-        .route("/{owner}/{repo}/blob/{branch}", get(handle_repo_branch))
+        .route(
+            "/{owner}/{repo}/blob/{branch}",
+            get(handle_repo_branch)
+                .head(head_repo_branch)
+                .layer(ranged()),
+        )
         .route(
             "/{owner}/{repo}/blob/{branch}/{*path}",
-            get(handle_repo_path),
+            get(handle_repo_path).head(head_repo_path).layer(ranged()),
         )
         // releases/tags
+        .route("/{owner}/{repo}/releases", get(list_releases))
         .route(
             "/{owner}/{repo}/releases/tag/{tag}",
-            get(handle_repo_tag),
+            get(handle_repo_tag).layer(ranged()),
         )
         // gitlab routes (uses /-/ separator)
-        .route("/{owner}/{repo}/-/tree/{branch}", get(handle_repo_branch))
+        .route(
+            "/{owner}/{repo}/-/tree/{branch}",
+            get(handle_repo_branch)
+                .head(head_repo_branch)
+                .layer(ranged()),
+        )
         .route(
             "/{owner}/{repo}/-/tree/{branch}/{*path}",
-            get(handle_repo_path),
+            get(handle_repo_path).head(head_repo_path).layer(ranged()),
         )
-        .route("/{owner}/{repo}/-/blob/{branch}", get(handle_repo_branch))
         .route(
-            "/{owner}/{repo}/-/blob/{branch}/{*path}",
-            get(handle_repo_path),
+            "/{owner}/{repo}/-/blob/{branch}",
+            get(handle_repo_branch)
+                .head(head_repo_branch)
+                .layer(ranged()),
         )
         .route(
-            "/{owner}/{repo}/-/commit/{commit_sha}",
-            get(handle_commit),
+            "/{owner}/{repo}/-/blob/{branch}/{*path}",
+            get(handle_repo_path).head(head_repo_path).layer(ranged()),
         )
+        .route("/{owner}/{repo}/-/commit/{commit_sha}", get(handle_commit))
         .route(
             "/{owner}/{repo}/-/compare/{compare_spec}",
             get(handle_repo_compare),
@@ -896,10 +2264,22 @@ pub fn create_router() -> Router {
             "/{owner}/{repo}/-/merge_requests/{mr_number}",
             get(handle_mr),
         )
-        .with_state(state);
+        .with_state(state.clone());
+
+    let router = crate::access::layer(router, &state);
 
     router.layer(
         ServiceBuilder::new()
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(crate::telemetry::make_span)
+                    .on_response(DefaultOnResponse::new().level(tracing::Level::INFO)),
+            )
+            .layer(PropagateRequestIdLayer::x_request_id())
+            .layer(axum::middleware::from_fn(
+                crate::telemetry::scope_request_id,
+            ))
             .layer(SetResponseHeaderLayer::overriding(
                 axum::http::header::X_FRAME_OPTIONS,
                 axum::http::HeaderValue::from_static("DENY"),
@@ -908,15 +2288,56 @@ pub fn create_router() -> Router {
                 axum::http::header::X_CONTENT_TYPE_OPTIONS,
                 axum::http::HeaderValue::from_static("nosniff"),
             ))
-            .layer(CorsLayer::permissive())
+            .layer(cors_layer(&config))
             .layer(CompressionLayer::new()),
     )
 }
 
-pub async fn serve(addr: std::net::SocketAddr) -> anyhow::Result<()> {
-    let app = create_router();
-    let listener = tokio::net::TcpListener::bind(addr).await?;
+/// permissive by default, each of origins, methods and headers can be locked
+/// down for private deployments (values were validated at startup)
+fn cors_layer(config: &Config) -> CorsLayer {
+    use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any};
+
+    let any_origin = Config::allows_any(&config.cors_origins);
+    let any_method = Config::allows_any(&config.cors_methods);
+    let any_header = Config::allows_any(&config.cors_headers);
+    if any_origin && any_method && any_header {
+        return CorsLayer::permissive();
+    }
+
+    let origins = if any_origin {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(config.cors_origins.iter().filter_map(|o| o.parse().ok()))
+    };
+
+    // credentials cannot be combined with wildcards, echo the request instead
+    let methods = match (any_method, config.cors_allow_credentials) {
+        (true, false) => AllowMethods::from(Any),
+        (true, true) => AllowMethods::mirror_request(),
+        (false, _) => AllowMethods::list(config.cors_methods.iter().filter_map(|m| {
+            axum::http::Method::from_bytes(m.to_ascii_uppercase().as_bytes()).ok()
+        })),
+    };
+    let headers = match (any_header, config.cors_allow_credentials) {
+        (true, false) => AllowHeaders::from(Any),
+        (true, true) => AllowHeaders::mirror_request(),
+        (false, _) => AllowHeaders::list(config.cors_headers.iter().filter_map(|h| h.parse().ok())),
+    };
+
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .allow_credentials(config.cors_allow_credentials)
+}
+
+pub async fn serve(
+    addr: std::net::SocketAddr,
+    tls: Option<crate::tls::TlsConfig>,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let app = create_router(state);
     println!("HTTP server listening on {addr}");
-    axum::serve(listener, app).await?;
-    Ok(())
+    crate::tls::serve(addr, app, tls).await
 }
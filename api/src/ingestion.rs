@@ -1,33 +1,77 @@
 use githem_core::{
-    count_files, estimate_tokens, generate_tree, is_remote_url, normalize_source_url, FilterPreset,
-    FilterStats, IngestOptions, Ingester, IngestionCallback,
+    ingest_async, is_remote_url, normalize_source_url, FilterPreset, FilterStats, HeaderDetail,
+    IngestOptions, Ingester, IngestionCallback, RefNotFound, ScratchConfig, Transform,
 };
 
 use serde::{Deserialize, Serialize};
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// hosts repositories may come from, unset or empty allows any supported host
+static ALLOWED_HOSTS: OnceLock<Vec<String>> = OnceLock::new();
+/// bounds concurrent clones, unset means unlimited
+static WORKERS: OnceLock<Semaphore> = OnceLock::new();
+/// max_total_size and max_total_tokens of every ingestion
+static OUTPUT_LIMITS: OnceLock<(Option<usize>, Option<usize>)> = OnceLock::new();
+/// retries of every clone and fetch, the core default when unset
+static CLONE_RETRIES: OnceLock<u32> = OnceLock::new();
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IngestionParams {
     pub url: String,
     pub branch: Option<String>,
+    /// several branches into one output, instead of `branch`
+    #[serde(default)]
+    pub branches: Vec<String>,
     pub subpath: Option<String>,
     pub path_prefix: Option<String>,
+    /// more subtrees next to `path_prefix`
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// subtrees left out entirely
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
     #[serde(default)]
     pub include_patterns: Vec<String>,
     #[serde(default)]
     pub exclude_patterns: Vec<String>,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// run on top of the default license compression
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+    #[serde(default)]
+    pub header_detail: HeaderDetail,
     #[serde(default = "default_max_file_size")]
     pub max_file_size: usize,
+    #[serde(default)]
+    pub min_file_size: usize,
+    #[serde(default)]
+    pub max_lines: Option<usize>,
+    #[serde(default)]
+    pub max_files: Option<usize>,
     pub filter_preset: Option<String>,
     #[serde(default)]
     pub raw: bool,
+    /// ingest exactly at this commit instead of the branch head
+    #[serde(default)]
+    pub commit: Option<String>,
 }
 
 fn default_max_file_size() -> usize {
     10 * 1024 * 1024
 }
 
+/// boxed so that a missing ref can still be told apart, see `AppError::ingestion_failed`
+fn boxed(error: anyhow::Error) -> Box<dyn std::error::Error + Send + Sync> {
+    match error.downcast::<RefNotFound>() {
+        Ok(missing) => Box::new(missing),
+        Err(error) => error.into(),
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IngestionResult {
     pub id: String,
@@ -36,6 +80,17 @@ pub struct IngestionResult {
     pub content: String,
     pub metadata: RepositoryMetadata,
     pub filter_stats: Option<FilterStats>,
+    /// full id of the commit ingested, None when it has none (an empty
+    /// repository)
+    #[serde(default)]
+    pub commit: Option<String>,
+}
+
+/// a generated diff and the full id of the commit it leads to
+#[derive(Debug, Clone)]
+pub struct Diff {
+    pub content: String,
+    pub commit: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +101,12 @@ pub struct IngestionSummary {
     pub files_analyzed: usize,
     pub total_size: usize,
     pub estimated_tokens: usize,
+    /// files left out once the server's output limit was reached
+    #[serde(default)]
+    pub skipped_files: usize,
+    /// files left out by `max_files`
+    #[serde(default)]
+    pub omitted_files: usize,
     pub filter_preset: String,
     pub filtering_enabled: bool,
 }
@@ -55,59 +116,173 @@ pub struct RepositoryMetadata {
     pub url: String,
     pub default_branch: String,
     pub branches: Vec<String>,
+    /// bytes of the checked out files
     pub size: Option<u64>,
+    /// short id and summary of the head commit
+    #[serde(default)]
+    pub last_commit: Option<String>,
+    #[serde(default)]
+    pub remote_url: Option<String>,
+}
+
+/// a tag of a remote repository and the release published for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub tag: String,
+    pub commit: String,
+    /// when the hosting api knows a release of the tag
+    pub date: Option<String>,
 }
 
 pub struct IngestionService;
 
 impl IngestionService {
+    /// apply the deployment wide settings, once at startup
+    pub fn configure(config: &crate::config::Config) {
+        let _ = ALLOWED_HOSTS.set(config.allowed_hosts.clone());
+        if config.max_concurrent_ingestions > 0 {
+            let _ = WORKERS.set(Semaphore::new(config.max_concurrent_ingestions));
+        }
+        let bytes = (config.max_output_mb > 0).then(|| config.max_output_mb as usize * 1024 * 1024);
+        let tokens = (config.max_output_tokens > 0).then_some(config.max_output_tokens);
+        let _ = OUTPUT_LIMITS.set((bytes, tokens));
+        let _ = CLONE_RETRIES.set(config.clone_retries);
+        githem_core::configure_scratch(ScratchConfig {
+            root: config.scratch_dir.clone(),
+            max_bytes: config.scratch_max_mb.saturating_mul(1024 * 1024),
+        });
+    }
+
+    /// the defaults with the deployment's clone retries
+    fn diff_options() -> IngestOptions {
+        let mut options = IngestOptions::default();
+        if let Some(&retries) = CLONE_RETRIES.get() {
+            options.retries = retries;
+        }
+        options
+    }
+
+    /// waits for a free worker slot, held until the returned permit drops
+    async fn worker() -> Option<SemaphorePermit<'static>> {
+        WORKERS.get()?.acquire().await.ok()
+    }
+
+    fn check_host(url: &str) -> Result<(), String> {
+        let Some(allowed) = ALLOWED_HOSTS.get().filter(|hosts| !hosts.is_empty()) else {
+            return Ok(());
+        };
+
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_ascii_lowercase));
+        match host {
+            Some(host) if allowed.contains(&host) => Ok(()),
+            _ => Err(format!(
+                "Repositories from {} are not allowed on this server",
+                url
+            )),
+        }
+    }
+
+    #[tracing::instrument(name = "ingest", skip_all, fields(url = %params.url))]
     pub async fn ingest(
         params: IngestionParams,
     ) -> Result<IngestionResult, Box<dyn std::error::Error + Send + Sync>> {
+        Self::run(params, false).await
+    }
+
+    /// ingest again with new filters from the checkout a previous `ingest`
+    /// left behind, without fetching
+    #[tracing::instrument(name = "refilter", skip_all, fields(url = %params.url))]
+    pub async fn refilter(
+        params: IngestionParams,
+    ) -> Result<IngestionResult, Box<dyn std::error::Error + Send + Sync>> {
+        Self::run(params, true).await
+    }
+
+    async fn run(
+        params: IngestionParams,
+        refilter: bool,
+    ) -> Result<IngestionResult, Box<dyn std::error::Error + Send + Sync>> {
+        let started = Instant::now();
         let params = Self::normalize_params(params)?;
+        let _worker = Self::worker().await;
 
         let filter_preset = if params.raw {
-            Some(FilterPreset::Raw)
-        } else if let Some(preset) = Self::parse_filter_preset(params.filter_preset.as_deref()) {
-            Some(preset)
+            FilterPreset::Raw
         } else {
-            Some(FilterPreset::Standard)
-        };
-
-        let filter_preset_name = match filter_preset {
-            Some(FilterPreset::Raw) => "raw",
-            Some(FilterPreset::Standard) => "standard",
-            Some(FilterPreset::CodeOnly) => "code-only",
-            Some(FilterPreset::Minimal) => "minimal",
-            None => "none",
+            Self::parse_filter_preset(params.filter_preset.as_deref())
+                .unwrap_or(FilterPreset::Standard)
         };
 
-        let options = IngestOptions {
-            include_patterns: params.include_patterns.clone(),
-            exclude_patterns: params.exclude_patterns.clone(),
-            max_file_size: params.max_file_size,
-            include_untracked: false,
-            branch: params.branch.clone(),
-            path_prefix: params.path_prefix.clone(),
-            filter_preset,
-            apply_default_filters: false,
-        };
-
-        let mut ingester = if is_remote_url(&params.url) {
-            Ingester::from_url_cached(&params.url, options)?
+        let filter_preset_name = filter_preset.as_str();
+
+        let mut builder = IngestOptions::builder()
+            .preset(filter_preset)
+            .includes(&params.include_patterns)
+            .excludes(&params.exclude_patterns)
+            .extensions(&params.extensions)
+            .max_file_size(params.max_file_size)
+            .min_file_size(params.min_file_size)
+            .max_lines(params.max_lines)
+            .max_files(params.max_files);
+        if let Some(&(bytes, tokens)) = OUTPUT_LIMITS.get() {
+            builder = builder.max_total_size(bytes).max_total_tokens(tokens);
+        }
+        if let Some(&retries) = CLONE_RETRIES.get() {
+            builder = builder.retries(retries);
+        }
+        if let Some(branch) = &params.branch {
+            builder = builder.branch(branch);
+        }
+        builder = builder.branches(&params.branches);
+        if let Some(prefix) = &params.path_prefix {
+            builder = builder.path_prefix(prefix);
+        }
+        builder = builder
+            .paths(&params.paths)
+            .exclude_paths(&params.exclude_paths);
+        for &transform in &params.transforms {
+            builder = builder.transform(transform);
+        }
+        builder = builder.header_detail(params.header_detail);
+        let options = builder.build()?;
+
+        let ingester = if let Some(ref commit) = params.commit {
+            if refilter {
+                return Err("Pinned commits are not kept for refiltering".into());
+            }
+            Ingester::from_url_at(&params.url, commit, options).map_err(boxed)?
+        } else if is_remote_url(&params.url) && refilter {
+            Ingester::from_checkout(&params.url, options)?
+        } else if is_remote_url(&params.url) {
+            Ingester::from_url_cached(&params.url, options).map_err(boxed)?
         } else {
             let path = std::path::PathBuf::from(&params.url);
             Ingester::from_path(&path, options)?
         };
 
         let filter_stats = ingester.get_filter_stats().ok();
+        let head_branch = ingester.head_branch();
+        // the output spans several commits, none of them is the one ingested
+        let commit = ingester
+            .head_commit()
+            .filter(|_| params.branches.is_empty());
+        let repository = ingester.get_metadata().ok();
+        let mut default_branch = ingester.default_branch();
+        if default_branch.is_none() && is_remote_url(&params.url) {
+            let url = params.url.clone();
+            default_branch = tokio::task::spawn_blocking(move || {
+                githem_core::get_remote_default_branch(&url).ok()
+            })
+            .await
+            .ok()
+            .flatten();
+        }
 
+        // written from a blocking thread, the runtime stays free meanwhile
         let mut content = Vec::new();
-        if ingester.cache_key.is_some() {
-            ingester.ingest_cached(&mut content)?;
-        } else {
-            ingester.ingest(&mut content)?;
-        }
+        let (_, report) = ingest_async(ingester, &mut content).await?;
 
         let content_str = String::from_utf8(content)?;
 
@@ -117,29 +292,66 @@ impl IngestionService {
             rand::random::<u32>()
         );
 
-        let tree = generate_tree(&content_str);
-        let files_analyzed = count_files(&content_str);
-        let total_size = content_str.len();
-        let estimated_tokens = estimate_tokens(&content_str);
+        let tree = report.tree();
+        let files_analyzed = report.file_count();
+        let total_size = report.total_size();
+        let estimated_tokens = report.total_tokens();
 
         let summary = IngestionSummary {
             repository: params.url.clone(),
-            branch: params.branch.unwrap_or_else(|| "main".to_string()),
+            branch: params
+                .commit
+                .clone()
+                .or(params.branch)
+                .or((!params.branches.is_empty()).then(|| params.branches.join(",")))
+                .or(head_branch)
+                .or(default_branch.clone())
+                .unwrap_or_else(|| "HEAD".to_string()),
             subpath: params.path_prefix.clone(),
             files_analyzed,
             total_size,
             estimated_tokens,
+            skipped_files: report.skipped.len(),
+            omitted_files: report.omitted.len(),
             filter_preset: filter_preset_name.to_string(),
-            filtering_enabled: filter_preset != Some(FilterPreset::Raw),
+            filtering_enabled: filter_preset != FilterPreset::Raw,
         };
 
+        let default_branch = default_branch.unwrap_or_else(|| summary.branch.clone());
+        let (mut branches, size, last_commit, remote_url) = repository
+            .map(|r| (r.branches, r.size, r.last_commit, r.remote_url))
+            .unwrap_or_default();
+        let ingested: Vec<&String> = if params.branches.is_empty() {
+            params
+                .commit
+                .is_none()
+                .then_some(&summary.branch)
+                .into_iter()
+                .collect()
+        } else {
+            params.branches.iter().collect()
+        };
+        for branch in std::iter::once(&default_branch).chain(ingested) {
+            if !branches.contains(branch) {
+                branches.push(branch.clone());
+            }
+        }
         let metadata = RepositoryMetadata {
             url: params.url,
-            default_branch: "main".to_string(),
-            branches: vec!["main".to_string()],
-            size: Some(total_size as u64),
+            default_branch,
+            branches,
+            size,
+            last_commit,
+            remote_url,
         };
 
+        tracing::info!(
+            files = files_analyzed,
+            bytes = total_size,
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "ingested"
+        );
+
         Ok(IngestionResult {
             id,
             summary,
@@ -147,6 +359,7 @@ impl IngestionService {
             content: content_str,
             metadata,
             filter_stats,
+            commit,
         })
     }
 
@@ -164,17 +377,13 @@ impl IngestionService {
         if !is_remote_url(&normalized_url) && !std::path::Path::new(&normalized_url).exists() {
             return Err("Invalid URL or path".to_string());
         }
+        Self::check_host(&normalized_url)?;
 
         Ok(IngestionParams {
             url: normalized_url,
-            subpath: params.subpath,
             branch: final_branch,
             path_prefix: final_path_prefix,
-            include_patterns: params.include_patterns,
-            exclude_patterns: params.exclude_patterns,
-            max_file_size: params.max_file_size,
-            filter_preset: params.filter_preset,
-            raw: params.raw,
+            ..params
         })
     }
 
@@ -184,6 +393,8 @@ impl IngestionService {
             "standard" => Some(FilterPreset::Standard),
             "code-only" | "code_only" | "codeonly" => Some(FilterPreset::CodeOnly),
             "minimal" => Some(FilterPreset::Minimal),
+            "docs-only" | "docs_only" | "docsonly" | "docs" => Some(FilterPreset::DocsOnly),
+            "tests-only" | "tests_only" | "testsonly" | "tests" => Some(FilterPreset::TestsOnly),
             _ => None,
         })
     }
@@ -195,18 +406,64 @@ impl IngestionService {
         _include_patterns: Option<&str>,
         _exclude_patterns: Option<&str>,
         context_lines: Option<u32>,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<Diff, Box<dyn std::error::Error + Send + Sync>> {
         if !is_remote_url(url) {
             return Err("Diff generation requires a remote URL".into());
         }
+        Self::check_host(url)?;
+        let _worker = Self::worker().await;
 
         // use optimized clone that only fetches the two refs needed
-        let repo = githem_core::clone_for_compare(url, base, head)?;
-        let options = IngestOptions::default();
-        let ingester = Ingester::new(repo, options);
+        let ingester = Ingester::from_url_compare(url, base, head, Self::diff_options())?;
 
-        let diff_content = ingester.generate_diff(base, head, context_lines)?;
-        Ok(diff_content)
+        let content = ingester.generate_diff(base, head, context_lines)?;
+        let commit = ingester.resolve_commit(head);
+        Ok(Diff { content, commit })
+    }
+
+    /// the tags of a remote repository from ls-remote, newest release first,
+    /// then the tags without a release in reverse name order
+    pub async fn list_releases(
+        url: &str,
+    ) -> Result<Vec<Release>, Box<dyn std::error::Error + Send + Sync>> {
+        if !is_remote_url(url) {
+            return Err("Listing releases requires a remote URL".into());
+        }
+        Self::check_host(url)?;
+
+        let url = url.to_string();
+        let (refs, dates) = tokio::task::spawn_blocking(move || {
+            githem_core::list_remote_refs(&url)
+                .map(|refs| (refs, crate::limits::lookup_release_dates(&url)))
+        })
+        .await??;
+
+        let mut releases: Vec<Release> = githem_core::tags_of(&refs)
+            .into_iter()
+            .map(|tag| Release {
+                date: dates.get(&tag.name).cloned(),
+                tag: tag.name,
+                commit: tag.commit,
+            })
+            .collect();
+        releases.sort_by(|a, b| (&b.date, &b.tag).cmp(&(&a.date, &a.tag)));
+        Ok(releases)
+    }
+
+    /// branches, tags and HEAD of a remote repository, read without cloning
+    #[cfg(feature = "grpc")]
+    pub async fn list_refs(
+        url: &str,
+    ) -> Result<Vec<githem_core::RemoteRef>, Box<dyn std::error::Error + Send + Sync>> {
+        if !is_remote_url(url) {
+            return Err("Listing refs requires a remote URL".into());
+        }
+        Self::check_host(url)?;
+
+        let url = url.to_string();
+        let refs =
+            tokio::task::spawn_blocking(move || githem_core::list_remote_refs(&url)).await??;
+        Ok(refs)
     }
 
     pub async fn generate_commit_diff(
@@ -215,17 +472,18 @@ impl IngestionService {
         _include_patterns: Option<&str>,
         _exclude_patterns: Option<&str>,
         context_lines: Option<u32>,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<Diff, Box<dyn std::error::Error + Send + Sync>> {
         if !is_remote_url(url) {
             return Err("Commit diff generation requires a remote URL".into());
         }
+        Self::check_host(url)?;
+        let _worker = Self::worker().await;
 
-        let repo = githem_core::clone_for_commit(url, commit_sha)?;
-        let options = IngestOptions::default();
-        let ingester = Ingester::new(repo, options);
+        let ingester = Ingester::from_url_commit(url, commit_sha, Self::diff_options())?;
 
-        let diff_content = ingester.generate_commit_diff(commit_sha, context_lines)?;
-        Ok(diff_content)
+        let content = ingester.generate_commit_diff(commit_sha, context_lines)?;
+        let commit = ingester.resolve_commit(commit_sha);
+        Ok(Diff { content, commit })
     }
 
     pub async fn generate_pr_diff(
@@ -234,16 +492,19 @@ impl IngestionService {
         _include_patterns: Option<&str>,
         _exclude_patterns: Option<&str>,
         context_lines: Option<u32>,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        let options = IngestOptions::default();
+    ) -> Result<Diff, Box<dyn std::error::Error + Send + Sync>> {
+        Self::check_host(url)?;
+        let _worker = Self::worker().await;
+        let options = Self::diff_options();
         let ingester = if is_remote_url(url) {
             Ingester::from_url(url, options)?
         } else {
             return Err("PR diff generation requires a remote URL".into());
         };
 
-        let diff_content = ingester.generate_pr_diff(pr_number, context_lines)?;
-        Ok(diff_content)
+        let content = ingester.generate_pr_diff(pr_number, context_lines)?;
+        let commit = ingester.resolve_commit(&format!("refs/pull/{pr_number}/head"));
+        Ok(Diff { content, commit })
     }
 
     pub async fn generate_mr_diff(
@@ -252,16 +513,19 @@ impl IngestionService {
         _include_patterns: Option<&str>,
         _exclude_patterns: Option<&str>,
         context_lines: Option<u32>,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        let options = IngestOptions::default();
+    ) -> Result<Diff, Box<dyn std::error::Error + Send + Sync>> {
+        Self::check_host(url)?;
+        let _worker = Self::worker().await;
+        let options = Self::diff_options();
         let ingester = if is_remote_url(url) {
             Ingester::from_url(url, options)?
         } else {
             return Err("MR diff generation requires a remote URL".into());
         };
 
-        let diff_content = ingester.generate_mr_diff(mr_number, context_lines)?;
-        Ok(diff_content)
+        let content = ingester.generate_mr_diff(mr_number, context_lines)?;
+        let commit = ingester.resolve_commit(&format!("refs/merge-requests/{mr_number}/head"));
+        Ok(Diff { content, commit })
     }
 }
 
@@ -276,11 +540,29 @@ where
 #[derive(Debug, Serialize)]
 #[serde(tag = "type")]
 pub enum WebSocketMessage {
-    Progress { stage: String, message: String },
-    File { path: String, content: String },
-    Complete { files: usize, bytes: usize },
-    Error { message: String },
-    FilterStats { stats: FilterStats },
+    Progress {
+        stage: String,
+        message: String,
+    },
+    File {
+        path: String,
+        content: String,
+    },
+    Complete {
+        files: usize,
+        bytes: usize,
+        /// branch ingested and the repository, unknown to callbacks
+        #[serde(skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        metadata: Option<RepositoryMetadata>,
+    },
+    Error {
+        message: String,
+    },
+    FilterStats {
+        stats: FilterStats,
+    },
 }
 
 impl<F> IngestionCallback for WebSocketCallback<F>
@@ -302,7 +584,12 @@ where
     }
 
     fn on_complete(&mut self, files: usize, bytes: usize) {
-        (self.send_fn)(WebSocketMessage::Complete { files, bytes });
+        (self.send_fn)(WebSocketMessage::Complete {
+            files,
+            bytes,
+            branch: None,
+            metadata: None,
+        });
     }
 
     fn on_error(&mut self, error: &str) {
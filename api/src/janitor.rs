@@ -0,0 +1,149 @@
+use crate::config::Config;
+use crate::http::AppState;
+use serde::Serialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// disk use after the last clean-up, shown in /health
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiskStats {
+    pub runs: u64,
+    /// unix seconds of the last clean-up
+    pub last_run: Option<u64>,
+    /// freed by the last clean-up
+    pub last_reclaimed_bytes: u64,
+    /// freed since startup
+    pub reclaimed_bytes: u64,
+    pub scratch_bytes: u64,
+    pub checkouts_bytes: u64,
+    pub results_bytes: u64,
+    pub checkouts_max_bytes: u64,
+    pub results_max_bytes: u64,
+}
+
+/// what one clean-up removed, per kind of storage
+struct Sweep {
+    scratch: (usize, u64),
+    checkouts: (usize, u64),
+    results: (usize, u64),
+}
+
+impl Sweep {
+    fn reclaimed(&self) -> u64 {
+        self.scratch.1 + self.checkouts.1 + self.results.1
+    }
+}
+
+/// keeps orphaned clones, persistent checkouts and stored results within
+/// their disk budgets, least recently used first
+pub fn spawn_janitor(state: AppState) {
+    let interval = state.config.janitor_interval_secs;
+    if interval == 0 {
+        return;
+    }
+    info!("Cleaning up disk storage every {}s", interval);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let config = state.config.clone();
+            let min_age = scratch_min_age(&state);
+            match tokio::task::spawn_blocking(move || clean_up(&config, min_age)).await {
+                Ok((sweep, usage)) => record(&state, sweep, usage).await,
+                Err(e) => warn!("Disk clean-up task failed: {}", e),
+            }
+
+            // ids whose results expired cannot resolve anymore
+            let expired = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+                .saturating_sub(state.config.cache_expire_secs);
+            if let Err(e) = state.store.prune_results(expired).await {
+                warn!("Disk clean-up: pruning result ids failed: {:#}", e);
+            }
+        }
+    });
+}
+
+/// nothing untouched for less than the ingestion timeout can be orphaned, the
+/// ingestion could still be running; the admin api may have raised it
+pub fn scratch_min_age(state: &AppState) -> Duration {
+    state
+        .limits
+        .timeout()
+        .max(Duration::from_secs(state.config.ingest_timeout_secs))
+}
+
+/// bytes in use afterwards: scratch, checkouts, results
+type Usage = (u64, u64, u64);
+
+fn clean_up(config: &Config, min_age: Duration) -> (Sweep, Usage) {
+    let scratch = githem_core::sweep_scratch(min_age);
+
+    let (mut checkouts, mut checkouts_bytes) = ((0, 0), 0);
+    match githem_core::RepositoryCache::new() {
+        Ok(mut cache) => {
+            if config.disk_cache_max_mb > 0 {
+                match cache.evict_to(config.disk_cache_max_mb.saturating_mul(1024 * 1024)) {
+                    Ok(evicted) => checkouts = evicted,
+                    Err(e) => warn!("Disk clean-up: evicting checkouts failed: {}", e),
+                }
+            }
+            checkouts_bytes = cache.disk_usage();
+        }
+        Err(e) => warn!("Disk clean-up: repository cache unavailable: {}", e),
+    }
+
+    let (mut results, mut results_bytes) = ((0, 0), 0);
+    match githem_core::DiskBackend::shared() {
+        Ok(store) => {
+            if config.disk_results_max_mb > 0 {
+                match store.evict_to(config.disk_results_max_mb.saturating_mul(1024 * 1024)) {
+                    Ok(evicted) => results = evicted,
+                    Err(e) => warn!("Disk clean-up: evicting results failed: {}", e),
+                }
+            }
+            results_bytes = store.usage();
+        }
+        Err(e) => warn!("Disk clean-up: result store unavailable: {}", e),
+    }
+
+    let sweep = Sweep {
+        scratch,
+        checkouts,
+        results,
+    };
+    (
+        sweep,
+        (githem_core::scratch_usage(), checkouts_bytes, results_bytes),
+    )
+}
+
+async fn record(state: &AppState, sweep: Sweep, (scratch, checkouts, results): Usage) {
+    let reclaimed = sweep.reclaimed();
+    if reclaimed > 0 {
+        info!(
+            "Disk clean-up reclaimed {} MB: {} scratch directories, {} checkouts, {} results",
+            reclaimed / 1024 / 1024,
+            sweep.scratch.0,
+            sweep.checkouts.0,
+            sweep.results.0
+        );
+    }
+
+    let mut stats = state.disk.write().await;
+    stats.runs += 1;
+    stats.last_run = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs());
+    stats.last_reclaimed_bytes = reclaimed;
+    stats.reclaimed_bytes += reclaimed;
+    stats.scratch_bytes = scratch;
+    stats.checkouts_bytes = checkouts;
+    stats.results_bytes = results;
+    stats.checkouts_max_bytes = state.config.disk_cache_max_mb.saturating_mul(1024 * 1024);
+    stats.results_max_bytes = state.config.disk_results_max_mb.saturating_mul(1024 * 1024);
+}
@@ -1,5 +1,21 @@
+pub mod access;
+pub mod admin;
 pub mod cache;
+pub mod config;
+pub mod dashboard;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod http;
 pub mod ingestion;
+pub mod janitor;
+pub mod limits;
 pub mod metrics;
+pub mod openapi;
+pub mod range;
+pub mod refresh;
+pub mod share;
+pub mod store;
+pub mod telemetry;
+pub mod tls;
+pub mod validation;
 pub mod websocket;
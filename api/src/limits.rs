@@ -0,0 +1,242 @@
+use crate::config::Config;
+use crate::http::AppError;
+use crate::ingestion::IngestionParams;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REMEMBERED_SIZES: usize = 10_000;
+
+/// per-deployment limits on what a single request may ingest,
+/// adjustable at runtime through the admin api
+#[derive(Clone)]
+pub struct IngestLimits {
+    timeout_secs: Arc<AtomicU64>,
+    /// repositories above this many MB are refused, also when the request is
+    /// narrowed to a path as the clone is not, 0 disables the check
+    max_repo_size_mb: Arc<AtomicU64>,
+    /// requests per client and minute, 0 disables rate limiting
+    rate_limit_per_minute: Arc<AtomicU32>,
+    /// size in KB per repository url, None when the host could not tell us
+    sizes: Arc<RwLock<HashMap<String, Option<u64>>>>,
+}
+
+impl IngestLimits {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            timeout_secs: Arc::new(AtomicU64::new(config.ingest_timeout_secs)),
+            max_repo_size_mb: Arc::new(AtomicU64::new(config.max_repo_size_mb)),
+            rate_limit_per_minute: Arc::new(AtomicU32::new(config.rate_limit_per_minute)),
+            sizes: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.load(Ordering::Relaxed))
+    }
+
+    pub fn set_timeout(&self, timeout: Duration) {
+        self.timeout_secs
+            .store(timeout.as_secs(), Ordering::Relaxed);
+    }
+
+    pub fn max_repo_size_mb(&self) -> u64 {
+        self.max_repo_size_mb.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_repo_size_mb(&self, max_repo_size_mb: u64) {
+        self.max_repo_size_mb
+            .store(max_repo_size_mb, Ordering::Relaxed);
+    }
+
+    pub fn rate_limit_per_minute(&self) -> u32 {
+        self.rate_limit_per_minute.load(Ordering::Relaxed)
+    }
+
+    pub fn set_rate_limit_per_minute(&self, per_minute: u32) {
+        self.rate_limit_per_minute
+            .store(per_minute, Ordering::Relaxed);
+    }
+
+    /// refuse oversized repositories before cloning them, whatever the
+    /// filters: narrowing filters the output, the clone is still the whole
+    /// repository
+    pub async fn check(&self, params: &IngestionParams) -> Result<(), AppError> {
+        let limit_mb = self.max_repo_size_mb();
+        if limit_mb == 0 {
+            return Ok(());
+        }
+
+        let Some(size_kb) = self.repository_size_kb(&params.url).await else {
+            return Ok(());
+        };
+
+        let size_mb = size_kb / 1024;
+        if size_mb > limit_mb {
+            return Err(AppError::TooLarge { size_mb, limit_mb });
+        }
+
+        Ok(())
+    }
+
+    /// size the hosting api reports for the repository, remembered per url
+    pub async fn repository_size_kb(&self, url: &str) -> Option<u64> {
+        if let Some(size) = self.sizes.read().await.get(url) {
+            return *size;
+        }
+
+        let lookup_url = url.to_string();
+        // an unreachable api never blocks ingestion, the timeout still applies
+        let size = tokio::task::spawn_blocking(move || lookup_repository_size_kb(&lookup_url))
+            .await
+            .ok()
+            .flatten();
+
+        let mut sizes = self.sizes.write().await;
+        if sizes.len() >= MAX_REMEMBERED_SIZES {
+            sizes.clear();
+        }
+        sizes.insert(url.to_string(), size);
+        size
+    }
+}
+
+/// `owner/repo` path of a github or gitlab url
+fn hosted_repository(url: &str) -> Option<(&'static str, String)> {
+    let parsed = url::Url::parse(url).ok()?;
+    let host = match parsed.host_str()? {
+        "github.com" | "www.github.com" => "github",
+        "gitlab.com" | "www.gitlab.com" => "gitlab",
+        _ => return None,
+    };
+
+    let path = parsed.path().trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if path.split('/').count() < 2 {
+        return None;
+    }
+
+    Some((host, path.to_string()))
+}
+
+/// repository size as reported by the hosting api, GITHUB_TOKEN / GITLAB_TOKEN
+/// raise the rate limit (and are required for gitlab statistics)
+fn lookup_repository_size_kb(url: &str) -> Option<u64> {
+    #[derive(Deserialize)]
+    struct GitHubRepository {
+        /// kilobytes
+        size: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct GitLabStatistics {
+        /// bytes
+        repository_size: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct GitLabProject {
+        statistics: Option<GitLabStatistics>,
+    }
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(LOOKUP_TIMEOUT))
+        .build()
+        .into();
+
+    match hosted_repository(url)? {
+        ("github", path) => {
+            let mut request = agent
+                .get(&format!("https://api.github.com/repos/{path}"))
+                .header("User-Agent", "githem")
+                .header("Accept", "application/vnd.github+json");
+            if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+                request = request.header("Authorization", &format!("Bearer {token}"));
+            }
+            let repository: GitHubRepository = request.call().ok()?.body_mut().read_json().ok()?;
+            Some(repository.size)
+        }
+        (_, path) => {
+            let encoded = path.replace('/', "%2F");
+            let mut request = agent
+                .get(&format!(
+                    "https://gitlab.com/api/v4/projects/{encoded}?statistics=true"
+                ))
+                .header("User-Agent", "githem");
+            if let Ok(token) = std::env::var("GITLAB_TOKEN") {
+                request = request.header("PRIVATE-TOKEN", &token);
+            }
+            let project: GitLabProject = request.call().ok()?.body_mut().read_json().ok()?;
+            Some(project.statistics?.repository_size / 1024)
+        }
+    }
+}
+
+/// publication dates of the releases the hosting api lists, by tag name,
+/// empty when it can't be reached
+pub(crate) fn lookup_release_dates(url: &str) -> HashMap<String, String> {
+    #[derive(Deserialize)]
+    struct GitHubRelease {
+        tag_name: String,
+        published_at: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct GitLabRelease {
+        tag_name: String,
+        released_at: Option<String>,
+    }
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(LOOKUP_TIMEOUT))
+        .build()
+        .into();
+
+    match hosted_repository(url) {
+        Some(("github", path)) => {
+            let mut request = agent
+                .get(&format!(
+                    "https://api.github.com/repos/{path}/releases?per_page=100"
+                ))
+                .header("User-Agent", "githem")
+                .header("Accept", "application/vnd.github+json");
+            if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+                request = request.header("Authorization", &format!("Bearer {token}"));
+            }
+            let releases: Option<Vec<GitHubRelease>> = request
+                .call()
+                .ok()
+                .and_then(|mut r| r.body_mut().read_json().ok());
+            releases
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|r| Some((r.tag_name, r.published_at?)))
+                .collect()
+        }
+        Some((_, path)) => {
+            let encoded = path.replace('/', "%2F");
+            let mut request = agent
+                .get(&format!(
+                    "https://gitlab.com/api/v4/projects/{encoded}/releases?per_page=100"
+                ))
+                .header("User-Agent", "githem");
+            if let Ok(token) = std::env::var("GITLAB_TOKEN") {
+                request = request.header("PRIVATE-TOKEN", &token);
+            }
+            let releases: Option<Vec<GitLabRelease>> = request
+                .call()
+                .ok()
+                .and_then(|mut r| r.body_mut().read_json().ok());
+            releases
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|r| Some((r.tag_name, r.released_at?)))
+                .collect()
+        }
+        None => HashMap::new(),
+    }
+}
@@ -1,40 +1,113 @@
+mod access;
+mod admin;
 mod cache;
+mod config;
+mod dashboard;
+#[cfg(feature = "grpc")]
+mod grpc;
 mod http;
 mod ingestion;
+mod janitor;
+mod limits;
 mod metrics;
+mod openapi;
+mod range;
+mod refresh;
+mod share;
+mod store;
+mod telemetry;
+mod tls;
+mod validation;
 mod websocket;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tracing::info;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "githem_api=info,tower_http=info".into()),
-        )
-        .init();
+    telemetry::init()?;
 
-    let http_port = std::env::var("HTTP_PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(42069);
+    // HTTPS_PROXY / NO_PROXY / GITHEM_CA_BUNDLE for all outbound fetches
+    let network = githem_core::NetworkConfig::from_env();
+    if network.proxy.is_some() {
+        info!("Outbound fetches go through the configured proxy");
+    }
+    githem_core::configure_network(network)?;
 
-    let http_addr = SocketAddr::from(([0, 0, 0, 0], http_port));
+    let config = Arc::new(config::Config::from_env()?);
+    if let Some(ref state) = config.state_dir {
+        std::fs::create_dir_all(state)
+            .with_context(|| format!("Failed to create state directory {}", state.display()))?;
+        info!("Keeping state in {}", state.display());
+    }
+    if let Some(dir) = config.cache_dir() {
+        githem_core::configure_cache_dir(dir);
+    }
+    ingestion::IngestionService::configure(&config);
 
-    let ws_port = std::env::var("WS_PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(42070);
+    // one state for every listener, so admin changes to the limits reach all of them
+    let state = http::AppState::new(config.clone());
 
-    let ws_addr = SocketAddr::from(([0, 0, 0, 0], ws_port));
+    // nothing of ours is running yet, but another instance may share the
+    // scratch root, so only clear what outlived the ingestion timeout
+    let (swept, _) = githem_core::sweep_scratch(janitor::scratch_min_age(&state));
+    if swept > 0 {
+        info!("Removed {} orphaned scratch directories", swept);
+    }
 
-    info!("Starting githem-api HTTP on http://{}", http_addr);
-    info!("Starting githem-api WebSocket on ws://{}", ws_addr);
+    let http_addr = SocketAddr::from(([0, 0, 0, 0], config.http_port));
 
-    tokio::try_join!(http::serve(http_addr), websocket::serve(ws_addr))?;
+    // websockets are served at /ws on the http port, the separate listener
+    // stays for existing clients and is disabled with WS_PORT=0
+    let ws_port = config.ws_port;
+
+    let tls = tls::TlsConfig::from_env().await?;
+    let (http_scheme, ws_scheme) = if tls.is_some() {
+        ("https", "wss")
+    } else {
+        ("http", "ws")
+    };
+
+    info!(
+        "Starting githem-api HTTP on {}://{}",
+        http_scheme, http_addr
+    );
+    info!(
+        "Starting githem-api WebSocket on {}://{}/ws",
+        ws_scheme, http_addr
+    );
+
+    let legacy_ws = async {
+        if ws_port == 0 {
+            return Ok(());
+        }
+        let ws_addr = SocketAddr::from(([0, 0, 0, 0], ws_port));
+        info!(
+            "Starting legacy WebSocket listener on {}://{}",
+            ws_scheme, ws_addr
+        );
+        websocket::serve(ws_addr, tls.clone(), state.clone()).await
+    };
+
+    #[cfg(feature = "grpc")]
+    let grpc = async {
+        if config.grpc_port == 0 {
+            return Ok(());
+        }
+        let grpc_addr = SocketAddr::from(([0, 0, 0, 0], config.grpc_port));
+        info!("Starting githem-api gRPC on {}", grpc_addr);
+        grpc::serve(grpc_addr, tls.clone(), state.clone()).await
+    };
+    #[cfg(not(feature = "grpc"))]
+    let grpc = async { Ok::<(), anyhow::Error>(()) };
+
+    tokio::try_join!(
+        http::serve(http_addr, tls.clone(), state.clone()),
+        legacy_ws,
+        grpc
+    )?;
 
     Ok(())
 }
@@ -32,9 +32,40 @@ pub struct HourlyStats {
     pub hour: u64,
     pub requests: u64,
     pub cache_hits: u64,
+    #[serde(default)]
+    pub errors: u64,
     pub bytes: u64,
 }
 
+impl Metrics {
+    /// the stats of the current hour, dropping those older than a day
+    fn this_hour(&mut self) -> &mut HourlyStats {
+        let hour = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            / 3600;
+
+        // keep only last 24 hours
+        let cutoff = hour.saturating_sub(24);
+        self.hourly_stats.retain(|s| s.hour > cutoff);
+
+        match self.hourly_stats.iter().position(|s| s.hour == hour) {
+            Some(i) => &mut self.hourly_stats[i],
+            None => {
+                self.hourly_stats.push(HourlyStats {
+                    hour,
+                    requests: 0,
+                    cache_hits: 0,
+                    errors: 0,
+                    bytes: 0,
+                });
+                self.hourly_stats.last_mut().unwrap()
+            }
+        }
+    }
+}
+
 pub struct MetricsCollector {
     metrics: Arc<RwLock<Metrics>>,
     response_times: Arc<RwLock<Vec<Duration>>>,
@@ -88,40 +119,31 @@ impl MetricsCollector {
             },
         );
 
-        // update hourly stats
-        let hour = now / 3600;
-        if let Some(stat) = metrics.hourly_stats.iter_mut().find(|s| s.hour == hour) {
-            stat.requests += 1;
-            stat.bytes += bytes;
-        } else {
-            metrics.hourly_stats.push(HourlyStats {
-                hour,
-                requests: 1,
-                cache_hits: 0,
-                bytes,
-            });
-        }
-
-        // keep only last 24 hours
-        let cutoff = hour.saturating_sub(24);
-        metrics.hourly_stats.retain(|s| s.hour > cutoff);
+        let stat = metrics.this_hour();
+        stat.requests += 1;
+        stat.bytes += bytes;
     }
 
-    pub async fn record_cache_hit(&self) {
+    /// count a request served from cache towards the repository's popularity
+    pub async fn record_repo_hit(&self, repo_url: &str) {
         let mut metrics = self.metrics.write().await;
-        metrics.cache_hits += 1;
-
-        let hour = std::time::SystemTime::now()
+        let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
-            .as_secs()
-            / 3600;
+            .as_secs();
 
-        if let Some(stat) = metrics.hourly_stats.iter_mut().find(|s| s.hour == hour) {
-            stat.cache_hits += 1;
+        if let Some(repo) = metrics.repositories.get_mut(repo_url) {
+            repo.request_count += 1;
+            repo.last_accessed = now;
         }
     }
 
+    pub async fn record_cache_hit(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.cache_hits += 1;
+        metrics.this_hour().cache_hits += 1;
+    }
+
     pub async fn record_cache_miss(&self) {
         let mut metrics = self.metrics.write().await;
         metrics.cache_misses += 1;
@@ -130,6 +152,7 @@ impl MetricsCollector {
     pub async fn record_error(&self) {
         let mut metrics = self.metrics.write().await;
         metrics.errors += 1;
+        metrics.this_hour().errors += 1;
     }
 
     pub async fn record_response_time(&self, duration: Duration) {
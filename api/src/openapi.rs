@@ -0,0 +1,44 @@
+use crate::http::{ErrorResponse, IngestRequest, IngestResponse, QueryParams, ShareRequest};
+use utoipa::OpenApi;
+
+/// openapi document for every public route, served at /api/openapi.json
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "githem",
+        description = "convert git repositories to llm-ready text"
+    ),
+    paths(
+        crate::http::health,
+        crate::http::api_info,
+        crate::http::get_metrics,
+        crate::http::get_top_repos,
+        crate::http::get_cache_stats,
+        crate::http::ingest_repository,
+        crate::http::get_result,
+        crate::http::download_content,
+        crate::http::handle_repo,
+        crate::http::head_repo,
+        crate::http::handle_repo_branch,
+        crate::http::handle_repo_path,
+        crate::http::handle_repo_tag,
+        crate::http::list_releases,
+        crate::http::handle_repo_at,
+        crate::http::handle_repo_at_path,
+        crate::http::handle_search,
+        crate::http::handle_commit,
+        crate::http::handle_pr,
+        crate::http::handle_mr,
+        crate::http::handle_repo_compare,
+        crate::http::create_share,
+        crate::http::handle_share,
+    ),
+    components(schemas(IngestRequest, IngestResponse, ErrorResponse, QueryParams, ShareRequest)),
+    tags(
+        (name = "ingest", description = "repository content"),
+        (name = "diff", description = "commits, pull requests and comparisons"),
+        (name = "share", description = "commit-pinned share links"),
+        (name = "status", description = "health, metrics and cache statistics"),
+    )
+)]
+pub struct ApiDoc;
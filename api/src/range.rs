@@ -0,0 +1,132 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// a single `bytes=` range of a body of `len` bytes as an inclusive span,
+/// Err(()) when it lies outside the body; None for anything this server
+/// does not serve partially (other units, several ranges)
+fn parse_range(value: &str, len: usize) -> Option<Result<(usize, usize), ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let span = if start.is_empty() {
+        // `bytes=-500`, the last 500 bytes
+        let suffix: usize = end.parse().ok()?;
+        if suffix == 0 || len == 0 {
+            return Some(Err(()));
+        }
+        (len.saturating_sub(suffix), len - 1)
+    } else {
+        let start: usize = start.parse().ok()?;
+        let end = match end {
+            "" => len.saturating_sub(1),
+            end => end.parse::<usize>().ok()?.min(len.saturating_sub(1)),
+        };
+        if start >= len || end < start {
+            return Some(Err(()));
+        }
+        (start, end)
+    };
+    Some(Ok(span))
+}
+
+/// `Range: bytes=...` on a route's full responses, so interrupted
+/// downloads of large outputs resume and clients can fetch just the tree
+/// at the start. The body is already in memory when a route answers, the
+/// slice is taken from it; `If-Range` cannot be validated and gets the
+/// whole body.
+pub async fn byte_ranges(request: Request, next: Next) -> Response {
+    let range = (request.method() == Method::GET
+        && !request.headers().contains_key(header::IF_RANGE))
+    .then(|| request.headers().get(header::RANGE).cloned())
+    .flatten();
+
+    let mut response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    response
+        .headers_mut()
+        .insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    let Some(range) = range else {
+        return response;
+    };
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let len = bytes.len();
+
+    match range
+        .to_str()
+        .ok()
+        .and_then(|value| parse_range(value, len))
+    {
+        None => Response::from_parts(parts, Body::from(bytes)),
+        Some(Err(())) => {
+            let mut response = StatusCode::RANGE_NOT_SATISFIABLE.into_response();
+            if let Ok(value) = format!("bytes */{len}").parse() {
+                response.headers_mut().insert(header::CONTENT_RANGE, value);
+            }
+            response
+        }
+        Some(Ok((start, end))) => {
+            parts.status = StatusCode::PARTIAL_CONTENT;
+            parts.headers.remove(header::CONTENT_LENGTH);
+            if let Ok(value) = format!("bytes {start}-{end}/{len}").parse() {
+                parts.headers.insert(header::CONTENT_RANGE, value);
+            }
+            Response::from_parts(parts, Body::from(bytes.slice(start..=end)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        let cases = [
+            ("bytes=0-99", 1000, Some(Ok((0, 99)))),
+            ("bytes=500-", 1000, Some(Ok((500, 999)))),
+            (" bytes= 1 - 2 ", 1000, Some(Ok((1, 2)))),
+            // suffix ranges, longer than the body is all of it
+            ("bytes=-500", 1000, Some(Ok((500, 999)))),
+            ("bytes=-5000", 1000, Some(Ok((0, 999)))),
+            ("bytes=-0", 1000, Some(Err(()))),
+            // an end past the body is cut to it, a start past it is not
+            ("bytes=900-5000", 1000, Some(Ok((900, 999)))),
+            ("bytes=1000-", 1000, Some(Err(()))),
+            ("bytes=1000-2000", 1000, Some(Err(()))),
+            // start after end
+            ("bytes=10-5", 1000, Some(Err(()))),
+            // nothing of an empty body is satisfiable
+            ("bytes=0-", 0, Some(Err(()))),
+            ("bytes=0-0", 0, Some(Err(()))),
+            ("bytes=-1", 0, Some(Err(()))),
+            // several ranges, other units and garbage get the whole body
+            ("bytes=0-1,5-6", 1000, None),
+            ("items=0-1", 1000, None),
+            ("bytes=a-b", 1000, None),
+            ("bytes=5", 1000, None),
+        ];
+        for (value, len, expected) in cases {
+            assert_eq!(
+                parse_range(value, len),
+                expected,
+                "{value:?} of {len} bytes"
+            );
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::cache::RefreshTarget;
+use crate::http::AppState;
+use crate::ingestion::IngestionService;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+/// keeps the most requested repositories warm so nobody pays the clone cost
+pub fn spawn_cache_refresher(state: AppState) {
+    let top_n = state.config.cache_refresh_top_n;
+    if top_n == 0 {
+        return;
+    }
+
+    let interval = state.config.cache_refresh_interval();
+    info!(
+        "Refreshing top {} repositories every {}s",
+        top_n,
+        interval.as_secs()
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // first tick completes immediately, nothing is cached yet
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            refresh_top_repositories(&state, top_n).await;
+        }
+    });
+}
+
+async fn refresh_top_repositories(state: &AppState, top_n: usize) {
+    for repo in state.metrics.get_top_repositories(top_n).await {
+        for target in state.repo_cache.refresh_targets(&repo.url).await {
+            refresh_entry(state, &repo.url, target).await;
+        }
+    }
+}
+
+async fn refresh_entry(state: &AppState, url: &str, target: RefreshTarget) {
+    // entries of several branches have no single head to follow, they expire
+    if target.commit_hash.is_empty() {
+        return;
+    }
+    let remote_url = url.to_string();
+    let branch = target.branch.clone();
+    let head = tokio::task::spawn_blocking(move || {
+        githem_core::get_remote_head(&remote_url, branch.as_deref())
+    })
+    .await;
+
+    let current_hash = match head {
+        Ok(Ok(hash)) => hash,
+        Ok(Err(e)) => {
+            warn!("Cache refresh: ls-remote failed for {}: {}", url, e);
+            return;
+        }
+        Err(e) => {
+            warn!("Cache refresh: ls-remote task failed for {}: {}", url, e);
+            return;
+        }
+    };
+
+    if current_hash == target.commit_hash {
+        state.repo_cache.mark_validated(&target.key).await;
+        return;
+    }
+
+    let result = match timeout(
+        state.limits.timeout(),
+        IngestionService::ingest(target.params.clone()),
+    )
+    .await
+    {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => {
+            warn!("Cache refresh: ingestion failed for {}: {}", url, e);
+            return;
+        }
+        Err(_) => {
+            warn!("Cache refresh: ingestion timed out for {}", url);
+            return;
+        }
+    };
+
+    info!(
+        "Cache refresh: {} moved to {}",
+        url,
+        &current_hash[..current_hash.len().min(8)]
+    );
+
+    state
+        .repo_cache
+        .put(
+            target.key,
+            url.to_string(),
+            target.branch,
+            current_hash,
+            result,
+            target.params,
+        )
+        .await;
+}
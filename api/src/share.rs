@@ -0,0 +1,208 @@
+use crate::ingestion::IngestionParams;
+use crate::store::Store;
+use anyhow::Context;
+use rand::Rng;
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ID_LENGTH: usize = 8;
+const ID_ALPHABET: &[u8] = b"abcdefghijkmnopqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// parameters pinned to a commit, stored under a short id
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SharedSnapshot {
+    pub id: String,
+    pub owner: String,
+    pub repo: String,
+    /// always carries `commit`, so the link never changes under its readers
+    pub params: IngestionParams,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+/// imports the share files of older versions from `dir`, by default
+/// `shares/` next to the repository cache
+pub fn spawn_import(shares: Arc<ShareStore>, dir: Option<PathBuf>) {
+    let Some(dir) = dir.or_else(|| {
+        githem_core::CacheManager::cache_dir()
+            .ok()
+            .map(|d| d.join("shares"))
+    }) else {
+        return;
+    };
+    tokio::spawn(async move {
+        match shares.import_dir(&dir).await {
+            Ok(0) => {}
+            Ok(imported) => tracing::info!("Imported {} shares from {}", imported, dir.display()),
+            Err(e) => tracing::warn!("Importing shares from {} failed: {:#}", dir.display(), e),
+        }
+    });
+}
+
+/// share links in the database, they survive restarts
+pub struct ShareStore {
+    store: Store,
+}
+
+impl ShareStore {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn is_valid_id(id: &str) -> bool {
+        id.len() == ID_LENGTH && id.bytes().all(|b| ID_ALPHABET.contains(&b))
+    }
+
+    /// move the json files older versions kept one per share in `dir` into
+    /// the database, returns how many were imported
+    pub async fn import_dir(&self, dir: &Path) -> anyhow::Result<usize> {
+        let mut entries = match tokio::fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut imported = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let data = tokio::fs::read(&path).await?;
+            let Ok(snapshot) = serde_json::from_slice::<SharedSnapshot>(&data) else {
+                tracing::warn!("Skipping unreadable share {}", path.display());
+                continue;
+            };
+            if self.insert(snapshot).await? {
+                imported += 1;
+            }
+            tokio::fs::remove_file(&path).await?;
+        }
+        Ok(imported)
+    }
+
+    /// false when the id is taken
+    async fn insert(&self, snapshot: SharedSnapshot) -> anyhow::Result<bool> {
+        let params = serde_json::to_string(&snapshot.params)?;
+        self.store
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT OR IGNORE INTO shares (id, owner, repo, params, created_at, expires_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    rusqlite::params![
+                        snapshot.id,
+                        snapshot.owner,
+                        snapshot.repo,
+                        params,
+                        snapshot.created_at as i64,
+                        snapshot.expires_at.map(|at| at as i64),
+                    ],
+                )
+                .map(|inserted| inserted == 1)
+            })
+            .await
+    }
+
+    pub async fn create(
+        &self,
+        owner: String,
+        repo: String,
+        params: IngestionParams,
+        expires_in: Option<u64>,
+    ) -> anyhow::Result<SharedSnapshot> {
+        let now = Self::now();
+        let expires_at = match expires_in {
+            Some(secs) => Some(
+                now.checked_add(secs)
+                    .context("expires_in is out of range")?,
+            ),
+            None => None,
+        };
+        loop {
+            let id: String = {
+                let mut rng = rand::rng();
+                (0..ID_LENGTH)
+                    .map(|_| ID_ALPHABET[rng.random_range(0..ID_ALPHABET.len())] as char)
+                    .collect()
+            };
+
+            let snapshot = SharedSnapshot {
+                id,
+                owner: owner.clone(),
+                repo: repo.clone(),
+                params: params.clone(),
+                created_at: now,
+                expires_at,
+            };
+
+            // a colliding id is retried instead of overwritten
+            if self.insert(snapshot.clone()).await? {
+                return Ok(snapshot);
+            }
+        }
+    }
+
+    /// None for unknown or expired ids; expired shares are deleted on access
+    pub async fn get(&self, id: &str) -> Option<SharedSnapshot> {
+        if !Self::is_valid_id(id) {
+            return None;
+        }
+
+        let owned = id.to_string();
+        let row = self
+            .store
+            .call(move |conn| {
+                conn.query_row(
+                    "SELECT owner, repo, params, created_at, expires_at FROM shares WHERE id = ?1",
+                    [owned],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, String>(2)?,
+                            row.get::<_, i64>(3)?,
+                            row.get::<_, Option<i64>>(4)?,
+                        ))
+                    },
+                )
+                .optional()
+            })
+            .await;
+        let (owner, repo, params, created_at, expires_at) = match row {
+            Ok(row) => row?,
+            Err(e) => {
+                tracing::warn!("Reading share {} failed: {:#}", id, e);
+                return None;
+            }
+        };
+
+        let expires_at = expires_at.map(|at| at as u64);
+        if expires_at.is_some_and(|at| at <= Self::now()) {
+            let owned = id.to_string();
+            let _ = self
+                .store
+                .call(move |conn| conn.execute("DELETE FROM shares WHERE id = ?1", [owned]))
+                .await;
+            return None;
+        }
+
+        Some(SharedSnapshot {
+            id: id.to_string(),
+            owner,
+            repo,
+            params: serde_json::from_str(&params).ok()?,
+            created_at: created_at as u64,
+            expires_at,
+        })
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS results (
+    id TEXT PRIMARY KEY,
+    cache_key TEXT NOT NULL,
+    url TEXT NOT NULL,
+    branch TEXT,
+    commit_hash TEXT,
+    files INTEGER NOT NULL,
+    total_size INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS results_cache_key ON results (cache_key);
+CREATE TABLE IF NOT EXISTS shares (
+    id TEXT PRIMARY KEY,
+    owner TEXT NOT NULL,
+    repo TEXT NOT NULL,
+    params TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER
+);
+";
+
+/// an ingestion result as the database knows it; its content stays in the
+/// repository cache under `cache_key`
+#[derive(Debug, Clone)]
+pub struct ResultRecord {
+    pub id: String,
+    pub cache_key: String,
+    pub url: String,
+    pub branch: Option<String>,
+    pub commit_hash: Option<String>,
+    pub files: usize,
+    pub total_size: usize,
+    pub created_at: u64,
+}
+
+/// the sqlite database holding what the server keeps between restarts:
+/// the index of ingestion results and the share links
+#[derive(Clone)]
+pub struct Store {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open database {}", path.display()))?;
+        // readers do not wait for the writer, and a busy database is retried
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        Self::init(conn)
+    }
+
+    /// a database that lives as long as the process
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    /// `path`, or an in-memory database when it cannot be opened
+    pub fn open_or_memory(path: &Path) -> Self {
+        Self::open(path)
+            .or_else(|e| {
+                tracing::warn!("Results and shares kept in memory only: {:#}", e);
+                Self::in_memory()
+            })
+            .expect("in-memory sqlite database")
+    }
+
+    fn init(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create database schema")?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// defaults to `githem.db` next to the repository cache
+    pub fn default_path() -> PathBuf {
+        githem_core::CacheManager::cache_dir()
+            .unwrap_or_else(|_| std::env::temp_dir().join("githem"))
+            .join("githem.db")
+    }
+
+    /// run `f` on the connection from a blocking thread
+    pub async fn call<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    ) -> anyhow::Result<T> {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+            f(&conn)
+        })
+        .await
+        .context("Database task failed")?
+        .context("Database query failed")
+    }
+
+    /// record a result, replacing the one of the same id
+    pub async fn put_result(&self, record: ResultRecord) -> anyhow::Result<()> {
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO results
+                 (id, cache_key, url, branch, commit_hash, files, total_size, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    record.id,
+                    record.cache_key,
+                    record.url,
+                    record.branch,
+                    record.commit_hash,
+                    record.files as i64,
+                    record.total_size as i64,
+                    record.created_at as i64,
+                ],
+            )
+            .map(drop)
+        })
+        .await
+    }
+
+    pub async fn result(&self, id: &str) -> anyhow::Result<Option<ResultRecord>> {
+        let id = id.to_string();
+        self.call(move |conn| {
+            conn.query_row(
+                "SELECT id, cache_key, url, branch, commit_hash, files, total_size, created_at
+                 FROM results WHERE id = ?1",
+                [id],
+                |row| {
+                    Ok(ResultRecord {
+                        id: row.get(0)?,
+                        cache_key: row.get(1)?,
+                        url: row.get(2)?,
+                        branch: row.get(3)?,
+                        commit_hash: row.get(4)?,
+                        files: row.get::<_, i64>(5)? as usize,
+                        total_size: row.get::<_, i64>(6)? as usize,
+                        created_at: row.get::<_, i64>(7)? as u64,
+                    })
+                },
+            )
+            .optional()
+        })
+        .await
+    }
+
+    pub async fn remove_result(&self, id: &str) -> anyhow::Result<()> {
+        let id = id.to_string();
+        self.call(move |conn| {
+            conn.execute("DELETE FROM results WHERE id = ?1", [id])
+                .map(drop)
+        })
+        .await
+    }
+
+    /// forget results recorded before `created_before`, returns how many
+    pub async fn prune_results(&self, created_before: u64) -> anyhow::Result<usize> {
+        self.call(move |conn| {
+            conn.execute(
+                "DELETE FROM results WHERE created_at < ?1",
+                [created_before as i64],
+            )
+        })
+        .await
+    }
+}
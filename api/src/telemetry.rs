@@ -0,0 +1,82 @@
+use axum::{extract::Request, middleware::Next, response::Response};
+use tower_http::request_id::RequestId;
+use tracing::Span;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// fmt logging filtered by RUST_LOG, plus OTLP export when built with `otlp`
+/// and OTEL_EXPORTER_OTLP_ENDPOINT is set
+pub fn init() -> anyhow::Result<()> {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "githem_api=info,tower_http=info".into());
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer());
+
+    #[cfg(feature = "otlp")]
+    if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some() {
+        registry.with(otlp_layer()?).try_init()?;
+        return Ok(());
+    }
+
+    registry.try_init()?;
+    Ok(())
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_layer<S>() -> anyhow::Result<impl tracing_subscriber::Layer<S>>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    use opentelemetry::trace::TracerProvider as _;
+
+    // endpoint, headers and timeout come from the standard OTEL_* variables
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()?;
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name("githem-api")
+                .build(),
+        )
+        .build();
+    let tracer = provider.tracer("githem-api");
+    opentelemetry::global::set_tracer_provider(provider);
+
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+fn request_id_of(request: &Request) -> Option<&str> {
+    request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+}
+
+/// the x-request-id of the request currently being handled
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// one span per request, so ingestion logs carry the request id
+pub fn make_span(request: &Request) -> Span {
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = request_id_of(request).unwrap_or("-"),
+    )
+}
+
+/// exposes the request id to error responses built inside the handler
+pub async fn scope_request_id(request: Request, next: Next) -> Response {
+    match request_id_of(&request).map(str::to_string) {
+        Some(id) => REQUEST_ID.scope(id, next.run(request)).await,
+        None => next.run(request).await,
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use axum::Router;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// rustls termination shared by the http and websocket listeners
+#[derive(Clone)]
+pub struct TlsConfig {
+    #[cfg(feature = "tls")]
+    inner: axum_server::tls_rustls::RustlsConfig,
+}
+
+impl TlsConfig {
+    /// TLS_CERT_PATH and TLS_KEY_PATH (pem), None when neither is set
+    pub async fn from_env() -> Result<Option<Self>> {
+        match (
+            std::env::var_os("TLS_CERT_PATH"),
+            std::env::var_os("TLS_KEY_PATH"),
+        ) {
+            (None, None) => Ok(None),
+            (Some(cert), Some(key)) => Self::load(cert.into(), key.into()).await.map(Some),
+            _ => anyhow::bail!("TLS_CERT_PATH and TLS_KEY_PATH must be set together"),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    async fn load(cert: PathBuf, key: PathBuf) -> Result<Self> {
+        use anyhow::Context;
+
+        // a provider may already be installed by another dependency
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        let inner = axum_server::tls_rustls::RustlsConfig::from_pem_file(&cert, &key)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to load TLS certificate {} with key {}",
+                    cert.display(),
+                    key.display()
+                )
+            })?;
+        Ok(Self { inner })
+    }
+
+    #[cfg(not(feature = "tls"))]
+    async fn load(_cert: PathBuf, _key: PathBuf) -> Result<Self> {
+        anyhow::bail!("TLS_CERT_PATH is set but githem-api was built without the `tls` feature")
+    }
+}
+
+/// serve `app` on `addr`, over https when `tls` is given
+pub async fn serve(addr: SocketAddr, app: Router, tls: Option<TlsConfig>) -> Result<()> {
+    #[cfg(feature = "tls")]
+    if let Some(tls) = tls {
+        axum_server::bind_rustls(addr, tls.inner)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await?;
+        return Ok(());
+    }
+    #[cfg(not(feature = "tls"))]
+    let _ = tls;
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+    Ok(())
+}
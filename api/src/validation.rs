@@ -0,0 +1,666 @@
+use crate::http::{AppError, IngestRequest, QueryParams, SearchParams, ShareRequest};
+use crate::ingestion::IngestionService;
+use crate::websocket::{ClientMessage, WsQuery};
+use axum::{
+    extract::{FromRequest, FromRequestParts, Json, Query, Request},
+    http::request::Parts,
+};
+use githem_core::{validate_path_prefix, validate_ref_name, HeaderDetail, Transform};
+use serde::de::DeserializeOwned;
+
+pub const MAX_FILE_SIZE: usize = 100 * 1024 * 1024;
+pub const MAX_CONTEXT_LINES: u32 = 1000;
+pub const MAX_LINES: usize = 1_000_000;
+pub const MAX_EXTENSION_LEN: usize = 16;
+pub const MAX_PATTERNS: usize = 100;
+pub const MAX_PATTERN_LEN: usize = 256;
+pub const MAX_PATH_LEN: usize = 1024;
+pub const MAX_REF_LEN: usize = 255;
+pub const MAX_BRANCHES: usize = 8;
+pub const MAX_PATHS: usize = 32;
+pub const MAX_URL_LEN: usize = 2048;
+/// a year
+pub const MAX_SHARE_EXPIRY_SECS: u64 = 365 * 24 * 3600;
+
+/// bounds checks and cleanup applied before a request reaches ingestion
+pub trait Validate {
+    fn validate(&mut self) -> Result<(), AppError>;
+}
+
+/// `Query<T>` that rejects out of range parameters with a 400 naming the field
+pub struct ValidQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidQuery<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(mut value) = Query::<T>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| AppError::InvalidRequest(e.body_text()))?;
+        value.validate()?;
+        Ok(Self(value))
+    }
+}
+
+/// `Json<T>` with the same checks as `ValidQuery`
+pub struct ValidJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(mut value) = Json::<T>::from_request(request, state)
+            .await
+            .map_err(|e| AppError::InvalidRequest(e.body_text()))?;
+        value.validate()?;
+        Ok(Self(value))
+    }
+}
+
+fn invalid(field: &'static str, message: impl Into<String>) -> AppError {
+    AppError::InvalidParameter {
+        field,
+        message: message.into(),
+    }
+}
+
+/// trims, turns empty strings into None and enforces a length limit
+fn text(field: &'static str, value: &mut Option<String>, max_len: usize) -> Result<(), AppError> {
+    let Some(inner) = value.as_mut() else {
+        return Ok(());
+    };
+
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        *value = None;
+        return Ok(());
+    }
+    if trimmed.len() > max_len {
+        return Err(invalid(
+            field,
+            format!("must be at most {max_len} characters"),
+        ));
+    }
+    if trimmed.chars().any(char::is_control) {
+        return Err(invalid(field, "must not contain control characters"));
+    }
+
+    *inner = trimmed.to_string();
+    Ok(())
+}
+
+/// `text` plus the core ref name rules, so nothing reaches a refspec unchecked
+fn ref_name(field: &'static str, value: &mut Option<String>) -> Result<(), AppError> {
+    text(field, value, MAX_REF_LEN)?;
+    match value {
+        Some(name) => validate_ref_name(name).map_err(|e| invalid(field, e)),
+        None => Ok(()),
+    }
+}
+
+/// several ref names, at most `MAX_BRANCHES`, and only without `branch`
+fn branches(
+    field: &'static str,
+    branches: &mut Vec<String>,
+    branch: &Option<String>,
+) -> Result<(), AppError> {
+    branches.iter_mut().for_each(|b| *b = b.trim().to_string());
+    branches.retain(|b| !b.is_empty());
+
+    if branches.len() > MAX_BRANCHES {
+        return Err(invalid(
+            field,
+            format!("at most {MAX_BRANCHES} branches are allowed"),
+        ));
+    }
+    if !branches.is_empty() && branch.is_some() {
+        return Err(invalid(field, "cannot be combined with branch"));
+    }
+    for name in branches.iter() {
+        validate_ref_name(name).map_err(|e| invalid(field, e))?;
+    }
+    Ok(())
+}
+
+/// comma separated branches, normalized in place
+fn branch_list(
+    field: &'static str,
+    value: &mut Option<String>,
+    branch: &Option<String>,
+) -> Result<(), AppError> {
+    let Some(inner) = value.as_ref() else {
+        return Ok(());
+    };
+
+    let mut list: Vec<String> = inner.split(',').map(str::to_string).collect();
+    branches(field, &mut list, branch)?;
+    *value = (!list.is_empty()).then(|| list.join(","));
+    Ok(())
+}
+
+/// `text` plus the core path rules (no `..`, no absolute paths)
+fn path(field: &'static str, value: &mut Option<String>) -> Result<(), AppError> {
+    text(field, value, MAX_PATH_LEN)?;
+    match value {
+        Some(path) => validate_path_prefix(path).map_err(|e| invalid(field, e)),
+        None => Ok(()),
+    }
+}
+
+/// several `path`s, at most `MAX_PATHS`
+fn paths(field: &'static str, paths: &mut Vec<String>) -> Result<(), AppError> {
+    paths.iter_mut().for_each(|p| *p = p.trim().to_string());
+    paths.retain(|p| !p.is_empty());
+
+    if paths.len() > MAX_PATHS {
+        return Err(invalid(
+            field,
+            format!("at most {MAX_PATHS} paths are allowed"),
+        ));
+    }
+    for path in paths.iter() {
+        if path.len() > MAX_PATH_LEN {
+            return Err(invalid(
+                field,
+                format!("paths must be at most {MAX_PATH_LEN} characters"),
+            ));
+        }
+        validate_path_prefix(path).map_err(|e| invalid(field, e))?;
+    }
+    Ok(())
+}
+
+/// comma separated paths, normalized in place
+fn path_list(field: &'static str, value: &mut Option<String>) -> Result<(), AppError> {
+    let Some(inner) = value.as_ref() else {
+        return Ok(());
+    };
+
+    let mut list: Vec<String> = inner.split(',').map(str::to_string).collect();
+    paths(field, &mut list)?;
+    *value = (!list.is_empty()).then(|| list.join(","));
+    Ok(())
+}
+
+fn patterns(field: &'static str, patterns: &mut Vec<String>) -> Result<(), AppError> {
+    patterns.iter_mut().for_each(|p| *p = p.trim().to_string());
+    patterns.retain(|p| !p.is_empty());
+
+    if patterns.len() > MAX_PATTERNS {
+        return Err(invalid(
+            field,
+            format!("at most {MAX_PATTERNS} patterns are allowed"),
+        ));
+    }
+    for pattern in patterns.iter() {
+        if pattern.len() > MAX_PATTERN_LEN {
+            return Err(invalid(
+                field,
+                format!("patterns must be at most {MAX_PATTERN_LEN} characters"),
+            ));
+        }
+        if pattern.chars().any(char::is_control) {
+            return Err(invalid(
+                field,
+                "patterns must not contain control characters",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// comma separated patterns, normalized in place
+fn pattern_list(field: &'static str, value: &mut Option<String>) -> Result<(), AppError> {
+    let Some(inner) = value.as_ref() else {
+        return Ok(());
+    };
+
+    let mut list: Vec<String> = inner.split(',').map(str::to_string).collect();
+    patterns(field, &mut list)?;
+    *value = (!list.is_empty()).then(|| list.join(","));
+    Ok(())
+}
+
+/// extensions are bare names like `rs` or `d.ts`, a leading dot is dropped
+fn extensions(field: &'static str, extensions: &mut Vec<String>) -> Result<(), AppError> {
+    extensions
+        .iter_mut()
+        .for_each(|e| *e = e.trim().trim_start_matches('.').to_string());
+    extensions.retain(|e| !e.is_empty());
+
+    if extensions.len() > MAX_PATTERNS {
+        return Err(invalid(
+            field,
+            format!("at most {MAX_PATTERNS} extensions are allowed"),
+        ));
+    }
+    let valid = |e: &String| {
+        e.len() <= MAX_EXTENSION_LEN
+            && e.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+'))
+    };
+    if !extensions.iter().all(valid) {
+        return Err(invalid(
+            field,
+            format!("extensions must be up to {MAX_EXTENSION_LEN} letters, digits or . _ - +"),
+        ));
+    }
+    Ok(())
+}
+
+/// comma separated extensions, normalized in place
+fn extension_list(field: &'static str, value: &mut Option<String>) -> Result<(), AppError> {
+    let Some(inner) = value.as_ref() else {
+        return Ok(());
+    };
+
+    let mut list: Vec<String> = inner.split(',').map(str::to_string).collect();
+    extensions(field, &mut list)?;
+    *value = (!list.is_empty()).then(|| list.join(","));
+    Ok(())
+}
+
+/// comma separated transform names, normalized in place
+fn transform_list(field: &'static str, value: &mut Option<String>) -> Result<(), AppError> {
+    let Some(inner) = value.as_ref() else {
+        return Ok(());
+    };
+
+    let mut names = Vec::new();
+    for name in inner.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let transform = Transform::parse(name).ok_or_else(|| {
+            let known: Vec<&str> = Transform::ALL.iter().map(|t| t.as_str()).collect();
+            invalid(field, format!("must be one of {}", known.join(", ")))
+        })?;
+        names.push(transform.as_str());
+    }
+    *value = (!names.is_empty()).then(|| names.join(","));
+    Ok(())
+}
+
+fn header_detail(field: &'static str, value: &mut Option<String>) -> Result<(), AppError> {
+    text(field, value, 16)?;
+    match value {
+        Some(name) if HeaderDetail::parse(name).is_none() => {
+            Err(invalid(field, "must be one of path, basic, full"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn max_size(field: &'static str, value: usize) -> Result<(), AppError> {
+    if value == 0 || value > MAX_FILE_SIZE {
+        return Err(invalid(
+            field,
+            format!("must be between 1 and {MAX_FILE_SIZE} bytes"),
+        ));
+    }
+    Ok(())
+}
+
+fn min_size(field: &'static str, value: usize, max: usize) -> Result<(), AppError> {
+    if value > max {
+        return Err(invalid(
+            field,
+            format!("must not exceed the max size of {max} bytes"),
+        ));
+    }
+    Ok(())
+}
+
+fn max_lines(field: &'static str, value: Option<usize>) -> Result<(), AppError> {
+    if value.is_some_and(|lines| lines == 0 || lines > MAX_LINES) {
+        return Err(invalid(field, format!("must be between 1 and {MAX_LINES}")));
+    }
+    Ok(())
+}
+
+fn max_files(field: &'static str, value: Option<usize>) -> Result<(), AppError> {
+    if value == Some(0) {
+        return Err(invalid(field, "must be positive"));
+    }
+    Ok(())
+}
+
+fn preset(field: &'static str, value: &Option<String>) -> Result<(), AppError> {
+    match value {
+        Some(name) if IngestionService::parse_filter_preset(Some(name)).is_none() => Err(invalid(
+            field,
+            "must be one of raw, standard, code-only, minimal, docs-only, tests-only",
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl Validate for QueryParams {
+    fn validate(&mut self) -> Result<(), AppError> {
+        ref_name("branch", &mut self.branch)?;
+        branch_list("branches", &mut self.branches, &self.branch)?;
+        path("subpath", &mut self.subpath)?;
+        path("path", &mut self.path)?;
+        path_list("paths", &mut self.paths)?;
+        path_list("exclude_paths", &mut self.exclude_paths)?;
+        pattern_list("include", &mut self.include)?;
+        pattern_list("exclude", &mut self.exclude)?;
+        extension_list("ext", &mut self.ext)?;
+        transform_list("transform", &mut self.transform)?;
+        header_detail("header", &mut self.header)?;
+        text("preset", &mut self.preset, 32)?;
+        preset("preset", &self.preset)?;
+        if let Some(size) = self.max_size {
+            max_size("max_size", size)?;
+        }
+        if let Some(size) = self.min_size {
+            min_size("min_size", size, self.max_size.unwrap_or(MAX_FILE_SIZE))?;
+        }
+        max_lines("max_lines", self.max_lines)?;
+        max_files("max_files", self.max_files)?;
+        if self.ctx.is_some_and(|ctx| ctx > MAX_CONTEXT_LINES) {
+            return Err(invalid(
+                "ctx",
+                format!("must be at most {MAX_CONTEXT_LINES}"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Validate for SearchParams {
+    fn validate(&mut self) -> Result<(), AppError> {
+        if self.q.len() > MAX_PATTERN_LEN {
+            return Err(invalid(
+                "q",
+                format!("must be at most {MAX_PATTERN_LEN} characters"),
+            ));
+        }
+        ref_name("branch", &mut self.branch)?;
+        path("path", &mut self.path)?;
+        text("preset", &mut self.preset, 32)?;
+        preset("preset", &self.preset)?;
+        if self
+            .context
+            .is_some_and(|context| context > MAX_CONTEXT_LINES as usize)
+        {
+            return Err(invalid(
+                "context",
+                format!("must be at most {MAX_CONTEXT_LINES}"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Validate for ShareRequest {
+    fn validate(&mut self) -> Result<(), AppError> {
+        if self
+            .expires_in
+            .is_some_and(|secs| secs == 0 || secs > MAX_SHARE_EXPIRY_SECS)
+        {
+            return Err(invalid(
+                "expires_in",
+                format!("must be between 1 and {MAX_SHARE_EXPIRY_SECS} seconds"),
+            ));
+        }
+        self.options.validate()
+    }
+}
+
+impl Validate for IngestRequest {
+    fn validate(&mut self) -> Result<(), AppError> {
+        self.url = self.url.trim().to_string();
+        if self.url.is_empty() {
+            return Err(invalid("url", "is required"));
+        }
+        if self.url.len() > MAX_URL_LEN {
+            return Err(invalid(
+                "url",
+                format!("must be at most {MAX_URL_LEN} characters"),
+            ));
+        }
+        ref_name("branch", &mut self.branch)?;
+        branches("branches", &mut self.branches, &self.branch)?;
+        path("subpath", &mut self.subpath)?;
+        path("path_prefix", &mut self.path_prefix)?;
+        paths("paths", &mut self.paths)?;
+        paths("exclude_paths", &mut self.exclude_paths)?;
+        patterns("include_patterns", &mut self.include_patterns)?;
+        patterns("exclude_patterns", &mut self.exclude_patterns)?;
+        extensions("extensions", &mut self.extensions)?;
+        max_size("max_file_size", self.max_file_size)?;
+        min_size("min_file_size", self.min_file_size, self.max_file_size)?;
+        max_lines("max_lines", self.max_lines)?;
+        max_files("max_files", self.max_files)?;
+        text("filter_preset", &mut self.filter_preset, 32)?;
+        preset("filter_preset", &self.filter_preset)
+    }
+}
+
+impl Validate for WsQuery {
+    fn validate(&mut self) -> Result<(), AppError> {
+        self.url = self.url.trim().to_string();
+        if self.url.is_empty() || self.url.len() > MAX_URL_LEN {
+            return Err(invalid(
+                "url",
+                format!("must be 1 to {MAX_URL_LEN} characters"),
+            ));
+        }
+        ref_name("branch", &mut self.branch)?;
+        branch_list("branches", &mut self.branches, &self.branch)?;
+        path("path", &mut self.path)?;
+        path_list("paths", &mut self.paths)?;
+        path_list("exclude_paths", &mut self.exclude_paths)?;
+        pattern_list("include", &mut self.include)?;
+        pattern_list("exclude", &mut self.exclude)?;
+        extension_list("ext", &mut self.ext)?;
+        transform_list("transform", &mut self.transform)?;
+        header_detail("header", &mut self.header)?;
+        max_size("max_size", self.max_size)?;
+        min_size("min_size", self.min_size, self.max_size)?;
+        max_lines("max_lines", self.max_lines)?;
+        max_files("max_files", self.max_files)?;
+        text("preset", &mut self.preset, 32)?;
+        preset("preset", &self.preset)
+    }
+}
+
+/// `check` for a filter that can be cleared: a given value that normalizes
+/// to nothing stays `Some("")` instead of becoming None, which means omitted
+fn clearable(
+    field: &'static str,
+    value: &mut Option<String>,
+    check: impl FnOnce(&'static str, &mut Option<String>) -> Result<(), AppError>,
+) -> Result<(), AppError> {
+    let given = value.is_some();
+    check(field, value)?;
+    if given && value.is_none() {
+        *value = Some(String::new());
+    }
+    Ok(())
+}
+
+impl Validate for ClientMessage {
+    fn validate(&mut self) -> Result<(), AppError> {
+        match self {
+            ClientMessage::Refilter {
+                include,
+                exclude,
+                ext,
+                preset: name,
+                ..
+            } => {
+                clearable("include", include, pattern_list)?;
+                clearable("exclude", exclude, pattern_list)?;
+                clearable("ext", ext, extension_list)?;
+                clearable("preset", name, |field, value| {
+                    text(field, value, 32)?;
+                    preset(field, value)
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the field a check rejected, None when it passed
+    fn rejected(result: Result<(), AppError>) -> Option<&'static str> {
+        match result {
+            Ok(()) => None,
+            Err(AppError::InvalidParameter { field, .. }) => Some(field),
+            Err(e) => panic!("unexpected error {e:?}"),
+        }
+    }
+
+    fn some(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[test]
+    fn test_text_and_refs() {
+        let mut value = some("  main  ");
+        assert_eq!(rejected(text("branch", &mut value, 8)), None);
+        assert_eq!(value.as_deref(), Some("main"));
+
+        let mut value = some("   ");
+        assert_eq!(rejected(text("branch", &mut value, 8)), None);
+        assert_eq!(value, None);
+
+        for bad in ["123456789", "ma\u{7}in"] {
+            assert_eq!(rejected(text("branch", &mut some(bad), 8)), Some("branch"));
+        }
+
+        assert_eq!(rejected(ref_name("branch", &mut some("feature/x"))), None);
+        for bad in ["a..b", "-x", "a b", "x.lock"] {
+            assert_eq!(rejected(ref_name("branch", &mut some(bad))), Some("branch"));
+        }
+
+        assert_eq!(rejected(path("path", &mut some("src/core/"))), None);
+        for bad in ["../etc", "/etc"] {
+            assert_eq!(rejected(path("path", &mut some(bad))), Some("path"));
+        }
+    }
+
+    #[test]
+    fn test_lists_normalize() {
+        let mut value = some(" src/ , ,*.rs ");
+        assert_eq!(rejected(pattern_list("include", &mut value)), None);
+        assert_eq!(value.as_deref(), Some("src/,*.rs"));
+
+        let mut value = some(".rs, d.ts,,");
+        assert_eq!(rejected(extension_list("ext", &mut value)), None);
+        assert_eq!(value.as_deref(), Some("rs,d.ts"));
+        assert_eq!(
+            rejected(extension_list("ext", &mut some("r s"))),
+            Some("ext")
+        );
+
+        let mut value = some("Line_Numbers, redact-secrets");
+        assert_eq!(rejected(transform_list("transform", &mut value)), None);
+        assert_eq!(value.as_deref(), Some("line-numbers,redact-secrets"));
+        assert_eq!(
+            rejected(transform_list("transform", &mut some("minify"))),
+            Some("transform")
+        );
+
+        let mut value = some("main, dev");
+        assert_eq!(rejected(branch_list("branches", &mut value, &None)), None);
+        assert_eq!(value.as_deref(), Some("main,dev"));
+        assert_eq!(
+            rejected(branch_list("branches", &mut some("dev"), &some("main"))),
+            Some("branches")
+        );
+        let many = ["b"; MAX_BRANCHES + 1].join(",");
+        assert_eq!(
+            rejected(branch_list("branches", &mut Some(many), &None)),
+            Some("branches")
+        );
+
+        // a cleared filter stays given, as opposed to omitted
+        let mut value = some(" , ");
+        assert_eq!(
+            rejected(clearable("include", &mut value, pattern_list)),
+            None
+        );
+        assert_eq!(value.as_deref(), Some(""));
+        let mut value = None;
+        assert_eq!(
+            rejected(clearable("include", &mut value, pattern_list)),
+            None
+        );
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(rejected(max_size("max_size", 1)), None);
+        assert_eq!(rejected(max_size("max_size", 0)), Some("max_size"));
+        assert_eq!(
+            rejected(max_size("max_size", MAX_FILE_SIZE + 1)),
+            Some("max_size")
+        );
+        assert_eq!(rejected(min_size("min_size", 10, 10)), None);
+        assert_eq!(rejected(min_size("min_size", 11, 10)), Some("min_size"));
+        assert_eq!(rejected(max_lines("max_lines", None)), None);
+        assert_eq!(rejected(max_lines("max_lines", Some(0))), Some("max_lines"));
+        assert_eq!(rejected(max_files("max_files", Some(0))), Some("max_files"));
+        assert_eq!(rejected(preset("preset", &some("code-only"))), None);
+        assert_eq!(
+            rejected(preset("preset", &some("everything"))),
+            Some("preset")
+        );
+    }
+
+    #[test]
+    fn test_ingest_request() {
+        let request =
+            |value: serde_json::Value| -> IngestRequest { serde_json::from_value(value).unwrap() };
+
+        let mut valid = request(serde_json::json!({
+            "url": " https://github.com/owner/repo ",
+            "branch": "main",
+            "extensions": [".rs", " "],
+            "include_patterns": ["src/", ""],
+        }));
+        assert_eq!(rejected(valid.validate()), None);
+        assert_eq!(valid.url, "https://github.com/owner/repo");
+        assert_eq!(valid.extensions, ["rs"]);
+        assert_eq!(valid.include_patterns, ["src/"]);
+
+        let cases = [
+            (serde_json::json!({ "url": "  " }), "url"),
+            (
+                serde_json::json!({ "url": "x".repeat(MAX_URL_LEN + 1) }),
+                "url",
+            ),
+            (
+                serde_json::json!({ "url": "a/b", "branch": "a", "branches": ["b"] }),
+                "branches",
+            ),
+            (
+                serde_json::json!({ "url": "a/b", "path_prefix": "../x" }),
+                "path_prefix",
+            ),
+            (
+                serde_json::json!({ "url": "a/b", "min_file_size": 11, "max_file_size": 10 }),
+                "min_file_size",
+            ),
+            (
+                serde_json::json!({ "url": "a/b", "filter_preset": "everything" }),
+                "filter_preset",
+            ),
+        ];
+        for (value, field) in cases {
+            assert_eq!(rejected(request(value).validate()), Some(field));
+        }
+    }
+}
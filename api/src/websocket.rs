@@ -1,165 +1,540 @@
-use crate::ingestion::{IngestionParams, IngestionService, WebSocketMessage};
+use crate::access::client_ip;
+use crate::config::Config;
+use crate::http::{AppError, AppState};
+use crate::ingestion::{IngestionParams, IngestionResult, IngestionService, WebSocketMessage};
+use crate::limits::IngestLimits;
+use crate::tls::TlsConfig;
+use crate::validation::{ValidQuery, Validate};
 use anyhow::Result;
 use axum::{
-    extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
-        Query,
-    },
-    response::IntoResponse,
+    extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
     routing::get,
-    Router,
+    Extension, Router,
 };
+use flate2::{write::DeflateEncoder, Compression};
+use githem_core::{split_files, validate_path_prefix, HeaderDetail, Transform};
 use serde::Deserialize;
-use std::net::SocketAddr;
-use std::time::Instant;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tokio::time::{interval_at, Instant, Interval, MissedTickBehavior};
 use tracing::{error, info};
 
 #[derive(Debug, Deserialize)]
-struct WsQuery {
-    url: String,
+pub(crate) struct WsQuery {
+    pub(crate) url: String,
+    /// comma-separated, like the http query parameters
+    #[serde(default)]
+    pub(crate) include: Option<String>,
+    #[serde(default)]
+    pub(crate) exclude: Option<String>,
+    #[serde(default)]
+    pub(crate) ext: Option<String>,
+    #[serde(default)]
+    pub(crate) transform: Option<String>,
+    #[serde(default)]
+    pub(crate) header: Option<String>,
+    #[serde(default)]
+    pub(crate) path: Option<String>,
+    /// comma-separated subtrees next to `path`
     #[serde(default)]
-    include: Vec<String>,
+    pub(crate) paths: Option<String>,
+    /// comma-separated subtrees to leave out
     #[serde(default)]
-    exclude: Vec<String>,
+    pub(crate) exclude_paths: Option<String>,
     #[serde(default = "default_max_size")]
-    max_size: usize,
+    pub(crate) max_size: usize,
     #[serde(default)]
-    branch: Option<String>,
+    pub(crate) min_size: usize,
     #[serde(default)]
-    preset: Option<String>,
+    pub(crate) max_lines: Option<usize>,
     #[serde(default)]
-    raw: bool,
+    pub(crate) max_files: Option<usize>,
+    #[serde(default)]
+    pub(crate) branch: Option<String>,
+    /// comma-separated, several branches into one output
+    #[serde(default)]
+    pub(crate) branches: Option<String>,
+    #[serde(default)]
+    pub(crate) preset: Option<String>,
+    #[serde(default)]
+    pub(crate) raw: bool,
+    /// send files as binary frames instead of JSON text
+    #[serde(default)]
+    pub(crate) binary: bool,
+    /// raw DEFLATE each binary frame, implies `binary`. This is githem's own
+    /// framing, not the permessage-deflate extension (RFC 7692), which is
+    /// not negotiated: clients inflate every frame themselves
+    #[serde(default)]
+    pub(crate) deflate: bool,
 }
 
 fn default_max_size() -> usize {
     10 * 1024 * 1024
 }
 
-async fn websocket_handler(
+fn split_patterns(patterns: Option<&str>) -> Vec<String> {
+    patterns
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// open sockets per client address, shared by /ws and the legacy listener
+static CONNECTIONS: LazyLock<Mutex<HashMap<IpAddr, usize>>> = LazyLock::new(Default::default);
+
+/// one of the sockets counted against a client address, released when the
+/// socket task ends
+struct ConnectionSlot(IpAddr);
+
+impl ConnectionSlot {
+    fn acquire(client: IpAddr, limit: usize) -> Option<Self> {
+        let mut connections = CONNECTIONS.lock().unwrap();
+        let open = connections.entry(client).or_insert(0);
+        if *open >= limit {
+            return None;
+        }
+        *open += 1;
+        Some(Self(client))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut connections = CONNECTIONS.lock().unwrap();
+        if let Some(open) = connections.get_mut(&self.0) {
+            *open -= 1;
+            if *open == 0 {
+                connections.remove(&self.0);
+            }
+        }
+    }
+}
+
+/// pings keep proxies from dropping the socket and expose dead clients, the
+/// idle timeout closes sockets the client stopped using
+#[derive(Debug, Clone, Copy)]
+struct Heartbeat {
+    ping_interval: Option<Duration>,
+    idle_timeout: Option<Duration>,
+}
+
+impl Heartbeat {
+    fn new(config: &Config) -> Self {
+        let secs = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+        Self {
+            ping_interval: secs(config.ws_ping_interval_secs),
+            idle_timeout: secs(config.ws_idle_timeout_secs),
+        }
+    }
+
+    /// ticks at the ping interval, or at the idle timeout without pings
+    fn ticker(&self) -> Option<Interval> {
+        let period = self.ping_interval.or(self.idle_timeout)?;
+        let mut ticker = interval_at(Instant::now() + period, period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Some(ticker)
+    }
+
+    /// `work` with pings sent meanwhile, None when the client is gone
+    async fn during<T>(&self, socket: &mut WebSocket, work: impl Future<Output = T>) -> Option<T> {
+        let mut ticker = self.ping_interval.and_then(|_| self.ticker());
+        tokio::pin!(work);
+        loop {
+            tokio::select! {
+                output = &mut work => return Some(output),
+                _ = tick(&mut ticker) => {
+                    if socket.send(Message::Ping(Default::default())).await.is_err() {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    /// `send` failing once it blocks for the idle timeout, a client that
+    /// stopped reading would otherwise hold the task forever
+    async fn send_within(
+        &self,
+        send: impl Future<Output = Result<(), axum::Error>>,
+    ) -> Result<(), axum::Error> {
+        match self.idle_timeout {
+            Some(limit) => tokio::time::timeout(limit, send)
+                .await
+                .unwrap_or_else(|_| Err(axum::Error::new("send timed out"))),
+            None => send.await,
+        }
+    }
+}
+
+async fn tick(ticker: &mut Option<Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+pub(crate) async fn websocket_handler(
     ws: WebSocketUpgrade,
-    Query(params): Query<WsQuery>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, params))
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    peer: Option<Extension<ConnectInfo<SocketAddr>>>,
+    ValidQuery(params): ValidQuery<WsQuery>,
+) -> Response {
+    let config = &state.config;
+    let limit = config.ws_max_connections_per_ip;
+    let peer = peer.map(|Extension(ConnectInfo(addr))| addr);
+    let client = client_ip(&headers, peer, config.trust_forwarded_for);
+
+    let slot = match client.filter(|_| limit > 0) {
+        Some(client) => match ConnectionSlot::acquire(client, limit) {
+            Some(slot) => Some(slot),
+            None => return AppError::TooManyConnections { limit }.into_response(),
+        },
+        None => None,
+    };
+
+    let heartbeat = Heartbeat::new(config);
+    let limits = state.limits.clone();
+    ws.on_upgrade(move |socket| async move {
+        handle_socket(socket, params, heartbeat, limits).await;
+        drop(slot);
+    })
 }
 
-async fn handle_socket(mut socket: WebSocket, params: WsQuery) {
-    let _start = Instant::now();
+/// sent by the client once an ingest is complete; each filter given replaces
+/// the one of the previous run, an empty string clears it and the ones left
+/// out are kept
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum ClientMessage {
+    Refilter {
+        #[serde(default)]
+        include: Option<String>,
+        #[serde(default)]
+        exclude: Option<String>,
+        #[serde(default)]
+        ext: Option<String>,
+        #[serde(default)]
+        preset: Option<String>,
+        #[serde(default)]
+        raw: Option<bool>,
+    },
+}
+
+/// `ingestion`, failing once it runs longer than `limit` like the http routes
+async fn within(
+    limit: Duration,
+    ingestion: impl Future<Output = Result<IngestionResult, Box<dyn std::error::Error + Send + Sync>>>,
+) -> Result<IngestionResult, Box<dyn std::error::Error + Send + Sync>> {
+    match tokio::time::timeout(limit, ingestion).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("timed out after {}s", limit.as_secs()).into()),
+    }
+}
 
-    if let Err(e) = socket
+async fn send(socket: &mut WebSocket, message: &WebSocketMessage) -> Result<(), axum::Error> {
+    socket
         .send(Message::Text(
-            serde_json::to_string(&WebSocketMessage::Progress {
-                stage: "starting".to_string(),
-                message: format!("Processing {}", params.url),
-            })
-            .unwrap()
-            .into(),
+            serde_json::to_string(message).unwrap().into(),
         ))
         .await
-    {
+}
+
+/// how file messages travel: JSON text, or binary frames laid out as
+/// `[u32 BE path length][path][content]` that skip the string escaping,
+/// raw DEFLATE compressed as a whole with `deflate`, each frame on its own
+/// (a substitute for permessage-deflate, which is not negotiated)
+#[derive(Debug, Clone, Copy)]
+struct Framing {
+    binary: bool,
+    deflate: bool,
+}
+
+fn file_frame(path: &str, content: &str, deflate: bool) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(4 + path.len() + content.len());
+    frame.extend_from_slice(&(path.len() as u32).to_be_bytes());
+    frame.extend_from_slice(path.as_bytes());
+    frame.extend_from_slice(content.as_bytes());
+    if !deflate {
+        return frame;
+    }
+
+    let mut encoder = DeflateEncoder::new(Vec::with_capacity(frame.len() / 4), Compression::fast());
+    encoder
+        .write_all(&frame)
+        .and_then(|()| encoder.finish())
+        .expect("deflating into memory")
+}
+
+/// filter stats, one message per file so clients can render them as they
+/// arrive, then the totals
+async fn send_result(
+    socket: &mut WebSocket,
+    result: &IngestionResult,
+    framing: Framing,
+) -> Result<(), axum::Error> {
+    if let Some(stats) = &result.filter_stats {
+        let stats = WebSocketMessage::FilterStats {
+            stats: stats.clone(),
+        };
+        send(socket, &stats).await?;
+    }
+
+    for (path, content) in split_files(&result.content) {
+        if framing.binary {
+            let frame = file_frame(path, content, framing.deflate);
+            socket.send(Message::Binary(frame.into())).await?;
+            continue;
+        }
+        let file = WebSocketMessage::File {
+            path: path.to_string(),
+            content: content.to_string(),
+        };
+        send(socket, &file).await?;
+    }
+
+    let complete = WebSocketMessage::Complete {
+        files: result.summary.files_analyzed,
+        bytes: result.summary.total_size,
+        branch: Some(result.summary.branch.clone()),
+        metadata: Some(result.metadata.clone()),
+    };
+    send(socket, &complete).await
+}
+
+async fn handle_socket(
+    mut socket: WebSocket,
+    params: WsQuery,
+    heartbeat: Heartbeat,
+    limits: IngestLimits,
+) {
+    let _start = Instant::now();
+
+    let starting = WebSocketMessage::Progress {
+        stage: "starting".to_string(),
+        message: format!("Processing {}", params.url),
+    };
+    if let Err(e) = send(&mut socket, &starting).await {
         error!("Failed to send message: {}", e);
         return;
     }
 
-    let ingestion_params = IngestionParams {
+    let framing = Framing {
+        binary: params.binary || params.deflate,
+        deflate: params.deflate,
+    };
+
+    let mut ingestion_params = IngestionParams {
         url: params.url.clone(),
         subpath: None,
         branch: params.branch,
-        path_prefix: None,
-        include_patterns: params.include,
-        exclude_patterns: params.exclude,
+        branches: split_patterns(params.branches.as_deref()),
+        path_prefix: params
+            .path
+            .clone()
+            .filter(|p| validate_path_prefix(p).is_ok()),
+        paths: split_patterns(params.paths.as_deref()),
+        exclude_paths: split_patterns(params.exclude_paths.as_deref()),
+        include_patterns: split_patterns(params.include.as_deref()),
+        exclude_patterns: split_patterns(params.exclude.as_deref()),
+        extensions: split_patterns(params.ext.as_deref()),
+        transforms: split_patterns(params.transform.as_deref())
+            .iter()
+            .filter_map(|name| Transform::parse(name))
+            .collect(),
+        header_detail: params
+            .header
+            .as_deref()
+            .and_then(HeaderDetail::parse)
+            .unwrap_or_default(),
         max_file_size: params.max_size,
+        min_file_size: params.min_size,
+        max_lines: params.max_lines,
+        max_files: params.max_files,
         filter_preset: params.preset,
         raw: params.raw,
+        commit: None,
     };
 
-    if let Err(e) = socket
-        .send(Message::Text(
-            serde_json::to_string(&WebSocketMessage::Progress {
-                stage: "cloning".to_string(),
-                message: "Cloning repository...".to_string(),
-            })
-            .unwrap()
-            .into(),
-        ))
-        .await
-    {
+    if let Err(e) = limits.check(&ingestion_params).await {
+        let message = match e {
+            AppError::TooLarge { size_mb, limit_mb } => {
+                format!(
+                    "Failed: repository is {size_mb} MB, above this server's {limit_mb} MB limit"
+                )
+            }
+            e => format!("Failed: {e:?}"),
+        };
+        let _ = send(&mut socket, &WebSocketMessage::Error { message }).await;
+        return;
+    }
+
+    let cloning = WebSocketMessage::Progress {
+        stage: "cloning".to_string(),
+        message: "Cloning repository...".to_string(),
+    };
+    if let Err(e) = send(&mut socket, &cloning).await {
         error!("Failed to send message: {}", e);
         return;
     }
 
-    match IngestionService::ingest(ingestion_params).await {
+    let ingest = within(
+        limits.timeout(),
+        IngestionService::ingest(ingestion_params.clone()),
+    );
+    let Some(ingested) = heartbeat.during(&mut socket, ingest).await else {
+        info!("WebSocket client left during {}", params.url);
+        return;
+    };
+
+    match ingested {
         Ok(result) => {
-            if let Err(e) = socket
-                .send(Message::Text(
-                    serde_json::to_string(&WebSocketMessage::Progress {
-                        stage: "ingesting".to_string(),
-                        message: "Processing files...".to_string(),
-                    })
-                    .unwrap()
-                    .into(),
-                ))
-                .await
-            {
+            let ingesting = WebSocketMessage::Progress {
+                stage: "ingesting".to_string(),
+                message: "Processing files...".to_string(),
+            };
+            if let Err(e) = send(&mut socket, &ingesting).await {
                 error!("Failed to send message: {}", e);
                 return;
             }
+            let sent = heartbeat
+                .send_within(send_result(&mut socket, &result, framing))
+                .await;
+            if sent.is_err() {
+                info!("WebSocket client left during {}", params.url);
+                return;
+            }
+            info!("WebSocket session completed for {}", params.url);
+        }
+        Err(e) => {
+            let failed = WebSocketMessage::Error {
+                message: format!("Failed: {e}"),
+            };
+            let _ = send(&mut socket, &failed).await;
+            return;
+        }
+    }
 
-            // Send filter stats if available
-            if let Some(stats) = &result.filter_stats {
-                let _ = socket
-                    .send(Message::Text(
-                        serde_json::to_string(&WebSocketMessage::FilterStats {
-                            stats: stats.clone(),
-                        })
-                        .unwrap()
-                        .into(),
-                    ))
-                    .await;
+    // the socket stays open so the client can refilter the same checkout
+    let mut ticker = heartbeat.ticker();
+    let mut last_seen = Instant::now();
+    let mut last_message = Instant::now();
+    loop {
+        let received = tokio::select! {
+            received = socket.recv() => received,
+            _ = tick(&mut ticker) => {
+                if heartbeat.idle_timeout.is_some_and(|limit| last_message.elapsed() >= limit) {
+                    let close = CloseFrame {
+                        code: close_code::AWAY,
+                        reason: "idle timeout".into(),
+                    };
+                    let _ = socket.send(Message::Close(Some(close))).await;
+                    break;
+                }
+                if let Some(period) = heartbeat.ping_interval {
+                    // no pong for two periods, the client is gone
+                    if last_seen.elapsed() >= period * 2 {
+                        break;
+                    }
+                    if socket.send(Message::Ping(Default::default())).await.is_err() {
+                        break;
+                    }
+                }
+                continue;
             }
+        };
 
-            let _ = socket
-                .send(Message::Text(
-                    serde_json::to_string(&WebSocketMessage::File {
-                        path: "all_files.txt".to_string(),
-                        content: result.content,
-                    })
-                    .unwrap()
-                    .into(),
-                ))
-                .await;
+        let Some(Ok(message)) = received else {
+            break;
+        };
+        last_seen = Instant::now();
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        last_message = Instant::now();
 
-            let _ = socket
-                .send(Message::Text(
-                    serde_json::to_string(&WebSocketMessage::Complete {
-                        files: result.summary.files_analyzed,
-                        bytes: result.summary.total_size,
-                    })
-                    .unwrap()
-                    .into(),
-                ))
-                .await;
+        let message = serde_json::from_str::<ClientMessage>(&text)
+            .map_err(|e| format!("Invalid message: {e}"))
+            .and_then(|mut message| match message.validate() {
+                Ok(()) => Ok(message),
+                Err(AppError::InvalidParameter { field, message }) => {
+                    Err(format!("Invalid {field}: {message}"))
+                }
+                Err(_) => Err("Invalid message".to_string()),
+            });
 
-            info!("WebSocket session completed for {}", params.url);
-        }
-        Err(e) => {
-            let _ = socket
-                .send(Message::Text(
-                    serde_json::to_string(&WebSocketMessage::Error {
-                        message: format!("Failed: {e}"),
-                    })
-                    .unwrap()
-                    .into(),
-                ))
-                .await;
+        let refilter = match message {
+            Ok(ClientMessage::Refilter {
+                include,
+                exclude,
+                ext,
+                preset,
+                raw,
+            }) => {
+                if let Some(include) = include {
+                    ingestion_params.include_patterns = split_patterns(Some(&include));
+                }
+                if let Some(exclude) = exclude {
+                    ingestion_params.exclude_patterns = split_patterns(Some(&exclude));
+                }
+                if let Some(ext) = ext {
+                    ingestion_params.extensions = split_patterns(Some(&ext));
+                }
+                if let Some(preset) = preset {
+                    ingestion_params.filter_preset = Some(preset).filter(|p| !p.is_empty());
+                }
+                if let Some(raw) = raw {
+                    ingestion_params.raw = raw;
+                }
+                let refilter = within(
+                    limits.timeout(),
+                    IngestionService::refilter(ingestion_params.clone()),
+                );
+                let Some(refiltered) = heartbeat.during(&mut socket, refilter).await else {
+                    break;
+                };
+                refiltered
+            }
+            Err(e) => Err(e.into()),
+        };
+
+        let sent = match refilter {
+            Ok(result) => {
+                heartbeat
+                    .send_within(send_result(&mut socket, &result, framing))
+                    .await
+            }
+            Err(e) => {
+                let failed = WebSocketMessage::Error {
+                    message: format!("Failed: {e}"),
+                };
+                send(&mut socket, &failed).await
+            }
+        };
+        if sent.is_err() {
+            break;
         }
     }
 }
 
-pub async fn serve(addr: SocketAddr) -> Result<()> {
-    let app = Router::new().route("/", get(websocket_handler));
-
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+pub async fn serve(addr: SocketAddr, tls: Option<TlsConfig>, state: AppState) -> Result<()> {
+    let router = Router::new()
+        .route("/", get(websocket_handler))
+        .with_state(state.clone());
+    let app = crate::access::layer(router, &state);
 
-    Ok(())
+    crate::tls::serve(addr, app, tls).await
 }
@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use githem_core::{apply_hunks, parse_patch, validate_write_path};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// apply a unified diff, e.g. a model's answer to --patch-mode output,
+/// to the files under `dir`; nothing is written unless every file applies
+pub fn run(patch: &str, dir: &Path, dry_run: bool, json: bool) -> Result<()> {
+    let text = if patch == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        fs::read_to_string(patch).with_context(|| format!("Failed to read {patch}"))?
+    };
+
+    // (path, new content, None for a deletion)
+    let mut changes: Vec<(PathBuf, Option<String>)> = Vec::new();
+    for file in parse_patch(&text)? {
+        let Some(path) = file.path() else {
+            continue;
+        };
+        validate_write_path(path).map_err(|e| anyhow::anyhow!("{path}: {e}"))?;
+        crate::extract::refuse_symlinks(dir, path)?;
+        let target = dir.join(path);
+
+        if file.is_deletion() {
+            if !target.is_file() {
+                return Err(anyhow::anyhow!(
+                    "{path}: cannot delete a file that does not exist"
+                ));
+            }
+            changes.push((target, None));
+            continue;
+        }
+
+        let original = if file.is_creation() {
+            String::new()
+        } else {
+            fs::read_to_string(&target).with_context(|| format!("{path}: cannot read file"))?
+        };
+        let patched = apply_hunks(&original, &file.hunks).with_context(|| path.to_string())?;
+        changes.push((target, Some(patched)));
+    }
+
+    if !dry_run {
+        for (target, content) in &changes {
+            match content {
+                Some(content) => {
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(target, content)?;
+                }
+                None => fs::remove_file(target)?,
+            }
+        }
+    }
+
+    if json {
+        let files: Vec<_> = changes
+            .iter()
+            .map(|(target, content)| {
+                serde_json::json!({
+                    "path": target.strip_prefix(dir).unwrap_or(target),
+                    "deleted": content.is_none(),
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::json!({ "applied": !dry_run, "files": files })
+        );
+        return Ok(());
+    }
+
+    for (target, content) in &changes {
+        let path = target.strip_prefix(dir).unwrap_or(target).display();
+        let verb = match (content, dry_run) {
+            (Some(_), false) => "Patched",
+            (Some(_), true) => "Would patch",
+            (None, false) => "Deleted",
+            (None, true) => "Would delete",
+        };
+        println!("✓ {verb} {path}");
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use clap::Subcommand;
+use githem_core::CacheManager;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// List cached repositories
+    List,
+    /// Remove the cache for a repository (URL, owner/repo or key prefix)
+    Rm { repo: String },
+    /// Remove entries not used recently
+    Prune {
+        /// Maximum age, e.g. 30m, 12h, 7d, 2w
+        #[arg(long, default_value = "7d", value_parser = parse_age)]
+        older_than: u64,
+    },
+    /// Print the cache directory
+    Path,
+}
+
+pub fn run(command: CacheCommand, json: bool) -> Result<()> {
+    match command {
+        CacheCommand::List => list(json),
+        CacheCommand::Rm { repo } => {
+            let removed = CacheManager::remove_repository(&repo)?;
+            if removed == 0 {
+                return Err(anyhow::anyhow!("No cache entries match '{repo}'"));
+            }
+            if json {
+                println!("{}", serde_json::json!({ "removed": removed }));
+                return Ok(());
+            }
+            println!(
+                "✓ Removed {removed} cache entr{}",
+                if removed == 1 { "y" } else { "ies" }
+            );
+            Ok(())
+        }
+        CacheCommand::Prune { older_than } => {
+            let removed = CacheManager::prune(older_than)?;
+            if json {
+                println!("{}", serde_json::json!({ "removed": removed }));
+                return Ok(());
+            }
+            println!(
+                "✓ Pruned {removed} cache entr{}",
+                if removed == 1 { "y" } else { "ies" }
+            );
+            Ok(())
+        }
+        CacheCommand::Path => {
+            let dir = CacheManager::cache_dir()?;
+            if json {
+                println!("{}", serde_json::json!({ "path": dir }));
+                return Ok(());
+            }
+            println!("{}", dir.display());
+            Ok(())
+        }
+    }
+}
+
+fn list(json: bool) -> Result<()> {
+    let entries = CacheManager::list_entries()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("Cache is empty");
+        return Ok(());
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    println!(
+        "{:<10} {:<50} {:<16} {:<10} {:>10} {:>6}",
+        "KEY", "REPOSITORY", "BRANCH", "COMMIT", "SIZE", "AGE"
+    );
+    for entry in entries {
+        let repo = if entry.repo_url.is_empty() {
+            "-"
+        } else {
+            entry
+                .repo_url
+                .trim_start_matches("https://")
+                .trim_start_matches("http://")
+        };
+        println!(
+            "{:<10} {:<50} {:<16} {:<10} {:>10} {:>6}",
+            &entry.key[..entry.key.len().min(8)],
+            repo,
+            if entry.branch.is_empty() {
+                "-"
+            } else {
+                &entry.branch
+            },
+            &entry.commit_hash[..entry.commit_hash.len().min(8)],
+            format!("{:.1} MB", entry.size as f64 / 1_048_576.0),
+            format_age(now.saturating_sub(entry.created_at)),
+        );
+    }
+
+    Ok(())
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
+
+/// "90", "30m", "12h", "7d", "2w" to seconds
+pub fn parse_age(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => value.split_at(idx),
+        None => (value, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid age '{value}', expected e.g. 7d"))?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => return Err(format!("invalid age unit '{unit}', use s, m, h, d or w")),
+    };
+    Ok(number * multiplier)
+}
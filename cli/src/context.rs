@@ -0,0 +1,54 @@
+use anyhow::Result;
+use githem_core::{IngestOptions, Ingester};
+use serde_json::json;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// "32000", "32k", "1m" to a token count
+pub fn parse_budget(value: &str) -> Result<usize, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.to_ascii_lowercase().strip_suffix('k') {
+        Some(number) => (number.to_string(), 1_000),
+        None => match value.to_ascii_lowercase().strip_suffix('m') {
+            Some(number) => (number.to_string(), 1_000_000),
+            None => (value.to_string(), 1),
+        },
+    };
+    let number: usize = number
+        .parse()
+        .map_err(|_| format!("invalid budget '{value}', expected e.g. 32k"))?;
+    Ok(number * multiplier)
+}
+
+/// the cursor location in `file` and the files around it within the token
+/// budget, for binding to an editor keystroke
+pub fn run(
+    dir: &Path,
+    file: &Path,
+    line: Option<usize>,
+    depth: usize,
+    budget: usize,
+    json: bool,
+) -> Result<()> {
+    let ingester = Ingester::from_path(dir, IngestOptions::default())?;
+
+    if json {
+        let mut content = Vec::new();
+        let selected = ingester.ingest_context(&mut content, file, line, depth, budget)?;
+        let files: Vec<_> = selected
+            .iter()
+            .map(|file| json!({ "path": file.path, "depth": file.depth, "tokens": file.tokens }))
+            .collect();
+        let result = json!({
+            "content": String::from_utf8_lossy(&content),
+            "files": files,
+        });
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+
+    let mut output = io::BufWriter::new(io::stdout());
+    ingester.ingest_context(&mut output, file, line, depth, budget)?;
+    output.flush()?;
+    Ok(())
+}
@@ -0,0 +1,332 @@
+use anyhow::{Context, Result};
+use githem_core::{
+    is_remote_url, normalize_source_url, split_files, FilterPreset, IngestOptions, Ingester,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const PRESETS: [FilterPreset; 6] = [
+    FilterPreset::Raw,
+    FilterPreset::Standard,
+    FilterPreset::CodeOnly,
+    FilterPreset::Minimal,
+    FilterPreset::DocsOnly,
+    FilterPreset::TestsOnly,
+];
+
+/// params of every method, the ones a method does not use are ignored
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Params {
+    /// local path or repository url, like the CLI source argument
+    source: String,
+    branch: Option<String>,
+    path: Option<String>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    ext: Vec<String>,
+    preset: Option<String>,
+    /// diff: refs to compare, head defaults to HEAD
+    base: Option<String>,
+    head: Option<String>,
+    context: Option<u32>,
+    /// tokens: count exactly with this tiktoken model instead of estimating
+    model: Option<String>,
+    /// context: the file and 1-based line the cursor is at
+    file: Option<PathBuf>,
+    line: Option<usize>,
+    depth: Option<usize>,
+    budget: Option<usize>,
+}
+
+struct Daemon {
+    /// when each remote branch was last fetched
+    fetched: Mutex<HashMap<String, Instant>>,
+    /// ingests within this long of a fetch reuse the checkout as it is
+    fresh: Duration,
+}
+
+impl Daemon {
+    fn options(params: &Params) -> Result<(String, IngestOptions)> {
+        let (url, branch, prefix) =
+            normalize_source_url(&params.source, params.branch.clone(), params.path.clone())
+                .map_err(anyhow::Error::msg)?;
+
+        let preset = match params.preset.as_deref() {
+            Some(name) => PRESETS
+                .into_iter()
+                .find(|preset| preset.as_str() == name)
+                .with_context(|| format!("Unknown preset '{}'", name))?,
+            None => FilterPreset::Standard,
+        };
+        let mut builder = IngestOptions::builder()
+            .preset(preset)
+            .includes(&params.include)
+            .excludes(&params.exclude)
+            .extensions(&params.ext);
+        if let Some(branch) = &branch {
+            builder = builder.branch(branch);
+        }
+        if let Some(prefix) = &prefix {
+            builder = builder.path_prefix(prefix);
+        }
+        Ok((url, builder.build()?))
+    }
+
+    /// local repositories are read as they are, remotes are fetched unless
+    /// `fetch` is off or they were fetched within the fresh window
+    fn open(&self, params: &Params, fetch: bool) -> Result<Ingester> {
+        let (url, options) = Self::options(params)?;
+        if !is_remote_url(&url) {
+            return Ingester::from_path(Path::new(&url), options);
+        }
+
+        let key = format!("{}#{}", url, options.branch.as_deref().unwrap_or_default());
+        let warm = self
+            .fetched
+            .lock()
+            .unwrap()
+            .get(&key)
+            .is_some_and(|at| at.elapsed() < self.fresh);
+        if warm || !fetch {
+            return Ingester::from_checkout(&url, options);
+        }
+
+        let ingester = Ingester::from_url_cached(&url, options)?;
+        self.fetched.lock().unwrap().insert(key, Instant::now());
+        Ok(ingester)
+    }
+
+    fn ingest(&self, params: &Params, fetch: bool) -> Result<Value> {
+        let mut ingester = self.open(params, fetch)?;
+        let (content, report) = ingester.ingest_to_string()?;
+        Ok(json!({
+            "content": content,
+            "files": report.file_count(),
+            "bytes": report.total_size(),
+            "tokens": report.total_tokens(),
+        }))
+    }
+
+    fn diff(&self, params: &Params) -> Result<Value> {
+        let base = params.base.as_deref().context("diff needs a base ref")?;
+        let head = params.head.as_deref().unwrap_or("HEAD");
+
+        let (url, options) = Self::options(params)?;
+        let ingester = if is_remote_url(&url) {
+            Ingester::from_url_compare(&url, base, head, options)?
+        } else {
+            Ingester::from_path(Path::new(&url), options)?
+        };
+        Ok(json!({ "diff": ingester.generate_diff(base, head, params.context)? }))
+    }
+
+    /// the same output as `githem context`, remotes read from their last
+    /// checkout as they are
+    fn context(&self, params: &Params) -> Result<Value> {
+        let file = params.file.as_deref().context("context needs a file")?;
+        let ingester = self.open(params, false)?;
+
+        let mut content = Vec::new();
+        let selected = ingester.ingest_context(
+            &mut content,
+            file,
+            params.line,
+            params.depth.unwrap_or(2),
+            params.budget.unwrap_or(32_000),
+        )?;
+        let files: Vec<_> = selected
+            .iter()
+            .map(|file| json!({ "path": file.path, "depth": file.depth, "tokens": file.tokens }))
+            .collect();
+        Ok(json!({
+            "content": String::from_utf8_lossy(&content),
+            "files": files,
+        }))
+    }
+
+    /// the same report as `--tokens --json`, largest files first
+    fn tokens(&self, params: &Params) -> Result<Value> {
+        let bpe = params
+            .model
+            .as_deref()
+            .map(crate::tokens::tokenizer)
+            .transpose()?;
+        let mut ingester = self.open(params, true)?;
+        let (content, report) = ingester.ingest_to_string()?;
+
+        let mut files: Vec<(String, usize)> = match &bpe {
+            Some(bpe) => split_files(&content)
+                .into_iter()
+                .map(|(path, body)| (path.to_string(), bpe.encode_ordinary(body).len()))
+                .collect(),
+            None => report
+                .files
+                .iter()
+                .map(|file| (file.path.display().to_string(), file.tokens))
+                .collect(),
+        };
+        files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let total: usize = files.iter().map(|(_, tokens)| tokens).sum();
+        let files: Vec<_> = files
+            .iter()
+            .map(|(path, tokens)| json!({ "path": path, "tokens": tokens }))
+            .collect();
+        Ok(json!({
+            "model": params.model.as_deref().unwrap_or("estimate"),
+            "total_tokens": total,
+            "files": files,
+        }))
+    }
+
+    /// one JSON-RPC 2.0 request in, its response out
+    fn handle(&self, line: &str) -> Value {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return error(Value::Null, -32700, format!("Parse error: {}", e)),
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return error(id, -32600, "Request without a method".to_string());
+        };
+        let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+        let params: Params = match serde_json::from_value(params) {
+            Ok(params) => params,
+            Err(e) => return error(id, -32602, format!("Invalid params: {}", e)),
+        };
+
+        let result = match method {
+            "ingest" => self.ingest(&params, true),
+            "refilter" => self.ingest(&params, false),
+            "diff" => self.diff(&params),
+            "tokens" => self.tokens(&params),
+            "context" => self.context(&params),
+            _ => return error(id, -32601, format!("Unknown method '{}'", method)),
+        };
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error(id, -32000, format!("{:#}", e)),
+        }
+    }
+}
+
+fn error(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// $XDG_RUNTIME_DIR/githem.sock, or githem.sock in a directory of the temp
+/// dir private to the user
+#[cfg(unix)]
+pub fn default_socket() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("githem.sock"),
+        None => {
+            let uid = unsafe { libc::geteuid() };
+            std::env::temp_dir()
+                .join(format!("githem-{uid}"))
+                .join("githem.sock")
+        }
+    }
+}
+
+/// create `dir` readable by its owner only, or make sure the existing one is
+/// ours and not open to others; the temp dir is shared by every user
+#[cfg(unix)]
+fn private_dir(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to create {}", dir.display())),
+    }
+    let metadata = std::fs::symlink_metadata(dir)?;
+    if !metadata.is_dir()
+        || metadata.uid() != unsafe { libc::geteuid() }
+        || metadata.mode() & 0o077 != 0
+    {
+        anyhow::bail!("{} is not a private directory of this user", dir.display());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn run(socket: Option<PathBuf>, fresh_secs: u64) -> Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::Arc;
+
+    let socket = match socket {
+        Some(socket) => socket,
+        None => {
+            let socket = default_socket();
+            if std::env::var_os("XDG_RUNTIME_DIR").is_none_or(|dir| dir.is_empty()) {
+                private_dir(socket.parent().expect("socket in a directory"))?;
+            }
+            socket
+        }
+    };
+    if let Ok(metadata) = std::fs::symlink_metadata(&socket) {
+        // someone else's socket could answer in our place or be a trap
+        if !metadata.file_type().is_socket() || metadata.uid() != unsafe { libc::geteuid() } {
+            anyhow::bail!(
+                "{} is not a socket of this user, refusing to use it",
+                socket.display()
+            );
+        }
+        if UnixStream::connect(&socket).is_ok() {
+            anyhow::bail!(
+                "A githem daemon is already listening on {}",
+                socket.display()
+            );
+        }
+        // left behind by a daemon that did not shut down cleanly
+        std::fs::remove_file(&socket)?;
+    }
+
+    let listener = UnixListener::bind(&socket)
+        .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+    std::fs::set_permissions(&socket, std::fs::Permissions::from_mode(0o600))?;
+    eprintln!("githem daemon listening on {}", socket.display());
+
+    let daemon = Arc::new(Daemon {
+        fetched: Mutex::new(HashMap::new()),
+        fresh: Duration::from_secs(fresh_secs),
+    });
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Warning: failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        let daemon = daemon.clone();
+        std::thread::spawn(move || -> std::io::Result<()> {
+            let mut writer = stream.try_clone()?;
+            for line in BufReader::new(stream).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let response = daemon.handle(&line);
+                writeln!(writer, "{}", response)?;
+            }
+            Ok(())
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn run(_socket: Option<PathBuf>, _fresh_secs: u64) -> Result<()> {
+    anyhow::bail!("githem daemon needs Unix domain sockets")
+}
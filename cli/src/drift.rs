@@ -0,0 +1,54 @@
+use anyhow::Result;
+use githem_core::{diff_outputs, fingerprint, split_files};
+use serde_json::json;
+use std::path::Path;
+
+pub fn print_fingerprint(content: &str, json: bool) -> Result<()> {
+    let fingerprint = fingerprint(content);
+    if json {
+        let files = split_files(content).len();
+        println!("{}", json!({ "fingerprint": fingerprint, "files": files }));
+        return Ok(());
+    }
+    println!("{fingerprint}");
+    Ok(())
+}
+
+/// what changed between `dump` and the current output, so a cached prompt
+/// context is only regenerated when it is stale
+pub fn report(content: &str, dump: &Path, exit_code: bool, json: bool) -> Result<()> {
+    let previous = crate::extract::read_dump(dump)?;
+    if split_files(&previous).is_empty() {
+        return Err(anyhow::anyhow!(
+            "No `=== path ===` file sections in {}",
+            dump.display()
+        ));
+    }
+    let drift = diff_outputs(&previous, content);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&drift)?);
+    } else {
+        for path in &drift.modified {
+            println!("M {path}");
+        }
+        for path in &drift.added {
+            println!("A {path}");
+        }
+        for path in &drift.removed {
+            println!("D {path}");
+        }
+        eprintln!(
+            "{} modified, {} added, {} removed, {} unchanged",
+            drift.modified.len(),
+            drift.added.len(),
+            drift.removed.len(),
+            drift.unchanged
+        );
+    }
+
+    if exit_code && !drift.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
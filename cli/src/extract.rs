@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use githem_core::{extract_files, validate_write_path, ExtractedBody};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// the text of a dump, decompressing --compress gzip / zstd output
+pub fn read_dump(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let bytes = match bytes.as_slice() {
+        [0x1f, 0x8b, ..] => {
+            let mut text = Vec::new();
+            flate2::read::GzDecoder::new(bytes.as_slice()).read_to_end(&mut text)?;
+            text
+        }
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => zstd::decode_all(bytes.as_slice())?,
+        _ => bytes,
+    };
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// fails when `path` under `root`, or a directory on the way to it, is a
+/// symlink: writing through one could land anywhere outside `root`
+pub fn refuse_symlinks(root: &Path, path: &str) -> Result<()> {
+    let mut current = root.to_path_buf();
+    for part in path.split('/').filter(|part| !part.is_empty()) {
+        current.push(part);
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(anyhow::anyhow!(
+                    "{} is a symlink, not writing through it",
+                    current.display()
+                ));
+            }
+            Ok(_) => {}
+            // nothing below a missing directory exists yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// write the files of a githem output back to disk under `out`
+pub fn run(dump: &Path, out: &Path, force: bool, dry_run: bool, json: bool) -> Result<()> {
+    let files = extract_files(&read_dump(dump)?);
+    if files.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No `=== path ===` file sections in {}",
+            dump.display()
+        ));
+    }
+
+    // check everything before writing anything
+    for file in &files {
+        validate_write_path(&file.path).map_err(|e| anyhow::anyhow!("{}: {e}", file.path))?;
+        refuse_symlinks(out, &file.path)?;
+        if !force && out.join(&file.path).exists() {
+            return Err(anyhow::anyhow!(
+                "{} already exists, use --force to overwrite",
+                out.join(&file.path).display()
+            ));
+        }
+    }
+
+    let mut written = Vec::new();
+    let mut skipped = Vec::new();
+    for file in &files {
+        let ExtractedBody::Text(ref content) = file.body else {
+            skipped.push(file);
+            continue;
+        };
+        if !dry_run {
+            let target = out.join(&file.path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, content)?;
+        }
+        written.push(file);
+    }
+
+    if json {
+        let path = |file: &&githem_core::ExtractedFile| file.path.clone();
+        println!(
+            "{}",
+            serde_json::json!({
+                "written": written.iter().map(path).collect::<Vec<_>>(),
+                "skipped": skipped.iter().map(path).collect::<Vec<_>>(),
+                "dry_run": dry_run,
+            })
+        );
+        return Ok(());
+    }
+
+    let verb = if dry_run {
+        "Would extract"
+    } else {
+        "Extracted"
+    };
+    println!("✓ {verb} {} files to {}", written.len(), out.display());
+    for file in skipped {
+        let reason = match file.body {
+            ExtractedBody::Binary => "binary, not in the dump".to_string(),
+            ExtractedBody::Symlink(ref target) => format!("symlink to {target}"),
+            ExtractedBody::Text(_) => unreachable!("text files are written"),
+        };
+        eprintln!("  skipped {} ({reason})", file.path);
+    }
+    Ok(())
+}
@@ -0,0 +1,285 @@
+use anyhow::{Context, Result};
+use clap::{Subcommand, ValueEnum};
+use git2::build::CheckoutBuilder;
+use git2::{Delta, DiffOptions, Index, Oid, Repository, Signature};
+use githem_core::{validate_write_path, FilterPreset, IngestOptions, Ingester, ScratchDir};
+use serde::Deserialize;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+/// marks hook scripts written by `githem hook install`
+const MARKER: &str = "# installed by githem hook install";
+
+#[derive(Subcommand)]
+pub enum HookCommand {
+    /// Install a git hook that regenerates the context file from .githem.toml
+    Install {
+        #[arg(long, value_enum, default_value = "pre-push")]
+        hook: HookKind,
+        /// Replace a hook that githem did not install
+        #[arg(long)]
+        force: bool,
+    },
+    /// Regenerate the context file if sources changed, run by the hook
+    #[command(hide = true)]
+    Run {
+        #[arg(value_enum)]
+        hook: HookKind,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum HookKind {
+    /// stage the regenerated file with the commit
+    PreCommit,
+    /// stop the push until the regenerated file is committed
+    PrePush,
+}
+
+impl HookKind {
+    fn file_name(self) -> &'static str {
+        match self {
+            HookKind::PreCommit => "pre-commit",
+            HookKind::PrePush => "pre-push",
+        }
+    }
+}
+
+/// `.githem.toml` at the repository root
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProjectConfig {
+    /// file kept up to date, relative to the repository root
+    output: PathBuf,
+    preset: Option<String>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    ext: Vec<String>,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
+            output: PathBuf::from("CONTEXT.md"),
+            preset: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            ext: Vec::new(),
+        }
+    }
+}
+
+impl ProjectConfig {
+    fn load(workdir: &Path) -> Result<Self> {
+        let path = workdir.join(".githem.toml");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(&path)?;
+        let config: Self =
+            toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+
+        // the file comes with the repository, its output must stay inside it
+        let output = config.output.to_string_lossy();
+        validate_write_path(&output)
+            .map_err(|e| anyhow::anyhow!("Invalid output in {}: {e}", path.display()))?;
+        crate::extract::refuse_symlinks(workdir, &output)?;
+        Ok(config)
+    }
+
+    fn options(&self) -> Result<IngestOptions> {
+        let preset = match self.preset.as_deref() {
+            Some(name) => crate::FilterPresetArg::from_str(name, true)
+                .map_err(|_| anyhow::anyhow!("Unknown preset '{}' in .githem.toml", name))?
+                .into(),
+            None => FilterPreset::Standard,
+        };
+        // the output would otherwise end up inside the next version of itself
+        let output = format!("/{}", self.output.to_string_lossy().replace('\\', "/"));
+        IngestOptions::builder()
+            .preset(preset)
+            .includes(&self.include)
+            .excludes(&self.exclude)
+            .exclude(output)
+            .extensions(&self.ext)
+            .build()
+    }
+}
+
+pub fn run(command: HookCommand) -> Result<()> {
+    let repo = Repository::discover(".").context("Not inside a git repository")?;
+    match command {
+        HookCommand::Install { hook, force } => install(&repo, hook, force),
+        HookCommand::Run { hook } => refresh(&repo, hook),
+    }
+}
+
+fn install(repo: &Repository, hook: HookKind, force: bool) -> Result<()> {
+    let hooks = match repo.config()?.get_path("core.hooksPath") {
+        Ok(dir) if dir.is_absolute() => dir,
+        Ok(dir) => repo.workdir().unwrap_or(repo.path()).join(dir),
+        Err(_) => repo.commondir().join("hooks"),
+    };
+    let path = hooks.join(hook.file_name());
+
+    if path.exists() && !force {
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        if !existing.contains(MARKER) {
+            return Err(anyhow::anyhow!(
+                "{} already exists, use --force to replace it",
+                path.display()
+            ));
+        }
+    }
+
+    let script = format!(
+        "#!/bin/sh\n{MARKER}\n\
+         command -v githem >/dev/null 2>&1 || {{ echo \"githem not found, context not refreshed\" >&2; exit 0; }}\n\
+         exec githem hook run {}\n",
+        hook.file_name()
+    );
+    fs::create_dir_all(&hooks)?;
+    fs::write(&path, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    println!("✓ Installed {}", path.display());
+    Ok(())
+}
+
+/// regenerates the output when files other than it changed, pre-commit
+/// stages it, pre-push fails so it can be committed before pushing again
+fn refresh(repo: &Repository, hook: HookKind) -> Result<()> {
+    let workdir = repo
+        .workdir()
+        .context("Repository has no working directory")?;
+    let config = ProjectConfig::load(workdir)?;
+
+    let changed = match hook {
+        HookKind::PreCommit => staged_paths(repo)?,
+        HookKind::PrePush => pushed_paths(repo)?,
+    };
+    if !changed.iter().any(|path| *path != config.output) {
+        return Ok(());
+    }
+
+    // what is being committed or pushed, not the working tree around it
+    let tree = match hook {
+        HookKind::PreCommit => index(repo)?.write_tree()?,
+        HookKind::PrePush => repo.head()?.peel_to_tree()?.id(),
+    };
+    let snapshot = ScratchDir::new("hook")?;
+    let mut ingester = Ingester::new(
+        checkout_tree(repo, tree, snapshot.path())?,
+        config.options()?,
+    );
+    let (content, _) = ingester.ingest_to_string()?;
+    let path = workdir.join(&config.output);
+    if fs::read_to_string(&path).is_ok_and(|existing| existing == content) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+
+    match hook {
+        HookKind::PreCommit => {
+            let mut index = index(repo)?;
+            index.add_path(&config.output)?;
+            index.write()?;
+            eprintln!("githem: regenerated {}", config.output.display());
+            Ok(())
+        }
+        HookKind::PrePush => Err(anyhow::anyhow!(
+            "{} was out of date and has been regenerated, commit it and push again",
+            config.output.display()
+        )),
+    }
+}
+
+/// a repository in `dir` with `tree` of `repo` checked out as its only
+/// commit, borrowing the objects of `repo` instead of copying them
+fn checkout_tree(repo: &Repository, tree: Oid, dir: &Path) -> Result<Repository> {
+    let snapshot = Repository::init(dir)?;
+    let objects = repo.commondir().join("objects");
+    fs::write(
+        snapshot.path().join("objects/info/alternates"),
+        format!("{}\n", objects.display()),
+    )?;
+
+    // reopened, the object database only reads the alternates when it opens
+    let snapshot = Repository::open(dir)?;
+    {
+        let tree = snapshot.find_tree(tree)?;
+        let signature = Signature::now("githem", "githem@localhost")?;
+        snapshot.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "githem hook snapshot",
+            &tree,
+            &[],
+        )?;
+        snapshot.checkout_head(Some(CheckoutBuilder::new().force()))?;
+    }
+    Ok(snapshot)
+}
+
+/// the index being committed, `git commit -a` and `--only` hand hooks a
+/// temporary one in GIT_INDEX_FILE
+fn index(repo: &Repository) -> Result<Index> {
+    let Some(path) = std::env::var_os("GIT_INDEX_FILE") else {
+        return Ok(repo.index()?);
+    };
+    let mut index = Index::open(Path::new(&path))?;
+    repo.set_index(&mut index)?;
+    Ok(index)
+}
+
+fn staged_paths(repo: &Repository) -> Result<Vec<PathBuf>> {
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_index(head.as_ref(), Some(&index(repo)?), None)?;
+    Ok(diff_paths(&diff))
+}
+
+/// files changed by the pushed commits, read from the `<local ref> <local
+/// sha> <remote ref> <remote sha>` lines git passes on stdin
+fn pushed_paths(repo: &Repository) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [_, local, _, remote] = fields[..] else {
+            continue;
+        };
+        let local = Oid::from_str(local)?;
+        if local.is_zero() {
+            // a deleted ref pushes nothing
+            continue;
+        }
+        let new = repo.find_commit(local)?.tree()?;
+        // a new branch, or a remote commit missing here, counts as all files
+        let old = Oid::from_str(remote)
+            .ok()
+            .filter(|oid| !oid.is_zero())
+            .and_then(|oid| repo.find_commit(oid).ok())
+            .and_then(|commit| commit.tree().ok());
+        let diff =
+            repo.diff_tree_to_tree(old.as_ref(), Some(&new), Some(&mut DiffOptions::new()))?;
+        paths.extend(diff_paths(&diff));
+    }
+    Ok(paths)
+}
+
+fn diff_paths(diff: &git2::Diff) -> Vec<PathBuf> {
+    diff.deltas()
+        .filter(|delta| delta.status() != Delta::Unmodified)
+        .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+        .map(Path::to_path_buf)
+        .collect()
+}
@@ -1,11 +1,30 @@
+mod apply;
+mod cache;
+mod context;
+mod daemon;
+mod drift;
+mod extract;
+mod hook;
+mod prefetch;
+mod progress;
+mod share;
+mod split;
+mod suggest;
+mod telemetry;
+mod tokens;
+
 use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use githem_core::{
-    checkout_branch, is_remote_url, parse_github_url, CacheManager, FilterPreset, GitHubUrlType,
-    IngestOptions, Ingester,
+    checkout_branch, chunk_output, configure_network, generate_tree_with_depth, is_remote_url,
+    parse_compare_spec, parse_github_url, search_output, set_passphrase_prompt, split_revision,
+    validate_path_prefix, validate_ref_name, CacheManager, ChunkOptions, EmbeddingBackend,
+    FilterPreset, FocusOptions, GitHubUrlType, HeaderDetail, IngestOptions, Ingester,
+    LexicalEmbedder, NetworkConfig, PatternSyntax, RelevanceOptions, SearchOptions, Transform,
+    PATCH_INSTRUCTIONS,
 };
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -13,6 +32,9 @@ use std::path::PathBuf;
 #[command(about = "Transform git repositories into LLM-ready text", long_about = None)]
 #[command(version, author = "Rotko Networks <hq@rotko.net>")]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Repository source
     #[arg(default_value = ".")]
     source: String,
@@ -21,7 +43,8 @@ struct Cli {
     #[arg(short, long)]
     output: Option<PathBuf>,
 
-    /// Include only files matching pattern (use trailing / for directories)
+    /// Include only files matching pattern (gitignore-style: trailing / for
+    /// directories, leading / to anchor at the root, (?i) to ignore case)
     #[arg(short, long)]
     include: Vec<String>,
 
@@ -29,27 +52,130 @@ struct Cli {
     #[arg(short, long)]
     exclude: Vec<String>,
 
+    /// Only files with these extensions, e.g. --ext rs,toml,proto
+    /// (narrows --include instead of adding to it)
+    #[arg(long, value_delimiter = ',')]
+    ext: Vec<String>,
+
     /// Maximum file size in bytes
     #[arg(short = 's', long, default_value = "1048576")]
     max_size: usize,
 
+    /// Skip files smaller than this many bytes (e.g. one-line re-exports)
+    #[arg(long, default_value = "0")]
+    min_size: usize,
+
+    /// Cut files off after this many lines
+    #[arg(long)]
+    max_lines: Option<usize>,
+
+    /// Stop before the file contents exceed this many bytes, listing the
+    /// files left out at the end
+    #[arg(long, value_name = "BYTES")]
+    max_total_size: Option<usize>,
+
+    /// Stop before the output exceeds this many estimated tokens, e.g. 200k
+    #[arg(long, value_name = "TOKENS", value_parser = context::parse_budget)]
+    max_total_tokens: Option<usize>,
+
+    /// Keep only this many files, READMEs, manifests, entry points and the
+    /// most imported files first, counting the rest per directory
+    #[arg(long, value_name = "N")]
+    max_files: Option<usize>,
+
     /// Branch to checkout
     #[arg(short, long)]
     branch: Option<String>,
 
+    /// Ingest several branches of a remote repository into one output, a
+    /// section each, e.g. --branches main,develop
+    #[arg(long, value_delimiter = ',', conflicts_with = "branch")]
+    branches: Vec<String>,
+
+    /// Attempts at a clone or fetch failing on a network error, after the
+    /// first, with exponential backoff
+    #[arg(long, value_name = "N", default_value_t = 2, global = true)]
+    retries: u32,
+
     /// Include untracked files
     #[arg(short = 'u', long)]
     untracked: bool,
 
-    /// Path prefix to filter
-    #[arg(short = 'p', long)]
-    path_prefix: Option<String>,
+    /// Inline symlinked files that stay inside the repository
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Match --include/--exclude with the prefix rules of earlier releases
+    #[arg(long)]
+    legacy_patterns: bool,
+
+    /// Keep license files as they are instead of a one line summary
+    #[arg(long)]
+    no_compress_license: bool,
+
+    /// Content transforms to apply, e.g. --transform strip-comments,redact-secrets
+    #[arg(long, value_delimiter = ',')]
+    transform: Vec<TransformArg>,
+
+    /// Prefix each content line with its number, as it is written after
+    /// any other transform
+    #[arg(long)]
+    line_numbers: bool,
+
+    /// Number lines and end the output with instructions to answer in
+    /// unified diff format, for `githem apply`
+    #[arg(long)]
+    patch_mode: bool,
+
+    /// Summarize directories above this many tokens (default 20000) with their
+    /// files, public symbols and token counts instead of the full contents
+    #[arg(long, value_name = "TOKENS", num_args = 0..=1, default_missing_value = "20000")]
+    summarize_dirs: Option<usize>,
+
+    /// READMEs and architecture docs first and whatever the preset, after a
+    /// section with the doc comments of the source files
+    #[arg(long)]
+    docs_first: bool,
+
+    /// Section mapping paths to owners from CODEOWNERS, MAINTAINERS and
+    /// Cargo.toml / package.json authors
+    #[arg(long)]
+    ownership: bool,
+
+    /// CI workflows, Dockerfiles and Makefiles whatever the preset, in a
+    /// Build & CI section ahead of the file tree
+    #[arg(long)]
+    build_ci: bool,
+
+    /// Also include the tests of included source files (tests/foo.rs,
+    /// foo_test.go, foo.spec.ts), whatever the preset
+    #[arg(long)]
+    with_tests: bool,
+
+    /// Also include the sources of included test files, e.g. with --preset tests-only
+    #[arg(long)]
+    with_sources: bool,
+
+    /// Metadata next to each file path: language, size and tokens (basic),
+    /// plus blob id and last commit date (full)
+    #[arg(long, value_enum, default_value = "path")]
+    header_detail: HeaderDetailArg,
+
+    /// Only files below this directory, repeat for several:
+    /// --path src/core --path proto
+    #[arg(short = 'p', long, visible_alias = "path")]
+    path_prefix: Vec<String>,
+
+    /// Leave out everything below this directory, repeat for several:
+    /// --exclude-path vendor/legacy
+    #[arg(long, value_name = "PATH")]
+    exclude_path: Vec<String>,
 
     /// Quiet mode
     #[arg(short = 'q', long)]
     quiet: bool,
 
-    /// Filter preset: raw, standard, code-only, minimal
+    /// Filter preset: raw, standard, code-only, minimal, docs-only, tests-only
     #[arg(long, value_enum)]
     preset: Option<FilterPresetArg>,
 
@@ -76,6 +202,241 @@ struct Cli {
     /// Force refresh (ignore cache)
     #[arg(long, short = 'f')]
     force: bool,
+
+    /// Search the ingested files for a pattern instead of printing them
+    #[arg(long, value_name = "PATTERN")]
+    grep: Option<String>,
+
+    /// Treat the --grep pattern as a regular expression
+    #[arg(long, requires = "grep")]
+    regex: bool,
+
+    /// Lines of context around each --grep match
+    #[arg(long, default_value = "2", requires = "grep")]
+    context: usize,
+
+    /// Only include the files most relevant to this question, ranked by the
+    /// words they share with it unless --embedding-api is given
+    #[arg(long, value_name = "QUERY")]
+    relevant_to: Option<String>,
+
+    /// Maximum number of files kept by --relevant-to
+    #[arg(long, default_value = "20", requires = "relevant_to")]
+    top_k: usize,
+
+    /// Estimated token budget for --relevant-to and --focus
+    #[arg(long, default_value = "100000")]
+    token_budget: usize,
+
+    /// Only include this file and the files it imports, transitively (repeatable)
+    #[arg(long, value_name = "PATH", conflicts_with = "relevant_to")]
+    focus: Vec<String>,
+
+    /// How many imports away from the --focus files to follow
+    #[arg(long, default_value = "2", requires = "focus")]
+    focus_depth: usize,
+
+    /// OpenAI-compatible embeddings endpoint for semantic ranking (default:
+    /// local lexical matching, no model)
+    #[arg(long, value_name = "URL", requires = "relevant_to")]
+    embedding_api: Option<String>,
+
+    /// Model name sent to --embedding-api
+    #[arg(long, default_value = "text-embedding-3-small")]
+    embedding_model: String,
+
+    /// Emit JSONL chunks of at most this many tokens instead of one text dump
+    #[arg(long, value_name = "TOKENS")]
+    chunk: Option<usize>,
+
+    /// Tokens shared between consecutive chunks
+    #[arg(long, default_value = "200", requires = "chunk")]
+    overlap: usize,
+
+    /// Proxy for remote repositories (default: HTTPS_PROXY / HTTP_PROXY)
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Additional CA bundle (PEM) to trust (default: GITHEM_CA_BUNDLE)
+    #[arg(long, value_name = "PATH")]
+    ca_bundle: Option<PathBuf>,
+
+    /// Never touch the network, remote repositories are read from their
+    /// cached checkout as last fetched (default: GITHEM_OFFLINE)
+    #[arg(long, conflicts_with_all = ["no_cache", "force", "share"])]
+    offline: bool,
+
+    /// Print a shareable link pinned to the current commit instead of the content
+    #[arg(long)]
+    share: bool,
+
+    /// Expire the share link after this long, e.g. 12h, 7d
+    #[arg(long, value_name = "AGE", requires = "share", value_parser = cache::parse_age)]
+    share_expires: Option<u64>,
+
+    /// Print the included paths, one per line, without reading any contents
+    #[arg(long, conflicts_with_all = ["tree_only", "tokens"])]
+    list: bool,
+
+    /// Print only the file structure header
+    #[arg(long, conflicts_with = "tokens")]
+    tree_only: bool,
+
+    /// With --tree-only, collapse directories deeper than this into a file count
+    #[arg(long, value_name = "DEPTH", requires = "tree_only")]
+    tree_depth: Option<usize>,
+
+    /// Show which files would be ingested without writing any content
+    #[arg(long, conflicts_with_all = ["list", "tree_only", "tokens"])]
+    dry_run: bool,
+
+    /// With --dry-run, list every file with the rule that includes or excludes it
+    #[arg(long, requires = "dry_run")]
+    explain: bool,
+
+    /// Print exact per-file token counts instead of the content
+    #[arg(long)]
+    tokens: bool,
+
+    /// Model whose tokenizer --tokens counts with
+    #[arg(long, default_value = "gpt-4o", requires = "tokens")]
+    model: String,
+
+    /// Write one output file per directory into DIR instead of a single dump
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["output", "list", "tree_only", "dry_run", "tokens", "grep", "chunk", "relevant_to", "focus"]
+    )]
+    output_dir: Option<PathBuf>,
+
+    /// How --output-dir groups files
+    #[arg(
+        long,
+        value_enum,
+        default_value = "top-level-dir",
+        requires = "output_dir"
+    )]
+    split_by: split::SplitBy,
+
+    /// Compress the output stream
+    #[arg(long, value_enum)]
+    compress: Option<CompressionArg>,
+
+    /// Print --stats, --cache-stats, --tokens and cache commands as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// githem server used for --share
+    #[arg(long, env = "GITHEM_API_URL", default_value = "https://githem.com")]
+    api_url: String,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect and manage the local repository cache
+    #[command(subcommand)]
+    Cache(cache::CacheCommand),
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print the roff man page
+    Man,
+    /// Keep repositories warm and serve ingest, refilter, diff, tokens and context
+    /// to editor plugins as JSON-RPC over a Unix socket, one request per line
+    Daemon {
+        /// Socket path (default: $XDG_RUNTIME_DIR/githem.sock, else a private
+        /// githem-<uid> directory in the temp dir)
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        /// Seconds after a fetch during which ingests skip fetching again
+        #[arg(long, default_value_t = 30)]
+        fresh: u64,
+    },
+    /// Print the lines around a cursor position followed by the file and the
+    /// files it imports, sized for an editor keystroke
+    Context {
+        /// File the cursor is in, relative to the repository or absolute
+        #[arg(long)]
+        file: PathBuf,
+        /// Cursor line, 1-based
+        #[arg(long)]
+        line: Option<usize>,
+        /// Estimated token budget, e.g. 32k
+        #[arg(long, default_value = "32k", value_parser = context::parse_budget)]
+        budget: usize,
+        /// How many imports away from the file to follow
+        #[arg(long, default_value_t = 2)]
+        depth: usize,
+        /// Repository the file belongs to
+        #[arg(short = 'C', long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Print a hash of the filtered files and their contents that only changes
+    /// when they do, e.g. `githem -i src/ owner/repo fingerprint`
+    Fingerprint,
+    /// List the files that changed since an earlier output of the same source
+    /// and filters
+    DiffContext {
+        /// Previous output (plain, gzip or zstd)
+        dump: PathBuf,
+        /// Exit with status 1 when any file changed
+        #[arg(long)]
+        exit_code: bool,
+    },
+    /// Suggest exclude patterns that bring the output under a token budget,
+    /// with the tokens each one saves
+    SuggestExcludes {
+        /// Estimated token budget, e.g. 100k
+        #[arg(long, default_value = "100k", value_parser = context::parse_budget)]
+        budget: usize,
+    },
+    /// Clone or fetch a repository into the cache and index it without any
+    /// output, e.g. from cron or a CI image build
+    Prefetch {
+        /// owner/repo or a repository URL
+        repo: String,
+        /// Branches to prefetch, e.g. main,develop, or all of them with `all`
+        /// (default: the branch of the URL, else the default branch)
+        #[arg(long, value_delimiter = ',')]
+        branches: Vec<String>,
+    },
+    /// Keep a committed context file (CONTEXT.md by default, see .githem.toml)
+    /// up to date from a git hook
+    #[command(subcommand)]
+    Hook(hook::HookCommand),
+    /// Anonymous usage reports (command, preset, size bucket, duration,
+    /// version), off unless turned on here or in ~/.config/githem/config.toml
+    #[command(subcommand)]
+    Telemetry(telemetry::TelemetryCommand),
+    /// Apply a unified diff, e.g. a reply to --patch-mode output, to the working tree
+    Apply {
+        /// Patch file, - for stdin
+        #[arg(default_value = "-")]
+        patch: String,
+        /// Directory the paths in the patch are relative to
+        #[arg(short = 'C', long, default_value = ".")]
+        dir: PathBuf,
+        /// Check that the patch applies without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Recreate the files of a githem output (plain, gzip or zstd) on disk
+    Extract {
+        /// Output file written by githem
+        dump: PathBuf,
+        /// Directory to restore the files into
+        #[arg(long, default_value = ".")]
+        out: PathBuf,
+        /// Overwrite files that already exist
+        #[arg(long)]
+        force: bool,
+        /// List what would be written without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -84,6 +445,51 @@ enum FilterPresetArg {
     Standard,
     CodeOnly,
     Minimal,
+    DocsOnly,
+    TestsOnly,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum TransformArg {
+    /// Remove comments from source files
+    StripComments,
+    /// Replace api keys, tokens and private keys with [REDACTED]
+    RedactSecrets,
+    /// Jupyter notebooks as `# %%` scripts without outputs
+    Notebooks,
+}
+
+impl From<TransformArg> for Transform {
+    fn from(arg: TransformArg) -> Self {
+        match arg {
+            TransformArg::StripComments => Transform::StripComments,
+            TransformArg::RedactSecrets => Transform::RedactSecrets,
+            TransformArg::Notebooks => Transform::Notebooks,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum HeaderDetailArg {
+    Path,
+    Basic,
+    Full,
+}
+
+impl From<HeaderDetailArg> for HeaderDetail {
+    fn from(arg: HeaderDetailArg) -> Self {
+        match arg {
+            HeaderDetailArg::Path => HeaderDetail::Path,
+            HeaderDetailArg::Basic => HeaderDetail::Basic,
+            HeaderDetailArg::Full => HeaderDetail::Full,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum CompressionArg {
+    Gzip,
+    Zstd,
 }
 
 impl From<FilterPresetArg> for FilterPreset {
@@ -93,16 +499,96 @@ impl From<FilterPresetArg> for FilterPreset {
             FilterPresetArg::Standard => FilterPreset::Standard,
             FilterPresetArg::CodeOnly => FilterPreset::CodeOnly,
             FilterPresetArg::Minimal => FilterPreset::Minimal,
+            FilterPresetArg::DocsOnly => FilterPreset::DocsOnly,
+            FilterPresetArg::TestsOnly => FilterPreset::TestsOnly,
         }
     }
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    let usage = match matches.subcommand_name() {
+        Some("telemetry") => None,
+        command => telemetry::Usage::start(command.unwrap_or("ingest"), preset_name(&cli)),
+    };
+    let result = run(cli);
+    if let Some(usage) = usage {
+        usage.finish(result.is_ok());
+    }
+    result
+}
+
+/// the preset an ingestion of `cli` uses, for usage reports
+fn preset_name(cli: &Cli) -> &'static str {
+    match (&cli.preset, cli.raw) {
+        (_, true) => FilterPreset::Raw.as_str(),
+        (Some(preset), false) => FilterPreset::from(preset.clone()).as_str(),
+        (None, false) => FilterPreset::Standard.as_str(),
+    }
+}
+
+fn run(mut cli: Cli) -> Result<()> {
+    // these run on the ingested output further down
+    let ingests = |command: &mut Command| {
+        matches!(
+            command,
+            Command::Fingerprint | Command::DiffContext { .. } | Command::SuggestExcludes { .. }
+        )
+    };
+    if let Some(command) = cli.command.take_if(|command| !ingests(command)) {
+        return match command {
+            Command::Cache(command) => cache::run(command, cli.json),
+            Command::Completions { shell } => {
+                clap_complete::generate(shell, &mut Cli::command(), "githem", &mut io::stdout());
+                Ok(())
+            }
+            Command::Man => {
+                clap_mangen::Man::new(Cli::command()).render(&mut io::stdout())?;
+                Ok(())
+            }
+            Command::Daemon { socket, fresh } => daemon::run(socket, fresh),
+            Command::Context {
+                file,
+                line,
+                budget,
+                depth,
+                dir,
+            } => context::run(&dir, &file, line, depth, budget, cli.json),
+            Command::Hook(command) => hook::run(command),
+            Command::Telemetry(command) => telemetry::run(command, cli.json),
+            Command::Prefetch { repo, branches } => {
+                configure_network(network_config(&cli))?;
+                let (url, branch) = remote_source(&repo)?;
+                prefetch::run(&url, branch, &branches, cli.retries, cli.json)
+            }
+            Command::Apply {
+                patch,
+                dir,
+                dry_run,
+            } => apply::run(&patch, &dir, dry_run, cli.json),
+            Command::Extract {
+                dump,
+                out,
+                force,
+                dry_run,
+            } => extract::run(&dump, &out, force, dry_run, cli.json),
+            Command::Fingerprint
+            | Command::DiffContext { .. }
+            | Command::SuggestExcludes { .. } => {
+                unreachable!()
+            }
+        };
+    }
 
     // Handle cache management commands
     if cli.cache_stats {
         let stats = CacheManager::get_stats()?;
+        if cli.json {
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+            return Ok(());
+        }
         println!("📊 Cache Statistics");
         println!("─────────────────────");
         println!("Location: {}", stats.cache_dir.display());
@@ -124,8 +610,36 @@ fn main() -> Result<()> {
         }
     }
 
+    configure_network(network_config(&cli))?;
+
+    if io::stdin().is_terminal() {
+        set_passphrase_prompt(prompt_passphrase);
+    }
+
+    progress::install(
+        cli.quiet,
+        cli.output.is_none() && io::stdout().is_terminal(),
+    );
+
+    if cli.share {
+        return share::share(&cli);
+    }
+
+    for prefix in &cli.path_prefix {
+        validate_path_prefix(prefix).map_err(|e| anyhow::anyhow!("Invalid --path-prefix: {e}"))?;
+    }
+
     let parsed_result = parse_source(&cli.source);
 
+    // local checkouts accept any revision, anything fetched ends up in a refspec
+    if let (Some(branch), false) = (&cli.branch, matches!(parsed_result, SourceType::Local(_))) {
+        validate_ref_name(branch).map_err(|e| anyhow::anyhow!("Invalid --branch: {e}"))?;
+    }
+    // several branches are checked out one after the other, only in a clone of our own
+    if !cli.branches.is_empty() && matches!(parsed_result, SourceType::Local(_)) {
+        anyhow::bail!("--branches needs a remote repository, use --branch for a local one");
+    }
+
     match parsed_result {
         SourceType::Local(path) => handle_local_repo(path, cli),
         SourceType::GitUrl(url) => handle_git_url(url, cli),
@@ -137,11 +651,25 @@ fn main() -> Result<()> {
             url_type,
         } => match url_type {
             GitHubUrlType::Compare => handle_compare(&owner, &repo, branch.as_deref(), cli),
+            GitHubUrlType::Pinned => handle_pinned(&owner, &repo, branch.as_deref(), path, cli),
             _ => handle_github_repo(owner, repo, branch, path, cli),
         },
     }
 }
 
+/// the environment's network settings with the command line's on top
+fn network_config(cli: &Cli) -> NetworkConfig {
+    let mut network = NetworkConfig::from_env();
+    if cli.proxy.is_some() {
+        network.proxy = cli.proxy.clone();
+    }
+    if cli.ca_bundle.is_some() {
+        network.ca_bundle = cli.ca_bundle.clone();
+    }
+    network.offline |= cli.offline;
+    network
+}
+
 enum SourceType {
     Local(String),
     GitUrl(String),
@@ -154,6 +682,12 @@ enum SourceType {
     },
 }
 
+fn prompt_passphrase(key: &std::path::Path) -> Option<String> {
+    rpassword::prompt_password(format!("Enter passphrase for key '{}': ", key.display()))
+        .ok()
+        .filter(|passphrase| !passphrase.is_empty())
+}
+
 fn parse_source(source: &str) -> SourceType {
     if let Some(parsed) = parse_github_url(source) {
         return SourceType::GitHub {
@@ -168,12 +702,18 @@ fn parse_source(source: &str) -> SourceType {
     if !source.contains("://") && source.matches('/').count() == 1 {
         let parts: Vec<&str> = source.split('/').collect();
         if parts.len() == 2 {
+            // owner/repo@<sha> pins the snapshot to an exact revision
+            let (repo, revision) = split_revision(parts[1]);
             return SourceType::GitHub {
                 owner: parts[0].to_string(),
-                repo: parts[1].to_string(),
-                branch: None,
+                repo: repo.to_string(),
+                branch: revision.map(|r| r.to_string()),
                 path: None,
-                url_type: GitHubUrlType::Repository,
+                url_type: if revision.is_some() {
+                    GitHubUrlType::Pinned
+                } else {
+                    GitHubUrlType::Repository
+                },
             };
         }
     }
@@ -198,29 +738,68 @@ fn parse_source(source: &str) -> SourceType {
     SourceType::Local(source.to_string())
 }
 
+/// url and branch of a remote repository source, for commands that only
+/// fetch
+fn remote_source(source: &str) -> Result<(String, Option<String>)> {
+    match parse_source(source) {
+        SourceType::GitHub {
+            owner,
+            repo,
+            branch,
+            url_type,
+            ..
+        } => {
+            if matches!(url_type, GitHubUrlType::Compare | GitHubUrlType::Pinned) {
+                anyhow::bail!("{source} is not a branch of a repository");
+            }
+            Ok((format!("https://github.com/{}/{}", owner, repo), branch))
+        }
+        SourceType::GitUrl(url) => Ok((url, None)),
+        SourceType::Local(_) => anyhow::bail!("{source} is not a remote repository"),
+    }
+}
+
 fn handle_compare(owner: &str, repo: &str, compare_spec: Option<&str>, cli: Cli) -> Result<()> {
     let compare_spec = compare_spec.ok_or_else(|| anyhow::anyhow!("Compare spec is required"))?;
 
-    let (base, head) = parse_compare_spec(compare_spec)
-        .ok_or_else(|| anyhow::anyhow!("Invalid compare format"))?;
+    let (base, head) = parse_compare_spec(compare_spec).map_err(|e| anyhow::anyhow!(e))?;
 
     let url = format!("https://github.com/{}/{}", owner, repo);
 
-    let options = create_ingest_options(&cli);
+    let options = create_ingest_options(&cli)?;
     let ingester = Ingester::from_url(&url, options)?;
 
     let diff_content = ingester.generate_diff(&base, &head, None)?;
 
-    let mut output: Box<dyn io::Write> = match cli.output {
-        Some(path) => Box::new(fs::File::create(path)?),
-        None => Box::new(io::stdout()),
-    };
+    let mut output = open_output(&cli)?;
 
     write!(output, "{}", diff_content)?;
 
     Ok(())
 }
 
+fn handle_pinned(
+    owner: &str,
+    repo: &str,
+    revision: Option<&str>,
+    path: Option<String>,
+    cli: Cli,
+) -> Result<()> {
+    let revision = revision.ok_or_else(|| anyhow::anyhow!("Revision is required"))?;
+    let url = format!("https://github.com/{}/{}", owner, repo);
+
+    let mut options = create_ingest_options(&cli)?;
+    if !options.branches.is_empty() {
+        anyhow::bail!("--branches cannot be combined with a pinned revision");
+    }
+    options.branch = None;
+    options.path_prefix = path;
+
+    // pinned snapshots are immutable, the cache is keyed by branch heads
+    let ingester = Ingester::from_url_at(&url, revision, options)?;
+    process_with_ingester(ingester, cli)
+}
+
 fn handle_github_repo(
     owner: String,
     repo: String,
@@ -230,15 +809,18 @@ fn handle_github_repo(
 ) -> Result<()> {
     let url = format!("https://github.com/{}/{}", owner, repo);
 
-    let mut options = create_ingest_options(&cli);
+    let mut options = create_ingest_options(&cli)?;
+    if branch.is_some() && !options.branches.is_empty() {
+        anyhow::bail!("--branches cannot be combined with a branch in the URL");
+    }
     options.branch = branch.or(cli.branch.clone());
-    options.path_prefix = path.or(cli.path_prefix.clone());
+    options.path_prefix = path;
 
     process_repository(&url, options, cli)
 }
 
 fn handle_git_url(url: String, cli: Cli) -> Result<()> {
-    let options = create_ingest_options(&cli);
+    let options = create_ingest_options(&cli)?;
     process_repository(&url, options, cli)
 }
 
@@ -249,7 +831,7 @@ fn handle_local_repo(path: String, cli: Cli) -> Result<()> {
         std::process::exit(1);
     }
 
-    let options = create_ingest_options(&cli);
+    let options = create_ingest_options(&cli)?;
     let ingester = Ingester::from_path(&path_buf, options)?;
 
     if let Some(branch) = &cli.branch {
@@ -260,25 +842,59 @@ fn handle_local_repo(path: String, cli: Cli) -> Result<()> {
     process_with_ingester(ingester, cli)
 }
 
-fn create_ingest_options(cli: &Cli) -> IngestOptions {
-    let filter_preset = if cli.raw {
-        Some(FilterPreset::Raw)
+fn create_ingest_options(cli: &Cli) -> Result<IngestOptions> {
+    let preset = if cli.raw {
+        FilterPreset::Raw
     } else if let Some(preset) = &cli.preset {
-        Some(preset.clone().into())
+        preset.clone().into()
     } else {
-        Some(FilterPreset::Standard)
+        FilterPreset::Standard
     };
 
-    IngestOptions {
-        include_patterns: cli.include.clone(),
-        exclude_patterns: cli.exclude.clone(),
-        max_file_size: cli.max_size,
-        include_untracked: cli.untracked,
-        branch: cli.branch.clone(),
-        path_prefix: cli.path_prefix.clone(),
-        filter_preset,
-        apply_default_filters: false,
+    let mut builder = IngestOptions::builder()
+        .preset(preset)
+        .includes(&cli.include)
+        .excludes(&cli.exclude)
+        .extensions(&cli.ext)
+        .max_file_size(cli.max_size)
+        .min_file_size(cli.min_size)
+        .max_lines(cli.max_lines)
+        .max_total_size(cli.max_total_size)
+        .max_total_tokens(cli.max_total_tokens)
+        .max_files(cli.max_files)
+        .retries(cli.retries)
+        .include_untracked(cli.untracked)
+        .follow_symlinks(cli.follow_symlinks)
+        .header_detail(cli.header_detail.into())
+        .summarize_dirs(cli.summarize_dirs)
+        .docs_first(cli.docs_first)
+        .ownership(cli.ownership)
+        .build_ci(cli.build_ci)
+        .with_tests(cli.with_tests)
+        .with_sources(cli.with_sources)
+        .pattern_syntax(if cli.legacy_patterns {
+            PatternSyntax::Legacy
+        } else {
+            PatternSyntax::Glob
+        });
+    if let Some(branch) = &cli.branch {
+        builder = builder.branch(branch);
+    }
+    builder = builder.branches(&cli.branches);
+    builder = builder
+        .paths(&cli.path_prefix)
+        .exclude_paths(&cli.exclude_path);
+    for &transform in &cli.transform {
+        builder = builder.transform(transform.into());
+    }
+    if cli.no_compress_license {
+        builder = builder.without_transform(Transform::CompressLicense);
+    }
+    if cli.line_numbers || cli.patch_mode {
+        builder = builder.transform(Transform::LineNumbers);
     }
+
+    builder.build()
 }
 
 fn process_repository(url: &str, options: IngestOptions, cli: Cli) -> Result<()> {
@@ -293,45 +909,311 @@ fn process_repository(url: &str, options: IngestOptions, cli: Cli) -> Result<()>
 
 fn process_with_ingester(mut ingester: Ingester, cli: Cli) -> Result<()> {
     if cli.stats {
-        show_stats(&ingester)?;
+        show_stats(&ingester, cli.json)?;
         return Ok(());
     }
 
-    let mut output: Box<dyn io::Write> = match cli.output {
-        Some(ref path) => Box::new(fs::File::create(path)?),
-        None => Box::new(io::stdout()),
-    };
+    if cli.dry_run {
+        return dry_run(&ingester, &cli);
+    }
+
+    if cli.list || cli.tree_only {
+        let files = ingester.list_files()?;
+        let mut output = open_output(&cli)?;
+        if cli.tree_only {
+            write!(
+                output,
+                "{}",
+                generate_tree_with_depth(&files, cli.tree_depth)
+            )?;
+        } else {
+            for file in &files {
+                writeln!(output, "{}", file.display())?;
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(command) = &cli.command {
+        let (content, _) = ingester.ingest_to_string()?;
+        return match command {
+            Command::DiffContext { dump, exit_code } => {
+                drift::report(&content, dump, *exit_code, cli.json)
+            }
+            Command::SuggestExcludes { budget } => suggest::run(&content, *budget, cli.json),
+            _ => drift::print_fingerprint(&content, cli.json),
+        };
+    }
+
+    if let Some(ref dir) = cli.output_dir {
+        let (content, _) = ingester.ingest_to_string()?;
+        return split::write_split(&content, dir, &cli, ingester.detect_license().as_deref());
+    }
+
+    if let Some(ref pattern) = cli.grep {
+        let (content, _) = ingester.ingest_to_string()?;
+        return print_search_results(&content, pattern, &cli);
+    }
+
+    if let Some(max_tokens) = cli.chunk {
+        let (content, _) = ingester.ingest_to_string()?;
+        return write_chunks(&content, max_tokens, &cli);
+    }
+
+    if cli.tokens {
+        let bpe = tokens::tokenizer(&cli.model)?;
+        let (content, _) = ingester.ingest_to_string()?;
+        let mut output = open_output(&cli)?;
+        return tokens::report(&content, &bpe, &cli, &mut output);
+    }
+
+    if !cli.focus.is_empty() {
+        let options = FocusOptions {
+            files: cli.focus.clone(),
+            depth: cli.focus_depth,
+            token_budget: cli.token_budget,
+        };
+
+        let mut output = open_output(&cli)?;
+
+        if !cli.quiet {
+            write_header(&mut output, &cli, ingester.detect_license().as_deref())?;
+        }
+
+        let selected = ingester.ingest_focus(&mut output, &options)?;
+        if cli.patch_mode {
+            write!(output, "{PATCH_INSTRUCTIONS}")?;
+        }
+
+        if !cli.quiet {
+            eprintln!(
+                "→ Selected {} files around {}",
+                selected.len(),
+                cli.focus.join(", ")
+            );
+            for file in &selected {
+                eprintln!(
+                    "  depth {}  {} ({} tokens)",
+                    file.depth,
+                    file.path.display(),
+                    file.tokens
+                );
+            }
+        }
+
+        return Ok(());
+    }
+
+    if let Some(ref query) = cli.relevant_to {
+        let backend = embedding_backend(&cli)?;
+        let options = RelevanceOptions {
+            query: query.clone(),
+            top_k: cli.top_k,
+            token_budget: cli.token_budget,
+            ..Default::default()
+        };
+
+        let mut output = open_output(&cli)?;
+
+        if !cli.quiet {
+            write_header(&mut output, &cli, ingester.detect_license().as_deref())?;
+        }
+
+        let selected = ingester.ingest_relevant(&mut output, &options, backend.as_ref())?;
+        if cli.patch_mode {
+            write!(output, "{PATCH_INSTRUCTIONS}")?;
+        }
+
+        if !cli.quiet {
+            eprintln!(
+                "→ Selected {} files relevant to \"{}\"",
+                selected.len(),
+                query
+            );
+            for file in &selected {
+                eprintln!(
+                    "  {:.3}  {} ({} tokens)",
+                    file.score,
+                    file.path.display(),
+                    file.tokens
+                );
+            }
+        }
+
+        return Ok(());
+    }
+
+    let mut output = open_output(&cli)?;
 
     if !cli.quiet {
-        write_header(&mut output, &cli)?;
+        write_header(&mut output, &cli, ingester.detect_license().as_deref())?;
     }
 
     if !cli.quiet && !matches!(ingester.get_filter_preset(), Some(FilterPreset::Raw)) {
-        show_filtering_info(&ingester)?;
+        show_filtering_info(&ingester, cli.json)?;
     }
 
-    // Use cached ingestion if enabled
-    if !cli.no_cache && !cli.force && ingester.cache_key.is_some() {
-        ingester.ingest_cached(&mut output)?;
-    } else {
-        ingester.ingest(&mut output)?;
+    // cached ingestion unless --no-cache or --force, see process_repository
+    let report = ingester.ingest_auto(&mut output)?;
+    telemetry::record_size(report.total_size());
+    if cli.patch_mode {
+        write!(output, "{PATCH_INSTRUCTIONS}")?;
+    }
+    output.flush()?;
+
+    if !cli.quiet && !report.skipped.is_empty() {
+        eprintln!(
+            "Warning: output limit reached, {} files left out",
+            report.skipped.len()
+        );
+    }
+    if !cli.quiet && !report.omitted.is_empty() {
+        eprintln!(
+            "Warning: --max-files left out {} less important files",
+            report.omitted.len()
+        );
     }
 
     Ok(())
 }
 
-fn parse_compare_spec(spec: &str) -> Option<(String, String)> {
-    if let Some((base, head)) = spec.split_once("...") {
-        Some((base.to_string(), head.to_string()))
-    } else if let Some((base, head)) = spec.split_once("..") {
-        Some((base.to_string(), head.to_string()))
-    } else {
-        None
+fn dry_run(ingester: &Ingester, cli: &Cli) -> Result<()> {
+    let decisions = ingester.explain()?;
+    let mut output = open_output(cli)?;
+
+    let included = decisions.iter().filter(|(_, d)| d.is_included()).count();
+    for (path, decision) in &decisions {
+        if cli.explain {
+            writeln!(output, "{}  ({})", path.display(), decision)?;
+        } else if decision.is_included() {
+            writeln!(output, "{}", path.display())?;
+        }
     }
+
+    if !cli.quiet {
+        eprintln!(
+            "→ Dry run: {} of {} files would be included",
+            included,
+            decisions.len()
+        );
+    }
+
+    Ok(())
 }
 
-fn write_header(output: &mut dyn io::Write, cli: &Cli) -> Result<()> {
+fn open_output(cli: &Cli) -> Result<Box<dyn io::Write>> {
+    let output: Box<dyn io::Write> = match cli.output {
+        Some(ref path) => Box::new(io::BufWriter::new(fs::File::create(path)?)),
+        None => {
+            if cli.compress.is_some() && io::stdout().is_terminal() {
+                return Err(anyhow::anyhow!(
+                    "Refusing to write compressed output to a terminal, use --output or a pipe"
+                ));
+            }
+            Box::new(io::BufWriter::new(io::stdout()))
+        }
+    };
+
+    compressed(output, cli)
+}
+
+/// wrap a writer in the --compress encoder, if any
+fn compressed(output: Box<dyn io::Write>, cli: &Cli) -> Result<Box<dyn io::Write>> {
+    // both encoders write their trailer when dropped
+    Ok(match cli.compress {
+        None => output,
+        Some(CompressionArg::Gzip) => Box::new(flate2::write::GzEncoder::new(
+            output,
+            flate2::Compression::default(),
+        )),
+        Some(CompressionArg::Zstd) => Box::new(zstd::Encoder::new(output, 0)?.auto_finish()),
+    })
+}
+
+fn embedding_backend(cli: &Cli) -> Result<Box<dyn EmbeddingBackend>> {
+    match cli.embedding_api {
+        #[cfg(feature = "embeddings-api")]
+        Some(ref endpoint) => {
+            let api_key = std::env::var("GITHEM_EMBEDDING_API_KEY")
+                .or_else(|_| std::env::var("OPENAI_API_KEY"))
+                .ok();
+            Ok(Box::new(githem_core::OpenAiEmbedder::new(
+                endpoint,
+                &cli.embedding_model,
+                api_key,
+            )))
+        }
+        #[cfg(not(feature = "embeddings-api"))]
+        Some(_) => Err(anyhow::anyhow!(
+            "--embedding-api requires githem to be built with the embeddings-api feature"
+        )),
+        None => Ok(Box::new(LexicalEmbedder::default())),
+    }
+}
+
+fn write_chunks(content: &str, max_tokens: usize, cli: &Cli) -> Result<()> {
+    let options = ChunkOptions {
+        max_tokens,
+        overlap_tokens: cli.overlap,
+    };
+    let chunks = chunk_output(content, &options);
+
+    let mut output = open_output(cli)?;
+
+    for chunk in &chunks {
+        serde_json::to_writer(&mut output, chunk)?;
+        writeln!(output)?;
+    }
+
+    if !cli.quiet {
+        eprintln!("→ Wrote {} chunks", chunks.len());
+    }
+
+    Ok(())
+}
+
+fn print_search_results(content: &str, pattern: &str, cli: &Cli) -> Result<()> {
+    let options = SearchOptions {
+        query: pattern.to_string(),
+        regex: cli.regex,
+        context_lines: cli.context,
+        max_matches: usize::MAX,
+        ..Default::default()
+    };
+
+    let results = search_output(content, &options)?;
+
+    let mut output = open_output(cli)?;
+
+    for file in &results {
+        for m in &file.matches {
+            let first = m.line_number - m.context_before.len();
+            for (i, line) in m.context_before.iter().enumerate() {
+                writeln!(output, "{}-{}-{}", file.path, first + i, line)?;
+            }
+            writeln!(output, "{}:{}:{}", file.path, m.line_number, m.line)?;
+            for (i, line) in m.context_after.iter().enumerate() {
+                writeln!(output, "{}-{}-{}", file.path, m.line_number + 1 + i, line)?;
+            }
+            if cli.context > 0 {
+                writeln!(output, "--")?;
+            }
+        }
+    }
+
+    if !cli.quiet {
+        let total: usize = results.iter().map(|f| f.matches.len()).sum();
+        eprintln!("→ {} matches in {} files", total, results.len());
+    }
+
+    Ok(())
+}
+
+fn write_header(output: &mut dyn io::Write, cli: &Cli, license: Option<&str>) -> Result<()> {
     writeln!(output, "# Repository: {}", cli.source)?;
+    if let Some(license) = license {
+        writeln!(output, "# License: {}", license)?;
+    }
     writeln!(output, "# Generated by githem-cli (rotko.net)")?;
 
     let preset_name = if cli.raw {
@@ -342,6 +1224,8 @@ fn write_header(output: &mut dyn io::Write, cli: &Cli) -> Result<()> {
             FilterPresetArg::Standard => "standard (smart filtering)",
             FilterPresetArg::CodeOnly => "code-only",
             FilterPresetArg::Minimal => "minimal filtering",
+            FilterPresetArg::DocsOnly => "docs-only",
+            FilterPresetArg::TestsOnly => "tests-only",
         }
     } else {
         "standard (smart filtering)"
@@ -358,9 +1242,14 @@ fn write_header(output: &mut dyn io::Write, cli: &Cli) -> Result<()> {
     Ok(())
 }
 
-fn show_stats(ingester: &Ingester) -> Result<()> {
+fn show_stats(ingester: &Ingester, json: bool) -> Result<()> {
     let stats = ingester.get_filter_stats()?;
 
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
     println!("📊 Filtering Statistics");
     println!("─────────────────────────");
     println!("Total files found: {}", stats.total_files);
@@ -393,8 +1282,14 @@ fn show_stats(ingester: &Ingester) -> Result<()> {
     Ok(())
 }
 
-fn show_filtering_info(ingester: &Ingester) -> Result<()> {
+fn show_filtering_info(ingester: &Ingester, json: bool) -> Result<()> {
     let stats = ingester.get_filter_stats()?;
+
+    // stdout carries the content, so the stats go to stderr as one line
+    if json {
+        eprintln!("{}", serde_json::to_string(&stats)?);
+        return Ok(());
+    }
     eprintln!(
         "ℹ️  Filtering: {} → {} files ({:.1}% reduction)",
         stats.total_files,
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use githem_core::{list_remote_refs, network::retry, IngestOptions, Ingester};
+
+/// clone or fetch `url` into the cache and index it, once per branch, so
+/// later ingests start warm; `all` stands for every branch of the remote
+pub fn run(
+    url: &str,
+    branch: Option<String>,
+    branches: &[String],
+    retries: u32,
+    json: bool,
+) -> Result<()> {
+    let branches: Vec<Option<String>> = if branches.iter().any(|b| b == "all") {
+        let refs = retry(retries, || list_remote_refs(url))?;
+        refs.iter()
+            .filter_map(|r| r.name.strip_prefix("refs/heads/"))
+            .map(|name| Some(name.to_string()))
+            .collect()
+    } else if branches.is_empty() {
+        vec![branch]
+    } else {
+        branches.iter().cloned().map(Some).collect()
+    };
+
+    let mut prefetched = Vec::new();
+    for branch in branches {
+        let mut builder = IngestOptions::builder().retries(retries);
+        if let Some(ref branch) = branch {
+            builder = builder.branch(branch);
+        }
+        let mut ingester = Ingester::from_url_cached(url, builder.build()?)?;
+        let entry = ingester.prefetch()?;
+
+        let branch = branch
+            .or_else(|| ingester.head_branch())
+            .unwrap_or_else(|| "HEAD".to_string());
+        if !json {
+            println!(
+                "✓ {url} {branch} at {}, {} files",
+                &entry.commit_hash[..entry.commit_hash.len().min(8)],
+                entry.metadata.total_files
+            );
+        }
+        prefetched.push(serde_json::json!({
+            "branch": branch,
+            "commit": entry.commit_hash,
+            "files": entry.metadata.total_files,
+            "bytes": entry.metadata.total_size,
+        }));
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "url": url, "branches": prefetched })
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use githem_core::{set_progress_handler, ProgressPhase};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static BAR: Mutex<Option<(ProgressPhase, ProgressBar)>> = Mutex::new(None);
+/// the write bar would tear output that goes to the same terminal
+static SHOW_WRITE: AtomicBool = AtomicBool::new(true);
+
+/// progress bars on stderr, silenced entirely under --quiet and
+/// left to the plain fallback when stderr is not a terminal
+pub fn install(quiet: bool, output_to_terminal: bool) {
+    if quiet {
+        set_progress_handler(|_, _, _| {});
+    } else if io::stderr().is_terminal() {
+        SHOW_WRITE.store(!output_to_terminal, Ordering::Relaxed);
+        set_progress_handler(update);
+    }
+}
+
+fn update(phase: ProgressPhase, position: u64, total: u64) {
+    if phase == ProgressPhase::Write && !SHOW_WRITE.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let Ok(mut current) = BAR.lock() else {
+        return;
+    };
+
+    if current.as_ref().is_none_or(|(p, _)| *p != phase) {
+        if let Some((_, bar)) = current.take() {
+            bar.finish_and_clear();
+        }
+        *current = Some((phase, new_bar(phase, total)));
+    }
+
+    let Some((_, bar)) = current.as_ref() else {
+        return;
+    };
+    bar.set_length(total);
+    bar.set_position(position);
+
+    if total > 0 && position >= total {
+        if let Some((_, bar)) = current.take() {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+fn new_bar(phase: ProgressPhase, total: u64) -> ProgressBar {
+    let label = match phase {
+        ProgressPhase::Clone => "Receiving objects",
+        ProgressPhase::Index => "Indexing files",
+        ProgressPhase::Write => "Writing files",
+    };
+
+    // totals of zero mean the remote has not announced its size yet
+    let bar = if total == 0 {
+        ProgressBar::new_spinner()
+    } else {
+        ProgressBar::new(total)
+    };
+    bar.set_draw_target(ProgressDrawTarget::stderr());
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} {msg:18} [{bar:30}] {pos}/{len} ({eta})")
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("=> "),
+    );
+    bar.set_message(label);
+    bar
+}
@@ -0,0 +1,139 @@
+use crate::{parse_source, Cli, FilterPresetArg, SourceType};
+use anyhow::{Context, Result};
+use githem_core::{parse_github_url, GitHubUrlType};
+use std::path::Path;
+
+struct ShareTarget {
+    owner: String,
+    repo: String,
+    branch: Option<String>,
+    path: Option<String>,
+    commit: Option<String>,
+}
+
+/// local checkouts are shared at their HEAD commit, which must be pushed
+fn local_target(path: &str) -> Result<ShareTarget> {
+    let repo = git2::Repository::open(Path::new(path)).context("Failed to open repository")?;
+    let origin = repo
+        .find_remote("origin")
+        .context("--share needs an origin remote on GitHub")?;
+    let origin_url = origin.url().unwrap_or_default().trim_end_matches(".git");
+
+    // git@github.com:owner/repo -> github.com/owner/repo
+    let normalized = match origin_url.strip_prefix("git@github.com:") {
+        Some(rest) => format!("https://github.com/{rest}"),
+        None => origin_url.to_string(),
+    };
+    let parsed = parse_github_url(&normalized)
+        .ok_or_else(|| anyhow::anyhow!("--share only supports repositories hosted on GitHub"))?;
+
+    let head = repo.head()?.peel_to_commit()?.id().to_string();
+
+    Ok(ShareTarget {
+        owner: parsed.owner,
+        repo: parsed.repo,
+        branch: None,
+        path: None,
+        commit: Some(head),
+    })
+}
+
+pub fn share(cli: &Cli) -> Result<()> {
+    if !cli.branches.is_empty() {
+        return Err(anyhow::anyhow!(
+            "--share pins a single commit, it does not support --branches"
+        ));
+    }
+    let mut target = match parse_source(&cli.source) {
+        SourceType::GitHub {
+            owner,
+            repo,
+            branch,
+            path,
+            url_type,
+        } => match url_type {
+            GitHubUrlType::Pinned => ShareTarget {
+                owner,
+                repo,
+                branch: None,
+                path,
+                commit: branch,
+            },
+            GitHubUrlType::Repository | GitHubUrlType::Tree | GitHubUrlType::Blob => ShareTarget {
+                owner,
+                repo,
+                branch,
+                path,
+                commit: None,
+            },
+            _ => return Err(anyhow::anyhow!("--share does not support this kind of URL")),
+        },
+        SourceType::Local(path) => local_target(&path)?,
+        SourceType::GitUrl(_) => {
+            return Err(anyhow::anyhow!(
+                "--share only supports repositories hosted on GitHub"
+            ))
+        }
+    };
+
+    if cli.branch.is_some() {
+        target.branch = cli.branch.clone();
+    }
+    match cli.path_prefix.as_slice() {
+        [] => {}
+        [path] => target.path = Some(path.clone()),
+        _ => return Err(anyhow::anyhow!("--share supports a single --path-prefix")),
+    }
+
+    let preset = if cli.raw {
+        Some("raw")
+    } else {
+        cli.preset.as_ref().map(|preset| match preset {
+            FilterPresetArg::Raw => "raw",
+            FilterPresetArg::Standard => "standard",
+            FilterPresetArg::CodeOnly => "code-only",
+            FilterPresetArg::Minimal => "minimal",
+            FilterPresetArg::DocsOnly => "docs-only",
+            FilterPresetArg::TestsOnly => "tests-only",
+        })
+    };
+
+    let body = serde_json::json!({
+        "owner": target.owner,
+        "repo": target.repo,
+        "commit": target.commit,
+        "branch": target.branch,
+        "path": target.path,
+        "preset": preset,
+        "include": (!cli.include.is_empty()).then(|| cli.include.join(",")),
+        "exclude": (!cli.exclude.is_empty()).then(|| cli.exclude.join(",")),
+        "exclude_paths": (!cli.exclude_path.is_empty()).then(|| cli.exclude_path.join(",")),
+        "max_size": cli.max_size,
+        "expires_in": cli.share_expires,
+    });
+
+    let endpoint = format!("{}/api/share", cli.api_url.trim_end_matches('/'));
+    let response: serde_json::Value = ureq::post(&endpoint)
+        .send_json(body)
+        .with_context(|| format!("Failed to create share link via {endpoint}"))?
+        .body_mut()
+        .read_json()?;
+
+    let url = response["url"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Share response did not contain a url"))?;
+
+    if !cli.quiet {
+        if let Some(commit) = response["commit"].as_str() {
+            eprintln!(
+                "✓ Pinned {}/{} at {}",
+                target.owner,
+                target.repo,
+                &commit[..commit.len().min(8)]
+            );
+        }
+    }
+    println!("{url}");
+
+    Ok(())
+}
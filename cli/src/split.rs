@@ -0,0 +1,69 @@
+use crate::{compressed, write_header, Cli, CompressionArg};
+use anyhow::Result;
+use githem_core::{generate_tree_from_paths, split_files};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// files directly in the repository root, which have no directory to go to
+const ROOT_GROUP: &str = "_root";
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum SplitBy {
+    /// one file per top-level directory
+    TopLevelDir,
+}
+
+impl SplitBy {
+    fn group<'a>(&self, path: &'a str) -> &'a str {
+        match self {
+            SplitBy::TopLevelDir => match path.split_once('/') {
+                Some((dir, _)) => dir,
+                None => ROOT_GROUP,
+            },
+        }
+    }
+}
+
+/// write each group of files with its own header and tree into `dir`
+pub fn write_split(content: &str, dir: &Path, cli: &Cli, license: Option<&str>) -> Result<()> {
+    let mut groups: BTreeMap<&str, Vec<(&str, &str)>> = BTreeMap::new();
+    for (path, body) in split_files(content) {
+        groups
+            .entry(cli.split_by.group(path))
+            .or_default()
+            .push((path, body));
+    }
+
+    fs::create_dir_all(dir)?;
+
+    let extension = match cli.compress {
+        None => "txt",
+        Some(CompressionArg::Gzip) => "txt.gz",
+        Some(CompressionArg::Zstd) => "txt.zst",
+    };
+
+    for (group, files) in &groups {
+        let file = fs::File::create(dir.join(format!("{group}.{extension}")))?;
+        let mut output = compressed(Box::new(file), cli)?;
+
+        if !cli.quiet {
+            write_header(&mut output, cli, license)?;
+        }
+
+        let paths: Vec<&str> = files.iter().map(|(path, _)| *path).collect();
+        write!(output, "{}", generate_tree_from_paths(&paths))?;
+
+        for (path, body) in files {
+            writeln!(output, "=== {path} ===")?;
+            write!(output, "{body}")?;
+        }
+    }
+
+    if !cli.quiet {
+        eprintln!("→ Wrote {} files to {}", groups.len(), dir.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use githem_core::{estimate_tokens, split_files, suggest_excludes};
+use serde_json::json;
+
+/// the suggested patterns with what each saves, then the flags that apply them
+pub fn run(content: &str, budget: usize, json: bool) -> Result<()> {
+    let total: usize = split_files(content)
+        .iter()
+        .map(|(_, body)| estimate_tokens(body))
+        .sum();
+    let suggestions = suggest_excludes(content, budget);
+    let remaining = total - suggestions.iter().map(|s| s.tokens).sum::<usize>();
+
+    if json {
+        let report = json!({
+            "budget": budget,
+            "total_tokens": total,
+            "remaining_tokens": remaining,
+            "suggestions": suggestions,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("{total} estimated tokens, budget {budget}");
+    if suggestions.is_empty() {
+        println!("Nothing to exclude");
+        return Ok(());
+    }
+
+    println!(
+        "{:>10}  {:>6}  {:<32}  reason",
+        "tokens", "files", "pattern"
+    );
+    for suggestion in &suggestions {
+        println!(
+            "{:>10}  {:>6}  {:<32}  {}",
+            suggestion.tokens, suggestion.files, suggestion.pattern, suggestion.reason
+        );
+    }
+    println!("{remaining} estimated tokens after excluding");
+    if remaining > budget {
+        println!("Still over budget, narrow it down with --include");
+    }
+
+    let flags: Vec<String> = suggestions
+        .iter()
+        .map(|s| format!("-e '{}'", s.pattern))
+        .collect();
+    println!("\n{}", flags.join(" "));
+    Ok(())
+}
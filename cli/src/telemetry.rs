@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// a report never holds up the command's exit longer than this
+const SEND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// bytes of file content the last ingestion wrote, usize::MAX for none
+static INGESTED_BYTES: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+#[derive(Subcommand)]
+pub enum TelemetryCommand {
+    /// Send anonymous usage reports to an endpoint you choose
+    On {
+        /// Where reports are POSTed (default: the endpoint already configured)
+        #[arg(long)]
+        endpoint: Option<String>,
+    },
+    /// Stop sending usage reports
+    Off,
+    /// Show whether reports are sent, where to and what one contains
+    Status,
+}
+
+/// the `[telemetry]` table of the user config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Settings {
+    enabled: bool,
+    endpoint: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct UserConfig {
+    telemetry: Settings,
+}
+
+/// `$XDG_CONFIG_HOME/githem/config.toml`, else `~/.config/githem/config.toml`
+fn config_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("githem").join("config.toml"))
+}
+
+fn load() -> Result<Settings> {
+    let Some(path) = config_path().filter(|path| path.exists()) else {
+        return Ok(Settings::default());
+    };
+    let text = std::fs::read_to_string(&path)?;
+    let config: UserConfig =
+        toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+    Ok(config.telemetry)
+}
+
+/// the endpoint to report to, None unless the config file turns reports
+/// on; DO_NOT_TRACK overrides it
+fn endpoint() -> Option<String> {
+    if std::env::var_os("DO_NOT_TRACK").is_some_and(|v| !v.is_empty() && v != "0") {
+        return None;
+    }
+    let settings = load().ok()?;
+    settings.endpoint.filter(|_| settings.enabled)
+}
+
+/// one command's usage, nothing that identifies the user or the repository
+#[derive(Debug, Serialize)]
+struct Report {
+    version: &'static str,
+    command: String,
+    preset: &'static str,
+    /// rough size of the ingested content, see `size_bucket`
+    size: Option<&'static str>,
+    duration_ms: u64,
+    success: bool,
+}
+
+fn size_bucket(bytes: usize) -> &'static str {
+    match bytes {
+        b if b < 100 << 10 => "<100KB",
+        b if b < 1 << 20 => "100KB-1MB",
+        b if b < 10 << 20 => "1-10MB",
+        b if b < 100 << 20 => "10-100MB",
+        _ => ">100MB",
+    }
+}
+
+/// remember the size of what was ingested for the report of this run
+pub fn record_size(bytes: usize) {
+    INGESTED_BYTES.store(bytes, Ordering::Relaxed);
+}
+
+/// a command being timed for its report, inert when reports are off
+pub struct Usage {
+    endpoint: String,
+    command: String,
+    preset: &'static str,
+    started: Instant,
+}
+
+impl Usage {
+    pub fn start(command: &str, preset: &'static str) -> Option<Self> {
+        Some(Self {
+            endpoint: endpoint()?,
+            command: command.to_string(),
+            preset,
+            started: Instant::now(),
+        })
+    }
+
+    /// send the report; failures are ignored, they must not fail the command
+    pub fn finish(self, success: bool) {
+        let bytes = INGESTED_BYTES.load(Ordering::Relaxed);
+        let report = Report {
+            version: env!("CARGO_PKG_VERSION"),
+            command: self.command,
+            preset: self.preset,
+            size: (bytes != usize::MAX).then(|| size_bucket(bytes)),
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            success,
+        };
+
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(SEND_TIMEOUT))
+            .build()
+            .into();
+        let _ = agent.post(&self.endpoint).send_json(&report);
+    }
+}
+
+pub fn run(command: TelemetryCommand, json: bool) -> Result<()> {
+    let path = config_path()
+        .context("Cannot locate the config file, neither XDG_CONFIG_HOME nor HOME is set")?;
+    match command {
+        TelemetryCommand::On { endpoint } => {
+            let endpoint = match endpoint.or(load()?.endpoint) {
+                Some(endpoint) => endpoint,
+                None => anyhow::bail!("No endpoint configured yet, pass one with --endpoint"),
+            };
+            if !endpoint.starts_with("https://") && !endpoint.starts_with("http://") {
+                anyhow::bail!("--endpoint must be an http:// or https:// url");
+            }
+            save(&path, true, Some(&endpoint))?;
+            if !json {
+                println!("✓ Usage reports go to {endpoint}");
+                return Ok(());
+            }
+        }
+        TelemetryCommand::Off => {
+            save(&path, false, None)?;
+            if !json {
+                println!("✓ Usage reports are off");
+                return Ok(());
+            }
+        }
+        TelemetryCommand::Status => {}
+    }
+
+    let settings = load()?;
+    let sending = endpoint();
+    let example = Report {
+        version: env!("CARGO_PKG_VERSION"),
+        command: "ingest".to_string(),
+        preset: "standard",
+        size: Some(size_bucket(3 << 20)),
+        duration_ms: 1200,
+        success: true,
+    };
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "config": path,
+                "enabled": settings.enabled,
+                "endpoint": settings.endpoint,
+                "sending": sending.is_some(),
+                "example": example,
+            })
+        );
+        return Ok(());
+    }
+
+    println!("Config: {}", path.display());
+    match (sending, settings.enabled) {
+        (Some(endpoint), _) => println!("Usage reports: on, sent to {endpoint}"),
+        (None, true) if settings.endpoint.is_some() => {
+            println!("Usage reports: off, DO_NOT_TRACK is set")
+        }
+        (None, true) => println!("Usage reports: off, no endpoint configured"),
+        (None, false) => println!("Usage reports: off"),
+    }
+    println!("A report looks like: {}", serde_json::to_string(&example)?);
+    Ok(())
+}
+
+/// update the `[telemetry]` table, leaving the rest of the file alone
+fn save(path: &std::path::Path, enabled: bool, endpoint: Option<&str>) -> Result<()> {
+    let mut config: toml::Table = match std::fs::read_to_string(path) {
+        Ok(text) => text
+            .parse()
+            .with_context(|| format!("Invalid {}", path.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let telemetry = config
+        .entry("telemetry")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    let Some(telemetry) = telemetry.as_table_mut() else {
+        anyhow::bail!("Invalid {}: telemetry must be a table", path.display());
+    };
+    telemetry.insert("enabled".to_string(), toml::Value::Boolean(enabled));
+    if let Some(endpoint) = endpoint {
+        telemetry.insert(
+            "endpoint".to_string(),
+            toml::Value::String(endpoint.to_string()),
+        );
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, toml::to_string(&config)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
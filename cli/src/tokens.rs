@@ -0,0 +1,70 @@
+use crate::Cli;
+use anyhow::Result;
+use githem_core::split_files;
+use std::io::Write;
+use tiktoken_rs::CoreBPE;
+
+/// resolved before ingesting, so a typo does not cost a clone
+pub fn tokenizer(model: &str) -> Result<CoreBPE> {
+    tiktoken_rs::get_bpe_from_model(model).map_err(|_| {
+        anyhow::anyhow!(
+            "Unknown model '{}' for --tokens, try gpt-4o, gpt-4 or gpt-3.5-turbo",
+            model
+        )
+    })
+}
+
+/// exact token count of every file, largest first with running totals
+pub fn report(content: &str, bpe: &CoreBPE, cli: &Cli, output: &mut dyn Write) -> Result<()> {
+    let mut files: Vec<(&str, usize)> = split_files(content)
+        .into_iter()
+        .map(|(path, body)| (path, bpe.encode_ordinary(body).len()))
+        .collect();
+    files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    let total: usize = files.iter().map(|(_, tokens)| tokens).sum();
+
+    if cli.json {
+        let files: Vec<_> = files
+            .iter()
+            .map(|(path, tokens)| serde_json::json!({ "path": path, "tokens": tokens }))
+            .collect();
+        let report = serde_json::json!({
+            "model": cli.model,
+            "total_tokens": total,
+            "files": files,
+        });
+        serde_json::to_writer_pretty(&mut *output, &report)?;
+        writeln!(output)?;
+        return Ok(());
+    }
+
+    writeln!(
+        output,
+        "{:>10}  {:>10}  {:>6}  path",
+        "tokens", "cumulative", "%"
+    )?;
+    let mut cumulative = 0;
+    for (path, tokens) in &files {
+        cumulative += tokens;
+        writeln!(
+            output,
+            "{:>10}  {:>10}  {:>5.1}%  {}",
+            tokens,
+            cumulative,
+            cumulative as f64 * 100.0 / total.max(1) as f64,
+            path
+        )?;
+    }
+    writeln!(
+        output,
+        "{:>10}  {:>10}  {:>6}  {} files ({})",
+        total,
+        "",
+        "",
+        files.len(),
+        cli.model
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+// cargo bench -p githem-core --features bench
+//
+// fixture repositories are generated into a temporary directory on each
+// run, one per shape: many flat files, a deep tree, a dependency heavy
+// checkout and a few large files. Peak heap use of each ingest is printed
+// before its timings.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use githem_core::{FilterExplainer, IngestOptions, Ingester};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tempfile::TempDir;
+
+/// the system allocator, counting the bytes in use and their peak
+struct PeakAlloc;
+
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let in_use = IN_USE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(in_use, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+/// the most heap `run` had allocated at once, beyond what was in use before
+fn peak_heap(run: impl FnOnce()) -> usize {
+    let before = IN_USE.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    run();
+    PEAK.load(Ordering::Relaxed) - before
+}
+
+struct Fixture {
+    name: &'static str,
+    dir: TempDir,
+    files: Vec<PathBuf>,
+}
+
+impl Fixture {
+    /// a repository with `files` committed
+    fn new(name: &'static str, files: impl IntoIterator<Item = (String, String)>) -> Self {
+        let dir = tempfile::tempdir().expect("fixture directory");
+        let repo = git2::Repository::init(dir.path()).expect("fixture repository");
+
+        let mut paths = Vec::new();
+        for (path, content) in files {
+            let full = dir.path().join(&path);
+            std::fs::create_dir_all(full.parent().unwrap()).unwrap();
+            std::fs::write(full, content).unwrap();
+            paths.push(PathBuf::from(path));
+        }
+
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("bench", "bench@githem").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "fixture", &tree, &[])
+            .unwrap();
+
+        Self {
+            name,
+            dir,
+            files: paths,
+        }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+fn source(i: usize) -> String {
+    format!("/// item {i}\npub fn item_{i}(x: u64) -> u64 {{\n    x.wrapping_mul({i})\n}}\n")
+        .repeat(20)
+}
+
+fn fixtures() -> Vec<Fixture> {
+    let flat = (0..1000).map(|i| (format!("src/file_{i}.rs"), source(i)));
+    let deep = (0..500).map(|i| {
+        let dirs: Vec<String> = (0..6)
+            .map(|level| format!("d{}", (i >> level) % 4))
+            .collect();
+        (format!("{}/file_{i}.rs", dirs.join("/")), source(i))
+    });
+    let vendored = (0..200)
+        .map(|i| (format!("src/file_{i}.rs"), source(i)))
+        .chain((0..2000).map(|i| {
+            (
+                format!("node_modules/pkg_{}/lib/index_{i}.js", i % 100),
+                source(i),
+            )
+        }));
+    let large = (0..8).map(|i| {
+        (
+            format!("data/large_{i}.txt"),
+            "lorem ipsum dolor sit amet\n".repeat(40_000),
+        )
+    });
+
+    vec![
+        Fixture::new("flat", flat),
+        Fixture::new("deep", deep),
+        Fixture::new("vendored", vendored),
+        Fixture::new("large", large),
+    ]
+}
+
+/// the defaults, without the size limit so the large files are written
+fn options() -> IngestOptions {
+    IngestOptions::builder()
+        .max_file_size(usize::MAX)
+        .build()
+        .unwrap()
+}
+
+fn ingest(path: &Path, options: IngestOptions) {
+    let ingester = Ingester::from_path(path, options).unwrap();
+    ingester.ingest(&mut std::io::sink()).unwrap();
+}
+
+/// walk, filter and write a local checkout
+fn bench_ingest(c: &mut Criterion) {
+    let fixtures = fixtures();
+    let mut group = c.benchmark_group("ingest");
+    group.sample_size(10);
+
+    for fixture in &fixtures {
+        let peak = peak_heap(|| ingest(fixture.path(), options()));
+        eprintln!("ingest/{}: peak heap {} KiB", fixture.name, peak / 1024);
+
+        group.throughput(Throughput::Elements(fixture.files.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(fixture.name),
+            fixture,
+            |b, fixture| {
+                b.iter(|| ingest(fixture.path(), options()));
+            },
+        );
+    }
+    group.finish();
+}
+
+/// a fresh clone of the fixture followed by the ingest, the path a remote
+/// url takes without the network
+fn bench_clone_to_output(c: &mut Criterion) {
+    let fixtures = fixtures();
+    let mut group = c.benchmark_group("clone_to_output");
+    group.sample_size(10);
+
+    for fixture in &fixtures {
+        let url = format!("file://{}", fixture.path().display());
+        group.throughput(Throughput::Elements(fixture.files.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(fixture.name), &url, |b, url| {
+            b.iter(|| {
+                let target = tempfile::tempdir().unwrap();
+                git2::build::RepoBuilder::new()
+                    .clone(url, target.path())
+                    .unwrap();
+                ingest(target.path(), options());
+            });
+        });
+    }
+    group.finish();
+}
+
+/// filter decisions alone, with the default excludes and with user patterns
+fn bench_filter(c: &mut Criterion) {
+    let fixtures = fixtures();
+    let mut group = c.benchmark_group("filter");
+
+    let explainers = [
+        ("standard", IngestOptions::default()),
+        (
+            "patterns",
+            IngestOptions::builder()
+                .include("src/**/*.rs")
+                .exclude("**/node_modules/")
+                .build()
+                .unwrap(),
+        ),
+    ];
+    for fixture in &fixtures {
+        for (name, options) in &explainers {
+            let explainer = FilterExplainer::new(options);
+            group.throughput(Throughput::Elements(fixture.files.len() as u64));
+            group.bench_with_input(
+                BenchmarkId::new(*name, fixture.name),
+                &fixture.files,
+                |b, files| {
+                    b.iter(|| {
+                        files
+                            .iter()
+                            .filter(|file| explainer.explain(file).is_included())
+                            .count()
+                    });
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_ingest, bench_clone_to_output, bench_filter);
+criterion_main!(benches);
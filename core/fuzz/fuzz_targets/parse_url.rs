@@ -0,0 +1,14 @@
+// cargo +nightly fuzz run parse_url (from core/)
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|url: &str| {
+    if let Some(parsed) = githem_core::parse_github_url(url).or_else(|| githem_core::parse_gitlab_url(url)) {
+        // whatever was parsed, its canonical url parses again
+        let again = githem_core::parse_github_url(&parsed.canonical_url)
+            .or_else(|| githem_core::parse_gitlab_url(&parsed.canonical_url));
+        assert!(again.is_some(), "{url:?} -> {:?}", parsed.canonical_url);
+    }
+    let _ = githem_core::normalize_source_url(url, None, None);
+});
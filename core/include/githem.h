@@ -0,0 +1,54 @@
+#ifndef GITHEM_H
+#define GITHEM_H
+
+/* Generated by cbindgen from core/src/ffi.rs - do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define GITHEM_OK 0
+
+#define GITHEM_ERR_INVALID_ARGUMENT 1
+
+#define GITHEM_ERR_INGEST 2
+
+// only returned by builds that unwind on panic, such as the `ffi` profile
+#define GITHEM_ERR_PANIC 3
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Ingest a repository (remote URL or local path) into `*out`.
+//
+// `options_json` is a JSON-encoded `IngestOptions` and may be NULL for
+// defaults. On success `*out` holds the output text and `GITHEM_OK` is
+// returned; on failure `*out` holds an error message and a non-zero code is
+// returned. Either way the caller owns `*out` and must pass it to
+// `githem_free`.
+//
+// # Safety
+//
+// `source` must be a valid NUL-terminated string, `options_json` must be NULL
+// or a valid NUL-terminated string, and `out` must be a valid pointer to
+// writable storage for one pointer.
+int githem_ingest(const char *source, const char *options_json, char **out);
+
+// Release a string returned by this library. NULL is ignored.
+//
+// # Safety
+//
+// `value` must be NULL or a pointer previously returned through this library
+// that has not already been freed.
+void githem_free(char *value);
+
+// Library version as a static NUL-terminated string; do not free.
+const char *githem_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GITHEM_H */
@@ -0,0 +1,310 @@
+use crate::{glob_match, is_remote_url};
+use git2::{Cred, CredentialType};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// default key names probed in ~/.ssh, in the order ssh itself tries them
+const DEFAULT_KEYS: &[&str] = &[
+    "id_ed25519",
+    "id_ed25519_sk",
+    "id_ecdsa",
+    "id_ecdsa_sk",
+    "id_rsa",
+];
+
+/// unencrypted openssh keys always start with `openssh-key-v1\0` + cipher "none"
+const OPENSSH_UNENCRYPTED: &str = "b3BlbnNzaC1rZXktdjEAAAAABG5vbmU";
+
+type PassphrasePrompt = fn(&Path) -> Option<String>;
+
+static PASSPHRASE_PROMPT: OnceLock<PassphrasePrompt> = OnceLock::new();
+static PASSPHRASES: Mutex<Option<HashMap<PathBuf, String>>> = Mutex::new(None);
+
+/// register how to ask for the passphrase of an encrypted key
+/// (the CLI prompts on the terminal, servers leave this unset)
+pub fn set_passphrase_prompt(prompt: PassphrasePrompt) {
+    let _ = PASSPHRASE_PROMPT.set(prompt);
+}
+
+/// user's home directory on unix and windows
+pub fn home_dir() -> Option<PathBuf> {
+    if let Ok(home) = std::env::var("HOME") {
+        if !home.is_empty() {
+            return Some(PathBuf::from(home));
+        }
+    }
+    if let Ok(profile) = std::env::var("USERPROFILE") {
+        if !profile.is_empty() {
+            return Some(PathBuf::from(profile));
+        }
+    }
+    match (std::env::var("HOMEDRIVE"), std::env::var("HOMEPATH")) {
+        (Ok(drive), Ok(path)) => Some(PathBuf::from(format!("{drive}{path}"))),
+        _ => None,
+    }
+}
+
+fn expand_home(path: &str, home: Option<&Path>) -> PathBuf {
+    let path = path.trim().trim_matches('"');
+    match home {
+        Some(home) => {
+            if let Some(rest) = path.strip_prefix("~/").or_else(|| path.strip_prefix("~\\")) {
+                return home.join(rest);
+            }
+            PathBuf::from(path.replace("%d", &home.to_string_lossy()))
+        }
+        None => PathBuf::from(path),
+    }
+}
+
+/// IdentityFile entries from an ssh config that apply to `host`
+fn identity_files_from_ssh_config(config: &str, host: &str, home: Option<&Path>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut applies = true;
+
+    for line in config.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = match line.split_once(|c: char| c.is_whitespace() || c == '=') {
+            Some((key, value)) => (
+                key,
+                value.trim_start_matches(|c: char| c.is_whitespace() || c == '='),
+            ),
+            None => continue,
+        };
+
+        if key.eq_ignore_ascii_case("host") {
+            let patterns: Vec<&str> = value.split_whitespace().collect();
+            let negated = patterns
+                .iter()
+                .filter_map(|p| p.strip_prefix('!'))
+                .any(|p| glob_match(p, host));
+            applies = !negated
+                && patterns
+                    .iter()
+                    .filter(|p| !p.starts_with('!'))
+                    .any(|p| *p == "*" || glob_match(p, host));
+        } else if key.eq_ignore_ascii_case("match") {
+            // match blocks need ssh's full evaluator, skip them
+            applies = false;
+        } else if applies && key.eq_ignore_ascii_case("identityfile") {
+            files.push(expand_home(value, home));
+        }
+    }
+
+    files
+}
+
+/// `-i <key>` from a `core.sshCommand` like `ssh -i ~/.ssh/deploy -o ...`
+fn identity_from_ssh_command(command: &str, home: Option<&Path>) -> Option<PathBuf> {
+    let mut parts = command.split_whitespace();
+    while let Some(part) = parts.next() {
+        if part == "-i" {
+            return parts.next().map(|p| expand_home(p, home));
+        }
+        if let Some(path) = part.strip_prefix("-i") {
+            return Some(expand_home(path, home));
+        }
+    }
+    None
+}
+
+fn host_of(url: &str) -> &str {
+    let rest = url.split("://").nth(1).unwrap_or(url);
+    let rest = rest.rsplit_once('@').map(|(_, h)| h).unwrap_or(rest);
+    rest.split(['/', ':']).next().unwrap_or_default()
+}
+
+/// private keys to try for `url`, most specific first, existing files only
+pub fn candidate_keys(url: &str) -> Vec<PathBuf> {
+    let home = home_dir();
+    let mut keys = Vec::new();
+
+    if let Ok(command) = git2::Config::open_default().and_then(|c| c.get_string("core.sshCommand"))
+    {
+        keys.extend(identity_from_ssh_command(&command, home.as_deref()));
+    }
+
+    if let Some(ref home) = home {
+        let ssh_dir = home.join(".ssh");
+        if let Ok(config) = std::fs::read_to_string(ssh_dir.join("config")) {
+            keys.extend(identity_files_from_ssh_config(
+                &config,
+                host_of(url),
+                Some(home),
+            ));
+        }
+        keys.extend(DEFAULT_KEYS.iter().map(|name| ssh_dir.join(name)));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    keys.retain(|key| key.is_file() && seen.insert(key.clone()));
+    keys
+}
+
+fn is_encrypted(private_key: &Path) -> bool {
+    match std::fs::read_to_string(private_key) {
+        Ok(content) => {
+            content.contains("ENCRYPTED")
+                || (content.contains("BEGIN OPENSSH PRIVATE KEY")
+                    && !content.contains(OPENSSH_UNENCRYPTED))
+        }
+        Err(_) => false,
+    }
+}
+
+fn passphrase_for(private_key: &Path) -> Option<String> {
+    let mut cache = PASSPHRASES.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if let Some(passphrase) = cache.get(private_key) {
+        return Some(passphrase.clone());
+    }
+
+    let passphrase = std::env::var("GITHEM_SSH_PASSPHRASE").ok().or_else(|| {
+        PASSPHRASE_PROMPT
+            .get()
+            .and_then(|prompt| prompt(private_key))
+    })?;
+    cache.insert(private_key.to_path_buf(), passphrase.clone());
+    Some(passphrase)
+}
+
+/// stateful credentials callback: libgit2 calls it again after each rejected
+/// credential, so every call moves on to the next candidate
+pub struct CredentialProvider {
+    tried_agent: bool,
+    keys: Option<Vec<PathBuf>>,
+    next_key: usize,
+    tried_default: bool,
+}
+
+impl CredentialProvider {
+    pub fn new() -> Self {
+        Self {
+            tried_agent: false,
+            keys: None,
+            next_key: 0,
+            tried_default: false,
+        }
+    }
+
+    pub fn credentials(
+        &mut self,
+        url: &str,
+        username_from_url: Option<&str>,
+        allowed_types: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        if !is_remote_url(url) {
+            return Err(git2::Error::from_str(
+                "Invalid URL for credential authentication",
+            ));
+        }
+
+        let username = username_from_url.unwrap_or("git");
+
+        if allowed_types.contains(CredentialType::SSH_KEY) {
+            if !self.tried_agent {
+                self.tried_agent = true;
+                if let Ok(cred) = Cred::ssh_key_from_agent(username) {
+                    return Ok(cred);
+                }
+            }
+
+            let keys = self.keys.get_or_insert_with(|| candidate_keys(url));
+            while let Some(private_key) = keys.get(self.next_key).cloned() {
+                self.next_key += 1;
+
+                let passphrase = if is_encrypted(&private_key) {
+                    match passphrase_for(&private_key) {
+                        Some(passphrase) => Some(passphrase),
+                        None => continue,
+                    }
+                } else {
+                    None
+                };
+
+                let public_key = private_key.with_extension(match private_key.extension() {
+                    Some(ext) => format!("{}.pub", ext.to_string_lossy()),
+                    None => "pub".to_string(),
+                });
+
+                if let Ok(cred) = Cred::ssh_key(
+                    username,
+                    public_key.is_file().then_some(public_key.as_path()),
+                    &private_key,
+                    passphrase.as_deref(),
+                ) {
+                    return Ok(cred);
+                }
+            }
+        }
+
+        if allowed_types.contains(CredentialType::DEFAULT)
+            && url.starts_with("https://")
+            && !self.tried_default
+        {
+            self.tried_default = true;
+            return Cred::default();
+        }
+
+        Err(git2::Error::from_str(
+            "No secure authentication method available",
+        ))
+    }
+}
+
+impl Default for CredentialProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// remote callbacks with githem's credential lookup installed
+pub fn remote_callbacks<'a>() -> git2::RemoteCallbacks<'a> {
+    let mut provider = CredentialProvider::new();
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(move |url, username_from_url, allowed_types| {
+        provider.credentials(url, username_from_url, allowed_types)
+    });
+    callbacks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ssh_config_identity_files() {
+        let config = "\
+Host *.corp.example gitlab-internal
+    IdentityFile ~/.ssh/work_rsa
+
+Host github.com
+    User git
+    IdentityFile=~/.ssh/github_ed25519
+
+Host *
+    IdentityFile %d/.ssh/fallback
+";
+        let home = Path::new("/home/dev");
+        assert_eq!(
+            identity_files_from_ssh_config(config, "github.com", Some(home)),
+            vec![
+                PathBuf::from("/home/dev/.ssh/github_ed25519"),
+                PathBuf::from("/home/dev/.ssh/fallback"),
+            ]
+        );
+        assert_eq!(
+            identity_files_from_ssh_config(config, "git.corp.example", Some(home))[0],
+            PathBuf::from("/home/dev/.ssh/work_rsa")
+        );
+        assert_eq!(
+            identity_from_ssh_command("ssh -i ~/.ssh/deploy -o IdentitiesOnly=yes", Some(home)),
+            Some(PathBuf::from("/home/dev/.ssh/deploy"))
+        );
+    }
+}
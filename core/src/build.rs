@@ -0,0 +1,55 @@
+/// CI configuration, container and make files collected by `build_ci`:
+/// GitHub workflows and actions, GitLab CI, Dockerfiles and Makefiles
+pub fn is_build_file(path: &str) -> bool {
+    let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let lower = name.to_ascii_lowercase();
+    let yaml = lower.ends_with(".yml") || lower.ends_with(".yaml");
+
+    if yaml
+        && (dir == ".github/workflows"
+            || (dir.starts_with(".github/actions/") && lower.starts_with("action.")))
+    {
+        return true;
+    }
+    if yaml && (lower.starts_with(".gitlab-ci.") || dir == ".gitlab/ci") {
+        return true;
+    }
+    if lower == "dockerfile"
+        || lower.starts_with("dockerfile.")
+        || lower.ends_with(".dockerfile")
+        || lower == "containerfile"
+        || (yaml && (lower.starts_with("docker-compose.") || lower.starts_with("compose.")))
+    {
+        return true;
+    }
+    matches!(lower.as_str(), "makefile" | "gnumakefile") || lower.ends_with(".mk")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_files() {
+        for path in [
+            ".github/workflows/ci.yml",
+            ".github/actions/setup/action.yaml",
+            ".gitlab-ci.yml",
+            "api/Dockerfile",
+            "Dockerfile.dev",
+            "docker-compose.yml",
+            "Makefile",
+            "mk/rules.mk",
+        ] {
+            assert!(is_build_file(path), "{path}");
+        }
+        for path in [
+            "src/workflows/ci.yml",
+            "config.yaml",
+            "docs/makefile.md",
+            ".github/dependabot.yml",
+        ] {
+            assert!(!is_build_file(path), "{path}");
+        }
+    }
+}
@@ -3,10 +3,61 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+#[cfg(feature = "zstd")]
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// starts every entry file, followed by the format version
+const ENTRY_MAGIC: [u8; 4] = *b"GHCE";
+/// entry files written with another layout are treated as missing
+pub const CACHE_VERSION: u32 = 3;
+
+/// set by `configure_cache_dir`, XDG_CACHE_HOME or HOME otherwise
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// keep the cache of this process in `dir` instead of under XDG_CACHE_HOME
+/// or HOME, first call wins
+pub fn configure_cache_dir(dir: PathBuf) {
+    let _ = CACHE_DIR.set(dir);
+}
+
+/// zstd-compress a cache payload (stored as-is without the zstd feature)
+pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    #[cfg(feature = "zstd")]
+    {
+        Ok(zstd::encode_all(data, COMPRESSION_LEVEL)?)
+    }
+    #[cfg(not(feature = "zstd"))]
+    {
+        Ok(data.to_vec())
+    }
+}
+
+/// inverse of `compress`; payloads written before compression pass through
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    if !data.starts_with(&ZSTD_MAGIC) {
+        return Ok(data.to_vec());
+    }
+    #[cfg(feature = "zstd")]
+    {
+        Ok(zstd::decode_all(data)?)
+    }
+    #[cfg(not(feature = "zstd"))]
+    {
+        Err(anyhow::anyhow!(
+            "Cache entry is zstd-compressed but zstd support is disabled"
+        ))
+    }
+}
+
+/// a cached repository: its file list plus where the checkout is, stored
+/// as a `CacheEntryHeader` and the files after it (see `CachedIndex`)
+#[derive(Debug, Clone)]
 pub struct CacheEntry {
     pub repo_url: String,
     pub branch: String,
@@ -18,12 +69,60 @@ pub struct CacheEntry {
     pub repo_path: PathBuf, // Path to cloned repository on disk
 }
 
+impl CacheEntry {
+    pub fn header(&self) -> CacheEntryHeader {
+        CacheEntryHeader {
+            repo_url: self.repo_url.clone(),
+            branch: self.branch.clone(),
+            commit_hash: self.commit_hash.clone(),
+            metadata: self.metadata.clone(),
+            created_at: self.created_at,
+            last_accessed: self.last_accessed,
+            repo_path: self.repo_path.clone(),
+        }
+    }
+
+    /// the entry file: magic, version, the header and the file count
+    /// uncompressed, then the file records compressed as one stream
+    fn encode(&self) -> Result<Vec<u8>> {
+        let header = bincode::serialize(&self.header())?;
+        let mut records = Vec::new();
+        for file in &self.files {
+            bincode::serialize_into(&mut records, file)?;
+        }
+
+        let mut data = Vec::with_capacity(header.len() + records.len() / 4 + 24);
+        data.extend_from_slice(&ENTRY_MAGIC);
+        data.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        data.extend_from_slice(&(header.len() as u64).to_le_bytes());
+        data.extend_from_slice(&header);
+        data.extend_from_slice(&(self.files.len() as u64).to_le_bytes());
+        data.extend_from_slice(&compress(&records)?);
+        Ok(data)
+    }
+}
+
+/// everything in a `CacheEntry` but the files, readable without them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntryHeader {
+    pub repo_url: String,
+    pub branch: String,
+    pub commit_hash: String,
+    pub metadata: CacheMetadata,
+    pub created_at: u64,
+    pub last_accessed: u64,
+    pub repo_path: PathBuf,
+}
+
+/// contents are never cached, ingestion reads them from the checkout
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CachedFile {
     pub path: PathBuf,
-    // REMOVED: pub content: Vec<u8>,  // Never store file contents in RAM!
     pub size: u64,
     pub is_binary: bool,
+    /// link target when the path is a symlink, which is never read through
+    #[serde(default)]
+    pub symlink: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,6 +155,165 @@ struct CacheEntryInfo {
     pub created_at: u64,
     pub last_accessed: u64,
     pub commit_hash: String,
+    // missing in indexes written by older versions
+    #[serde(default)]
+    pub repo_url: String,
+    #[serde(default)]
+    pub branch: String,
+}
+
+/// one cached repository as shown by `githem cache list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntrySummary {
+    pub key: String,
+    pub repo_url: String,
+    pub branch: String,
+    pub commit_hash: String,
+    /// index entry plus the persistent checkout, in bytes
+    pub size: u64,
+    pub created_at: u64,
+    pub last_accessed: u64,
+}
+
+/// the bytes of an entry file, mapped when possible so only the pages
+/// that are read get loaded
+enum EntryBytes {
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+    Read(Vec<u8>),
+}
+
+impl EntryBytes {
+    fn load(path: &Path) -> Result<Self> {
+        #[cfg(feature = "mmap")]
+        {
+            let file = fs::File::open(path)?;
+            // SAFETY: entry files are written under another name and renamed
+            // into place by `RepositoryCache::put`, never changed in place
+            if let Ok(map) = unsafe { memmap2::Mmap::map(&file) } {
+                return Ok(Self::Mapped(map));
+            }
+        }
+        Ok(Self::Read(fs::read(path)?))
+    }
+}
+
+impl std::ops::Deref for EntryBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(feature = "mmap")]
+            Self::Mapped(map) => map,
+            Self::Read(data) => data,
+        }
+    }
+}
+
+/// an opened entry file: the header is decoded right away, the file
+/// records only as `files` is iterated
+pub struct CachedIndex {
+    bytes: EntryBytes,
+    header: CacheEntryHeader,
+    file_count: usize,
+    /// offset of the compressed file records
+    files_at: usize,
+}
+
+impl CachedIndex {
+    pub fn open(path: &Path) -> Result<Self> {
+        let bytes = EntryBytes::load(path)?;
+        // lengths come from the file, a corrupt one must not overflow
+        let truncated = || anyhow::anyhow!("Truncated cache entry {}", path.display());
+        let field = |at: usize, len: usize| {
+            at.checked_add(len)
+                .and_then(|end| bytes.get(at..end))
+                .ok_or_else(truncated)
+        };
+        let u64_at = |at: usize| -> Result<usize> {
+            Ok(u64::from_le_bytes(field(at, 8)?.try_into()?) as usize)
+        };
+
+        if field(0, 4)? != ENTRY_MAGIC {
+            anyhow::bail!("{} is not a cache entry of this version", path.display());
+        }
+        let version = u32::from_le_bytes(field(4, 4)?.try_into()?);
+        if version != CACHE_VERSION {
+            anyhow::bail!("Cache entry version {version}, expected {CACHE_VERSION}");
+        }
+        let header_len = u64_at(8)?;
+        let header = bincode::deserialize(field(16, header_len)?)?;
+        let header_end = header_len.checked_add(16).ok_or_else(truncated)?;
+        let file_count = u64_at(header_end)?;
+
+        Ok(Self {
+            header,
+            file_count,
+            // the count was read, so this is within the file
+            files_at: header_end + 8,
+            bytes,
+        })
+    }
+
+    pub fn header(&self) -> &CacheEntryHeader {
+        &self.header
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.file_count
+    }
+
+    /// the file records, decompressed and decoded one at a time
+    pub fn files(&self) -> Result<impl Iterator<Item = Result<CachedFile>> + '_> {
+        let mut reader = decompress_reader(&self.bytes[self.files_at..])?;
+        Ok((0..self.file_count).map(move |_| Ok(bincode::deserialize_from(&mut reader)?)))
+    }
+
+    pub fn into_entry(self) -> Result<CacheEntry> {
+        let files = self.files()?.collect::<Result<Vec<_>>>()?;
+        let header = self.header;
+        Ok(CacheEntry {
+            repo_url: header.repo_url,
+            branch: header.branch,
+            commit_hash: header.commit_hash,
+            files,
+            metadata: header.metadata,
+            created_at: header.created_at,
+            last_accessed: header.last_accessed,
+            repo_path: header.repo_path,
+        })
+    }
+}
+
+/// `decompress` as a stream over `data`
+fn decompress_reader(data: &[u8]) -> Result<Box<dyn Read + '_>> {
+    if !data.starts_with(&ZSTD_MAGIC) {
+        return Ok(Box::new(data));
+    }
+    #[cfg(feature = "zstd")]
+    {
+        Ok(Box::new(zstd::stream::read::Decoder::with_buffer(data)?))
+    }
+    #[cfg(not(feature = "zstd"))]
+    {
+        Err(anyhow::anyhow!(
+            "Cache entry is zstd-compressed but zstd support is disabled"
+        ))
+    }
+}
+
+pub(crate) fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            Ok(t) if t.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
 }
 
 pub struct RepositoryCache {
@@ -85,7 +343,9 @@ impl RepositoryCache {
     }
 
     fn get_cache_dir() -> Result<PathBuf> {
-        let cache_dir = if let Ok(xdg_cache) = std::env::var("XDG_CACHE_HOME") {
+        let cache_dir = if let Some(dir) = CACHE_DIR.get() {
+            dir.clone()
+        } else if let Ok(xdg_cache) = std::env::var("XDG_CACHE_HOME") {
             PathBuf::from(xdg_cache).join("githem")
         } else if let Ok(home) = std::env::var("HOME") {
             PathBuf::from(home).join(".cache").join("githem")
@@ -96,15 +356,39 @@ impl RepositoryCache {
     }
 
     pub fn generate_cache_key(url: &str, branch: Option<&str>) -> String {
+        Self::generate_variant_key(url, branch, None, None)
+    }
+
+    /// the key of one filtered output of a repository; without a preset
+    /// and path it is the key of the checkout itself
+    pub fn generate_variant_key(
+        url: &str,
+        branch: Option<&str>,
+        preset: Option<&str>,
+        path: Option<&str>,
+    ) -> String {
         let mut hasher = Sha256::new();
         hasher.update(url.as_bytes());
-        if let Some(branch) = branch {
+        for part in [branch, preset, path].into_iter().flatten() {
             hasher.update(b":");
-            hasher.update(branch.as_bytes());
+            hasher.update(part.as_bytes());
         }
         format!("{:x}", hasher.finalize())
     }
 
+    /// persistent clone reused across runs so updates are a fetch, not a reclone
+    pub fn checkout_dir(&self, key: &str) -> PathBuf {
+        self.cache_dir.join("repos").join(key)
+    }
+
+    fn remove_checkout(&self, key: &str) -> Result<()> {
+        let dir = self.checkout_dir(key);
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
     pub fn get(&mut self, key: &str) -> Result<Option<CacheEntry>> {
         if let Some(info) = self.index.get_mut(key) {
             let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
@@ -118,8 +402,12 @@ impl RepositoryCache {
 
             let cache_path = &info.path;
             if cache_path.exists() {
-                let data = fs::read(cache_path)?;
-                let entry: CacheEntry = bincode::deserialize(&data)?;
+                let Ok(entry) = CachedIndex::open(cache_path).and_then(CachedIndex::into_entry)
+                else {
+                    // written by another version, reindexed like a miss
+                    self.remove(key)?;
+                    return Ok(None);
+                };
                 self.save_index()?;
                 return Ok(Some(entry));
             }
@@ -127,14 +415,31 @@ impl RepositoryCache {
         Ok(None)
     }
 
+    /// the entry without reading its file list or marking it used, for
+    /// listings and stats; None when missing, expired or unreadable
+    pub fn open(&self, key: &str) -> Result<Option<CachedIndex>> {
+        let Some(info) = self.index.get(key) else {
+            return Ok(None);
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        if now - info.created_at > self.max_age_seconds || !info.path.exists() {
+            return Ok(None);
+        }
+        Ok(CachedIndex::open(&info.path).ok())
+    }
+
     pub fn put(&mut self, key: String, entry: CacheEntry) -> Result<()> {
-        let serialized = bincode::serialize(&entry)?;
+        let serialized = entry.encode()?;
+        // sizes are tracked post-compression, that is what occupies the disk
         let entry_size = serialized.len() as u64;
 
-        self.evict_if_needed(entry_size)?;
+        self.evict_if_needed(&key, entry_size)?;
 
+        // renamed into place, readers may have the old file mapped
         let cache_file = self.cache_dir.join(format!("{}.cache", key));
-        fs::write(&cache_file, serialized)?;
+        let partial = cache_file.with_extension("cache.tmp");
+        fs::write(&partial, serialized)?;
+        fs::rename(&partial, &cache_file)?;
 
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
         self.index.insert(
@@ -146,6 +451,8 @@ impl RepositoryCache {
                 created_at: now,
                 last_accessed: now,
                 commit_hash: entry.commit_hash.clone(),
+                repo_url: entry.repo_url.clone(),
+                branch: entry.branch.clone(),
             },
         );
 
@@ -175,14 +482,20 @@ impl RepositoryCache {
         Ok(())
     }
 
-    fn evict_if_needed(&mut self, new_entry_size: u64) -> Result<()> {
+    fn evict_if_needed(&mut self, new_key: &str, new_entry_size: u64) -> Result<()> {
         let total_size: u64 = self.index.values().map(|e| e.size).sum();
 
         if total_size + new_entry_size <= self.max_cache_size {
             return Ok(());
         }
 
-        let mut entries: Vec<_> = self.index.values().cloned().collect();
+        // never evict the entry being replaced, its checkout is in use
+        let mut entries: Vec<_> = self
+            .index
+            .values()
+            .filter(|e| e.key != new_key)
+            .cloned()
+            .collect();
         entries.sort_by_key(|e| e.last_accessed);
 
         let mut freed_space = 0u64;
@@ -190,8 +503,11 @@ impl RepositoryCache {
             if total_size - freed_space + new_entry_size <= self.max_cache_size {
                 break;
             }
+            let Some(_lock) = CheckoutLock::acquire(self.checkout_dir(&entry.key)) else {
+                continue;
+            };
             freed_space += entry.size;
-            self.remove(&entry.key)?;
+            self.remove_entry(&entry.key)?;
         }
 
         Ok(())
@@ -218,10 +534,125 @@ impl RepositoryCache {
         Ok(())
     }
 
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// all entries, most recently used first
+    pub fn entries(&self) -> Vec<CacheEntrySummary> {
+        let mut entries: Vec<_> = self
+            .index
+            .values()
+            .map(|info| CacheEntrySummary {
+                key: info.key.clone(),
+                repo_url: info.repo_url.clone(),
+                branch: info.branch.clone(),
+                commit_hash: info.commit_hash.clone(),
+                size: info.size + dir_size(&self.checkout_dir(&info.key)),
+                created_at: info.created_at,
+                last_accessed: info.last_accessed,
+            })
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.last_accessed));
+        entries
+    }
+
+    /// drop an entry together with its checkout
+    pub fn remove_entry(&mut self, key: &str) -> Result<()> {
+        self.remove(key)?;
+        self.remove_checkout(key)
+    }
+
+    /// remove entries whose url is `repo`, ends with `/repo` (e.g. `owner/repo`),
+    /// or whose key starts with `repo`; returns the number removed
+    pub fn remove_repository(&mut self, repo: &str) -> Result<usize> {
+        let wanted = repo.trim_end_matches('/').trim_end_matches(".git");
+        if wanted.is_empty() {
+            return Ok(0);
+        }
+
+        let keys: Vec<String> = self
+            .index
+            .values()
+            .filter(|info| {
+                let url = info.repo_url.trim_end_matches('/').trim_end_matches(".git");
+                (!url.is_empty() && (url == wanted || url.ends_with(&format!("/{wanted}"))))
+                    || (wanted.len() >= 8 && info.key.starts_with(wanted))
+            })
+            .map(|info| info.key.clone())
+            .collect();
+
+        for key in &keys {
+            self.remove_entry(key)?;
+        }
+        Ok(keys.len())
+    }
+
+    /// remove entries not used within `max_age_seconds`; returns the number removed
+    pub fn prune_older_than(&mut self, max_age_seconds: u64) -> Result<usize> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let keys: Vec<String> = self
+            .index
+            .values()
+            .filter(|info| now.saturating_sub(info.last_accessed) > max_age_seconds)
+            .map(|info| info.key.clone())
+            .collect();
+
+        for key in &keys {
+            self.remove_entry(key)?;
+        }
+        Ok(keys.len())
+    }
+
+    /// entry files and checkouts together
+    pub fn disk_usage(&self) -> u64 {
+        self.index
+            .values()
+            .map(|info| info.size + dir_size(&self.checkout_dir(&info.key)))
+            .sum()
+    }
+
+    /// remove the least recently used entries with their checkouts until
+    /// both fit in `max_bytes`, leaving checkouts that are being updated
+    /// alone; returns how many were removed and the bytes freed
+    pub fn evict_to(&mut self, max_bytes: u64) -> Result<(usize, u64)> {
+        let mut entries: Vec<(u64, String, u64)> = self
+            .index
+            .values()
+            .map(|info| {
+                let size = info.size + dir_size(&self.checkout_dir(&info.key));
+                (info.last_accessed, info.key.clone(), size)
+            })
+            .collect();
+        entries.sort();
+
+        let mut total: u64 = entries.iter().map(|(_, _, size)| size).sum();
+        let (mut removed, mut freed) = (0, 0);
+        for (_, key, size) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            let Some(_lock) = CheckoutLock::acquire(self.checkout_dir(&key)) else {
+                continue;
+            };
+            self.remove_entry(&key)?;
+            total -= size;
+            removed += 1;
+            freed += size;
+        }
+        Ok((removed, freed))
+    }
+
     pub fn clear_all(&mut self) -> Result<()> {
         for key in self.index.keys().cloned().collect::<Vec<_>>() {
             self.remove(&key)?;
         }
+        for dir in ["repos", RESULTS_DIR] {
+            let dir = self.cache_dir.join(dir);
+            if dir.exists() {
+                fs::remove_dir_all(dir)?;
+            }
+        }
         Ok(())
     }
 
@@ -248,6 +679,188 @@ impl RepositoryCache {
     }
 }
 
+/// subdirectory of the cache dir holding `DiskBackend` values
+const RESULTS_DIR: &str = "results";
+
+/// where finished outputs are kept by key, so every process sharing a
+/// cache directory (the cli, the api, the api after a restart) finds them
+pub trait CacheBackend: Send + Sync {
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>>;
+    fn store(&self, key: &str, data: &[u8]) -> Result<()>;
+    fn remove(&self, key: &str) -> Result<()>;
+    fn keys(&self) -> Result<Vec<String>>;
+}
+
+/// one file per key in a directory
+pub struct DiskBackend {
+    dir: PathBuf,
+}
+
+impl DiskBackend {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// the results directory next to the checkouts of `RepositoryCache`
+    pub fn shared() -> Result<Self> {
+        Self::new(RepositoryCache::get_cache_dir()?.join(RESULTS_DIR))
+    }
+
+    /// bytes of all values
+    pub fn usage(&self) -> u64 {
+        dir_size(&self.dir)
+    }
+
+    /// remove the least recently used values until the rest fit in
+    /// `max_bytes`; returns how many were removed and the bytes freed
+    pub fn evict_to(&self, max_bytes: u64) -> Result<(usize, u64)> {
+        let mut values = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                values.push((metadata.modified()?, entry.path(), metadata.len()));
+            }
+        }
+        values.sort();
+
+        let mut total: u64 = values.iter().map(|(_, _, size)| size).sum();
+        let (mut removed, mut freed) = (0, 0);
+        for (_, path, size) in values {
+            if total <= max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= size;
+            removed += 1;
+            freed += size;
+        }
+        Ok((removed, freed))
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            anyhow::bail!("Invalid cache key '{key}'");
+        }
+        Ok(self.dir.join(key))
+    }
+}
+
+impl CacheBackend for DiskBackend {
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.path(key)?;
+        match fs::read(&path) {
+            Ok(data) => {
+                // the modification time doubles as the last use, see `evict_to`
+                let _ = fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .and_then(|file| file.set_modified(SystemTime::now()));
+                Ok(Some(data))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store(&self, key: &str, data: &[u8]) -> Result<()> {
+        // renamed into place so readers never see half a value
+        let path = self.path(key)?;
+        let partial = path.with_extension("tmp");
+        fs::write(&partial, data)?;
+        fs::rename(&partial, &path)?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        match fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn keys(&self) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if !name.contains('.') {
+                keys.push(name);
+            }
+        }
+        Ok(keys)
+    }
+}
+
+/// values that live as long as the process
+#[derive(Default)]
+pub struct MemoryBackend {
+    values: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryBackend {
+    fn values(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<u8>>> {
+        self.values
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl CacheBackend for MemoryBackend {
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.values().get(key).cloned())
+    }
+
+    fn store(&self, key: &str, data: &[u8]) -> Result<()> {
+        self.values().insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        self.values().remove(key);
+        Ok(())
+    }
+
+    fn keys(&self) -> Result<Vec<String>> {
+        Ok(self.values().keys().cloned().collect())
+    }
+}
+
+/// exclusive use of a cached checkout, released on drop
+/// works across processes so the CLI and API can share a cache dir
+pub struct CheckoutLock {
+    dir: PathBuf,
+    /// locked by the OS, which also releases it when the process dies, so
+    /// there are no stale locks to break; the file is never removed, as a
+    /// new file under the same name could be locked while this one is held
+    _file: fs::File,
+}
+
+impl CheckoutLock {
+    /// None when another ingestion currently holds the checkout
+    pub fn acquire(dir: PathBuf) -> Option<Self> {
+        let parent = dir.parent()?;
+        fs::create_dir_all(parent).ok()?;
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.with_extension("lock"))
+            .ok()?;
+        file.try_lock().ok()?;
+
+        Some(Self { dir, _file: file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum CacheCommitStatus {
     Match,
@@ -268,4 +881,169 @@ impl CacheManager {
         let cache = RepositoryCache::new()?;
         Ok(cache.get_stats())
     }
+
+    pub fn list_entries() -> Result<Vec<CacheEntrySummary>> {
+        let cache = RepositoryCache::new()?;
+        Ok(cache.entries())
+    }
+
+    pub fn remove_repository(repo: &str) -> Result<usize> {
+        let mut cache = RepositoryCache::new()?;
+        cache.remove_repository(repo)
+    }
+
+    pub fn prune(max_age_seconds: u64) -> Result<usize> {
+        let mut cache = RepositoryCache::new()?;
+        cache.prune_older_than(max_age_seconds)
+    }
+
+    pub fn cache_dir() -> Result<PathBuf> {
+        RepositoryCache::get_cache_dir()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_roundtrip() {
+        let data = "=== src/lib.rs ===\nfn main() {}\n\n".repeat(200);
+        let compressed = compress(data.as_bytes()).unwrap();
+        #[cfg(feature = "zstd")]
+        assert!(compressed.len() < data.len() / 4);
+        assert_eq!(decompress(&compressed).unwrap(), data.as_bytes());
+
+        // entries written before compression are read back unchanged
+        assert_eq!(decompress(b"legacy").unwrap(), b"legacy");
+    }
+
+    #[test]
+    fn test_entry_file_roundtrip() {
+        let files: Vec<CachedFile> = (0..500)
+            .map(|i| CachedFile {
+                path: PathBuf::from(format!("src/module_{i}.rs")),
+                size: i * 10,
+                is_binary: i % 100 == 0,
+                symlink: (i == 7).then(|| PathBuf::from("target.rs")),
+            })
+            .collect();
+        let entry = CacheEntry {
+            repo_url: "https://github.com/owner/repo".to_string(),
+            branch: "main".to_string(),
+            commit_hash: "abc123".to_string(),
+            metadata: CacheMetadata {
+                total_files: files.len(),
+                total_size: files.iter().map(|f| f.size).sum(),
+                tree_hash: "abc123".to_string(),
+                cache_version: CACHE_VERSION.to_string(),
+            },
+            files,
+            created_at: 1,
+            last_accessed: 2,
+            repo_path: PathBuf::from("/cache/repos/key"),
+        };
+
+        let path = std::env::temp_dir().join(format!("githem-entry-{}.cache", std::process::id()));
+        fs::write(&path, entry.encode().unwrap()).unwrap();
+        let index = CachedIndex::open(&path).unwrap();
+        assert_eq!(index.header().commit_hash, "abc123");
+        assert_eq!(index.file_count(), 500);
+        let paths: Vec<PathBuf> = index
+            .files()
+            .unwrap()
+            .take(2)
+            .map(|f| f.unwrap().path)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("src/module_0.rs"),
+                PathBuf::from("src/module_1.rs")
+            ]
+        );
+
+        let read = index.into_entry().unwrap();
+        assert_eq!(read.files.len(), 500);
+        assert_eq!(
+            read.files[7].symlink.as_deref(),
+            Some(Path::new("target.rs"))
+        );
+        assert_eq!(read.files[499].size, 4990);
+        assert_eq!(read.repo_path, entry.repo_path);
+
+        // entries of the previous layout are not mistaken for this one
+        fs::write(&path, compress(b"old bincode entry").unwrap()).unwrap();
+        assert!(CachedIndex::open(&path).is_err());
+
+        // a corrupt header length is a truncated entry, not an overflow
+        let mut corrupt = ENTRY_MAGIC.to_vec();
+        corrupt.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        corrupt.extend_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&path, corrupt).unwrap();
+        let err = CachedIndex::open(&path).err().unwrap();
+        assert!(err.to_string().starts_with("Truncated cache entry"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_backends() {
+        let dir = std::env::temp_dir().join(format!("githem-backend-{}", std::process::id()));
+        let disk = DiskBackend::new(dir.clone()).unwrap();
+        for backend in [&disk as &dyn CacheBackend, &MemoryBackend::default()] {
+            let key = RepositoryCache::generate_variant_key(
+                "https://github.com/o/r",
+                Some("main"),
+                None,
+                None,
+            );
+            assert_eq!(
+                key,
+                RepositoryCache::generate_cache_key("https://github.com/o/r", Some("main"))
+            );
+            assert_eq!(backend.load(&key).unwrap(), None);
+            backend.store(&key, b"first").unwrap();
+            backend.store(&key, b"second").unwrap();
+            assert_eq!(backend.load(&key).unwrap().as_deref(), Some(&b"second"[..]));
+            assert_eq!(backend.keys().unwrap(), [key.as_str()]);
+            backend.remove(&key).unwrap();
+            backend.remove(&key).unwrap();
+            assert!(backend.keys().unwrap().is_empty());
+        }
+        assert!(disk.store("../escape", b"x").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_checkout_lock() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("repos").join("key");
+        let lock = CheckoutLock::acquire(dir.clone()).unwrap();
+        assert!(CheckoutLock::acquire(dir.clone()).is_none());
+        drop(lock);
+        assert!(CheckoutLock::acquire(dir).is_some());
+    }
+
+    #[test]
+    fn test_disk_backend_evict_to() {
+        let dir = std::env::temp_dir().join(format!("githem-evict-{}", std::process::id()));
+        let disk = DiskBackend::new(dir.clone()).unwrap();
+        let now = SystemTime::now();
+        for (age, key) in [(30, "a"), (20, "b"), (10, "c")] {
+            disk.store(key, &[0; 10]).unwrap();
+            let file = fs::File::options().write(true).open(dir.join(key)).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+        assert_eq!(disk.usage(), 30);
+
+        // a load counts as a use, so b is now the least recent
+        disk.load("a").unwrap();
+        assert_eq!(disk.evict_to(20).unwrap(), (1, 10));
+        let mut keys = disk.keys().unwrap();
+        keys.sort();
+        assert_eq!(keys, ["a", "c"]);
+        assert_eq!(disk.evict_to(100).unwrap(), (0, 0));
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
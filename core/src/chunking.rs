@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+/// Options for splitting output into token-bounded chunks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkOptions {
+    /// Target maximum estimated tokens per chunk
+    pub max_tokens: usize,
+    /// Estimated tokens repeated from the end of the previous chunk
+    pub overlap_tokens: usize,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: 1500,
+            overlap_tokens: 200,
+        }
+    }
+}
+
+/// A single chunk of one file, ready for a vector store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    pub path: String,
+    /// 1-based, inclusive
+    pub start_line: usize,
+    /// 1-based, inclusive
+    pub end_line: usize,
+    pub tokens: usize,
+    pub content: String,
+}
+
+/// split every file section of an ingestion output into overlapping chunks.
+/// chunks never span files and always break on line boundaries; a single
+/// line larger than the budget becomes its own chunk.
+pub fn chunk_output(content: &str, options: &ChunkOptions) -> Vec<Chunk> {
+    let max_tokens = options.max_tokens.max(1);
+    // overlap must leave room for new lines or chunking would never advance
+    let overlap_tokens = options.overlap_tokens.min(max_tokens / 2);
+
+    let mut chunks = Vec::new();
+
+    for (path, body) in crate::split_files(content) {
+        let lines: Vec<&str> = body.trim_end_matches('\n').lines().collect();
+        let line_tokens: Vec<usize> = lines
+            .iter()
+            .map(|l| crate::estimate_tokens(l).max(1))
+            .collect();
+
+        let mut start = 0;
+        while start < lines.len() {
+            let mut end = start;
+            let mut tokens = 0;
+            while end < lines.len() && (end == start || tokens + line_tokens[end] <= max_tokens) {
+                tokens += line_tokens[end];
+                end += 1;
+            }
+
+            chunks.push(Chunk {
+                path: path.to_string(),
+                start_line: start + 1,
+                end_line: end,
+                tokens,
+                content: lines[start..end].join("\n"),
+            });
+
+            if end >= lines.len() {
+                break;
+            }
+
+            // walk back from the end to find where the overlap begins
+            let mut next = end;
+            let mut overlap = 0;
+            while next > start + 1 && overlap + line_tokens[next - 1] <= overlap_tokens {
+                overlap += line_tokens[next - 1];
+                next -= 1;
+            }
+            start = next;
+        }
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_overlap_and_stay_within_files() {
+        let body: String = (1..=100)
+            .map(|i| format!("let value_{i} = {i};\n"))
+            .collect();
+        let output = format!("=== a.rs ===\n{body}\n=== b.rs ===\nfn b() {{}}\n\n");
+
+        let options = ChunkOptions {
+            max_tokens: 100,
+            overlap_tokens: 20,
+        };
+        let chunks = chunk_output(&output, &options);
+
+        let a: Vec<_> = chunks.iter().filter(|c| c.path == "a.rs").collect();
+        assert!(a.len() > 1);
+        assert_eq!(a[0].start_line, 1);
+        assert_eq!(a.last().unwrap().end_line, 100);
+        for pair in a.windows(2) {
+            assert!(pair[1].start_line <= pair[0].end_line);
+            assert!(pair[1].start_line > pair[0].start_line);
+        }
+        assert!(a.iter().all(|c| c.tokens <= 100));
+
+        let b: Vec<_> = chunks.iter().filter(|c| c.path == "b.rs").collect();
+        assert_eq!(b.len(), 1);
+        assert_eq!(b[0].content, "fn b() {}");
+    }
+}
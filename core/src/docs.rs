@@ -0,0 +1,224 @@
+/// prose files kept whole by the docs-only preset
+pub const DOC_EXTENSIONS: &[&str] = &["md", "markdown", "mdx", "rst", "adoc", "asciidoc", "txt"];
+
+/// source files the docs-only preset reads doc comments from
+pub const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "go", "py", "java", "kt", "scala", "swift", "dart", "cs", "js", "jsx", "mjs", "cjs",
+    "ts", "tsx", "c", "h", "cc", "cpp", "hpp", "php",
+];
+
+fn extension(path: &str) -> Option<String> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+}
+
+/// documentation that is ingested as is, including extensionless
+/// files like README or CONTRIBUTING
+pub fn is_documentation(path: &str) -> bool {
+    match extension(path) {
+        Some(ext) => DOC_EXTENSIONS.contains(&ext.as_str()),
+        None => true,
+    }
+}
+
+/// READMEs (at the root or one directory down) and architecture, design
+/// and contributing guides at the root or in a docs directory
+pub fn is_key_document(path: &str) -> bool {
+    let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+    if !is_documentation(name) {
+        return false;
+    }
+    let stem = name.split('.').next().unwrap_or(name).to_ascii_lowercase();
+    if stem == "readme" {
+        return !dir.contains('/');
+    }
+    let docs_dir = dir.is_empty() || matches!(dir.to_ascii_lowercase().as_str(), "docs" | "doc");
+    docs_dir
+        && matches!(
+            stem.as_str(),
+            "architecture" | "design" | "overview" | "contributing" | "hacking"
+        )
+}
+
+/// the doc comments of a source file with their comment markers removed,
+/// None when there are none or the language is not supported
+pub fn extract_doc_comments(path: &str, content: &str) -> Option<String> {
+    let ext = extension(path)?;
+    let blocks = match ext.as_str() {
+        "rs" => {
+            let mut blocks = line_comments(content, &["///", "//!"]);
+            blocks.extend(block_comments(content, &["/**", "/*!"]));
+            blocks
+        }
+        "cs" => line_comments(content, &["///"]),
+        "swift" | "dart" => {
+            let mut blocks = line_comments(content, &["///"]);
+            blocks.extend(block_comments(content, &["/**"]));
+            blocks
+        }
+        "go" => go_comments(content),
+        "py" => docstrings(content),
+        _ if SOURCE_EXTENSIONS.contains(&ext.as_str()) => block_comments(content, &["/**"]),
+        _ => return None,
+    };
+
+    let blocks: Vec<String> = blocks
+        .into_iter()
+        .filter(|b| !b.trim().is_empty())
+        .collect();
+    (!blocks.is_empty()).then(|| blocks.join("\n\n"))
+}
+
+/// consecutive lines starting with one of `markers`, one block per run
+fn line_comments(content: &str, markers: &[&str]) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        match markers.iter().find_map(|m| trimmed.strip_prefix(m)) {
+            Some(text) => current.push(text.strip_prefix(' ').unwrap_or(text)),
+            None if !current.is_empty() => blocks.push(std::mem::take(&mut current).join("\n")),
+            None => {}
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current.join("\n"));
+    }
+
+    blocks
+}
+
+/// `/** ... */` style blocks, with the leading ` * ` of each line removed
+fn block_comments(content: &str, openers: &[&str]) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut rest = content;
+
+    while let Some((start, opener)) = openers
+        .iter()
+        .filter_map(|o| rest.find(o).map(|i| (i, *o)))
+        .min_by_key(|(i, _)| *i)
+    {
+        let body = &rest[start + opener.len()..];
+        // `/**/` is an empty comment, not the start of a doc block
+        if let Some(after) = body.strip_prefix('/') {
+            rest = after;
+            continue;
+        }
+        let Some(end) = body.find("*/") else {
+            break;
+        };
+        let text: Vec<&str> = body[..end]
+            .lines()
+            .map(|line| {
+                let line = line.trim();
+                let line = line.strip_prefix('*').unwrap_or(line);
+                line.strip_prefix(' ').unwrap_or(line)
+            })
+            .collect();
+        blocks.push(text.join("\n").trim().to_string());
+        rest = &body[end + 2..];
+    }
+
+    blocks
+}
+
+/// `//` blocks directly above a package or top-level declaration
+fn go_comments(content: &str) -> Vec<String> {
+    const DECLARATIONS: &[&str] = &["package ", "func ", "type ", "var ", "const "];
+
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+
+    for line in content.lines() {
+        if let Some(text) = line.strip_prefix("//") {
+            current.push(text.strip_prefix(' ').unwrap_or(text));
+            continue;
+        }
+        if !current.is_empty() && DECLARATIONS.iter().any(|d| line.starts_with(d)) {
+            blocks.push(current.join("\n"));
+        }
+        current.clear();
+    }
+
+    blocks
+}
+
+/// triple quoted strings that open a line, which covers module, class
+/// and function docstrings
+fn docstrings(content: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut lines = content.lines();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        let Some(quote) = ["\"\"\"", "'''"]
+            .into_iter()
+            .find(|q| trimmed.starts_with(q))
+        else {
+            continue;
+        };
+
+        let first = &trimmed[3..];
+        if let Some(end) = first.find(quote) {
+            blocks.push(first[..end].trim().to_string());
+            continue;
+        }
+
+        let mut text = vec![first.trim()];
+        for line in lines.by_ref() {
+            match line.find(quote) {
+                Some(end) => {
+                    text.push(line[..end].trim());
+                    break;
+                }
+                None => text.push(line.trim()),
+            }
+        }
+        blocks.push(text.join("\n").trim().to_string());
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_doc_comments() {
+        let rust = "//! crate docs\nuse x;\n\n/// adds one\n/// to n\nfn inc(n: u8) -> u8 { n + 1 } // not a doc\n";
+        assert_eq!(
+            extract_doc_comments("src/lib.rs", rust).as_deref(),
+            Some("crate docs\n\nadds one\nto n")
+        );
+
+        let ts = "/**\n * Fetch a user.\n * @param id the id\n */\nexport function get(id) {}\n/* plain */\n";
+        assert_eq!(
+            extract_doc_comments("api.ts", ts).as_deref(),
+            Some("Fetch a user.\n@param id the id")
+        );
+
+        let go = "// Package x does y.\npackage x\n\n// helper is internal\n\nfunc helper() {}\n";
+        assert_eq!(
+            extract_doc_comments("x.go", go).as_deref(),
+            Some("Package x does y.")
+        );
+
+        let py = "def f():\n    \"\"\"Return one.\"\"\"\n    return 1\n";
+        assert_eq!(
+            extract_doc_comments("f.py", py).as_deref(),
+            Some("Return one.")
+        );
+
+        assert_eq!(extract_doc_comments("main.rs", "fn main() {}\n"), None);
+        assert_eq!(extract_doc_comments("data.bin", "///"), None);
+        assert!(is_documentation("docs/guide.md"));
+        assert!(is_documentation("README"));
+        assert!(!is_documentation("src/lib.rs"));
+        assert!(is_key_document("README.md") && is_key_document("api/README"));
+        assert!(is_key_document("docs/ARCHITECTURE.md"));
+        assert!(!is_key_document("src/design.rs") && !is_key_document("a/b/README.md"));
+    }
+}
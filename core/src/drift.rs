@@ -0,0 +1,78 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// sha256 over the files of an output, ordered by path and without the
+/// header detail, so the same filtered files give the same fingerprint
+/// whatever the checkout location, times or file order
+pub fn fingerprint(content: &str) -> String {
+    let files: BTreeMap<&str, &str> = crate::split_files(content).into_iter().collect();
+    let mut hasher = Sha256::new();
+    for (path, body) in files {
+        hasher.update((path.len() as u64).to_le_bytes());
+        hasher.update(path);
+        hasher.update((body.len() as u64).to_le_bytes());
+        hasher.update(body);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// files that differ between a previous output and the current one
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+pub struct ContextDrift {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl ContextDrift {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+pub fn diff_outputs(old: &str, new: &str) -> ContextDrift {
+    let old: BTreeMap<&str, &str> = crate::split_files(old).into_iter().collect();
+    let new: BTreeMap<&str, &str> = crate::split_files(new).into_iter().collect();
+
+    let mut drift = ContextDrift::default();
+    for (path, body) in &new {
+        match old.get(path) {
+            None => drift.added.push(path.to_string()),
+            Some(previous) if previous != body => drift.modified.push(path.to_string()),
+            Some(_) => drift.unchanged += 1,
+        }
+    }
+    drift.removed = old
+        .keys()
+        .filter(|path| !new.contains_key(*path))
+        .map(|path| path.to_string())
+        .collect();
+    drift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_and_drift() {
+        let old = "# header\n=== b.rs ===\nfn b() {}\n\n=== a.rs (10 B, 1 line) ===\nfn a() {}\n\n=== c.rs ===\nc\n";
+        let reordered = "=== a.rs ===\nfn a() {}\n\n=== c.rs ===\nc\n=== b.rs ===\nfn b() {}\n\n";
+        assert_eq!(fingerprint(old), fingerprint(reordered));
+
+        let new = "=== a.rs ===\nfn a() { 1 }\n\n=== b.rs ===\nfn b() {}\n\n=== d.rs ===\nd\n";
+        assert_ne!(fingerprint(old), fingerprint(new));
+        assert_eq!(
+            diff_outputs(old, new),
+            ContextDrift {
+                added: vec!["d.rs".to_string()],
+                removed: vec!["c.rs".to_string()],
+                modified: vec!["a.rs".to_string()],
+                unchanged: 1,
+            }
+        );
+        assert!(diff_outputs(old, reordered).is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+/// what a file section of an ingestion output holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractedBody {
+    Text(String),
+    /// `[symlink -> target]`, the target is not part of the output
+    Symlink(String),
+    /// `[binary file]`, the bytes are not part of the output
+    Binary,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedFile {
+    pub path: String,
+    pub body: ExtractedBody,
+}
+
+/// the files of an ingestion output with their contents as they were
+/// written: escapes undone, `--line-numbers` prefixes removed. Transforms
+/// like license compression cannot be undone and come back as written.
+pub fn extract_files(output: &str) -> Vec<ExtractedFile> {
+    crate::split_files(output)
+        .into_iter()
+        // `=== dir/ ===` sections are summaries, not files
+        .filter(|(path, _)| !path.ends_with('/'))
+        .map(|(path, section)| ExtractedFile {
+            path: path.to_string(),
+            body: extract_body(section),
+        })
+        .collect()
+}
+
+fn extract_body(section: &str) -> ExtractedBody {
+    // each file is written as `{content}\n` followed by a blank line
+    let text = section
+        .strip_suffix("\n\n")
+        .or_else(|| section.strip_suffix('\n'))
+        .unwrap_or(section);
+
+    if text == "[binary file]" {
+        return ExtractedBody::Binary;
+    }
+    if let Some(target) = text
+        .strip_prefix("[symlink -> ")
+        .and_then(|t| t.strip_suffix(']'))
+    {
+        if !target.contains('\n') {
+            return ExtractedBody::Symlink(target.to_string());
+        }
+    }
+
+    let text = crate::unescape_content(text);
+    ExtractedBody::Text(strip_line_numbers(&text).unwrap_or_else(|| text.into_owned()))
+}
+
+/// content without `  42 | ` prefixes, when every line carries the next number
+fn strip_line_numbers(text: &str) -> Option<String> {
+    let mut stripped = String::with_capacity(text.len());
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(line) => (line, "\n"),
+            None => (line, ""),
+        };
+        let (number, rest) = crate::transform::split_line_number(line)?;
+        if number != i + 1 {
+            return None;
+        }
+        stripped.push_str(rest);
+        stripped.push_str(newline);
+    }
+    (!stripped.is_empty()).then_some(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IngestOptions, MemoryIngester, Transform};
+
+    #[test]
+    fn test_extract_round_trip() {
+        let files = vec![
+            ("src/main.rs".to_string(), "fn main() {}\n".to_string()),
+            (
+                "notes.txt".to_string(),
+                "=== fake.rs ===\nno newline".to_string(),
+            ),
+        ];
+        for line_numbers in [false, true] {
+            let mut options =
+                IngestOptions::builder().without_transform(Transform::CompressLicense);
+            if line_numbers {
+                options = options.transform(Transform::LineNumbers);
+            }
+            let options = options.build().unwrap();
+            let output = MemoryIngester::new(options).ingest_to_string(&files);
+            let mut extracted = extract_files(&output);
+            extracted.sort_by(|a, b| b.path.cmp(&a.path));
+            let expected: Vec<ExtractedFile> = files
+                .iter()
+                .map(|(path, content)| ExtractedFile {
+                    path: path.clone(),
+                    body: ExtractedBody::Text(content.clone()),
+                })
+                .collect();
+            assert_eq!(extracted, expected);
+        }
+
+        let odd = "=== a.bin ===\n[binary file]\n\n=== link ===\n[symlink -> a.bin]\n\n";
+        let bodies: Vec<_> = extract_files(odd).into_iter().map(|f| f.body).collect();
+        assert_eq!(
+            bodies,
+            [
+                ExtractedBody::Binary,
+                ExtractedBody::Symlink("a.bin".to_string())
+            ]
+        );
+    }
+}
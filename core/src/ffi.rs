@@ -0,0 +1,137 @@
+//! Stable C ABI for embedding githem from other languages.
+//!
+//! Strings crossing the boundary are NUL-terminated UTF-8. Every string handed
+//! out by this module must be released with `githem_free`. The header lives in
+//! `core/include/githem.h` and is regenerated with
+//! `cbindgen --config core/cbindgen.toml --crate githem-core --output core/include/githem.h`.
+//!
+//! The library is built with
+//! `cargo rustc -p githem-core --profile ffi --features ffi --crate-type cdylib`
+//! (or `staticlib`) and ends up in `target/ffi/`. Panics only become
+//! `GITHEM_ERR_PANIC` in builds that unwind like the `ffi` profile; under
+//! `panic = "abort"`, as in the release profile, they abort the host process.
+
+use crate::{is_remote_url, IngestOptions, Ingester};
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+pub const GITHEM_OK: c_int = 0;
+pub const GITHEM_ERR_INVALID_ARGUMENT: c_int = 1;
+pub const GITHEM_ERR_INGEST: c_int = 2;
+/// only returned by builds that unwind on panic, such as the `ffi` profile
+pub const GITHEM_ERR_PANIC: c_int = 3;
+
+fn to_c_string(value: String) -> *mut c_char {
+    // interior NULs cannot be represented, replace them rather than failing
+    let sanitized = value.replace('\0', "\u{FFFD}");
+    CString::new(sanitized)
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+unsafe fn read_str<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        return None;
+    }
+    CStr::from_ptr(value).to_str().ok()
+}
+
+fn run_ingest(source: &str, options_json: Option<&str>) -> anyhow::Result<String> {
+    let options: IngestOptions = match options_json {
+        Some(json) if !json.trim().is_empty() => serde_json::from_str(json)?,
+        _ => IngestOptions::default(),
+    };
+
+    let mut ingester = if is_remote_url(source) {
+        Ingester::from_url(source, options)?
+    } else {
+        Ingester::from_path(Path::new(source), options)?
+    };
+
+    let mut output = Vec::new();
+    if ingester.cache_key.is_some() {
+        ingester.ingest_cached(&mut output)?;
+    } else {
+        ingester.ingest(&mut output)?;
+    }
+
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+/// Ingest a repository (remote URL or local path) into `*out`.
+///
+/// `options_json` is a JSON-encoded `IngestOptions` and may be NULL for
+/// defaults. On success `*out` holds the output text and `GITHEM_OK` is
+/// returned; on failure `*out` holds an error message and a non-zero code is
+/// returned. Either way the caller owns `*out` and must pass it to
+/// `githem_free`.
+///
+/// # Safety
+///
+/// `source` must be a valid NUL-terminated string, `options_json` must be NULL
+/// or a valid NUL-terminated string, and `out` must be a valid pointer to
+/// writable storage for one pointer.
+#[no_mangle]
+pub unsafe extern "C" fn githem_ingest(
+    source: *const c_char,
+    options_json: *const c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    if out.is_null() {
+        return GITHEM_ERR_INVALID_ARGUMENT;
+    }
+    *out = ptr::null_mut();
+
+    let Some(source) = read_str(source) else {
+        *out = to_c_string("source must be a valid UTF-8 string".to_string());
+        return GITHEM_ERR_INVALID_ARGUMENT;
+    };
+
+    let options_json = if options_json.is_null() {
+        None
+    } else {
+        match read_str(options_json) {
+            Some(json) => Some(json),
+            None => {
+                *out = to_c_string("options_json must be valid UTF-8".to_string());
+                return GITHEM_ERR_INVALID_ARGUMENT;
+            }
+        }
+    };
+
+    match catch_unwind(AssertUnwindSafe(|| run_ingest(source, options_json))) {
+        Ok(Ok(content)) => {
+            *out = to_c_string(content);
+            GITHEM_OK
+        }
+        Ok(Err(e)) => {
+            *out = to_c_string(format!("{e:#}"));
+            GITHEM_ERR_INGEST
+        }
+        Err(_) => {
+            *out = to_c_string("githem panicked during ingestion".to_string());
+            GITHEM_ERR_PANIC
+        }
+    }
+}
+
+/// Release a string returned by this library. NULL is ignored.
+///
+/// # Safety
+///
+/// `value` must be NULL or a pointer previously returned through this library
+/// that has not already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn githem_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Library version as a static NUL-terminated string; do not free.
+#[no_mangle]
+pub extern "C" fn githem_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
@@ -1,5 +1,7 @@
 // core/src/filtering.rs
+use crate::pattern::{pattern_matches, PatternSyntax};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 /// Centralized filtering configuration for githem
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -340,6 +342,24 @@ pub enum FilterPreset {
     CodeOnly,
     /// Minimal filtering - just exclude obvious binary/large files
     Minimal,
+    /// Documentation files plus the doc comments of source files
+    DocsOnly,
+    /// Test directories and test files
+    TestsOnly,
+}
+
+impl FilterPreset {
+    /// name as accepted by the CLI and the api
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterPreset::Raw => "raw",
+            FilterPreset::Standard => "standard",
+            FilterPreset::CodeOnly => "code-only",
+            FilterPreset::Minimal => "minimal",
+            FilterPreset::DocsOnly => "docs-only",
+            FilterPreset::TestsOnly => "tests-only",
+        }
+    }
 }
 
 impl FilterConfig {
@@ -380,7 +400,9 @@ impl FilterConfig {
     pub fn get_excludes_for_preset(&self, preset: FilterPreset) -> Vec<String> {
         match preset {
             FilterPreset::Raw => Vec::new(),
-            FilterPreset::Standard => self.default_excludes.clone(),
+            FilterPreset::Standard | FilterPreset::DocsOnly | FilterPreset::TestsOnly => {
+                self.default_excludes.clone()
+            }
             FilterPreset::CodeOnly => {
                 let mut excludes = Vec::new();
                 excludes.extend(self.categories.lock_files.clone());
@@ -427,6 +449,37 @@ impl FilterConfig {
         }
     }
 
+    /// Patterns a file must match to be part of a preset, empty for the
+    /// presets that only exclude
+    pub fn get_includes_for_preset(&self, preset: FilterPreset) -> Vec<String> {
+        match preset {
+            FilterPreset::DocsOnly => crate::docs::DOC_EXTENSIONS
+                .iter()
+                .chain(crate::docs::SOURCE_EXTENSIONS)
+                .map(|ext| format!("*.{ext}"))
+                .chain(["README*", "CONTRIBUTING*", "CHANGELOG*"].map(String::from))
+                .collect(),
+            FilterPreset::TestsOnly => [
+                "test/",
+                "tests/",
+                "__tests__/",
+                "spec/",
+                "testdata/",
+                "*_test.*",
+                "*_tests.*",
+                "test_*.py",
+                "*.test.*",
+                "*.spec.*",
+                "*_spec.rb",
+                "*Test.java",
+                "*Tests.cs",
+            ]
+            .map(String::from)
+            .to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
     /// Get excludes for specific categories
     pub fn get_excludes_for_categories(&self, categories: &[&str]) -> Vec<String> {
         let mut excludes = Vec::new();
@@ -492,6 +545,259 @@ impl FilterConfig {
     }
 }
 
+/// Why a path was kept or dropped, the explanation behind `is_path_included`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterDecision {
+    Included,
+    IncludedByPattern(String),
+    /// a README or architecture document kept by `docs_first`
+    KeyDocument,
+    /// a CI, container or make file kept by `build_ci`
+    BuildFile,
+    /// the test or source of an included file, kept by `with_tests` or
+    /// `with_sources`
+    PairedWith(String),
+    /// inside the .git directory
+    ExcludedGitDir,
+    /// a user exclude pattern
+    ExcludedByPattern(String),
+    ExcludedByPreset {
+        preset: FilterPreset,
+        pattern: String,
+    },
+    /// include patterns were given and none matched
+    NotIncluded,
+    /// an extension allow list was given without this file's extension
+    ExtensionNotAllowed,
+    /// not part of a preset that selects files, like docs-only
+    OutsidePreset(FilterPreset),
+    OutsidePathPrefix,
+    /// below one of the excluded paths
+    ExcludedPath(String),
+    TooLarge {
+        size: u64,
+        limit: u64,
+    },
+    TooSmall {
+        size: u64,
+        limit: u64,
+    },
+}
+
+impl FilterDecision {
+    pub fn is_included(&self) -> bool {
+        matches!(
+            self,
+            FilterDecision::Included
+                | FilterDecision::IncludedByPattern(_)
+                | FilterDecision::KeyDocument
+                | FilterDecision::BuildFile
+                | FilterDecision::PairedWith(_)
+        )
+    }
+}
+
+impl std::fmt::Display for FilterDecision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterDecision::Included => write!(f, "included"),
+            FilterDecision::IncludedByPattern(pattern) => {
+                write!(f, "included by pattern: {pattern}")
+            }
+            FilterDecision::KeyDocument => write!(f, "included: key documentation (--docs-first)"),
+            FilterDecision::BuildFile => write!(f, "included: build & CI config (--build-ci)"),
+            FilterDecision::PairedWith(path) => write!(f, "included: paired with {path}"),
+            FilterDecision::ExcludedGitDir => write!(f, "excluded: git metadata"),
+            FilterDecision::ExcludedByPattern(pattern) => {
+                write!(f, "excluded by pattern: {pattern}")
+            }
+            FilterDecision::ExcludedByPreset { preset, pattern } => {
+                write!(f, "excluded by preset {}: {pattern}", preset.as_str())
+            }
+            FilterDecision::NotIncluded => write!(f, "excluded: matches no include pattern"),
+            FilterDecision::ExtensionNotAllowed => {
+                write!(f, "excluded: extension not in --ext list")
+            }
+            FilterDecision::OutsidePreset(preset) => {
+                write!(f, "excluded: not part of preset {}", preset.as_str())
+            }
+            FilterDecision::OutsidePathPrefix => write!(f, "excluded: outside path prefix"),
+            FilterDecision::ExcludedPath(path) => write!(f, "excluded: below excluded path {path}"),
+            FilterDecision::TooLarge { size, limit } => {
+                write!(f, "excluded: {size} bytes exceeds max size {limit}")
+            }
+            FilterDecision::TooSmall { size, limit } => {
+                write!(f, "excluded: {size} bytes is below min size {limit}")
+            }
+        }
+    }
+}
+
+/// Decides whether a path is ingested and attributes the decision to the
+/// rule responsible, checking user excludes before preset ones. The
+/// ingesters filter through it, `is_path_included` covers patterns only.
+pub struct FilterExplainer {
+    include_patterns: Vec<String>,
+    exclude_patterns: Vec<String>,
+    preset: Option<FilterPreset>,
+    preset_excludes: Vec<String>,
+    syntax: PatternSyntax,
+    extensions: Vec<String>,
+    preset_includes: Vec<String>,
+    key_documents: bool,
+    build_files: bool,
+}
+
+impl FilterExplainer {
+    pub fn new(options: &crate::IngestOptions) -> Self {
+        let preset = options.filter_preset.or(options
+            .apply_default_filters
+            .then_some(FilterPreset::Standard));
+        Self {
+            include_patterns: options.include_patterns.clone(),
+            exclude_patterns: options.exclude_patterns.clone(),
+            preset,
+            preset_excludes: preset.map(get_excludes_for_preset).unwrap_or_default(),
+            syntax: options.pattern_syntax,
+            extensions: options.extensions.clone(),
+            preset_includes: preset.map(get_includes_for_preset).unwrap_or_default(),
+            key_documents: options.docs_first,
+            build_files: options.build_ci,
+        }
+    }
+
+    pub fn explain(&self, path: &Path) -> FilterDecision {
+        if path.components().any(|c| c.as_os_str() == ".git") {
+            return FilterDecision::ExcludedGitDir;
+        }
+
+        let path_str = path.to_string_lossy();
+
+        if let Some(pattern) = self
+            .exclude_patterns
+            .iter()
+            .find(|p| self.syntax.excludes(p, &path_str))
+        {
+            return FilterDecision::ExcludedByPattern(pattern.clone());
+        }
+
+        // presets, --ext and includes do not apply, user excludes still do
+        if self.key_documents && crate::docs::is_key_document(&path_str) {
+            return FilterDecision::KeyDocument;
+        }
+        if self.build_files && crate::build::is_build_file(&path_str) {
+            return FilterDecision::BuildFile;
+        }
+
+        if let (Some(preset), Some(pattern)) = (
+            self.preset,
+            self.preset_excludes
+                .iter()
+                .find(|p| self.syntax.excludes(p, &path_str)),
+        ) {
+            return FilterDecision::ExcludedByPreset {
+                preset,
+                pattern: pattern.clone(),
+            };
+        }
+
+        if !extension_allowed(&self.extensions, path) {
+            return FilterDecision::ExtensionNotAllowed;
+        }
+
+        // preset includes are written in glob syntax whatever the user picked
+        if let Some(preset) = self.preset {
+            if !self.preset_includes.is_empty()
+                && !self
+                    .preset_includes
+                    .iter()
+                    .any(|p| pattern_matches(p, &path_str))
+            {
+                return FilterDecision::OutsidePreset(preset);
+            }
+        }
+
+        if self.include_patterns.is_empty() {
+            return FilterDecision::Included;
+        }
+
+        match self
+            .include_patterns
+            .iter()
+            .find(|p| self.syntax.includes(p, &path_str))
+        {
+            Some(pattern) => FilterDecision::IncludedByPattern(pattern.clone()),
+            None => FilterDecision::NotIncluded,
+        }
+    }
+
+    /// whether `explain` excludes every file below the directory `dir`;
+    /// preset excludes only count when no key documents or build files
+    /// could be let through from below it
+    pub fn excludes_dir(&self, dir: &Path) -> bool {
+        let dir = dir.to_string_lossy();
+        if self
+            .exclude_patterns
+            .iter()
+            .any(|p| self.syntax.excludes_dir(p, &dir))
+        {
+            return true;
+        }
+        !self.key_documents
+            && !self.build_files
+            && self
+                .preset_excludes
+                .iter()
+                .any(|p| self.syntax.excludes_dir(p, &dir))
+    }
+}
+
+/// Whether `path` has one of `extensions` (`rs` or `.rs`, any case),
+/// an empty list allows everything
+pub fn extension_allowed(extensions: &[String], path: &Path) -> bool {
+    if extensions.is_empty() {
+        return true;
+    }
+    let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+        return false;
+    };
+    extensions.iter().any(|allowed| {
+        allowed
+            .trim_start_matches('.')
+            .eq_ignore_ascii_case(extension)
+    })
+}
+
+/// Decide whether a repository-relative path passes the exclude and include patterns.
+/// Pure path logic with no git or filesystem access, shared by all ingesters.
+pub fn is_path_included(
+    path: &Path,
+    include_patterns: &[String],
+    excludes: &[String],
+    syntax: PatternSyntax,
+) -> bool {
+    if path.components().any(|c| c.as_os_str() == ".git") {
+        return false;
+    }
+
+    let path_str = path.to_string_lossy();
+
+    if excludes
+        .iter()
+        .any(|pattern| syntax.excludes(pattern, &path_str))
+    {
+        return false;
+    }
+
+    if !include_patterns.is_empty() {
+        return include_patterns
+            .iter()
+            .any(|p| syntax.includes(p, &path_str));
+    }
+
+    true
+}
+
 /// Helper function to get default excludes (for backward compatibility)
 pub fn get_default_excludes() -> Vec<String> {
     FilterConfig::new().default_excludes
@@ -502,6 +808,11 @@ pub fn get_excludes_for_preset(preset: FilterPreset) -> Vec<String> {
     FilterConfig::new().get_excludes_for_preset(preset)
 }
 
+/// Helper function to get the include patterns of a preset
+pub fn get_includes_for_preset(preset: FilterPreset) -> Vec<String> {
+    FilterConfig::new().get_includes_for_preset(preset)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -554,4 +865,63 @@ mod tests {
         assert!(!config.default_excludes.is_empty());
         assert!(config.get_category_names().contains(&"lock_files"));
     }
+
+    #[test]
+    fn test_extension_allow_list() {
+        let extensions = vec!["rs".to_string(), ".TOML".to_string()];
+        assert!(extension_allowed(&extensions, Path::new("src/main.rs")));
+        assert!(extension_allowed(&extensions, Path::new("Cargo.toml")));
+        assert!(!extension_allowed(&extensions, Path::new("README.md")));
+        assert!(!extension_allowed(&extensions, Path::new("Makefile")));
+        assert!(extension_allowed(&[], Path::new("Makefile")));
+    }
+
+    #[test]
+    fn test_explainer_names_the_rule() {
+        let options = crate::IngestOptions::builder()
+            .includes(["src/", "*.lock"])
+            .exclude("src/generated/*")
+            .preset(FilterPreset::Standard)
+            .build()
+            .unwrap();
+        let explainer = FilterExplainer::new(&options);
+
+        assert_eq!(
+            explainer.explain(Path::new("src/main.rs")),
+            FilterDecision::IncludedByPattern("src/".to_string())
+        );
+        assert_eq!(
+            explainer.explain(Path::new("src/generated/api.rs")),
+            FilterDecision::ExcludedByPattern("src/generated/*".to_string())
+        );
+        assert_eq!(
+            explainer.explain(Path::new("Cargo.lock")),
+            FilterDecision::ExcludedByPreset {
+                preset: FilterPreset::Standard,
+                pattern: "*.lock".to_string()
+            }
+        );
+        assert_eq!(
+            explainer.explain(Path::new("README.md")),
+            FilterDecision::NotIncluded
+        );
+
+        // the explanation agrees with the filter itself
+        for path in [
+            "src/main.rs",
+            "src/generated/api.rs",
+            "Cargo.lock",
+            "README.md",
+        ] {
+            assert_eq!(
+                explainer.explain(Path::new(path)).is_included(),
+                is_path_included(
+                    Path::new(path),
+                    &options.include_patterns,
+                    &options.get_effective_excludes(),
+                    options.pattern_syntax,
+                )
+            );
+        }
+    }
 }
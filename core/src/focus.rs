@@ -0,0 +1,530 @@
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+#[derive(Debug, Clone)]
+pub struct FocusOptions {
+    /// files whose dependencies are followed, relative to the repository
+    pub files: Vec<String>,
+    /// how many imports away from a focus file to follow
+    pub depth: usize,
+    /// Maximum estimated tokens across the selected files, focus files
+    /// are always kept
+    pub token_budget: usize,
+}
+
+impl Default for FocusOptions {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            depth: 2,
+            token_budget: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusedFile {
+    pub path: PathBuf,
+    /// imports between a focus file and this one, 0 for focus files
+    pub depth: usize,
+    pub tokens: usize,
+}
+
+/// resolves the imports, `mod` declarations and includes of a file to
+/// other files of the repository with per language heuristics: Rust,
+/// Python, JavaScript/TypeScript (relative specifiers), Go and C/C++
+pub struct ImportIndex {
+    files: BTreeSet<String>,
+    /// directory to its non-test .go files
+    go_packages: BTreeMap<String, Vec<String>>,
+}
+
+impl ImportIndex {
+    pub fn new<S: Into<String>>(files: impl IntoIterator<Item = S>) -> Self {
+        let files: BTreeSet<String> = files.into_iter().map(Into::into).collect();
+        let mut go_packages: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for file in files
+            .iter()
+            .filter(|f| f.ends_with(".go") && !f.ends_with("_test.go"))
+        {
+            go_packages
+                .entry(parent(file).to_string())
+                .or_default()
+                .push(file.clone());
+        }
+        Self { files, go_packages }
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.files.contains(path)
+    }
+
+    /// the files `path` depends on, in the order they are imported
+    pub fn imports(&self, path: &str, content: &str) -> Vec<String> {
+        let extension = path
+            .rsplit_once('.')
+            .map(|(_, ext)| ext)
+            .unwrap_or_default();
+        let mut found = match extension {
+            "rs" => self.rust_imports(path, content),
+            "py" => self.python_imports(path, content),
+            "go" => self.go_imports(path, content),
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" => self.c_includes(path, content),
+            ext if JS_EXTENSIONS.contains(&ext) => self.js_imports(path, content),
+            _ => Vec::new(),
+        };
+        let mut seen = HashSet::new();
+        found.retain(|file| file != path && seen.insert(file.clone()));
+        found
+    }
+
+    fn first_existing(&self, candidates: impl IntoIterator<Item = String>) -> Option<String> {
+        candidates
+            .into_iter()
+            .find(|candidate| self.files.contains(candidate))
+    }
+
+    fn rust_imports(&self, path: &str, content: &str) -> Vec<String> {
+        static MOD: OnceLock<Regex> = OnceLock::new();
+        static USE: OnceLock<Regex> = OnceLock::new();
+        let mod_decl = MOD.get_or_init(|| {
+            Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+([A-Za-z_]\w*)\s*;")
+                .expect("mod pattern compiles")
+        });
+        let use_decl = USE.get_or_init(|| {
+            Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?use\s+([^;]+);")
+                .expect("use pattern compiles")
+        });
+
+        let module_dir = rust_module_dir(path);
+        let mut found = Vec::new();
+        for name in mod_decl.captures_iter(content).map(|c| c[1].to_string()) {
+            found.extend(self.first_existing([
+                join(&module_dir, &format!("{name}.rs")),
+                join(&module_dir, &format!("{name}/mod.rs")),
+            ]));
+        }
+
+        for tree in use_decl.captures_iter(content) {
+            for use_path in expand_use(&tree[1]) {
+                let mut segments: Vec<&str> = use_path.split("::").map(str::trim).collect();
+                let mut base = match segments.first() {
+                    Some(&"crate") => match self.rust_crate_root(path) {
+                        Some(root) => root,
+                        None => continue,
+                    },
+                    Some(&"self") | Some(&"super") => module_dir.clone(),
+                    // other crates, or modules declared with `mod` above
+                    _ => continue,
+                };
+                if segments[0] != "super" {
+                    segments.remove(0);
+                }
+                while segments.first() == Some(&"super") {
+                    segments.remove(0);
+                    base = parent(&base).to_string();
+                }
+                // the longest prefix naming a file: `a::b::Item` is a/b.rs,
+                // `super::Item` the file of the parent module itself
+                let module = (1..=segments.len()).rev().find_map(|len| {
+                    let module = segments[..len].join("/");
+                    self.first_existing([
+                        join(&base, &format!("{module}.rs")),
+                        join(&base, &format!("{module}/mod.rs")),
+                    ])
+                });
+                found.extend(module.or_else(|| {
+                    self.first_existing([
+                        format!("{base}.rs"),
+                        join(&base, "mod.rs"),
+                        join(&base, "lib.rs"),
+                        join(&base, "main.rs"),
+                    ])
+                }));
+            }
+        }
+        found
+    }
+
+    /// the directory holding the lib.rs or main.rs `path` belongs to
+    fn rust_crate_root(&self, path: &str) -> Option<String> {
+        let mut dir = parent(path);
+        loop {
+            if self.files.contains(&join(dir, "lib.rs"))
+                || self.files.contains(&join(dir, "main.rs"))
+            {
+                return Some(dir.to_string());
+            }
+            if dir.is_empty() {
+                return None;
+            }
+            dir = parent(dir);
+        }
+    }
+
+    fn python_imports(&self, path: &str, content: &str) -> Vec<String> {
+        static FROM: OnceLock<Regex> = OnceLock::new();
+        static IMPORT: OnceLock<Regex> = OnceLock::new();
+        let from_import = FROM.get_or_init(|| {
+            Regex::new(r"(?m)^\s*from\s+(\.*)([\w.]*)\s+import\s+\(?([\w\s,*]+)")
+                .expect("from pattern compiles")
+        });
+        let plain_import = IMPORT.get_or_init(|| {
+            Regex::new(
+                r"(?m)^\s*import\s+([\w.]+(?:\s+as\s+\w+)?(?:\s*,\s*[\w.]+(?:\s+as\s+\w+)?)*)",
+            )
+            .expect("import pattern compiles")
+        });
+
+        let module_file = |base: &str, module: &str| {
+            let module = module.replace('.', "/");
+            self.first_existing([
+                join(base, &format!("{module}.py")),
+                join(base, &format!("{module}/__init__.py")),
+            ])
+        };
+        // absolute imports resolve from the root or a src/ layout
+        let roots = ["", "src"];
+
+        let mut found = Vec::new();
+        for c in from_import.captures_iter(content) {
+            let module = &c[2];
+            let bases: Vec<String> = if c[1].is_empty() {
+                roots.iter().map(|r| r.to_string()).collect()
+            } else {
+                let mut base = parent(path);
+                for _ in 1..c[1].len() {
+                    base = parent(base);
+                }
+                vec![base.to_string()]
+            };
+            for base in &bases {
+                let names = c[3]
+                    .split(',')
+                    .map(|n| n.split_whitespace().next().unwrap_or_default());
+                // `from pkg import module` imports pkg/module.py
+                let submodules: Vec<String> = names
+                    .filter(|n| !n.is_empty() && *n != "*")
+                    .filter_map(|name| match module {
+                        "" => module_file(base, name),
+                        module => module_file(base, &format!("{module}.{name}")),
+                    })
+                    .collect();
+                if submodules.is_empty() && !module.is_empty() {
+                    found.extend(module_file(base, module));
+                }
+                found.extend(submodules);
+            }
+        }
+        for c in plain_import.captures_iter(content) {
+            for module in c[1].split(',').filter_map(|m| m.split_whitespace().next()) {
+                found.extend(roots.iter().find_map(|root| module_file(root, module)));
+            }
+        }
+        found
+    }
+
+    fn js_imports(&self, path: &str, content: &str) -> Vec<String> {
+        static SPECIFIER: OnceLock<Regex> = OnceLock::new();
+        let specifier = SPECIFIER.get_or_init(|| {
+            Regex::new(r#"(?:\bfrom\s*|\bimport\s*\(?\s*|\brequire\s*\(\s*)['"](\.\.?/[^'"]+)['"]"#)
+                .expect("specifier pattern compiles")
+        });
+
+        let dir = parent(path);
+        let mut found = Vec::new();
+        for c in specifier.captures_iter(content) {
+            let target = join(dir, &c[1]);
+            // `./util.js` is how TypeScript spells an import of util.ts
+            let stem = target
+                .rsplit_once('.')
+                .filter(|(_, ext)| !ext.contains('/'))
+                .map(|(stem, _)| stem.to_string());
+            let mut candidates = vec![target.clone()];
+            for ext in JS_EXTENSIONS {
+                candidates.push(format!("{target}.{ext}"));
+                candidates.extend(stem.as_ref().map(|stem| format!("{stem}.{ext}")));
+                candidates.push(format!("{target}/index.{ext}"));
+            }
+            found.extend(self.first_existing(candidates));
+        }
+        found
+    }
+
+    fn go_imports(&self, path: &str, content: &str) -> Vec<String> {
+        static SINGLE: OnceLock<Regex> = OnceLock::new();
+        static BLOCK: OnceLock<Regex> = OnceLock::new();
+        static QUOTED: OnceLock<Regex> = OnceLock::new();
+        let single = SINGLE.get_or_init(|| {
+            Regex::new(r#"(?m)^import\s+(?:[\w.]+\s+)?"([^"]+)""#).expect("import pattern compiles")
+        });
+        let block = BLOCK.get_or_init(|| {
+            Regex::new(r"(?s)\bimport\s*\((.*?)\)").expect("block pattern compiles")
+        });
+        let quoted =
+            QUOTED.get_or_init(|| Regex::new(r#""([^"]+)""#).expect("quoted pattern compiles"));
+
+        let mut imports: Vec<String> = single
+            .captures_iter(content)
+            .map(|c| c[1].to_string())
+            .collect();
+        for c in block.captures_iter(content) {
+            imports.extend(quoted.captures_iter(&c[1]).map(|q| q[1].to_string()));
+        }
+
+        // the rest of the package is always part of the picture
+        let mut found: Vec<String> = self
+            .go_packages
+            .get(parent(path))
+            .cloned()
+            .unwrap_or_default();
+        for import in imports {
+            let segments: Vec<&str> = import.split('/').collect();
+            // match the longest tail of the import path naming a package
+            // directory, at least two segments so `fmt` finds nothing
+            let package = (2..=segments.len()).rev().find_map(|len| {
+                self.go_packages
+                    .get(&segments[segments.len() - len..].join("/"))
+            });
+            found.extend(package.into_iter().flatten().cloned());
+        }
+        found
+    }
+
+    fn c_includes(&self, path: &str, content: &str) -> Vec<String> {
+        static INCLUDE: OnceLock<Regex> = OnceLock::new();
+        let include = INCLUDE.get_or_init(|| {
+            Regex::new(r#"(?m)^\s*#\s*include\s+"([^"]+)""#).expect("include pattern compiles")
+        });
+
+        include
+            .captures_iter(content)
+            .filter_map(|c| {
+                self.first_existing([
+                    join(parent(path), &c[1]),
+                    c[1].to_string(),
+                    join("include", &c[1]),
+                ])
+            })
+            .collect()
+    }
+}
+
+/// the focus files and the files they import, breadth first up to
+/// `options.depth` imports away while the token budget lasts; `read`
+/// returns the content of a file, None to leave it out
+pub fn dependency_closure(
+    index: &ImportIndex,
+    options: &FocusOptions,
+    mut read: impl FnMut(&str) -> Option<String>,
+) -> Vec<FocusedFile> {
+    let mut queue: VecDeque<(String, usize)> =
+        options.files.iter().map(|f| (f.clone(), 0)).collect();
+    let mut seen: HashSet<String> = options.files.iter().cloned().collect();
+    let mut selected = Vec::new();
+    let mut used = 0;
+
+    while let Some((path, depth)) = queue.pop_front() {
+        let Some(content) = read(&path) else {
+            continue;
+        };
+        let tokens = crate::estimate_tokens(&content);
+        if depth > 0 && used + tokens > options.token_budget {
+            continue;
+        }
+        used += tokens;
+        if depth < options.depth {
+            for import in index.imports(&path, &content) {
+                if seen.insert(import.clone()) {
+                    queue.push_back((import, depth + 1));
+                }
+            }
+        }
+        selected.push(FocusedFile {
+            path: PathBuf::from(path),
+            depth,
+            tokens,
+        });
+    }
+    selected
+}
+
+/// the lines of `content` within `radius` of the 1-based `line`, numbered
+/// and with `line` itself marked, None when the file has no such line
+pub fn cursor_excerpt(content: &str, line: usize, radius: usize) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    if line == 0 || line > lines.len() {
+        return None;
+    }
+    let first = line.saturating_sub(radius).max(1);
+    let last = (line + radius).min(lines.len());
+    let width = last.to_string().len();
+
+    let mut excerpt = String::new();
+    for number in first..=last {
+        let marker = if number == line { '>' } else { ' ' };
+        excerpt.push_str(&format!(
+            "{marker} {number:>width$} | {}\n",
+            lines[number - 1]
+        ));
+    }
+    Some(excerpt)
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("")
+}
+
+/// `relative` resolved against `dir`, `.` and `..` folded
+fn join(dir: &str, relative: &str) -> String {
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for part in relative.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// where the submodules of a Rust file live: next to lib.rs, main.rs and
+/// mod.rs, in a directory named after the file otherwise
+fn rust_module_dir(path: &str) -> String {
+    let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+    match name {
+        "lib.rs" | "main.rs" | "mod.rs" => dir.to_string(),
+        name => join(dir, name.trim_end_matches(".rs")),
+    }
+}
+
+/// the paths of a use tree, `a::{b, c::d}` as `a::b` and `a::c::d`
+fn expand_use(tree: &str) -> Vec<String> {
+    let tree = tree.trim();
+    let (Some(open), Some(close)) = (tree.find('{'), tree.rfind('}')) else {
+        let path = tree.split(" as ").next().unwrap_or(tree).trim();
+        return vec![path.trim_end_matches("::*").to_string()];
+    };
+    let prefix = tree[..open].trim_end_matches("::");
+
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut start = open + 1;
+    for (i, c) in tree[open + 1..close]
+        .char_indices()
+        .map(|(i, c)| (i + open + 1, c))
+    {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&tree[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&tree[start..close]);
+
+    items
+        .into_iter()
+        .filter(|item| !item.trim().is_empty())
+        .flat_map(expand_use)
+        .map(|item| match item.as_str() {
+            "self" => prefix.to_string(),
+            item => format!("{prefix}::{item}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_closure() {
+        let index = ImportIndex::new([
+            "src/lib.rs",
+            "src/api/mod.rs",
+            "src/api/http.rs",
+            "src/config.rs",
+            "src/unused.rs",
+            "pkg/util.py",
+            "pkg/__init__.py",
+            "app.py",
+            "web/app.ts",
+            "web/lib/index.ts",
+            "server/main.go",
+            "server/routes.go",
+            "internal/db/db.go",
+        ]);
+
+        let http = "use super::Router;\nuse crate::{config::Config, api::{self}};\nuse std::io;\n";
+        assert_eq!(
+            index.imports("src/api/http.rs", http),
+            ["src/api/mod.rs", "src/config.rs"]
+        );
+        assert_eq!(
+            index.imports("src/lib.rs", "pub mod api;\nmod missing;\n"),
+            ["src/api/mod.rs"]
+        );
+        assert_eq!(
+            index.imports("app.py", "from pkg import util\nimport os\n"),
+            ["pkg/util.py"]
+        );
+        assert_eq!(
+            index.imports("web/app.ts", "import { x } from './lib';\n"),
+            ["web/lib/index.ts"]
+        );
+        assert_eq!(
+            index.imports(
+                "server/main.go",
+                "import (\n\t\"fmt\"\n\t\"example.com/app/internal/db\"\n)\n"
+            ),
+            ["server/routes.go", "internal/db/db.go"]
+        );
+
+        let contents = BTreeMap::from([
+            ("src/lib.rs", "pub mod api;\n"),
+            ("src/api/mod.rs", "mod http;\n"),
+            ("src/api/http.rs", "use crate::config;\n"),
+            ("src/config.rs", "pub struct Config;\n"),
+        ]);
+        let options = FocusOptions {
+            files: vec!["src/api/http.rs".to_string()],
+            depth: 1,
+            ..Default::default()
+        };
+        let closure =
+            dependency_closure(&index, &options, |p| contents.get(p).map(|c| c.to_string()));
+        let paths: Vec<_> = closure
+            .iter()
+            .map(|f| (f.path.to_string_lossy().into_owned(), f.depth))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                ("src/api/http.rs".to_string(), 0),
+                ("src/config.rs".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cursor_excerpt() {
+        let content = "fn main() {\n    let x = 1;\n    run(x);\n}\n";
+        assert_eq!(
+            cursor_excerpt(content, 2, 1).unwrap(),
+            "  1 | fn main() {\n> 2 |     let x = 1;\n  3 |     run(x);\n"
+        );
+        assert_eq!(cursor_excerpt(content, 4, 5).unwrap().lines().count(), 4);
+        assert!(cursor_excerpt(content, 0, 1).is_none());
+        assert!(cursor_excerpt(content, 5, 1).is_none());
+    }
+}
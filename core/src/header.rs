@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// what the `=== path ===` marker in front of each file carries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeaderDetail {
+    /// the path alone
+    #[default]
+    Path,
+    /// language, size and estimated tokens
+    Basic,
+    /// `Basic` plus the git blob id and the date of the last commit
+    /// touching the file, which costs a walk over the history
+    Full,
+}
+
+impl HeaderDetail {
+    pub const ALL: [HeaderDetail; 3] =
+        [HeaderDetail::Path, HeaderDetail::Basic, HeaderDetail::Full];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HeaderDetail::Path => "path",
+            HeaderDetail::Basic => "basic",
+            HeaderDetail::Full => "full",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|detail| detail.as_str() == name)
+    }
+}
+
+/// metadata written next to the path, `(rust, 4.1 KB, 812 tokens, abc1234, 2026-01-02)`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDetail {
+    pub language: Option<&'static str>,
+    pub size: usize,
+    pub tokens: usize,
+    /// abbreviated git blob id of the file as it is on disk
+    pub blob: Option<String>,
+    /// day of the last commit touching the file
+    pub modified: Option<String>,
+}
+
+impl FileDetail {
+    /// language, size and tokens of the content as written
+    pub fn new(path: &str, content: &str) -> Self {
+        Self {
+            language: language(path),
+            size: content.len(),
+            tokens: crate::estimate_tokens(content),
+            blob: None,
+            modified: None,
+        }
+    }
+}
+
+impl fmt::Display for FileDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(language) = self.language {
+            write!(f, "{language}, ")?;
+        }
+        write!(f, "{}, {} tokens", human_size(self.size), self.tokens)?;
+        for extra in [&self.blob, &self.modified].into_iter().flatten() {
+            write!(f, ", {extra}")?;
+        }
+        Ok(())
+    }
+}
+
+/// the header line of a file, without the trailing newline
+pub fn format_header(path: impl fmt::Display, detail: Option<&FileDetail>) -> String {
+    match detail {
+        Some(detail) => format!("=== {path} ({detail}) ==="),
+        None => format!("=== {path} ==="),
+    }
+}
+
+/// split `path (detail)` into its parts; a trailing group only counts as
+/// detail when it has a size in it, so `notes (draft).md` stays a path
+pub(crate) fn split_detail(inner: &str) -> (&str, Option<&str>) {
+    let detail = inner
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once(" ("))
+        .filter(|(path, detail)| !path.is_empty() && detail.split(", ").any(is_size));
+    match detail {
+        Some((path, detail)) => (path, Some(detail)),
+        None => (inner, None),
+    }
+}
+
+fn is_size(part: &str) -> bool {
+    let Some((number, unit)) = part.split_once(' ') else {
+        return false;
+    };
+    matches!(unit, "B" | "KB" | "MB" | "GB")
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// `812 B`, `4.1 KB`, `2.0 MB`
+pub fn human_size(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// `YYYY-MM-DD` of a unix timestamp, in UTC
+pub fn format_day(seconds: i64) -> String {
+    // Howard Hinnant's civil_from_days
+    let days = seconds.div_euclid(86400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// language of a file, by extension or by well known file names
+pub fn language(path: &str) -> Option<&'static str> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let by_name = match name {
+        "Dockerfile" | "Containerfile" => Some("dockerfile"),
+        "Makefile" | "GNUmakefile" => Some("make"),
+        "CMakeLists.txt" => Some("cmake"),
+        "Justfile" | "justfile" => Some("just"),
+        _ => None,
+    };
+    if by_name.is_some() {
+        return by_name;
+    }
+
+    let (_, extension) = name.rsplit_once('.')?;
+    let language = match extension.to_ascii_lowercase().as_str() {
+        "rs" => "rust",
+        "go" => "go",
+        "py" | "pyi" => "python",
+        "ipynb" => "jupyter",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "swift" => "swift",
+        "dart" => "dart",
+        "cs" => "csharp",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "cpp",
+        "rb" => "ruby",
+        "php" => "php",
+        "sh" | "bash" | "zsh" => "shell",
+        "ps1" => "powershell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" | "sass" => "scss",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "md" | "markdown" | "mdx" => "markdown",
+        "rst" => "rst",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "json" => "json",
+        "xml" => "xml",
+        "proto" => "protobuf",
+        "nix" => "nix",
+        "lua" => "lua",
+        "zig" => "zig",
+        "sol" => "solidity",
+        _ => return None,
+    };
+    Some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_header_detail() {
+        let detail = FileDetail {
+            blob: Some("abc1234".to_string()),
+            modified: Some(format_day(1_700_000_000)),
+            ..FileDetail::new("src/lib.rs", &"x".repeat(4200))
+        };
+        let header = format_header("src/lib.rs", Some(&detail));
+        assert_eq!(
+            header,
+            format!(
+                "=== src/lib.rs (rust, 4.1 KB, {} tokens, abc1234, 2023-11-14) ===",
+                detail.tokens
+            )
+        );
+        assert_eq!(crate::file_header(&header), Some("src/lib.rs"));
+        assert_eq!(
+            crate::file_header("=== notes (draft).md ==="),
+            Some("notes (draft).md")
+        );
+        assert_eq!(human_size(812), "812 B");
+        assert_eq!(format_day(0), "1970-01-01");
+    }
+}
@@ -0,0 +1,223 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// files at the root that say what a project is and how it builds
+const MANIFESTS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "go.mod",
+    "pyproject.toml",
+    "setup.py",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "CMakeLists.txt",
+    "Gemfile",
+    "composer.json",
+    "mix.exs",
+];
+
+/// source file stems a program or library starts from
+const ENTRY_STEMS: &[&str] = &["main", "lib", "index", "app", "__main__"];
+
+/// extensions `ImportIndex` resolves imports for, the only files read
+const IMPORTING_EXTENSIONS: &[&str] = &[
+    "rs", "py", "go", "c", "h", "cc", "cpp", "cxx", "hpp", "hh", "ts", "tsx", "js", "jsx", "mjs",
+    "cjs",
+];
+
+fn extension(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.').map_or("", |(_, ext)| ext)
+}
+
+/// 0 for key documents, 1 for manifests and entry points, 2 for the rest
+fn tier(path: &str) -> u8 {
+    if crate::docs::is_key_document(path) {
+        return 0;
+    }
+    let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let stem = name.split('.').next().unwrap_or(name);
+    let source = crate::docs::SOURCE_EXTENSIONS.contains(&extension(name));
+    if (dir.is_empty() && MANIFESTS.contains(&name)) || (source && ENTRY_STEMS.contains(&stem)) {
+        return 1;
+    }
+    2
+}
+
+/// the indices of `paths` from most to least important: READMEs and
+/// architecture docs, then manifests and entry points, then the files
+/// imported by the most others, shallower paths first on ties. `read`
+/// is only asked for the source files whose imports are followed
+pub fn rank_by_importance<S: AsRef<str>>(
+    paths: &[S],
+    mut read: impl FnMut(&str) -> Option<String>,
+) -> Vec<usize> {
+    let paths: Vec<&str> = paths.iter().map(AsRef::as_ref).collect();
+    let index = crate::ImportIndex::new(paths.iter().copied());
+
+    let mut importers: HashMap<String, usize> = HashMap::new();
+    for path in paths
+        .iter()
+        .filter(|path| IMPORTING_EXTENSIONS.contains(&extension(path)))
+    {
+        let Some(content) = read(path) else {
+            continue;
+        };
+        for import in index.imports(path, &content) {
+            *importers.entry(import).or_default() += 1;
+        }
+    }
+
+    let mut order: Vec<usize> = (0..paths.len()).collect();
+    order.sort_by_key(|&i| {
+        let path = paths[i];
+        let imported = importers.get(path).copied().unwrap_or_default();
+        (
+            tier(path),
+            Reverse(imported),
+            path.matches('/').count(),
+            path,
+        )
+    });
+    order
+}
+
+/// the indices of the `max_files` most important of `paths`, in their
+/// original order, see `rank_by_importance`
+pub fn select_important<S: AsRef<str>>(
+    paths: &[S],
+    max_files: usize,
+    read: impl FnMut(&str) -> Option<String>,
+) -> Vec<usize> {
+    if paths.len() <= max_files {
+        return (0..paths.len()).collect();
+    }
+    let mut kept = rank_by_importance(paths, read);
+    kept.truncate(max_files);
+    kept.sort_unstable();
+    kept
+}
+
+/// `files` cut down to the `max_files` most important, the paths of the
+/// others are pushed to `omitted`
+pub(crate) fn keep_important<T>(
+    files: Vec<T>,
+    max_files: Option<usize>,
+    path: impl Fn(&T) -> &Path,
+    read: impl FnMut(&str) -> Option<String>,
+    omitted: &mut Vec<PathBuf>,
+) -> Vec<T> {
+    let Some(max_files) = max_files.filter(|max| files.len() > *max) else {
+        return files;
+    };
+    let paths: Vec<String> = files
+        .iter()
+        .map(|file| path(file).to_string_lossy().into_owned())
+        .collect();
+    let mut kept = select_important(&paths, max_files, read)
+        .into_iter()
+        .peekable();
+
+    let mut selected = Vec::with_capacity(max_files);
+    for (i, file) in files.into_iter().enumerate() {
+        if kept.next_if_eq(&i).is_some() {
+            selected.push(file);
+        } else {
+            omitted.push(path(&file).to_path_buf());
+        }
+    }
+    selected
+}
+
+/// the notice after the file tree listing how many files `max_files`
+/// left out of each directory
+pub(crate) fn write_omitted<W: std::io::Write, P: AsRef<Path>>(
+    omitted: &[P],
+    max_files: usize,
+    output: &mut W,
+) -> std::io::Result<()> {
+    if omitted.is_empty() {
+        return Ok(());
+    }
+    let mut dirs: BTreeMap<String, usize> = BTreeMap::new();
+    for path in omitted {
+        let dir = path
+            .as_ref()
+            .parent()
+            .map(|dir| dir.to_string_lossy().replace('\\', "/"));
+        let dir = dir
+            .filter(|dir| !dir.is_empty())
+            .unwrap_or_else(|| ".".to_string());
+        *dirs.entry(dir).or_default() += 1;
+    }
+    writeln!(
+        output,
+        "[... {} files omitted by max_files {max_files}:",
+        omitted.len()
+    )?;
+    for (dir, count) in dirs {
+        let noun = if count == 1 { "file" } else { "files" };
+        writeln!(output, "{dir}/ ({count} {noun})")?;
+    }
+    writeln!(output, "]\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_important() {
+        let files = [
+            ("Cargo.toml", ""),
+            ("README.md", "# crate"),
+            ("docs/guide.md", "guide"),
+            ("src/lib.rs", "mod parser;\nmod util;\n"),
+            ("src/parser.rs", "use crate::util::split;\n"),
+            ("src/render.rs", "use crate::util::join;\n"),
+            ("src/util.rs", "pub fn split() {}\npub fn join() {}\n"),
+            ("tests/it.rs", "fn it() {}\n"),
+        ];
+        let paths: Vec<&str> = files.iter().map(|(path, _)| *path).collect();
+        let content: HashMap<&str, &str> = files.iter().copied().collect();
+        let read = |path: &str| content.get(path).map(|c| c.to_string());
+
+        let ranked: Vec<&str> = rank_by_importance(&paths, read)
+            .into_iter()
+            .map(|i| paths[i])
+            .collect();
+        assert_eq!(
+            ranked,
+            [
+                "README.md",
+                "Cargo.toml",
+                "src/lib.rs",
+                "src/util.rs",
+                "src/parser.rs",
+                "docs/guide.md",
+                "src/render.rs",
+                "tests/it.rs"
+            ]
+        );
+
+        let kept = select_important(&paths, 4, read);
+        assert_eq!(kept, [0, 1, 3, 6]);
+        assert_eq!(select_important(&paths, 20, read).len(), paths.len());
+
+        let omitted: Vec<&str> = [
+            "docs/guide.md",
+            "src/parser.rs",
+            "src/render.rs",
+            "build.rs",
+        ]
+        .into();
+        let mut notice = Vec::new();
+        write_omitted(&omitted, 4, &mut notice).unwrap();
+        assert_eq!(
+            String::from_utf8(notice).unwrap(),
+            "[... 4 files omitted by max_files 4:\n./ (1 file)\ndocs/ (1 file)\nsrc/ (2 files)\n]\n\n"
+        );
+    }
+}
@@ -1,178 +1,764 @@
-use crate::{cache::*, clone_repository, glob_match, RepositoryMetadata};
+use crate::focus::{FocusOptions, FocusedFile};
+use crate::network::{is_transient, retry};
+use crate::options::TotalLimit;
+use crate::progress::{self, ProgressPhase};
+use crate::relevance::{EmbeddingBackend, RankedFile, RelevanceOptions};
+use crate::scratch::ScratchDir;
+use crate::{
+    cache::*, clone_at_revision, clone_branches, clone_for_commit, clone_for_compare,
+    clone_repository_into, preflight_refs, summary::FileSummary, update_repository, FileDetail,
+    HeaderDetail, IngestReport, IngestedFile, RepositoryMetadata,
+};
 use anyhow::{Context, Result};
 use git2::{Repository, Status, StatusOptions};
-use serde::{Deserialize, Serialize};
-use std::io::Write;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct IngestOptions {
-    pub include_patterns: Vec<String>,
-    pub exclude_patterns: Vec<String>,
-    pub max_file_size: usize,
-    pub include_untracked: bool,
-    pub branch: Option<String>,
-    pub path_prefix: Option<String>,
-    pub filter_preset: Option<crate::FilterPreset>,
-    pub apply_default_filters: bool,
+pub use crate::options::{FilterStats, IngestOptions, IngestionCallback};
+
+/// lines shown on each side of the cursor by `ingest_context`
+const CURSOR_RADIUS: usize = 20;
+
+/// a working tree path as ingestion sees it, links are never read through
+/// unless they resolve to a file inside the checkout
+enum WorkdirEntry {
+    File(PathBuf),
+    /// `inline` is the resolved target when `follow_symlinks` is set and
+    /// the link stays inside the repository
+    Symlink {
+        target: PathBuf,
+        inline: Option<PathBuf>,
+    },
 }
 
-impl Default for IngestOptions {
-    fn default() -> Self {
-        Self {
-            include_patterns: Vec::new(),
-            exclude_patterns: Vec::new(),
-            max_file_size: 1048576,
-            include_untracked: false,
-            branch: None,
-            path_prefix: None,
-            filter_preset: None,
-            apply_default_filters: true,
+impl WorkdirEntry {
+    /// the file whose contents get written, if any
+    fn readable(&self) -> Option<&Path> {
+        match self {
+            WorkdirEntry::File(path) => Some(path),
+            WorkdirEntry::Symlink { inline, .. } => inline.as_deref(),
+        }
+    }
+
+    fn size(&self) -> u64 {
+        self.readable()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .map_or(0, |m| m.len())
+    }
+
+    /// what git stores for the entry, also for symlinks that are inlined
+    fn blob(&self) -> Blob<'_> {
+        match self {
+            WorkdirEntry::File(path) => Blob::File(path),
+            WorkdirEntry::Symlink { target, .. } => Blob::Link(target),
         }
     }
 }
 
-impl IngestOptions {
-    pub fn with_preset(preset: crate::FilterPreset) -> Self {
-        Self {
-            filter_preset: Some(preset),
-            apply_default_filters: false,
-            ..Default::default()
+/// the content `HeaderDetail::Full` hashes into a blob id
+enum Blob<'a> {
+    File(&'a Path),
+    /// git stores a symlink as a blob of its target path
+    Link(&'a Path),
+}
+
+impl Blob<'_> {
+    fn id(&self) -> Option<git2::Oid> {
+        match self {
+            Blob::File(path) => git2::Oid::hash_file(git2::ObjectType::Blob, path).ok(),
+            #[cfg(unix)]
+            Blob::Link(target) => {
+                use std::os::unix::ffi::OsStrExt;
+                git2::Oid::hash_object(git2::ObjectType::Blob, target.as_os_str().as_bytes()).ok()
+            }
+            #[cfg(not(unix))]
+            Blob::Link(target) => git2::Oid::hash_object(
+                git2::ObjectType::Blob,
+                target.to_string_lossy().replace('\\', "/").as_bytes(),
+            )
+            .ok(),
+        }
+    }
+}
+
+/// `clone` into `dir`, retried on network errors from an emptied `dir`
+/// since a clone refuses to start over a partial one
+fn clone_retrying(
+    retries: u32,
+    dir: &Path,
+    clone: impl Fn(&Path) -> Result<Repository>,
+) -> Result<Repository> {
+    retry(retries, || {
+        let repo = clone(dir);
+        if repo.is_err() && dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+            std::fs::create_dir_all(dir)?;
         }
+        repo
+    })
+}
+
+/// canonical target of `link` when it is a regular file inside `workdir`
+/// and outside `.git`, whose config and objects are never part of the output
+fn resolve_inside(workdir: &Path, link: &Path) -> Option<PathBuf> {
+    let root = workdir.canonicalize().ok()?;
+    let resolved = link.canonicalize().ok()?;
+    let inside = resolved.strip_prefix(&root).ok()?;
+    let in_git_dir = inside
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().eq_ignore_ascii_case(".git"));
+    (!in_git_dir && resolved.is_file()).then_some(resolved)
+}
+
+/// files from this size on are mapped instead of read, when nothing
+/// rewrites their content
+#[cfg(feature = "mmap")]
+const MAP_THRESHOLD: u64 = 1 << 20;
+
+/// the content of a file to write, large files stay mapped so a 100MB
+/// text file costs page cache rather than heap
+enum FileContent {
+    Read(String),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl FileContent {
+    /// `disk` mapped, `unreadable` when it is not UTF-8; None for small
+    /// files and when mapping fails
+    #[cfg(feature = "mmap")]
+    fn map(disk: &Path, unreadable: &str) -> Option<Self> {
+        let file = std::fs::File::open(disk).ok()?;
+        if file.metadata().ok()?.len() < MAP_THRESHOLD {
+            return None;
+        }
+        // SAFETY: checkouts are not written while they are ingested, a
+        // file truncated underneath the map faults like any mapped read
+        let map = unsafe { memmap2::Mmap::map(&file) }.ok()?;
+        if std::str::from_utf8(&map).is_err() {
+            return Some(Self::Read(unreadable.to_string()));
+        }
+        Some(Self::Mapped(map))
     }
+}
 
-    pub fn get_effective_excludes(&self) -> Vec<String> {
-        let mut excludes = self.exclude_patterns.clone();
+impl std::ops::Deref for FileContent {
+    type Target = str;
 
-        if let Some(preset) = self.filter_preset {
-            excludes.extend(crate::get_excludes_for_preset(preset));
-        } else if self.apply_default_filters {
-            excludes.extend(crate::get_default_excludes());
+    fn deref(&self) -> &str {
+        match self {
+            Self::Read(content) => content,
+            // SAFETY: validated as UTF-8 by `FileContent::map`
+            #[cfg(feature = "mmap")]
+            Self::Mapped(map) => unsafe { std::str::from_utf8_unchecked(map) },
         }
+    }
+}
 
-        excludes.sort();
-        excludes.dedup();
-        excludes
+fn written(path: &Path, content: &str) -> IngestedFile {
+    IngestedFile {
+        path: path.to_path_buf(),
+        size: content.len(),
+        tokens: crate::estimate_tokens(content),
     }
 }
 
 pub struct Ingester {
     repo: Repository,
     pub options: IngestOptions,
-    effective_excludes: Vec<String>,
+    filter: crate::FilterExplainer,
+    transforms: crate::TransformPipeline,
     pub cache: Option<RepositoryCache>,
     pub cache_key: Option<String>,
+    remote_url: Option<String>,
+    // declared last so the checkout is released after the repo handle
+    checkout_lock: Option<CheckoutLock>,
+    /// throwaway clone directory, deleted when the ingester is dropped
+    scratch: Option<ScratchDir>,
 }
 
 impl Ingester {
     pub fn new(repo: Repository, options: IngestOptions) -> Self {
-        let effective_excludes = options.get_effective_excludes();
+        let filter = crate::FilterExplainer::new(&options);
+        let transforms = crate::TransformPipeline::from_options(&options);
         Self {
             repo,
             options,
-            effective_excludes,
+            filter,
+            transforms,
             cache: None,
             cache_key: None,
+            remote_url: None,
+            checkout_lock: None,
+            scratch: None,
         }
     }
 
+    fn in_scratch(repo: Repository, scratch: ScratchDir, options: IngestOptions) -> Self {
+        let mut ingester = Self::new(repo, options);
+        ingester.scratch = Some(scratch);
+        ingester
+    }
+
     pub fn from_path(path: &Path, options: IngestOptions) -> Result<Self> {
         let repo = Repository::open(path).context("Failed to open repository")?;
         Ok(Self::new(repo, options))
     }
 
     pub fn from_url(url: &str, options: IngestOptions) -> Result<Self> {
-        let repo = clone_repository(url, options.branch.as_deref())?;
-        Ok(Self::new(repo, options))
+        let revisions = || {
+            options
+                .branch
+                .iter()
+                .chain(&options.branches)
+                .map(String::as_str)
+        };
+        retry(options.retries, || preflight_refs(url, revisions()))?;
+        let scratch = ScratchDir::new("clone")?;
+        let repo = clone_retrying(options.retries, scratch.path(), |dir| {
+            if options.branches.is_empty() {
+                clone_repository_into(url, options.branch.as_deref(), dir)
+            } else {
+                clone_branches(url, &options.branches, dir)
+            }
+        })?;
+        Ok(Self::in_scratch(repo, scratch, options))
     }
 
     pub fn from_url_cached(url: &str, options: IngestOptions) -> Result<Self> {
-        let repo = clone_repository(url, options.branch.as_deref())?;
-        let mut ingester = Self::new(repo, options.clone());
+        // the shared checkout follows one branch, several need their own clone
+        if !options.branches.is_empty() {
+            return Self::from_url(url, options);
+        }
+        // as last fetched, recloning is no fallback either
+        if crate::network::network_config().offline {
+            return Self::from_checkout(url, options)
+                .with_context(|| format!("{url} is not available offline"));
+        }
+        let cache = RepositoryCache::new().ok();
+        let cache_key = RepositoryCache::generate_cache_key(url, options.branch.as_deref());
+        let branch = options.branch.as_deref();
+        retry(options.retries, || preflight_refs(url, branch))?;
+
+        let lock = cache
+            .as_ref()
+            .and_then(|cache| CheckoutLock::acquire(cache.checkout_dir(&cache_key)));
+
+        let mut scratch = None;
+        let repo = match lock {
+            Some(ref lock) => Self::open_checkout(url, branch, lock.path(), options.retries)?,
+            // someone else is updating the shared checkout, use a throwaway clone
+            None => {
+                let dir = scratch.insert(ScratchDir::new("clone")?);
+                clone_retrying(options.retries, dir.path(), |dir| {
+                    clone_repository_into(url, branch, dir)
+                })?
+            }
+        };
 
-        ingester.cache = RepositoryCache::new().ok();
-        ingester.cache_key = Some(RepositoryCache::generate_cache_key(
-            url,
-            options.branch.as_deref(),
-        ));
+        let mut ingester = Self::new(repo, options);
+        ingester.scratch = scratch;
+        ingester.cache = cache;
+        ingester.cache_key = Some(cache_key);
+        ingester.remote_url = Some(url.to_string());
+        ingester.checkout_lock = lock;
 
         Ok(ingester)
     }
 
+    /// the persistent checkout of `url` as it is, without fetching, for
+    /// re-filtering a repository that was just ingested
+    pub fn from_checkout(url: &str, options: IngestOptions) -> Result<Self> {
+        if !options.branches.is_empty() {
+            anyhow::bail!("Checkouts of several branches are not kept");
+        }
+        let cache = RepositoryCache::new()?;
+        let cache_key = RepositoryCache::generate_cache_key(url, options.branch.as_deref());
+        let lock = CheckoutLock::acquire(cache.checkout_dir(&cache_key))
+            .ok_or_else(|| anyhow::anyhow!("Checkout of {url} is being updated"))?;
+        let repo = Repository::open(lock.path())
+            .map_err(|_| anyhow::anyhow!("No checkout of {url}, ingest it first"))?;
+
+        let mut ingester = Self::new(repo, options);
+        ingester.cache = Some(cache);
+        ingester.cache_key = Some(cache_key);
+        ingester.remote_url = Some(url.to_string());
+        ingester.checkout_lock = Some(lock);
+
+        Ok(ingester)
+    }
+
+    /// ingest the repository exactly at `revision` (commit SHA, tag or branch)
+    pub fn from_url_at(url: &str, revision: &str, options: IngestOptions) -> Result<Self> {
+        retry(options.retries, || preflight_refs(url, [revision]))?;
+        let scratch = ScratchDir::new("at")?;
+        let repo = clone_retrying(options.retries, scratch.path(), |dir| {
+            clone_at_revision(url, revision, dir)
+        })?;
+        Ok(Self::in_scratch(repo, scratch, options))
+    }
+
+    /// bare clone holding just `base` and `head`, for `generate_diff`
+    pub fn from_url_compare(
+        url: &str,
+        base: &str,
+        head: &str,
+        options: IngestOptions,
+    ) -> Result<Self> {
+        let scratch = ScratchDir::new("compare")?;
+        let repo = clone_retrying(options.retries, scratch.path(), |dir| {
+            clone_for_compare(url, base, head, dir)
+        })?;
+        Ok(Self::in_scratch(repo, scratch, options))
+    }
+
+    /// full history clone, for `generate_commit_diff`
+    pub fn from_url_commit(url: &str, commit_sha: &str, options: IngestOptions) -> Result<Self> {
+        let scratch = ScratchDir::new("commit")?;
+        let repo = clone_retrying(options.retries, scratch.path(), |dir| {
+            clone_for_commit(url, commit_sha, dir)
+        })?;
+        Ok(Self::in_scratch(repo, scratch, options))
+    }
+
+    /// reuse the persistent checkout with an incremental fetch, recloning
+    /// only when it is missing or broken
+    fn open_checkout(
+        url: &str,
+        branch: Option<&str>,
+        dir: &Path,
+        retries: u32,
+    ) -> Result<Repository> {
+        if let Ok(repo) = Repository::open(dir) {
+            // a retried fetch resumes from the objects already in the checkout
+            match retry(retries, || update_repository(&repo, url, branch)) {
+                Ok(()) => return Ok(repo),
+                // the checkout is fine, the network is not
+                Err(e) if is_transient(&e) => return Err(e),
+                Err(e) => eprintln!("Warning: incremental fetch failed ({e}), recloning"),
+            }
+        }
+
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        clone_retrying(retries, dir, |dir| clone_repository_into(url, branch, dir))
+    }
+
     pub fn get_filter_preset(&self) -> Option<crate::FilterPreset> {
         self.options.filter_preset
     }
 
-    fn should_include(&self, path: &Path) -> Result<bool> {
-        let status = self.repo.status_file(path)?;
+    /// run `transform` after the ones the options select
+    pub fn add_transform(&mut self, transform: impl crate::ContentTransform + 'static) {
+        self.transforms.push(transform);
+    }
+
+    /// the transformed and line capped content of a file, None to skip it
+    fn transform(&self, path: &str, content: String) -> Option<String> {
+        let content = self.transforms.apply(path, content)?;
+        Some(self.options.cap_lines(content))
+    }
+
+    /// SPDX style expression of the license files at the repository root
+    pub fn detect_license(&self) -> Option<String> {
+        let workdir = self.repo.workdir()?;
+        let files: Vec<(String, String)> = std::fs::read_dir(workdir)
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| crate::license::is_license_file(name))
+            .filter_map(|name| {
+                let content = std::fs::read_to_string(workdir.join(&name)).ok()?;
+                Some((name, content))
+            })
+            .collect();
+
+        crate::license_expression(
+            files
+                .iter()
+                .map(|(name, content)| (name.as_str(), content.as_str())),
+        )
+    }
+
+    /// no git status is needed: the paths come from the committed tree or
+    /// from untracked files listed without ignored ones, and git never
+    /// ignores a tracked file
+    fn should_include(&self, path: &Path) -> bool {
+        self.filter.explain(path).is_included()
+    }
+
+    pub fn ingest<W: Write>(&self, output: &mut W) -> Result<IngestReport> {
+        let workdir = self
+            .repo
+            .workdir()
+            .context("Repository has no working directory")?;
+        let mut report = IngestReport::default();
+        let mut files = crate::importance::keep_important(
+            self.collect_filtered_files()?,
+            self.options.max_files,
+            PathBuf::as_path,
+            |path| {
+                std::fs::read_to_string(self.workdir_entry(workdir, Path::new(path))?.readable()?)
+                    .ok()
+            },
+            &mut report.omitted,
+        );
+        // one write per file line would be a syscall each on stdout
+        let mut output = BufWriter::new(output);
+
+        if self.options.ownership {
+            self.write_ownership(workdir, &mut output)?;
+        }
+        if self.options.docs_first {
+            files.sort_by_key(|file| !crate::docs::is_key_document(&file.to_string_lossy()));
+            let readable = files.iter().filter_map(|file| {
+                let disk = self.workdir_entry(workdir, file)?.readable()?.to_path_buf();
+                Some((file.as_path(), disk))
+            });
+            self.write_documentation(readable, &mut output)?;
+        }
+        if self.options.build_ci {
+            let readable = files
+                .iter()
+                .filter(|file| self.in_build_section(file))
+                .filter_map(|file| {
+                    let entry = self.workdir_entry(workdir, file)?;
+                    (entry.readable().is_some() && self.options.size_in_range(entry.size()))
+                        .then_some((file.as_path(), entry))
+                });
+            self.write_build_ci(readable, &mut output, &mut report)?;
+        }
 
-        if status.contains(Status::IGNORED) && !self.options.include_untracked {
-            return Ok(false);
+        // write file tree structure at the start
+        let tree_structure = crate::generate_tree_from_paths(&files);
+        write!(output, "{}", tree_structure)?;
+        crate::importance::write_omitted(
+            &report.omitted,
+            self.options.max_files.unwrap_or_default(),
+            &mut output,
+        )?;
+        let modified = self.last_modified(&files);
+        let sizes = files.iter().map(|file| {
+            let size = self
+                .workdir_entry(workdir, file)
+                .map_or(0, |entry| entry.size());
+            (file.as_path(), size)
+        });
+        let summarized = self.summarized_groups(sizes);
+        let mut summaries = BTreeMap::new();
+        let mut limit = TotalLimit::new(&self.options);
+
+        let total = files.len() as u64;
+        for (i, file) in files.iter().enumerate() {
+            progress::report(ProgressPhase::Write, i as u64 + 1, total);
+            if self.in_build_section(file) {
+                continue;
+            }
+            if let Some(group) = self.summary_group(file, &summarized) {
+                let disk = self
+                    .workdir_entry(workdir, file)
+                    .and_then(|e| e.readable().map(Path::to_path_buf));
+                if let Some(summary) = disk.and_then(|disk| self.summarize_file(file, &disk)) {
+                    summaries
+                        .entry(group)
+                        .or_insert_with(Vec::new)
+                        .push(summary);
+                }
+                continue;
+            }
+            if limit.reached() {
+                // listed without reading, the rest may be huge
+                let entry = self.workdir_entry(workdir, file);
+                if entry.is_some_and(|entry| self.options.size_in_range(entry.size())) {
+                    report.skipped.push(file.clone());
+                }
+                continue;
+            }
+            let Some((entry, content)) = self.read_file(workdir, file) else {
+                continue;
+            };
+            if !limit.admit(&written(file, content.as_deref().unwrap_or_default())) {
+                report.skipped.push(file.clone());
+                continue;
+            }
+            report.files.push(self.write_file(
+                file,
+                &entry,
+                content.as_deref(),
+                &modified,
+                &mut output,
+            )?);
         }
+        self.write_summaries(summaries, &mut output, &mut report)?;
+        limit.write_notice(&report.skipped, &mut output)?;
+        output.flush()?;
 
-        if path.components().any(|c| c.as_os_str() == ".git") {
-            return Ok(false);
+        if report.files.is_empty() {
+            eprintln!("Warning: No files found to ingest");
         }
 
-        let path_str = path.to_string_lossy();
+        Ok(report)
+    }
 
-        for pattern in &self.effective_excludes {
-            if glob_match(pattern, &path_str) {
-                return Ok(false);
-            }
+    /// paths that `ingest` would write, without reading any contents
+    pub fn list_files(&self) -> Result<Vec<PathBuf>> {
+        if let Some(index) = self.cached_index() {
+            let files: Vec<CachedFile> = index.files()?.collect::<Result<_>>()?;
+            let selected = self.select_cached_files(&files)?;
+            return Ok(selected.into_iter().map(|f| f.path.clone()).collect());
         }
 
-        if !self.options.include_patterns.is_empty() {
-            return Ok(self.options.include_patterns.iter().any(|p| {
-                // Handle directory patterns (ending with /)
-                if p.ends_with("/") {
-                    let dir_prefix = &p[..p.len() - 1];
-                    path_str.starts_with(dir_prefix) && path_str.len() > dir_prefix.len()
-                } else if !p.contains('/') {
-                    // Pattern without path separator - match filename only
-                    path.file_name()
-                        .and_then(|n| n.to_str())
-                        .map(|filename| glob_match(p, filename))
-                        .unwrap_or(false)
-                } else {
-                    // Pattern with path separator - match full path
-                    glob_match(p, &path_str)
+        let workdir = self
+            .repo
+            .workdir()
+            .context("Repository has no working directory")?;
+
+        let mut files = self.collect_filtered_files()?;
+        files.retain(|file| {
+            self.workdir_entry(workdir, file)
+                .is_some_and(|entry| self.options.size_in_range(entry.size()))
+        });
+        Ok(files)
+    }
+
+    /// every file in the repository with the rule that keeps or drops it
+    pub fn explain(&self) -> Result<Vec<(PathBuf, crate::FilterDecision)>> {
+        use crate::FilterDecision;
+
+        let workdir = self
+            .repo
+            .workdir()
+            .context("Repository has no working directory")?;
+        let explainer = crate::FilterExplainer::new(&self.options);
+
+        let mut decisions = Vec::new();
+        for file in self.collect_all_repository_files()? {
+            let decision = if let Some(excluded) = self.options.excluded_by(&file) {
+                FilterDecision::ExcludedPath(excluded.to_string())
+            } else if !self.in_path_prefix(&file) {
+                FilterDecision::OutsidePathPrefix
+            } else {
+                match explainer.explain(&file) {
+                    decision if decision.is_included() => {
+                        let size = self
+                            .workdir_entry(workdir, &file)
+                            .map_or(0, |entry| entry.size());
+                        let (min, max) = (
+                            self.options.min_file_size as u64,
+                            self.options.max_file_size as u64,
+                        );
+                        if size > max {
+                            FilterDecision::TooLarge { size, limit: max }
+                        } else if size < min {
+                            FilterDecision::TooSmall { size, limit: min }
+                        } else {
+                            decision
+                        }
+                    }
+                    decision => decision,
                 }
-            }));
+            };
+            decisions.push((file, decision));
         }
 
-        Ok(true)
+        let included: Vec<PathBuf> = decisions
+            .iter()
+            .filter(|(_, decision)| decision.is_included())
+            .map(|(file, _)| file.clone())
+            .collect();
+        let all: Vec<PathBuf> = decisions.iter().map(|(f, _)| f.clone()).collect();
+        let paired: HashMap<PathBuf, PathBuf> =
+            self.paired_files(&included, &all).into_iter().collect();
+        for (file, decision) in &mut decisions {
+            let Some(partner) = paired.get(file) else {
+                continue;
+            };
+            // size limits and .gitignore hold for paired files too
+            let filtered_out = matches!(
+                decision,
+                FilterDecision::ExcludedByPreset { .. }
+                    | FilterDecision::OutsidePreset(_)
+                    | FilterDecision::NotIncluded
+                    | FilterDecision::ExtensionNotAllowed
+            );
+            let size = self
+                .workdir_entry(workdir, file)
+                .map_or(0, |entry| entry.size());
+            if filtered_out && self.options.size_in_range(size) {
+                *decision = FilterDecision::PairedWith(partner.to_string_lossy().into_owned());
+            }
+        }
+
+        decisions.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(decisions)
     }
 
-    pub fn ingest<W: Write>(&self, output: &mut W) -> Result<()> {
-        let files = self.collect_filtered_files()?;
+    fn workdir_entry(&self, workdir: &Path, relative: &Path) -> Option<WorkdirEntry> {
+        let full_path = workdir.join(relative);
+        let metadata = std::fs::symlink_metadata(&full_path).ok()?;
+
+        if metadata.is_file() {
+            return Some(WorkdirEntry::File(full_path));
+        }
+        if !metadata.file_type().is_symlink() {
+            return None;
+        }
+
+        let target = std::fs::read_link(&full_path).ok()?;
+        let inline = if self.options.follow_symlinks {
+            resolve_inside(workdir, &full_path)
+        } else {
+            None
+        };
+        Some(WorkdirEntry::Symlink { target, inline })
+    }
+
+    fn in_path_prefix(&self, path: &Path) -> bool {
+        self.options.in_prefixes(path) && self.options.excluded_by(path).is_none()
+    }
+
+    /// ingest only the files most relevant to `options.query`, best match first
+    pub fn ingest_relevant<W: Write>(
+        &self,
+        output: &mut W,
+        options: &RelevanceOptions,
+        backend: &dyn EmbeddingBackend,
+    ) -> Result<Vec<RankedFile>> {
         let workdir = self
             .repo
             .workdir()
             .context("Repository has no working directory")?;
 
-        // write file tree structure at the start
-        let tree_structure = crate::generate_tree_from_paths(&files);
+        let mut candidates = Vec::new();
+        for file in self.collect_filtered_files()? {
+            let Some(entry) = self.workdir_entry(workdir, &file) else {
+                continue;
+            };
+            let Some(path) = entry.readable() else {
+                continue;
+            };
+            if !self.options.size_in_range(entry.size()) {
+                continue;
+            }
+            // binary files carry no meaning for embeddings
+            if let Ok(content) = std::fs::read_to_string(path) {
+                candidates.push((file, content));
+            }
+        }
+
+        let ranked = crate::relevance::rank_files(&candidates, options, backend)?;
+        let selected = crate::relevance::select_within_budget(ranked, options);
+
+        let mut output = BufWriter::new(output);
+        let paths: Vec<&PathBuf> = selected.iter().map(|f| &f.path).collect();
+        let tree_structure = crate::generate_tree_from_paths(&paths);
         write!(output, "{}", tree_structure)?;
+        let modified = self.last_modified(&paths);
 
-        let mut processed = 0;
-        for file in files {
-            let full_path = workdir.join(&file);
-            if full_path.exists() && full_path.is_file() {
-                self.ingest_file(&full_path, &file, output)?;
-                processed += 1;
+        for file in &selected {
+            self.ingest_file(workdir, &file.path, &modified, &mut output)?;
+        }
+        output.flush()?;
+
+        Ok(selected)
+    }
+
+    /// ingest the focus files and the files they import, see `FocusOptions`
+    pub fn ingest_focus<W: Write>(
+        &self,
+        output: &mut W,
+        options: &FocusOptions,
+    ) -> Result<Vec<FocusedFile>> {
+        let workdir = self
+            .repo
+            .workdir()
+            .context("Repository has no working directory")?;
+
+        // focus files are kept whatever the filters, their imports are not
+        let mut candidates: Vec<String> = self
+            .collect_filtered_files()?
+            .iter()
+            .map(|file| file.to_string_lossy().into_owned())
+            .collect();
+        for focus in &options.files {
+            crate::validate_path_prefix(focus).map_err(|e| anyhow::anyhow!("{focus}: {e}"))?;
+            if !workdir.join(focus).is_file() {
+                return Err(anyhow::anyhow!("Focus file not found: {focus}"));
             }
+            candidates.push(focus.clone());
         }
+        let index = crate::ImportIndex::new(candidates);
 
-        if processed == 0 {
-            eprintln!("Warning: No files found to ingest");
+        let selected = crate::dependency_closure(&index, options, |path| {
+            let entry = self.workdir_entry(workdir, Path::new(path))?;
+            if !self.options.size_in_range(entry.size()) {
+                return None;
+            }
+            std::fs::read_to_string(entry.readable()?).ok()
+        });
+
+        let mut output = BufWriter::new(output);
+        let paths: Vec<&PathBuf> = selected.iter().map(|f| &f.path).collect();
+        let tree_structure = crate::generate_tree_from_paths(&paths);
+        write!(output, "{}", tree_structure)?;
+        let modified = self.last_modified(&paths);
+
+        for file in &selected {
+            self.ingest_file(workdir, &file.path, &modified, &mut output)?;
         }
+        output.flush()?;
 
-        Ok(())
+        Ok(selected)
     }
 
-    pub fn ingest_cached<W: Write>(&mut self, output: &mut W) -> Result<()> {
+    /// editor context for `file`: the lines around the cursor `line`, then
+    /// the file and its imports as `ingest_focus` selects them; `file` may
+    /// also be an absolute path inside the working directory
+    pub fn ingest_context<W: Write>(
+        &self,
+        output: &mut W,
+        file: &Path,
+        line: Option<usize>,
+        depth: usize,
+        token_budget: usize,
+    ) -> Result<Vec<FocusedFile>> {
+        let workdir = self
+            .repo
+            .workdir()
+            .context("Repository has no working directory")?;
+
+        let relative = if file.is_absolute() {
+            let workdir = workdir.canonicalize()?;
+            file.canonicalize()
+                .ok()
+                .and_then(|file| file.strip_prefix(&workdir).ok().map(Path::to_path_buf))
+                .with_context(|| format!("{} is not inside the repository", file.display()))?
+        } else {
+            file.to_path_buf()
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+
+        if let Some(line) = line {
+            let content = std::fs::read_to_string(workdir.join(&relative))
+                .with_context(|| format!("Failed to read {relative}"))?;
+            let excerpt = crate::cursor_excerpt(&content, line, CURSOR_RADIUS)
+                .with_context(|| format!("{relative} has no line {line}"))?;
+            writeln!(output, "Cursor at {relative}:{line}\n\n{excerpt}")?;
+        }
+
+        let options = FocusOptions {
+            files: vec![relative],
+            depth,
+            token_budget,
+        };
+        self.ingest_focus(output, &options)
+    }
+
+    pub fn ingest_cached<W: Write>(&mut self, output: &mut W) -> Result<IngestReport> {
         let commit_hash = self.get_current_commit()?;
 
         if let Some(ref mut cache) = self.cache {
@@ -185,8 +771,8 @@ impl Ingester {
                         }
                     }
                     CacheCommitStatus::Outdated => {
-                        eprintln!("↻ Cache outdated, fetching new data...");
-                        let _ = cache.remove(cache_key);
+                        // the checkout is already at the new head, just re-index it
+                        eprintln!("↻ Cache outdated, re-indexing updated checkout...");
                     }
                     CacheCommitStatus::NotCached => {
                         eprintln!("→ No cache found, fetching repository...");
@@ -199,28 +785,405 @@ impl Ingester {
         self.filter_cached_files(cache_entry, output)
     }
 
-    fn ingest_file<W: Write>(&self, path: &Path, relative: &Path, output: &mut W) -> Result<()> {
-        let metadata = std::fs::metadata(path)?;
+    /// bring the cache entry of the checkout up to its head without
+    /// writing anything, for warming the cache ahead of the first ingest
+    pub fn prefetch(&mut self) -> Result<CacheEntryHeader> {
+        let commit_hash = self.get_current_commit()?;
+        let (Some(cache), Some(cache_key)) = (self.cache.as_mut(), self.cache_key.as_ref()) else {
+            anyhow::bail!("Only cached remote repositories can be prefetched");
+        };
+        if let CacheCommitStatus::Match = cache.check_commit(cache_key, &commit_hash) {
+            if let Ok(Some(cache_entry)) = cache.get(cache_key) {
+                return Ok(cache_entry.header());
+            }
+        }
+        Ok(self.fetch_and_cache()?.header())
+    }
+
+    /// `ingest_cached` when the ingester was opened with a cache, `ingest`
+    /// otherwise
+    pub fn ingest_auto<W: Write>(&mut self, output: &mut W) -> Result<IngestReport> {
+        if !self.options.branches.is_empty() {
+            self.ingest_branches(output)
+        } else if self.cache_key.is_some() {
+            self.ingest_cached(output)
+        } else {
+            self.ingest(output)
+        }
+    }
 
-        if metadata.len() > self.options.max_file_size as u64 {
-            return Ok(());
+    /// `ingest` for each of `options.branches` in turn, under a `# Branch`
+    /// heading with its commit; the report paths start with the branch.
+    /// Only throwaway clones are checked out this way, never a repository
+    /// someone works in
+    pub fn ingest_branches<W: Write>(&self, output: &mut W) -> Result<IngestReport> {
+        if self.scratch.is_none() {
+            anyhow::bail!("Several branches can only be ingested from a fresh clone");
+        }
+        let mut report = IngestReport::default();
+        for branch in &self.options.branches {
+            let commit = self.checkout(branch)?;
+            writeln!(
+                output,
+                "# Branch {} ({})\n",
+                branch,
+                &commit[..commit.len().min(8)]
+            )?;
+            let section = self.ingest(output)?;
+
+            let prefixed = |path: PathBuf| Path::new(branch).join(path);
+            report
+                .files
+                .extend(section.files.into_iter().map(|file| IngestedFile {
+                    path: prefixed(file.path),
+                    ..file
+                }));
+            report
+                .skipped
+                .extend(section.skipped.into_iter().map(prefixed));
+            report
+                .omitted
+                .extend(section.omitted.into_iter().map(prefixed));
         }
+        Ok(report)
+    }
 
-        let mut content = std::fs::read_to_string(path).unwrap_or_else(|_| "[binary file]".to_string());
+    /// force the working tree to `revision` with a detached HEAD, returning
+    /// the full id of the commit checked out
+    pub fn checkout(&self, revision: &str) -> Result<String> {
+        let commit = self.resolve_revision(revision)?.peel_to_commit()?;
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.force().remove_untracked(true);
+        self.repo
+            .checkout_tree(commit.as_object(), Some(&mut checkout))?;
+        self.repo.set_head_detached(commit.id())?;
+        Ok(commit.id().to_string())
+    }
+
+    /// the whole output in memory, for callers that post-process it
+    pub fn ingest_to_string(&mut self) -> Result<(String, IngestReport)> {
+        let mut buffer = Vec::new();
+        let report = self.ingest_auto(&mut buffer)?;
+        let content = String::from_utf8(buffer)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned());
+        Ok((content, report))
+    }
+
+    /// write the output to `path`, replacing any existing file
+    pub fn ingest_to_file(&mut self, path: &Path) -> Result<IngestReport> {
+        let mut file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        self.ingest_auto(&mut file)
+    }
+
+    /// None when the path was skipped (missing, not a file, too large or
+    /// dropped by a transform)
+    fn ingest_file<W: Write>(
+        &self,
+        workdir: &Path,
+        relative: &Path,
+        modified: &HashMap<PathBuf, String>,
+        output: &mut W,
+    ) -> Result<Option<IngestedFile>> {
+        let Some((entry, content)) = self.read_file(workdir, relative) else {
+            return Ok(None);
+        };
+        self.write_file(relative, &entry, content.as_deref(), modified, output)
+            .map(Some)
+    }
+
+    /// what `ingest_file` writes for `relative`, None when it is left out;
+    /// links that are not inlined have no content
+    fn read_file(
+        &self,
+        workdir: &Path,
+        relative: &Path,
+    ) -> Option<(WorkdirEntry, Option<FileContent>)> {
+        let entry = self.workdir_entry(workdir, relative)?;
+        if !self.options.size_in_range(entry.size()) {
+            return None;
+        }
+
+        let content = match entry.readable() {
+            Some(path) => {
+                Some(self.read_content(&relative.to_string_lossy(), path, "[binary file]")?)
+            }
+            None => None,
+        };
+        Some((entry, content))
+    }
 
-        // compress license files to save tokens
-        let path_str = relative.to_string_lossy();
-        if let Some(compressed) = crate::compress_license(&path_str, &content) {
-            content = compressed;
+    /// the transformed content of `disk`, or `unreadable` when it is not
+    /// text; large files nothing rewrites are mapped instead of read
+    fn read_content(&self, relative: &str, disk: &Path, unreadable: &str) -> Option<FileContent> {
+        #[cfg(feature = "mmap")]
+        if self.options.max_lines.is_none() && !self.transforms.rewrites(relative) {
+            if let Some(content) = FileContent::map(disk, unreadable) {
+                return Some(content);
+            }
         }
+        let content = std::fs::read_to_string(disk).unwrap_or_else(|_| unreadable.to_string());
+        self.transform(relative, content).map(FileContent::Read)
+    }
 
-        writeln!(output, "=== {} ===", relative.display())?;
-        writeln!(output, "{content}")?;
+    fn write_file<W: Write>(
+        &self,
+        relative: &Path,
+        entry: &WorkdirEntry,
+        content: Option<&str>,
+        modified: &HashMap<PathBuf, String>,
+        output: &mut W,
+    ) -> Result<IngestedFile> {
+        let header = self.header(
+            relative,
+            entry.blob(),
+            content.unwrap_or_default(),
+            modified,
+        );
+        writeln!(output, "{header}")?;
+        if let WorkdirEntry::Symlink { ref target, .. } = entry {
+            writeln!(output, "[symlink -> {}]", target.display())?;
+        }
+        if let Some(content) = content {
+            crate::write_escaped(output, content)?;
+            writeln!(output)?;
+        }
         writeln!(output)?;
 
+        Ok(written(relative, content.unwrap_or_default()))
+    }
+
+    /// the leading `# Documentation` section of `docs_first`: doc comments
+    /// of the source files, so intent is read before implementation
+    fn write_documentation<'a, W: Write>(
+        &self,
+        files: impl IntoIterator<Item = (&'a Path, PathBuf)>,
+        output: &mut W,
+    ) -> Result<()> {
+        // docs-only already writes doc comments as the file contents
+        if self.options.filter_preset == Some(crate::FilterPreset::DocsOnly) {
+            return Ok(());
+        }
+
+        let mut section = String::new();
+        for (relative, disk) in files {
+            let path = relative.to_string_lossy();
+            let Ok(content) = std::fs::read_to_string(&disk) else {
+                continue;
+            };
+            if let Some(docs) = crate::docs::extract_doc_comments(&path, &content) {
+                section.push_str(&format!(
+                    "## {path}\n\n{}\n\n",
+                    crate::escape_content(&docs)
+                ));
+            }
+        }
+        if !section.is_empty() {
+            write!(output, "# Documentation\n\n{section}")?;
+        }
         Ok(())
     }
 
+    /// whether `relative` is written in the `# Build & CI` section
+    fn in_build_section(&self, relative: &Path) -> bool {
+        self.options.build_ci && crate::build::is_build_file(&relative.to_string_lossy())
+    }
+
+    /// the `# Build & CI` section of `build_ci`, ahead of the file tree, so
+    /// how the project is built and tested is read as one block; the files
+    /// are written as usual, only grouped under the heading
+    fn write_build_ci<'a, W: Write>(
+        &self,
+        files: impl IntoIterator<Item = (&'a Path, WorkdirEntry)>,
+        output: &mut W,
+        report: &mut IngestReport,
+    ) -> Result<()> {
+        let files: Vec<_> = files.into_iter().collect();
+        let modified = self.last_modified(
+            &files
+                .iter()
+                .map(|(relative, _)| *relative)
+                .collect::<Vec<_>>(),
+        );
+
+        let mut section = Vec::new();
+        for (relative, entry) in files {
+            let path = relative.to_string_lossy();
+            let Some(Ok(content)) = entry.readable().map(std::fs::read_to_string) else {
+                continue;
+            };
+            let Some(content) = self.transform(&path, content) else {
+                continue;
+            };
+            writeln!(
+                section,
+                "{}",
+                self.header(relative, entry.blob(), &content, &modified)
+            )?;
+            crate::write_escaped(&mut section, &content)?;
+            write!(section, "\n\n")?;
+            report.files.push(written(relative, &content));
+        }
+        if !section.is_empty() {
+            write!(output, "# Build & CI\n\n")?;
+            output.write_all(&section)?;
+        }
+        Ok(())
+    }
+
+    /// the leading `# Ownership` section: CODEOWNERS, MAINTAINERS and the
+    /// authors of every manifest, whatever the filters
+    fn write_ownership<W: Write>(&self, workdir: &Path, output: &mut W) -> Result<()> {
+        let mut sources: Vec<(String, String)> = self
+            .collect_all_repository_files()?
+            .into_iter()
+            .map(|file| file.to_string_lossy().into_owned())
+            .filter(|path| crate::ownership::is_ownership_source(path))
+            .filter_map(|path| {
+                let content = std::fs::read_to_string(workdir.join(&path)).ok()?;
+                Some((path, content))
+            })
+            .collect();
+        // CODEOWNERS in GitHub's lookup order, then MAINTAINERS, then manifests
+        sources.sort_by_key(|(path, _)| {
+            let rank = crate::ownership::CODEOWNERS_PATHS
+                .iter()
+                .chain(crate::ownership::MAINTAINERS_PATHS)
+                .position(|p| p == path);
+            (
+                rank.unwrap_or(usize::MAX),
+                path.matches('/').count(),
+                path.clone(),
+            )
+        });
+
+        let rules =
+            crate::ownership::collect_rules(sources.iter().map(|(p, c)| (p.as_str(), c.as_str())));
+        if let Some(section) = crate::ownership::render_ownership(&rules) {
+            write!(output, "{}", crate::escape_content(&section))?;
+        }
+        Ok(())
+    }
+
+    /// directories `summarize_dirs` replaces with a summary, from file sizes
+    fn summarized_groups<'a>(
+        &self,
+        files: impl IntoIterator<Item = (&'a Path, u64)>,
+    ) -> Vec<String> {
+        match self.options.summarize_dirs {
+            Some(max_tokens) => {
+                crate::summary::oversized_groups(files, &self.options.prefixes(), max_tokens)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// the summarized directory `relative` belongs to, if any
+    fn summary_group(&self, relative: &Path, summarized: &[String]) -> Option<String> {
+        if summarized.is_empty() {
+            return None;
+        }
+        crate::summary::summary_group(relative, &self.options.prefixes())
+            .filter(|group| summarized.contains(group))
+    }
+
+    /// what a directory summary lists for a file, None for binary files
+    /// and files a transform drops
+    fn summarize_file(&self, relative: &Path, disk: &Path) -> Option<FileSummary> {
+        let path = relative.to_string_lossy();
+        let content = std::fs::read_to_string(disk).ok()?;
+        let content = self.transforms.apply(&path, content)?;
+        Some(FileSummary::new(&path, &content))
+    }
+
+    /// one `=== dir/ ===` section per summarized directory, after the files
+    fn write_summaries<W: Write>(
+        &self,
+        summaries: BTreeMap<String, Vec<FileSummary>>,
+        output: &mut W,
+        report: &mut IngestReport,
+    ) -> Result<()> {
+        for (dir, files) in summaries {
+            let path = format!("{dir}/");
+            let summary = crate::summary::render_summary(&dir, &files);
+            let detail = crate::summary::summary_detail(&files);
+            writeln!(output, "{}", crate::format_header(&path, Some(&detail)))?;
+            writeln!(output, "{summary}")?;
+            report.files.push(written(Path::new(&path), &summary));
+        }
+        Ok(())
+    }
+
+    /// the `=== path ===` line of a file, with the detail `header_detail` asks for
+    fn header(
+        &self,
+        relative: &Path,
+        blob: Blob<'_>,
+        content: &str,
+        modified: &HashMap<PathBuf, String>,
+    ) -> String {
+        let path = relative.to_string_lossy();
+        let detail = match self.options.header_detail {
+            HeaderDetail::Path => None,
+            HeaderDetail::Basic => Some(FileDetail::new(&path, content)),
+            HeaderDetail::Full => Some(FileDetail {
+                blob: blob.id().map(|id| id.to_string()[..7].to_string()),
+                modified: modified.get(relative).cloned(),
+                ..FileDetail::new(&path, content)
+            }),
+        };
+        crate::format_header(relative.display(), detail.as_ref())
+    }
+
+    /// day of the last commit touching each of `paths`, walking back from
+    /// HEAD until all are found; only done for `HeaderDetail::Full`
+    fn last_modified<P: AsRef<Path>>(&self, paths: &[P]) -> HashMap<PathBuf, String> {
+        let mut found = HashMap::new();
+        if self.options.header_detail != HeaderDetail::Full {
+            return found;
+        }
+        let mut wanted: HashSet<&Path> = paths.iter().map(AsRef::as_ref).collect();
+
+        let Ok(mut walk) = self.repo.revwalk() else {
+            return found;
+        };
+        if walk.push_head().is_err() || walk.set_sorting(git2::Sort::TIME).is_err() {
+            return found;
+        }
+
+        for oid in walk.flatten() {
+            if wanted.is_empty() {
+                break;
+            }
+            let Ok(commit) = self.repo.find_commit(oid) else {
+                continue;
+            };
+            let Ok(tree) = commit.tree() else {
+                continue;
+            };
+            // the root of a shallow clone is diffed against the empty tree
+            let parent = commit.parent(0).ok().and_then(|parent| parent.tree().ok());
+            let Ok(diff) = self
+                .repo
+                .diff_tree_to_tree(parent.as_ref(), Some(&tree), None)
+            else {
+                continue;
+            };
+            let day = crate::header::format_day(commit.time().seconds());
+            for delta in diff.deltas() {
+                if let Some(path) = delta
+                    .new_file()
+                    .path()
+                    .filter(|path| wanted.remove::<Path>(path))
+                {
+                    found.insert(path.to_path_buf(), day.clone());
+                }
+            }
+        }
+
+        found
+    }
+
     fn collect_filtered_files(&self) -> Result<Vec<PathBuf>> {
         let head_result = self.repo.head();
         let has_commits = head_result.is_ok();
@@ -231,45 +1194,55 @@ impl Ingester {
             let head = head_result?;
             let tree = head.peel_to_tree()?;
 
-            // when path_prefix is set, walk from that subtree
-            // otherwise walk from root
-            let (tree_to_walk, is_subtree) = if let Some(prefix) = &self.options.path_prefix {
-                match tree.get_path(Path::new(prefix)) {
-                    Ok(entry) => (self.repo.find_tree(entry.id())?, true),
-                    Err(_) => return Ok(Vec::new()),
+            // with path prefixes, walk just those subtrees, otherwise from root
+            let prefixes = self.options.prefixes();
+            let mut subtrees = Vec::new();
+            for prefix in &prefixes {
+                if self.options.excluded_by(Path::new(prefix)).is_some() {
+                    continue;
                 }
-            } else {
-                (tree, false)
-            };
-
-            tree_to_walk.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
-                if entry.kind() == Some(git2::ObjectType::Blob) {
-                    if let Some(name) = entry.name() {
-                        let path = if dir.is_empty() {
-                            PathBuf::from(name)
-                        } else {
-                            PathBuf::from(dir).join(name)
-                        };
-
-                        // when walking a subtree, paths are relative to that subtree
-                        // prepend the prefix to get the full repository path
-                        let full_path = if is_subtree {
-                            if let Some(prefix) = &self.options.path_prefix {
-                                PathBuf::from(prefix).join(path)
-                            } else {
-                                path
-                            }
-                        } else {
-                            path
-                        };
-
-                        if self.should_include(&full_path).unwrap_or(false) {
-                            files.push(full_path);
-                        }
+                // a missing prefix has nothing to walk, a file is taken as it is
+                let Ok(entry) = tree.get_path(Path::new(prefix)) else {
+                    continue;
+                };
+                match entry.kind() {
+                    Some(git2::ObjectType::Tree) => {
+                        subtrees.push((self.repo.find_tree(entry.id())?, PathBuf::from(prefix)));
                     }
+                    Some(git2::ObjectType::Blob) if self.should_include(Path::new(prefix)) => {
+                        files.push(PathBuf::from(prefix));
+                    }
+                    _ => {}
                 }
-                git2::TreeWalkResult::Ok
-            })?;
+            }
+            if prefixes.is_empty() {
+                subtrees.push((tree, PathBuf::new()));
+            }
+
+            for (subtree, root) in subtrees {
+                subtree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+                    let Some(name) = entry.name() else {
+                        return git2::TreeWalkResult::Ok;
+                    };
+                    // paths in a subtree are relative to it
+                    let full_path = root.join(dir).join(name);
+                    // excluded directories are pruned, not descended into
+                    if self.options.excluded_by(&full_path).is_some() {
+                        return git2::TreeWalkResult::Skip;
+                    }
+                    if entry.kind() == Some(git2::ObjectType::Tree)
+                        && self.filter.excludes_dir(&full_path)
+                    {
+                        return git2::TreeWalkResult::Skip;
+                    }
+                    if entry.kind() == Some(git2::ObjectType::Blob)
+                        && self.should_include(&full_path)
+                    {
+                        files.push(full_path);
+                    }
+                    git2::TreeWalkResult::Ok
+                })?;
+            }
         }
 
         // handle untracked files
@@ -284,12 +1257,10 @@ impl Ingester {
                 if status.status().contains(Status::WT_NEW) {
                     if let Some(path) = status.path() {
                         let path_buf = PathBuf::from(path);
-                        if let Some(prefix) = &self.options.path_prefix {
-                            if !path.starts_with(prefix) {
-                                continue;
-                            }
+                        if !self.in_path_prefix(&path_buf) {
+                            continue;
                         }
-                        if self.should_include(&path_buf).unwrap_or(false) {
+                        if self.should_include(&path_buf) {
                             files.push(path_buf);
                         }
                     }
@@ -297,17 +1268,92 @@ impl Ingester {
             }
         }
 
+        if has_commits {
+            let all = self.collect_all_repository_files()?;
+            files.extend(
+                self.paired_files(&files, &all)
+                    .into_iter()
+                    .map(|(file, _)| file),
+            );
+        }
+
         files.sort();
         files.dedup();
         Ok(files)
     }
 
+    /// the tests or sources `with_tests` and `with_sources` add to
+    /// `included`, each with the file it pairs with; presets, --ext and
+    /// includes do not apply, user excludes and the path prefix still do
+    fn paired_files(
+        &self,
+        included: &[PathBuf],
+        candidates: &[PathBuf],
+    ) -> Vec<(PathBuf, PathBuf)> {
+        use crate::FilterDecision;
+
+        if !self.options.with_tests && !self.options.with_sources {
+            return Vec::new();
+        }
+        let included: Vec<String> = included
+            .iter()
+            .map(|f| f.to_string_lossy().into_owned())
+            .collect();
+        let candidates: Vec<String> = candidates
+            .iter()
+            .filter(|file| self.in_path_prefix(file))
+            .filter(|file| {
+                !matches!(
+                    self.filter.explain(file),
+                    FilterDecision::ExcludedGitDir | FilterDecision::ExcludedByPattern(_)
+                )
+            })
+            .map(|f| f.to_string_lossy().into_owned())
+            .collect();
+
+        crate::pairing::paired_files(
+            included.iter().map(String::as_str),
+            candidates.iter().map(String::as_str),
+            self.options.with_tests,
+            self.options.with_sources,
+        )
+        .into_iter()
+        .map(|(file, partner)| (PathBuf::from(file), PathBuf::from(partner)))
+        .collect()
+    }
+
     fn get_current_commit(&self) -> Result<String> {
         let head = self.repo.head()?;
         let commit = head.peel_to_commit()?;
         Ok(commit.id().to_string())
     }
 
+    /// full id of the commit checked out, the one `ingest` reads
+    pub fn head_commit(&self) -> Option<String> {
+        self.get_current_commit().ok()
+    }
+
+    /// full id of the commit `revision` names, resolved like the refs of
+    /// `generate_diff`
+    pub fn resolve_commit(&self, revision: &str) -> Option<String> {
+        let commit = self
+            .resolve_revision(revision)
+            .ok()?
+            .peel_to_commit()
+            .ok()?;
+        Some(commit.id().to_string())
+    }
+
+    /// a branch, tag or commit, also looked up as a remote branch and a tag
+    fn resolve_revision(&self, name: &str) -> Result<git2::Object<'_>> {
+        let repo = &self.repo;
+        repo.revparse_ext(name)
+            .or_else(|_| repo.revparse_ext(&format!("origin/{}", name)))
+            .or_else(|_| repo.revparse_ext(&format!("refs/tags/{}", name)))
+            .map(|(obj, _)| obj)
+            .with_context(|| format!("Failed to resolve reference: {}", name))
+    }
+
     fn fetch_and_cache(&mut self) -> Result<CacheEntry> {
         let workdir = self
             .repo
@@ -322,15 +1368,30 @@ impl Ingester {
 
         eprintln!("→ Indexing {} files...", all_files.len());
 
+        let total_paths = all_files.len() as u64;
+
         // Only store METADATA, never file contents
-        for file_path in all_files {
+        for (i, file_path) in all_files.into_iter().enumerate() {
+            progress::report(ProgressPhase::Index, i as u64 + 1, total_paths);
             let full_path = workdir.join(&file_path);
 
-            if !full_path.exists() || !full_path.is_file() {
+            let Ok(metadata) = std::fs::symlink_metadata(&full_path) else {
+                continue;
+            };
+            // links are resolved when written, with the options of that request
+            if metadata.file_type().is_symlink() {
+                files.push(CachedFile {
+                    path: file_path,
+                    size: 0,
+                    is_binary: false,
+                    symlink: std::fs::read_link(&full_path).ok(),
+                });
+                continue;
+            }
+            if !metadata.is_file() {
                 continue;
             }
 
-            let metadata = std::fs::metadata(&full_path)?;
             total_size += metadata.len();
 
             // Quick check for binary files without loading entire file
@@ -347,13 +1408,17 @@ impl Ingester {
                 path: file_path,
                 size: metadata.len(),
                 is_binary,
+                symlink: None,
             });
         }
 
         let total_files = files.len();
 
         let cache_entry = CacheEntry {
-            repo_url: self.repo.path().to_string_lossy().to_string(),
+            repo_url: self
+                .remote_url
+                .clone()
+                .unwrap_or_else(|| self.repo.path().to_string_lossy().to_string()),
             branch: self
                 .options
                 .branch
@@ -365,7 +1430,7 @@ impl Ingester {
                 total_files,
                 total_size,
                 tree_hash: commit_hash.clone(),
-                cache_version: "2.0.0".to_string(), // Bumped version for streaming cache
+                cache_version: CACHE_VERSION.to_string(),
             },
             created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
             last_accessed: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
@@ -409,126 +1474,263 @@ impl Ingester {
         Ok(files)
     }
 
-    fn filter_cached_files<W: Write>(&self, cache_entry: CacheEntry, output: &mut W) -> Result<()> {
-        let mut processed = 0;
-        let mut filtered_size = 0u64;
-
-        // first pass: collect files that pass filtering for tree structure
+    /// the cached files that pass the filters, plus the files paired with them
+    fn select_cached_files<'a>(&self, files: &'a [CachedFile]) -> Result<Vec<&'a CachedFile>> {
         let mut filtered_files = Vec::new();
 
-        for cached_file in &cache_entry.files {
-            // Apply path_prefix filter first if set
-            if let Some(ref prefix) = self.options.path_prefix {
-                let path_str = cached_file.path.to_string_lossy();
-                // Ensure we are checking directory boundaries properly
-                let prefix_with_slash = if prefix.ends_with("/") {
-                    prefix.to_string()
-                } else {
-                    format!("{}/", prefix)
-                };
-                if !path_str.starts_with(&prefix_with_slash) {
-                    continue;
-                }
+        for cached_file in files {
+            // Apply the path prefixes first if set
+            if !self.in_path_prefix(&cached_file.path) {
+                continue;
             }
 
-            if !self.should_include(&cached_file.path)? {
+            if !self.should_include(&cached_file.path) {
                 continue;
             }
 
-            if cached_file.size > self.options.max_file_size as u64 {
+            // symlinks are sized when written, through their target
+            if cached_file.symlink.is_none() && !self.options.size_in_range(cached_file.size) {
                 continue;
             }
 
             filtered_files.push(cached_file);
         }
 
+        let included: Vec<PathBuf> = filtered_files.iter().map(|f| f.path.clone()).collect();
+        let all: Vec<PathBuf> = files.iter().map(|f| f.path.clone()).collect();
+        let paired: HashSet<PathBuf> = self
+            .paired_files(&included, &all)
+            .into_iter()
+            .map(|(file, _)| file)
+            .collect();
+        if !paired.is_empty() {
+            filtered_files.extend(files.iter().filter(|f| {
+                paired.contains(&f.path)
+                    && (f.symlink.is_some() || self.options.size_in_range(f.size))
+            }));
+            filtered_files.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        Ok(filtered_files)
+    }
+
+    fn filter_cached_files<W: Write>(
+        &self,
+        cache_entry: CacheEntry,
+        output: &mut W,
+    ) -> Result<IngestReport> {
+        let mut report = IngestReport::default();
+        let mut filtered_size = 0u64;
+
+        // first pass: collect files that pass filtering for tree structure
+        let filtered_files = self.select_cached_files(&cache_entry.files)?;
+        let mut filtered_files = crate::importance::keep_important(
+            filtered_files,
+            self.options.max_files,
+            |f| f.path.as_path(),
+            |path| {
+                // links are not read through for ranking either
+                let disk = cache_entry.repo_path.join(path);
+                std::fs::symlink_metadata(&disk)
+                    .ok()?
+                    .is_file()
+                    .then(|| std::fs::read_to_string(&disk).ok())?
+            },
+            &mut report.omitted,
+        );
+
+        let mut output = BufWriter::new(output);
+        if self.options.ownership {
+            self.write_ownership(&cache_entry.repo_path, &mut output)?;
+        }
+        if self.options.docs_first {
+            filtered_files
+                .sort_by_key(|f| !crate::docs::is_key_document(&f.path.to_string_lossy()));
+            let readable = filtered_files
+                .iter()
+                .filter(|f| f.symlink.is_none() && !f.is_binary)
+                .map(|f| (f.path.as_path(), cache_entry.repo_path.join(&f.path)));
+            self.write_documentation(readable, &mut output)?;
+        }
+        if self.options.build_ci {
+            let readable = filtered_files
+                .iter()
+                .filter(|f| f.symlink.is_none() && !f.is_binary && self.in_build_section(&f.path))
+                .map(|f| {
+                    let disk = cache_entry.repo_path.join(&f.path);
+                    (f.path.as_path(), WorkdirEntry::File(disk))
+                });
+            self.write_build_ci(readable, &mut output, &mut report)?;
+        }
+
         // write file tree structure at the start
         let paths: Vec<_> = filtered_files.iter().map(|f| &f.path).collect();
         let tree_structure = crate::generate_tree_from_paths(&paths);
         write!(output, "{}", tree_structure)?;
+        crate::importance::write_omitted(
+            &report.omitted,
+            self.options.max_files.unwrap_or_default(),
+            &mut output,
+        )?;
+        let modified = self.last_modified(&paths);
+        let summarized =
+            self.summarized_groups(filtered_files.iter().map(|f| (f.path.as_path(), f.size)));
+        let mut summaries = BTreeMap::new();
+        let mut limit = TotalLimit::new(&self.options);
 
         // second pass: write file contents
-        for cached_file in filtered_files {
-            // Stream file content from disk - NEVER load into RAM
+        let total = filtered_files.len() as u64;
+        for (i, cached_file) in filtered_files.into_iter().enumerate() {
+            progress::report(ProgressPhase::Write, i as u64 + 1, total);
+            if self.in_build_section(&cached_file.path) {
+                continue;
+            }
+            if let Some(group) = self.summary_group(&cached_file.path, &summarized) {
+                let disk = cache_entry.repo_path.join(&cached_file.path);
+                if let Some(summary) = self.summarize_file(&cached_file.path, &disk) {
+                    summaries
+                        .entry(group)
+                        .or_insert_with(Vec::new)
+                        .push(summary);
+                }
+                continue;
+            }
+            if limit.reached() {
+                report.skipped.push(cached_file.path.clone());
+                continue;
+            }
+            if cached_file.symlink.is_some() {
+                let Some((entry, content)) =
+                    self.read_file(&cache_entry.repo_path, &cached_file.path)
+                else {
+                    continue;
+                };
+                if !limit.admit(&written(
+                    &cached_file.path,
+                    content.as_deref().unwrap_or_default(),
+                )) {
+                    report.skipped.push(cached_file.path.clone());
+                    continue;
+                }
+                let file = self.write_file(
+                    &cached_file.path,
+                    &entry,
+                    content.as_deref(),
+                    &modified,
+                    &mut output,
+                )?;
+                report.files.push(file);
+                continue;
+            }
             let full_path = cache_entry.repo_path.join(&cached_file.path);
-            let mut content = if cached_file.is_binary {
-                "[binary file]".to_string()
+            let path_str = cached_file.path.to_string_lossy();
+            let content = if cached_file.is_binary {
+                self.transform(&path_str, "[binary file]".to_string())
+                    .map(FileContent::Read)
             } else {
-                std::fs::read_to_string(&full_path)
-                    .unwrap_or_else(|_| "[error reading file]".to_string())
+                self.read_content(&path_str, &full_path, "[error reading file]")
             };
-
-            // compress license files to save tokens
-            let path_str = cached_file.path.to_string_lossy();
-            if let Some(compressed) = crate::compress_license(&path_str, &content) {
-                content = compressed;
+            let Some(content) = content else {
+                continue;
+            };
+            let file = written(&cached_file.path, &content);
+            if !limit.admit(&file) {
+                report.skipped.push(cached_file.path.clone());
+                continue;
             }
 
-            writeln!(output, "=== {} ===", cached_file.path.display())?;
-            writeln!(output, "{}", content)?;
+            let header = self.header(
+                &cached_file.path,
+                Blob::File(&full_path),
+                &content,
+                &modified,
+            );
+            writeln!(output, "{header}")?;
+            crate::write_escaped(&mut output, &content)?;
+            writeln!(output)?;
             writeln!(output)?;
 
-            processed += 1;
+            report.files.push(file);
             filtered_size += cached_file.size;
         }
+        self.write_summaries(summaries, &mut output, &mut report)?;
+        limit.write_notice(&report.skipped, &mut output)?;
+        output.flush()?;
 
         eprintln!(
             "→ Filtered: {} files ({:.2} MB) from {} total",
-            processed,
+            report.file_count(),
             filtered_size as f64 / 1_048_576.0,
             cache_entry.metadata.total_files
         );
 
-        Ok(())
+        Ok(report)
     }
 
     pub fn get_filter_stats(&self) -> Result<FilterStats> {
+        let mut stats = FilterStats::default();
+
+        if let Some(index) = self.cached_index() {
+            // sizes come from the index, nothing is walked or stat'ed
+            stats.total_files = index.file_count();
+            for file in index.files()? {
+                let file = file?;
+                self.count_filtered(&mut stats, &file.path, file.size);
+            }
+            return Ok(stats);
+        }
+
         let workdir = self
             .repo
             .workdir()
             .context("Repository has no working directory")?;
         let all_files = self.collect_all_repository_files()?;
-
-        let mut stats = FilterStats {
-            total_files: all_files.len(),
-            ..Default::default()
-        };
         stats.total_files = all_files.len();
 
         for file in all_files {
             let full_path = workdir.join(&file);
 
-            if let Ok(metadata) = std::fs::metadata(&full_path) {
-                stats.total_size += metadata.len();
-
-                if self.should_include(&file)? {
-                    stats.included_files += 1;
-                    stats.included_size += metadata.len();
-                } else {
-                    stats.excluded_files += 1;
-                    stats.excluded_size += metadata.len();
-                }
+            if let Ok(metadata) = std::fs::symlink_metadata(&full_path) {
+                self.count_filtered(&mut stats, &file, metadata.len());
             }
         }
 
         Ok(stats)
     }
 
-    pub fn generate_diff(&self, base: &str, head: &str, context_lines: Option<u32>) -> Result<String> {
+    fn count_filtered(&self, stats: &mut FilterStats, path: &Path, size: u64) {
+        stats.total_size += size;
+        if self.should_include(path) {
+            stats.included_files += 1;
+            stats.included_size += size;
+        } else {
+            stats.excluded_files += 1;
+            stats.excluded_size += size;
+        }
+    }
+
+    /// the cache entry of this checkout when it was indexed at the current
+    /// commit, opened without reading its file list
+    fn cached_index(&self) -> Option<CachedIndex> {
+        let (cache, key) = (self.cache.as_ref()?, self.cache_key.as_ref()?);
+        let commit = self.get_current_commit().ok()?;
+        if cache.check_commit(key, &commit) != CacheCommitStatus::Match {
+            return None;
+        }
+        cache.open(key).ok().flatten()
+    }
+
+    pub fn generate_diff(
+        &self,
+        base: &str,
+        head: &str,
+        context_lines: Option<u32>,
+    ) -> Result<String> {
         let repo = &self.repo;
 
         // Try to resolve references (branches, tags, or commit hashes)
         // refs should already be fetched by clone_for_compare
-        let resolve_ref = |ref_name: &str| -> Result<git2::Object> {
-            repo.revparse_ext(ref_name)
-                .or_else(|_| repo.revparse_ext(&format!("origin/{}", ref_name)))
-                .or_else(|_| repo.revparse_ext(&format!("refs/tags/{}", ref_name)))
-                .map(|(obj, _)| obj)
-                .with_context(|| format!("Failed to resolve reference: {}", ref_name))
-        };
-
-        let base_object = resolve_ref(base)?;
-        let head_object = resolve_ref(head)?;
+        let base_object = self.resolve_revision(base)?;
+        let head_object = self.resolve_revision(head)?;
 
         let base_commit = base_object.peel_to_commit()?;
         let head_commit = head_object.peel_to_commit()?;
@@ -564,14 +1766,20 @@ impl Ingester {
         Ok(output)
     }
 
-    pub fn generate_commit_diff(&self, commit_sha: &str, context_lines: Option<u32>) -> Result<String> {
+    pub fn generate_commit_diff(
+        &self,
+        commit_sha: &str,
+        context_lines: Option<u32>,
+    ) -> Result<String> {
         let repo = &self.repo;
 
         // find the commit - use revparse to support short SHAs
-        let object = repo.revparse_single(commit_sha)
+        let object = repo
+            .revparse_single(commit_sha)
             .with_context(|| format!("Failed to find commit: {}", commit_sha))?;
 
-        let commit = object.peel_to_commit()
+        let commit = object
+            .peel_to_commit()
             .with_context(|| format!("Not a commit: {}", commit_sha))?;
 
         let commit_tree = commit.tree()?;
@@ -627,28 +1835,40 @@ impl Ingester {
         let repo = &self.repo;
 
         // gitlab MRs use refs/merge-requests/N/head
-        let mut remote = repo.find_remote("origin")
+        let mut remote = repo
+            .find_remote("origin")
             .context("Failed to find origin remote")?;
 
         let mr_ref = format!("refs/merge-requests/{}/head", mr_number);
 
-        eprintln!("-> Fetching MR !{} and base branches from GitLab...", mr_number);
+        eprintln!(
+            "-> Fetching MR !{} and base branches from GitLab...",
+            mr_number
+        );
 
         // fetch MR ref
         let mr_refspec = format!("+{}:{}", mr_ref, mr_ref);
-        remote.fetch(&[&mr_refspec], None, None)
+        crate::network::ensure_online(remote.url().unwrap_or_default())?;
+        let mut fetch_opts = git2::FetchOptions::new();
+        fetch_opts.proxy_options(crate::network::proxy_options(
+            remote.url().unwrap_or_default(),
+        ));
+        remote
+            .fetch(&[&mr_refspec], Some(&mut fetch_opts), None)
             .context("Failed to fetch MR ref from GitLab")?;
 
         // fetch common base branches
         for branch in &["main", "master", "develop"] {
             let branch_refspec = format!("+refs/heads/{}:refs/remotes/origin/{}", branch, branch);
-            let _ = remote.fetch(&[&branch_refspec], None, None);
+            let _ = remote.fetch(&[&branch_refspec], Some(&mut fetch_opts), None);
         }
 
         // get the MR head commit
-        let mr_ref_obj = repo.find_reference(&mr_ref)
+        let mr_ref_obj = repo
+            .find_reference(&mr_ref)
             .context("Failed to find MR ref after fetch")?;
-        let mr_commit = mr_ref_obj.peel_to_commit()
+        let mr_commit = mr_ref_obj
+            .peel_to_commit()
             .context("Failed to peel MR ref to commit")?;
 
         // find base branch
@@ -660,9 +1880,15 @@ impl Ingester {
 
             if let Ok((obj, _)) = repo.revparse_ext(&origin_ref) {
                 if let Ok(branch_commit) = obj.peel_to_commit() {
-                    eprintln!("-> Found base branch {} at {}", base_name, branch_commit.id());
-
-                    let base_commit = if let Ok(merge_base_oid) = repo.merge_base(branch_commit.id(), mr_commit.id()) {
+                    eprintln!(
+                        "-> Found base branch {} at {}",
+                        base_name,
+                        branch_commit.id()
+                    );
+
+                    let base_commit = if let Ok(merge_base_oid) =
+                        repo.merge_base(branch_commit.id(), mr_commit.id())
+                    {
                         if let Ok(merge_base_commit) = repo.find_commit(merge_base_oid) {
                             eprintln!("-> Using merge base {}", merge_base_oid);
                             merge_base_commit
@@ -679,8 +1905,8 @@ impl Ingester {
             }
         }
 
-        let (base_name, base_commit) = base_info
-            .context("Could not find any base branch (main/master/develop)")?;
+        let (base_name, base_commit) =
+            base_info.context("Could not find any base branch (main/master/develop)")?;
 
         let base_tree = base_commit.tree()?;
         let mr_tree = mr_commit.tree()?;
@@ -689,7 +1915,8 @@ impl Ingester {
         if let Some(ctx) = context_lines {
             diff_opts.context_lines(ctx);
         }
-        let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&mr_tree), Some(&mut diff_opts))?;
+        let diff =
+            repo.diff_tree_to_tree(Some(&base_tree), Some(&mr_tree), Some(&mut diff_opts))?;
 
         let mut output = String::new();
         output.push_str(&format!("# Merge Request !{}\n\n", mr_number));
@@ -718,28 +1945,40 @@ impl Ingester {
         let repo = &self.repo;
 
         // Fetch the PR ref and common base branches from GitHub
-        let mut remote = repo.find_remote("origin")
+        let mut remote = repo
+            .find_remote("origin")
             .context("Failed to find origin remote")?;
 
         let pr_ref = format!("refs/pull/{}/head", pr_number);
 
-        eprintln!("→ Fetching PR #{} and base branches from GitHub...", pr_number);
+        eprintln!(
+            "→ Fetching PR #{} and base branches from GitHub...",
+            pr_number
+        );
 
         // Fetch PR ref
         let pr_refspec = format!("+{}:{}", pr_ref, pr_ref);
-        remote.fetch(&[&pr_refspec], None, None)
+        crate::network::ensure_online(remote.url().unwrap_or_default())?;
+        let mut fetch_opts = git2::FetchOptions::new();
+        fetch_opts.proxy_options(crate::network::proxy_options(
+            remote.url().unwrap_or_default(),
+        ));
+        remote
+            .fetch(&[&pr_refspec], Some(&mut fetch_opts), None)
             .context("Failed to fetch PR ref from GitHub")?;
 
         // Fetch common base branches (ignore errors if they don't exist)
         for branch in &["main", "master", "develop"] {
             let branch_refspec = format!("+refs/heads/{}:refs/remotes/origin/{}", branch, branch);
-            let _ = remote.fetch(&[&branch_refspec], None, None);
+            let _ = remote.fetch(&[&branch_refspec], Some(&mut fetch_opts), None);
         }
 
         // Get the PR head commit
-        let pr_ref_obj = repo.find_reference(&pr_ref)
+        let pr_ref_obj = repo
+            .find_reference(&pr_ref)
             .context("Failed to find PR ref after fetch")?;
-        let pr_commit = pr_ref_obj.peel_to_commit()
+        let pr_commit = pr_ref_obj
+            .peel_to_commit()
             .context("Failed to peel PR ref to commit")?;
 
         // Find a base branch and use merge base if available, otherwise use branch HEAD
@@ -751,10 +1990,16 @@ impl Ingester {
 
             if let Ok((obj, _)) = repo.revparse_ext(&origin_ref) {
                 if let Ok(branch_commit) = obj.peel_to_commit() {
-                    eprintln!("→ Found base branch {} at {}", base_name, branch_commit.id());
+                    eprintln!(
+                        "→ Found base branch {} at {}",
+                        base_name,
+                        branch_commit.id()
+                    );
 
                     // Try to find merge base, fall back to branch HEAD
-                    let base_commit = if let Ok(merge_base_oid) = repo.merge_base(branch_commit.id(), pr_commit.id()) {
+                    let base_commit = if let Ok(merge_base_oid) =
+                        repo.merge_base(branch_commit.id(), pr_commit.id())
+                    {
                         if let Ok(merge_base_commit) = repo.find_commit(merge_base_oid) {
                             eprintln!("→ Using merge base {}", merge_base_oid);
                             merge_base_commit
@@ -773,8 +2018,8 @@ impl Ingester {
             }
         }
 
-        let (base_name, base_commit) = base_info
-            .context("Could not find any base branch (main/master/develop)")?;
+        let (base_name, base_commit) =
+            base_info.context("Could not find any base branch (main/master/develop)")?;
 
         let base_tree = base_commit.tree()?;
         let pr_tree = pr_commit.tree()?;
@@ -783,7 +2028,8 @@ impl Ingester {
         if let Some(ctx) = context_lines {
             diff_opts.context_lines(ctx);
         }
-        let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&pr_tree), Some(&mut diff_opts))?;
+        let diff =
+            repo.diff_tree_to_tree(Some(&base_tree), Some(&pr_tree), Some(&mut diff_opts))?;
 
         let mut output = String::new();
         output.push_str(&format!("# Pull Request #{}\n\n", pr_number));
@@ -808,21 +2054,63 @@ impl Ingester {
         Ok(output)
     }
 
+    /// the branch checked out, None on a detached head
+    pub fn head_branch(&self) -> Option<String> {
+        let head = self.repo.head().ok()?;
+        head.is_branch()
+            .then(|| head.shorthand().map(String::from))
+            .flatten()
+    }
+
+    /// the default branch of the repository: what origin/HEAD points at,
+    /// else the branch checked out when no other branch was asked for,
+    /// which a clone takes from the remote HEAD
+    pub fn default_branch(&self) -> Option<String> {
+        let origin_head = self
+            .repo
+            .find_reference("refs/remotes/origin/HEAD")
+            .ok()
+            .and_then(|r| r.symbolic_target().map(String::from))
+            .and_then(|target| {
+                target
+                    .strip_prefix("refs/remotes/origin/")
+                    .map(String::from)
+            });
+        origin_head.or_else(|| {
+            self.options
+                .branch
+                .is_none()
+                .then(|| self.head_branch())
+                .flatten()
+        })
+    }
+
     pub fn get_metadata(&self) -> Result<RepositoryMetadata> {
         let repo = &self.repo;
 
-        let default_branch = repo
-            .head()
-            .ok()
-            .and_then(|h| h.shorthand().map(String::from))
-            .unwrap_or_else(|| "main".to_string());
+        let default_branch = self
+            .default_branch()
+            .or_else(|| self.head_branch())
+            .unwrap_or_else(|| "HEAD".to_string());
 
+        // local branches plus the ones the clone fetched from origin
         let mut branches = Vec::new();
-        for (branch, _) in (repo.branches(Some(git2::BranchType::Local))?).flatten() {
-            if let Ok(Some(name)) = branch.name() {
+        for (branch, kind) in (repo.branches(None)?).flatten() {
+            let Ok(Some(name)) = branch.name() else {
+                continue;
+            };
+            let name = match kind {
+                git2::BranchType::Local => name,
+                git2::BranchType::Remote => match name.strip_prefix("origin/") {
+                    Some(name) if name != "HEAD" => name,
+                    _ => continue,
+                },
+            };
+            if !branches.iter().any(|b| b == name) {
                 branches.push(name.to_string());
             }
         }
+        branches.sort();
 
         let remote_url = repo
             .find_remote("origin")
@@ -841,10 +2129,17 @@ impl Ingester {
                 )
             });
 
+        // the checked out files, without the git directory
         let size = repo.workdir().and_then(|w| {
             walkdir::WalkDir::new(w)
                 .into_iter()
                 .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter(|e| {
+                    !e.path()
+                        .strip_prefix(w)
+                        .is_ok_and(|p| p.starts_with(".git"))
+                })
                 .filter_map(|e| e.metadata().ok())
                 .map(|m| m.len())
                 .reduce(|a, b| a + b)
@@ -861,38 +2156,43 @@ impl Ingester {
     }
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
-pub struct FilterStats {
-    pub total_files: usize,
-    pub included_files: usize,
-    pub excluded_files: usize,
-    pub total_size: u64,
-    pub included_size: u64,
-    pub excluded_size: u64,
-    pub excluded_by_filter: usize,
-}
-
-impl FilterStats {
-    pub fn inclusion_rate(&self) -> f64 {
-        if self.total_files == 0 {
-            0.0
-        } else {
-            self.included_files as f64 / self.total_files as f64
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_inside() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        std::fs::write(root.join(".git/config"), "[remote]").unwrap();
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        std::os::unix::fs::symlink("a.rs", root.join("b.rs")).unwrap();
+        std::os::unix::fs::symlink(".git/config", root.join("config")).unwrap();
+
+        assert!(resolve_inside(root, &root.join("b.rs")).is_some());
+        assert!(resolve_inside(root, &root.join("config")).is_none());
     }
 
-    pub fn size_reduction(&self) -> f64 {
-        if self.total_size == 0 {
-            0.0
-        } else {
-            self.excluded_size as f64 / self.total_size as f64
-        }
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_blob_matches_git() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() {}").unwrap();
+        std::os::unix::fs::symlink("a.rs", dir.path().join("b.rs")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.rs")).unwrap();
+        index.add_path(Path::new("b.rs")).unwrap();
+        let staged = |path: &str| index.get_path(Path::new(path), 0).unwrap().id;
+
+        let link = WorkdirEntry::Symlink {
+            target: PathBuf::from("a.rs"),
+            inline: Some(dir.path().join("a.rs")),
+        };
+        assert_eq!(link.blob().id(), Some(staged("b.rs")));
+        let file = WorkdirEntry::File(dir.path().join("a.rs"));
+        assert_eq!(file.blob().id(), Some(staged("a.rs")));
     }
 }
-
-pub trait IngestionCallback: Send + Sync {
-    fn on_progress(&mut self, _stage: &str, _message: &str) {}
-    fn on_file(&mut self, _path: &Path, _content: &str) {}
-    fn on_complete(&mut self, _files: usize, _bytes: usize) {}
-    fn on_error(&mut self, _error: &str) {}
-}
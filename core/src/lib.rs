@@ -1,23 +1,100 @@
+#[cfg(feature = "git")]
+pub mod auth;
+pub mod build;
 pub mod cache;
+pub mod chunking;
+pub mod docs;
+pub mod drift;
+pub mod extract;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod filtering;
+pub mod focus;
+pub mod header;
+pub mod importance;
+#[cfg(feature = "git")]
 pub mod ingester;
+pub mod license;
+pub mod memory;
+#[cfg(feature = "git")]
+pub mod network;
+pub mod options;
+pub mod ownership;
+pub mod pairing;
 pub mod parser;
-
+pub mod patch;
+pub mod pattern;
+pub mod preflight;
+pub mod progress;
+pub mod relevance;
+#[cfg(feature = "git")]
+pub mod scratch;
+pub mod search;
+#[cfg(feature = "async")]
+pub mod sink;
+pub mod suggest;
+pub mod summary;
+pub mod transform;
+pub mod tree;
+
+#[cfg(feature = "git")]
+pub use auth::{home_dir, set_passphrase_prompt, CredentialProvider};
 pub use cache::{
-    CacheCommitStatus, CacheEntry, CacheManager, CacheStats, CachedFile, RepositoryCache,
+    configure_cache_dir, CacheBackend, CacheCommitStatus, CacheEntry, CacheEntryHeader,
+    CacheEntrySummary, CacheManager, CacheStats, CachedFile, CachedIndex, DiskBackend,
+    MemoryBackend, RepositoryCache,
+};
+pub use chunking::{chunk_output, Chunk, ChunkOptions};
+pub use drift::{diff_outputs, fingerprint, ContextDrift};
+pub use extract::{extract_files, ExtractedBody, ExtractedFile};
+pub use filtering::{
+    get_default_excludes, get_excludes_for_preset, get_includes_for_preset, is_path_included,
+    FilterConfig, FilterDecision, FilterExplainer, FilterPreset,
+};
+pub use focus::{cursor_excerpt, dependency_closure, FocusOptions, FocusedFile, ImportIndex};
+pub use header::{format_header, FileDetail, HeaderDetail};
+pub use importance::{rank_by_importance, select_important};
+#[cfg(feature = "git")]
+pub use ingester::Ingester;
+pub use license::{compress_license, identify_license, license_expression, License};
+pub use memory::MemoryIngester;
+#[cfg(feature = "git")]
+pub use network::{configure_network, NetworkConfig};
+pub use options::{
+    FilterStats, IngestOptions, IngestOptionsBuilder, IngestReport, IngestedFile, IngestionCallback,
 };
-pub use filtering::{get_default_excludes, get_excludes_for_preset, FilterConfig, FilterPreset};
-pub use ingester::{FilterStats, IngestOptions, Ingester, IngestionCallback};
 pub use parser::{
-    normalize_source_url, parse_github_url, validate_github_name, GitHubUrlType, ParsedGitHubUrl,
+    normalize_source_url, parse_compare_spec, parse_github_url, parse_gitlab_url, split_revision,
+    validate_github_name, validate_path_prefix, validate_ref_name, validate_write_path,
+    GitHubUrlType, ParsedGitHubUrl,
 };
-
+pub use patch::{apply_hunks, parse_patch, FilePatch, Hunk, HunkLine, PATCH_INSTRUCTIONS};
+pub use pattern::{pattern_matches, PatternSyntax};
+#[cfg(feature = "git")]
+pub use preflight::preflight_refs;
+pub use preflight::{check_ref, RefNotFound};
+pub use progress::{set_progress_handler, ProgressPhase};
+#[cfg(feature = "embeddings-api")]
+pub use relevance::OpenAiEmbedder;
+pub use relevance::{EmbeddingBackend, LexicalEmbedder, RankedFile, RelevanceOptions};
+#[cfg(feature = "git")]
+pub use scratch::{configure_scratch, scratch_usage, sweep_scratch, ScratchConfig, ScratchDir};
+pub use search::{search_output, FileMatches, SearchMatch, SearchOptions};
+#[cfg(feature = "async")]
+pub use sink::ingest_async;
+pub use suggest::{suggest_excludes, ExcludeSuggestion};
+pub use transform::{ContentTransform, Transform, TransformPipeline};
+pub use tree::{parse_tree, render_tree};
+
+#[cfg(feature = "git")]
 use anyhow::Result;
+#[cfg(feature = "git")]
 use git2::Repository;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+#[cfg(feature = "git")]
 use std::io::IsTerminal;
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepositoryMetadata {
@@ -39,66 +116,30 @@ pub fn is_remote_url(source: &str) -> bool {
         || source.starts_with("https://gist.githubusercontent.com/")
 }
 
-/// clone a bare repository and fetch only specific refs for comparison
-pub fn clone_for_compare(url: &str, base_ref: &str, head_ref: &str) -> Result<Repository> {
+#[cfg(feature = "git")]
+/// init a bare repository in `path` and fetch only specific refs for comparison
+pub fn clone_for_compare(
+    url: &str,
+    base_ref: &str,
+    head_ref: &str,
+    path: &Path,
+) -> Result<Repository> {
     if !is_remote_url(url) {
         return Err(anyhow::anyhow!("Invalid or unsafe URL"));
     }
-
-    let temp_id = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-    let path = std::env::temp_dir().join(format!("githem-compare-{temp_id}"));
+    network::ensure_online(url)?;
+    for name in [base_ref, head_ref] {
+        validate_ref_name(name).map_err(|e| anyhow::anyhow!(e))?;
+    }
 
     // create bare repository (no working tree, minimal disk usage)
-    let repo = Repository::init_bare(&path)?;
+    let repo = Repository::init_bare(path)?;
 
     let mut remote = repo.remote("origin", url)?;
 
     let mut fetch_opts = git2::FetchOptions::new();
-    let mut callbacks = git2::RemoteCallbacks::new();
-
-    callbacks.credentials(|url, username_from_url, allowed_types| {
-        if !is_remote_url(url) {
-            return Err(git2::Error::from_str(
-                "Invalid URL for credential authentication",
-            ));
-        }
-
-        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
-            if let Ok(cred) = git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git")) {
-                return Ok(cred);
-            }
-
-            if let Ok(home) = std::env::var("HOME") {
-                let ssh_dir = Path::new(&home).join(".ssh");
-                if ssh_dir.exists() {
-                    let private_key = ssh_dir.join("id_ed25519");
-                    let public_key = ssh_dir.join("id_ed25519.pub");
-
-                    if private_key.exists() && public_key.exists() {
-                        return git2::Cred::ssh_key(
-                            username_from_url.unwrap_or("git"),
-                            Some(&public_key),
-                            &private_key,
-                            None,
-                        );
-                    }
-                }
-            }
-        }
-
-        if allowed_types.contains(git2::CredentialType::DEFAULT) && url.starts_with("https://") {
-            return git2::Cred::default();
-        }
-
-        Err(git2::Error::from_str(
-            "No secure authentication method available",
-        ))
-    });
-
-    fetch_opts.remote_callbacks(callbacks);
+    fetch_opts.remote_callbacks(quota_callbacks());
+    fetch_opts.proxy_options(network::proxy_options(url));
     fetch_opts.depth(1);
     fetch_opts.download_tags(git2::AutotagOption::None);
 
@@ -120,197 +161,288 @@ pub fn clone_for_compare(url: &str, base_ref: &str, head_ref: &str) -> Result<Re
     Ok(repo)
 }
 
+#[cfg(feature = "git")]
 /// clone a repository with full history for commit diffing
 /// unlike clone_repository, this doesn't use depth=1 because we need
 /// the full history to resolve short SHAs and access parent commits
-pub fn clone_for_commit(url: &str, _commit_sha: &str) -> Result<Repository> {
+pub fn clone_for_commit(url: &str, _commit_sha: &str, path: &Path) -> Result<Repository> {
     if !is_remote_url(url) {
         return Err(anyhow::anyhow!("Invalid or unsafe URL"));
     }
-
-    let temp_id = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-    let path = std::env::temp_dir().join(format!("githem-commit-{temp_id}"));
+    network::ensure_online(url)?;
 
     let mut fetch_opts = git2::FetchOptions::new();
-    let mut callbacks = git2::RemoteCallbacks::new();
-
-    callbacks.credentials(|url, username_from_url, allowed_types| {
-        if !is_remote_url(url) {
-            return Err(git2::Error::from_str(
-                "Invalid URL for credential authentication",
-            ));
-        }
-
-        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
-            if let Ok(cred) = git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git")) {
-                return Ok(cred);
-            }
-
-            if let Ok(home) = std::env::var("HOME") {
-                let ssh_dir = Path::new(&home).join(".ssh");
-                if ssh_dir.exists() {
-                    let private_key = ssh_dir.join("id_ed25519");
-                    let public_key = ssh_dir.join("id_ed25519.pub");
-
-                    if private_key.exists() && public_key.exists() {
-                        return git2::Cred::ssh_key(
-                            username_from_url.unwrap_or("git"),
-                            Some(&public_key),
-                            &private_key,
-                            None,
-                        );
-                    }
-                }
-            }
-        }
-
-        if allowed_types.contains(git2::CredentialType::DEFAULT) && url.starts_with("https://") {
-            return git2::Cred::default();
-        }
-
-        Err(git2::Error::from_str(
-            "No secure authentication method available",
-        ))
-    });
-
-    fetch_opts.remote_callbacks(callbacks);
+    fetch_opts.remote_callbacks(quota_callbacks());
+    fetch_opts.proxy_options(network::proxy_options(url));
     // no depth limit - we need full history for commit lookups
     fetch_opts.download_tags(git2::AutotagOption::None);
 
     let mut builder = git2::build::RepoBuilder::new();
     builder.fetch_options(fetch_opts);
 
-    let repo = builder.clone(url, &path)?;
+    let repo = builder.clone(url, path).map_err(scratch::fetch_error)?;
     Ok(repo)
 }
 
-pub fn clone_repository(url: &str, branch: Option<&str>) -> Result<Repository> {
-    if !is_remote_url(url) {
-        return Err(anyhow::anyhow!("Invalid or unsafe URL"));
+#[cfg(feature = "git")]
+/// credentials plus the scratch size limit, for fetches without progress output
+fn quota_callbacks<'a>() -> git2::RemoteCallbacks<'a> {
+    let mut callbacks = auth::remote_callbacks();
+    if scratch::max_bytes() > 0 {
+        callbacks.transfer_progress(|stats| !scratch::over_quota(stats.received_bytes()));
     }
+    callbacks
+}
 
-    let temp_id = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-    let path = std::env::temp_dir().join(format!("githem-{temp_id}"));
-
+#[cfg(feature = "git")]
+fn shallow_fetch_options<'a>(url: &str) -> git2::FetchOptions<'a> {
     let mut fetch_opts = git2::FetchOptions::new();
-    let mut callbacks = git2::RemoteCallbacks::new();
+    let mut callbacks = auth::remote_callbacks();
 
-    callbacks.credentials(|url, username_from_url, allowed_types| {
-        if !is_remote_url(url) {
-            return Err(git2::Error::from_str(
-                "Invalid URL for credential authentication",
-            ));
-        }
-
-        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
-            if let Ok(cred) = git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git")) {
-                return Ok(cred);
+    let show_progress = std::io::stderr().is_terminal() || progress::has_handler();
+    if show_progress || scratch::max_bytes() > 0 {
+        callbacks.transfer_progress(move |stats| {
+            if scratch::over_quota(stats.received_bytes()) {
+                return false;
             }
-
-            if let Ok(home) = std::env::var("HOME") {
-                let ssh_dir = Path::new(&home).join(".ssh");
-                if ssh_dir.exists() {
-                    let private_key = ssh_dir.join("id_ed25519");
-                    let public_key = ssh_dir.join("id_ed25519.pub");
-
-                    if private_key.exists() && public_key.exists() {
-                        return git2::Cred::ssh_key(
-                            username_from_url.unwrap_or("git"),
-                            Some(&public_key),
-                            &private_key,
-                            None,
-                        );
-                    }
-                }
+            if !show_progress {
+                return true;
             }
-        }
-
-        if allowed_types.contains(git2::CredentialType::DEFAULT) && url.starts_with("https://") {
-            return git2::Cred::default();
-        }
-
-        Err(git2::Error::from_str(
-            "No secure authentication method available",
-        ))
-    });
-
-    if std::io::stderr().is_terminal() {
-        callbacks.transfer_progress(|stats| {
-            if stats.total_objects() > 0 {
-                eprint!(
-                    "\rReceiving objects: {}% ({}/{})",
-                    (100 * stats.received_objects()) / stats.total_objects(),
-                    stats.received_objects(),
-                    stats.total_objects()
-                );
+            let (received, total) = (stats.received_objects(), stats.total_objects());
+            if progress::report(ProgressPhase::Clone, received as u64, total as u64) {
+                return true;
+            }
+            if let Some(percent) = (100 * received).checked_div(total) {
+                eprint!("\rReceiving objects: {percent}% ({received}/{total})");
             }
             true
         });
     }
 
     fetch_opts.remote_callbacks(callbacks);
+    fetch_opts.proxy_options(network::proxy_options(url));
     fetch_opts.depth(1);
     fetch_opts.download_tags(git2::AutotagOption::None);
+    fetch_opts
+}
+
+#[cfg(feature = "git")]
+/// shallow clone into a specific directory
+pub fn clone_repository_into(url: &str, branch: Option<&str>, path: &Path) -> Result<Repository> {
+    if !is_remote_url(url) {
+        return Err(anyhow::anyhow!("Invalid or unsafe URL"));
+    }
+    network::ensure_online(url)?;
+    if let Some(branch) = branch {
+        validate_ref_name(branch).map_err(|e| anyhow::anyhow!(e))?;
+    }
 
     let mut builder = git2::build::RepoBuilder::new();
-    builder.fetch_options(fetch_opts);
+    builder.fetch_options(shallow_fetch_options(url));
 
     if let Some(branch) = branch {
         builder.branch(branch);
     }
 
-    let repo = builder.clone(url, &path)?;
+    let repo = builder.clone(url, path).map_err(scratch::fetch_error)?;
+
+    if std::io::stderr().is_terminal() && !progress::has_handler() {
+        eprintln!();
+    }
+
+    Ok(repo)
+}
+
+#[cfg(feature = "git")]
+/// shallow clone of the first of `branches` into `path`, with the heads of
+/// the others fetched as `origin/<branch>` into the same repository
+pub fn clone_branches(url: &str, branches: &[String], path: &Path) -> Result<Repository> {
+    let (first, rest) = branches
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("No branches to clone"))?;
+    for branch in rest {
+        validate_ref_name(branch).map_err(|e| anyhow::anyhow!(e))?;
+    }
+
+    let repo = clone_repository_into(url, Some(first), path)?;
+    let mut remote = repo.find_remote("origin")?;
+    let refspecs: Vec<String> = rest
+        .iter()
+        .map(|branch| format!("+refs/heads/{branch}:refs/remotes/origin/{branch}"))
+        .collect();
+    if !refspecs.is_empty() {
+        remote
+            .fetch(&refspecs, Some(&mut shallow_fetch_options(url)), None)
+            .map_err(scratch::fetch_error)?;
+        if std::io::stderr().is_terminal() && !progress::has_handler() {
+            eprintln!();
+        }
+    }
+    for branch in rest {
+        repo.find_reference(&format!("refs/remotes/origin/{branch}"))
+            .map_err(|_| anyhow::anyhow!("Branch {} not found in {}", branch, url))?;
+    }
+    drop(remote);
+
+    Ok(repo)
+}
+
+#[cfg(feature = "git")]
+/// bring an existing clone up to the remote head with a shallow fetch
+/// and force the working tree to match it
+pub fn update_repository(repo: &Repository, url: &str, branch: Option<&str>) -> Result<()> {
+    if !is_remote_url(url) {
+        return Err(anyhow::anyhow!("Invalid or unsafe URL"));
+    }
+    network::ensure_online(url)?;
+    if let Some(branch) = branch {
+        validate_ref_name(branch).map_err(|e| anyhow::anyhow!(e))?;
+    }
+
+    let mut remote = repo.find_remote("origin")?;
+    let refspec = match branch {
+        Some(branch) => format!("+refs/heads/{branch}:refs/remotes/origin/{branch}"),
+        None => "HEAD".to_string(),
+    };
+
+    remote
+        .fetch(
+            &[refspec.as_str()],
+            Some(&mut shallow_fetch_options(url)),
+            None,
+        )
+        .map_err(scratch::fetch_error)?;
+
+    if std::io::stderr().is_terminal() && !progress::has_handler() {
+        eprintln!();
+    }
+
+    let target = repo.find_reference("FETCH_HEAD")?.peel_to_commit()?;
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.force().remove_untracked(true);
+    repo.reset(
+        target.as_object(),
+        git2::ResetType::Hard,
+        Some(&mut checkout),
+    )?;
+
+    Ok(())
+}
+
+#[cfg(feature = "git")]
+/// clone a repository into `path`, checked out (detached) at an exact revision
+/// full SHAs are fetched directly with depth 1, anything else needs the
+/// remote branches and tags to resolve against
+pub fn clone_at_revision(url: &str, revision: &str, path: &Path) -> Result<Repository> {
+    if !is_remote_url(url) {
+        return Err(anyhow::anyhow!("Invalid or unsafe URL"));
+    }
+    network::ensure_online(url)?;
+
+    let repo = Repository::init(path)?;
+    let mut remote = repo.remote("origin", url)?;
 
-    if std::io::stderr().is_terminal() {
+    let is_full_sha = revision.len() == 40 && revision.chars().all(|c| c.is_ascii_hexdigit());
+    let pinned = if is_full_sha {
+        let refspec = format!("+{revision}:refs/githem/pinned");
+        remote
+            .fetch(
+                &[refspec.as_str()],
+                Some(&mut shallow_fetch_options(url)),
+                None,
+            )
+            .ok()
+            .and_then(|_| repo.refname_to_id("refs/githem/pinned").ok())
+    } else if validate_ref_name(revision).is_ok() {
+        // tags and branches by name are just as shallow, tags first like git
+        let candidates = [
+            (
+                format!("refs/tags/{revision}"),
+                format!("refs/tags/{revision}"),
+            ),
+            (
+                format!("refs/heads/{revision}"),
+                format!("refs/remotes/origin/{revision}"),
+            ),
+        ];
+        candidates.iter().find_map(|(source, local)| {
+            let refspec = format!("+{source}:{local}");
+            remote
+                .fetch(
+                    &[refspec.as_str()],
+                    Some(&mut shallow_fetch_options(url)),
+                    None,
+                )
+                .ok()?;
+            Some(repo.find_reference(local).ok()?.peel_to_commit().ok()?.id())
+        })
+    } else {
+        None
+    };
+
+    let oid = match pinned {
+        Some(oid) => oid,
+        None => {
+            let mut fetch_opts = shallow_fetch_options(url);
+            // short SHAs may be anywhere in history
+            fetch_opts.depth(0);
+            fetch_opts.download_tags(git2::AutotagOption::All);
+            remote
+                .fetch(
+                    &[
+                        "+refs/heads/*:refs/remotes/origin/*",
+                        "+refs/tags/*:refs/tags/*",
+                    ],
+                    Some(&mut fetch_opts),
+                    None,
+                )
+                .map_err(scratch::fetch_error)?;
+
+            repo.revparse_single(revision)
+                .or_else(|_| repo.revparse_single(&format!("origin/{revision}")))
+                .map_err(|_| anyhow::anyhow!("Revision {} not found in {}", revision, url))?
+                .peel_to_commit()?
+                .id()
+        }
+    };
+
+    if std::io::stderr().is_terminal() && !progress::has_handler() {
         eprintln!();
     }
 
+    drop(remote);
+
+    repo.set_head_detached(oid)?;
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.force();
+    repo.checkout_head(Some(&mut checkout))?;
+
     Ok(repo)
 }
 
+#[cfg(feature = "git")]
 /// quickly fetch the latest commit hash for a branch without cloning
 /// uses git ls-remote which is very fast
 pub fn get_remote_head(url: &str, branch: Option<&str>) -> Result<String> {
     if !is_remote_url(url) {
         return Err(anyhow::anyhow!("Invalid URL"));
     }
+    network::ensure_online(url)?;
 
     let mut remote = git2::Remote::create_detached(url)?;
 
-    let mut callbacks = git2::RemoteCallbacks::new();
-    callbacks.credentials(|url, username_from_url, allowed_types| {
-        if !is_remote_url(url) {
-            return Err(git2::Error::from_str("Invalid URL"));
-        }
-
-        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
-            if let Ok(cred) = git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git")) {
-                return Ok(cred);
-            }
-        }
-
-        if allowed_types.contains(git2::CredentialType::DEFAULT) && url.starts_with("https://") {
-            return git2::Cred::default();
-        }
-
-        Err(git2::Error::from_str("No auth method"))
-    });
+    let callbacks = auth::remote_callbacks();
 
-    remote.connect_auth(git2::Direction::Fetch, Some(callbacks), None)?;
+    remote.connect_auth(
+        git2::Direction::Fetch,
+        Some(callbacks),
+        Some(network::proxy_options(url)),
+    )?;
 
     let refs = remote.list()?;
 
     let target_ref = branch.unwrap_or("HEAD");
-    let search_refs = [
-        format!("refs/heads/{}", target_ref),
-        target_ref.to_string(),
-    ];
+    let search_refs = [format!("refs/heads/{}", target_ref), target_ref.to_string()];
 
     for r in refs {
         let name = r.name();
@@ -329,6 +461,90 @@ pub fn get_remote_head(url: &str, branch: Option<&str>) -> Result<String> {
     Err(anyhow::anyhow!("Could not find ref {}", target_ref))
 }
 
+/// a ref advertised by a remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteRef {
+    /// full name like refs/heads/main, or HEAD
+    pub name: String,
+    pub oid: String,
+    /// the ref a symbolic ref like HEAD points at
+    pub target: Option<String>,
+}
+
+#[cfg(feature = "git")]
+/// every ref the remote advertises, without fetching any objects
+pub fn list_remote_refs(url: &str) -> Result<Vec<RemoteRef>> {
+    if !is_remote_url(url) {
+        return Err(anyhow::anyhow!("Invalid URL"));
+    }
+    network::ensure_online(url)?;
+
+    let mut remote = git2::Remote::create_detached(url)?;
+    remote.connect_auth(
+        git2::Direction::Fetch,
+        Some(auth::remote_callbacks()),
+        Some(network::proxy_options(url)),
+    )?;
+
+    let refs = remote
+        .list()?
+        .iter()
+        .map(|r| RemoteRef {
+            name: r.name().to_string(),
+            oid: r.oid().to_string(),
+            target: r.symref_target().map(str::to_string),
+        })
+        .collect();
+    Ok(refs)
+}
+
+/// a tag advertised by a remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteTag {
+    /// short name like v1.2.3
+    pub name: String,
+    /// the commit tagged, annotated tags peeled
+    pub commit: String,
+}
+
+/// the tags among refs listed by `list_remote_refs`, in listing order
+pub fn tags_of(refs: &[RemoteRef]) -> Vec<RemoteTag> {
+    // annotated tags are listed twice, the peeled entry names the commit
+    let peeled: std::collections::HashMap<&str, &str> = refs
+        .iter()
+        .filter_map(|r| Some((r.name.strip_suffix("^{}")?, r.oid.as_str())))
+        .collect();
+    refs.iter()
+        .filter(|r| !r.name.ends_with("^{}"))
+        .filter_map(|r| {
+            let name = r.name.strip_prefix("refs/tags/")?;
+            let commit = peeled.get(r.name.as_str()).copied().unwrap_or(&r.oid);
+            Some(RemoteTag {
+                name: name.to_string(),
+                commit: commit.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// the branch a remote's HEAD points at, from refs listed by `list_remote_refs`
+pub fn default_branch_of(refs: &[RemoteRef]) -> Option<String> {
+    refs.iter()
+        .find(|r| r.name == "HEAD")?
+        .target
+        .as_deref()?
+        .strip_prefix("refs/heads/")
+        .map(str::to_string)
+}
+
+#[cfg(feature = "git")]
+/// the default branch of a remote repository, without cloning
+pub fn get_remote_default_branch(url: &str) -> Result<String> {
+    default_branch_of(&list_remote_refs(url)?)
+        .ok_or_else(|| anyhow::anyhow!("Remote HEAD of {url} is not a branch"))
+}
+
+#[cfg(feature = "git")]
 pub fn checkout_branch(repo: &Repository, branch_name: &str) -> Result<()> {
     let (object, reference) = repo.revparse_ext(branch_name)?;
     repo.checkout_tree(&object, None)?;
@@ -367,144 +583,118 @@ pub fn estimate_tokens(content: &str) -> usize {
     ((chars as f32 / 3.3 + words as f32 * 0.75) / 2.0 + lines as f32 * 0.1) as usize
 }
 
-/// detect and compress common license files and headers into a single line
-pub fn compress_license(path: &str, content: &str) -> Option<String> {
-    let path_lower = path.to_lowercase();
-    let content_lower = content.to_lowercase();
-
-    // for dedicated license files
-    if path_lower.contains("license") || path_lower.contains("licence")
-        || path_lower.contains("copying") {
-
-        // mit license
-        if (content_lower.contains("permission is hereby granted, free of charge")
-            && content_lower.contains("mit license")) || (content_lower.contains("without restriction")
-            && content_lower.contains("above copyright notice")) {
-            return Some("[mit license - https://opensource.org/licenses/MIT]".to_string());
-        }
-
-        // apache 2.0
-        if content_lower.contains("apache license") && content_lower.contains("version 2.0") {
-            return Some("[apache license 2.0 - https://www.apache.org/licenses/LICENSE-2.0]".to_string());
-        }
-
-        // gpl v3
-        if content_lower.contains("gnu general public license") && content_lower.contains("version 3") {
-            return Some("[gnu gpl v3 - https://www.gnu.org/licenses/gpl-3.0.html]".to_string());
-        }
-
-        // gpl v2
-        if content_lower.contains("gnu general public license") && content_lower.contains("version 2") {
-            return Some("[gnu gpl v2 - https://www.gnu.org/licenses/gpl-2.0.html]".to_string());
-        }
+/// the path of a `=== path ===` file header line, without any detail
+/// written after it (see `HeaderDetail`)
+pub fn file_header(line: &str) -> Option<&str> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let inner = line.strip_prefix("=== ")?.strip_suffix(" ===")?;
+    Some(header::split_detail(inner).0).filter(|path| !path.is_empty())
+}
 
-        // bsd 3-clause
-        if content_lower.contains("redistribution and use in source and binary forms")
-            && content_lower.contains("neither the name of") {
-            return Some("[bsd 3-clause license - https://opensource.org/licenses/BSD-3-Clause]".to_string());
-        }
+/// lines that could be mistaken for a file header, with any `\` escapes
+fn header_like(line: &str) -> bool {
+    line.trim_start_matches('\\').starts_with("=== ")
+}
 
-        // bsd 2-clause
-        if content_lower.contains("redistribution and use in source and binary forms")
-            && !content_lower.contains("neither the name of") {
-            return Some("[bsd 2-clause license - https://opensource.org/licenses/BSD-2-Clause]".to_string());
-        }
+/// prefix content lines starting with `=== ` (or `\=== `, `\\=== `, ...)
+/// with one more `\`, so only the headers githem writes read as headers
+pub fn escape_content(content: &str) -> Cow<'_, str> {
+    if !content.split('\n').any(header_like) {
+        return Cow::Borrowed(content);
+    }
 
-        // isc license
-        if content_lower.contains("isc license") || (content_lower.contains("permission to use, copy, modify")
-            && content_lower.contains("and/or sell copies")) {
-            return Some("[isc license - https://opensource.org/licenses/ISC]".to_string());
+    let mut escaped = String::with_capacity(content.len() + 16);
+    for line in content.split_inclusive('\n') {
+        if header_like(line) {
+            escaped.push('\\');
         }
+        escaped.push_str(line);
+    }
+    Cow::Owned(escaped)
+}
 
-        // mozilla public license
-        if content_lower.contains("mozilla public license") && content_lower.contains("version 2.0") {
-            return Some("[mozilla public license 2.0 - https://www.mozilla.org/MPL/2.0/]".to_string());
+/// `escape_content` written to `output` in runs between the escaped
+/// lines, without building an escaped copy
+pub fn write_escaped<W: std::io::Write>(output: &mut W, content: &str) -> std::io::Result<()> {
+    let mut start = 0;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if header_like(line) {
+            output.write_all(&content.as_bytes()[start..offset])?;
+            output.write_all(b"\\")?;
+            start = offset;
         }
+        offset += line.len();
+    }
+    output.write_all(&content.as_bytes()[start..])
+}
 
-        // lgpl
-        if content_lower.contains("gnu lesser general public license") {
-            return Some("[gnu lgpl - https://www.gnu.org/licenses/lgpl.html]".to_string());
-        }
+/// a content line as it was before `escape_content`
+pub fn unescape_line(line: &str) -> &str {
+    match line.strip_prefix('\\') {
+        Some(rest) if header_like(rest) => rest,
+        _ => line,
+    }
+}
 
-        // agpl
-        if content_lower.contains("gnu affero general public license") {
-            return Some("[gnu agpl - https://www.gnu.org/licenses/agpl.html]".to_string());
-        }
+/// undo `escape_content` on a file body taken from an ingestion output
+pub fn unescape_content(content: &str) -> Cow<'_, str> {
+    if !content
+        .split('\n')
+        .any(|line| unescape_line(line).len() != line.len())
+    {
+        return Cow::Borrowed(content);
+    }
+    Cow::Owned(content.split_inclusive('\n').map(unescape_line).collect())
+}
 
-        // unlicense
-        if content_lower.contains("this is free and unencumbered software released into the public domain") {
-            return Some("[unlicense - public domain - https://unlicense.org/]".to_string());
-        }
+pub fn count_files(content: &str) -> usize {
+    content
+        .lines()
+        .filter(|line| file_header(line).is_some())
+        .count()
+}
 
-        // creative commons
-        if content_lower.contains("creative commons") {
-            return Some("[creative commons license - see repository for details]".to_string());
+/// split an ingestion output into (path, content) pairs, one per file section;
+/// contents are still escaped, see `unescape_content`
+pub fn split_files(content: &str) -> Vec<(&str, &str)> {
+    let mut files = Vec::new();
+    let mut current: Option<(&str, usize)> = None;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        if let Some(path) = file_header(line) {
+            if let Some((path, start)) = current.take() {
+                files.push((path, &content[start..offset]));
+            }
+            current = Some((path, offset + line.len()));
         }
+        offset += line.len();
     }
 
-    None
-}
+    if let Some((path, start)) = current {
+        files.push((path, &content[start..]));
+    }
 
-pub fn count_files(content: &str) -> usize {
-    content.matches("=== ").count()
+    files
 }
 
 pub fn generate_tree(content: &str) -> String {
-    let mut tree = String::new();
-    tree.push_str("Repository structure:\n");
-    for line in content.lines() {
-        if line.starts_with("=== ") && line.ends_with(" ===") {
-            let path = &line[4..line.len() - 4];
-            tree.push_str(&format!("📄 {path}\n"));
-        }
-    }
-    tree
+    let paths: Vec<&str> = content.lines().filter_map(file_header).collect();
+    format!("Repository structure:\n{}", render_tree(&paths, None))
 }
 
 /// generate a tree structure from a list of file paths
 pub fn generate_tree_from_paths<P: AsRef<Path>>(paths: &[P]) -> String {
-    use std::collections::BTreeMap;
-
-    // build directory tree structure
-    let mut tree: BTreeMap<String, Vec<String>> = BTreeMap::new();
-
-    for path in paths {
-        let path_str = path.as_ref().to_string_lossy().to_string();
-        let parts: Vec<&str> = path_str.split('/').collect();
-
-        if parts.len() == 1 {
-            // root level file
-            tree.entry(".".to_string())
-                .or_insert_with(Vec::new)
-                .push(path_str.clone());
-        } else {
-            // file in subdirectory
-            let dir = parts[..parts.len() - 1].join("/");
-            tree.entry(dir)
-                .or_insert_with(Vec::new)
-                .push(path_str.clone());
-        }
-    }
+    generate_tree_with_depth(paths, None)
+}
 
+/// `generate_tree_from_paths` with directories below `max_depth` collapsed
+pub fn generate_tree_with_depth<P: AsRef<Path>>(paths: &[P], max_depth: Option<usize>) -> String {
     let mut output = String::new();
     output.push_str("# File Structure\n\n");
     output.push_str(&format!("Total files: {}\n\n", paths.len()));
-
-    // output directories and their files
-    for (dir, files) in tree {
-        if dir == "." {
-            for file in files {
-                output.push_str(&format!("  {}\n", file));
-            }
-        } else {
-            output.push_str(&format!("  {}/\n", dir));
-            for file in files {
-                let filename = file.split('/').last().unwrap_or(&file);
-                output.push_str(&format!("    {}\n", filename));
-            }
-        }
-    }
-
-    output.push_str("\n");
+    output.push_str(&render_tree(paths, max_depth));
+    output.push('\n');
     output
 }
@@ -0,0 +1,166 @@
+/// A license recognized from its text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct License {
+    /// SPDX identifier, `LicenseRef-*` where there is none
+    pub spdx: &'static str,
+    /// the single line a license file is compressed to
+    pub summary: &'static str,
+}
+
+const fn license(spdx: &'static str, summary: &'static str) -> License {
+    License { spdx, summary }
+}
+
+/// LICENSE, LICENSE-MIT, COPYING.txt, ... judged by the file name, and
+/// never a source file like `license.rs`
+pub fn is_license_file(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path).to_lowercase();
+    let (stem, extension) = name.split_once('.').unwrap_or((&name, ""));
+    if crate::docs::SOURCE_EXTENSIONS.contains(&extension) {
+        return false;
+    }
+    ["license", "licence", "copying", "unlicense"]
+        .iter()
+        .any(|base| match stem.strip_prefix(base) {
+            Some(rest) => rest.is_empty() || rest.starts_with(['-', '_']),
+            None => false,
+        })
+}
+
+/// identify a license text; the gpl variants that quote the gpl are
+/// checked before it
+pub fn identify_license(content: &str) -> Option<License> {
+    let content = content.to_lowercase();
+    let has = |needle: &str| content.contains(needle);
+
+    let found = if has("gnu lesser general public license") && has("version 3") {
+        license(
+            "LGPL-3.0",
+            "[gnu lgpl v3 - https://www.gnu.org/licenses/lgpl-3.0.html]",
+        )
+    } else if has("gnu lesser general public license") {
+        license(
+            "LGPL-2.1",
+            "[gnu lgpl - https://www.gnu.org/licenses/lgpl.html]",
+        )
+    } else if has("gnu affero general public license") {
+        license(
+            "AGPL-3.0",
+            "[gnu agpl - https://www.gnu.org/licenses/agpl.html]",
+        )
+    } else if has("gnu general public license") && has("version 3") {
+        license(
+            "GPL-3.0",
+            "[gnu gpl v3 - https://www.gnu.org/licenses/gpl-3.0.html]",
+        )
+    } else if has("gnu general public license") && has("version 2") {
+        license(
+            "GPL-2.0",
+            "[gnu gpl v2 - https://www.gnu.org/licenses/gpl-2.0.html]",
+        )
+    } else if has("apache license") && has("version 2.0") {
+        license(
+            "Apache-2.0",
+            "[apache license 2.0 - https://www.apache.org/licenses/LICENSE-2.0]",
+        )
+    } else if has("mozilla public license") && has("version 2.0") {
+        license(
+            "MPL-2.0",
+            "[mozilla public license 2.0 - https://www.mozilla.org/MPL/2.0/]",
+        )
+    } else if (has("permission is hereby granted, free of charge") && has("mit license"))
+        || (has("without restriction") && has("above copyright notice"))
+    {
+        license("MIT", "[mit license - https://opensource.org/licenses/MIT]")
+    } else if has("redistribution and use in source and binary forms") && has("neither the name of")
+    {
+        license(
+            "BSD-3-Clause",
+            "[bsd 3-clause license - https://opensource.org/licenses/BSD-3-Clause]",
+        )
+    } else if has("redistribution and use in source and binary forms") {
+        license(
+            "BSD-2-Clause",
+            "[bsd 2-clause license - https://opensource.org/licenses/BSD-2-Clause]",
+        )
+    } else if has("isc license")
+        || (has("permission to use, copy, modify") && has("and/or sell copies"))
+    {
+        license("ISC", "[isc license - https://opensource.org/licenses/ISC]")
+    } else if has("this is free and unencumbered software released into the public domain") {
+        license(
+            "Unlicense",
+            "[unlicense - public domain - https://unlicense.org/]",
+        )
+    } else if has("creative commons") {
+        license(
+            "LicenseRef-CC",
+            "[creative commons license - see repository for details]",
+        )
+    } else {
+        return None;
+    };
+
+    Some(found)
+}
+
+/// detect and compress common license files into a single line
+pub fn compress_license(path: &str, content: &str) -> Option<String> {
+    if !is_license_file(path) {
+        return None;
+    }
+    identify_license(content).map(|license| license.summary.to_string())
+}
+
+/// SPDX style expression for the license files of a repository root,
+/// `MIT OR Apache-2.0` for dual licensed projects
+pub fn license_expression<'a>(
+    files: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Option<String> {
+    let mut ids: Vec<&str> = files
+        .into_iter()
+        .filter(|(path, _)| is_license_file(path))
+        .filter_map(|(_, content)| identify_license(content))
+        .map(|license| license.spdx)
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    (!ids.is_empty()).then(|| ids.join(" OR "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIT: &str =
+        "MIT License\n\nPermission is hereby granted, free of charge, to any person...";
+    const LGPL: &str = "GNU LESSER GENERAL PUBLIC LICENSE\nVersion 3\n\
+        This version of the GNU Lesser General Public License incorporates\n\
+        the terms and conditions of version 3 of the GNU General Public License";
+
+    #[test]
+    fn test_license_detection() {
+        assert_eq!(
+            compress_license("LICENSE", MIT).as_deref(),
+            Some("[mit license - https://opensource.org/licenses/MIT]")
+        );
+        // the lgpl quotes the gpl and used to be reported as gpl v3
+        assert_eq!(identify_license(LGPL).map(|l| l.spdx), Some("LGPL-3.0"));
+        // only license files are compressed, not code that mentions licenses
+        assert_eq!(compress_license("src/licenses.rs", MIT), None);
+        assert_eq!(compress_license("src/lib.rs", MIT), None);
+
+        let apache = "Apache License\nVersion 2.0, January 2004";
+        let files = [
+            ("LICENSE-MIT", MIT),
+            ("LICENSE-APACHE", apache),
+            ("README.md", MIT),
+        ];
+        assert_eq!(
+            license_expression(files).as_deref(),
+            Some("Apache-2.0 OR MIT")
+        );
+        assert_eq!(license_expression([("README.md", MIT)]), None);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::options::{IngestOptions, TotalLimit};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+/// Ingests files that are already in memory, e.g. fetched through a hosting
+/// provider's REST API. Needs neither git nor a filesystem, so it is what the
+/// wasm build of githem-core exposes to the browser.
+pub struct MemoryIngester {
+    pub options: IngestOptions,
+    filter: crate::FilterExplainer,
+    transforms: crate::TransformPipeline,
+}
+
+impl MemoryIngester {
+    pub fn new(options: IngestOptions) -> Self {
+        let filter = crate::FilterExplainer::new(&options);
+        let transforms = crate::TransformPipeline::from_options(&options);
+        Self {
+            options,
+            filter,
+            transforms,
+        }
+    }
+
+    /// run `transform` after the ones the options select
+    pub fn add_transform(&mut self, transform: impl crate::ContentTransform + 'static) {
+        self.transforms.push(transform);
+    }
+
+    pub fn should_include(&self, path: &str) -> bool {
+        let path_ref = Path::new(path);
+        if !self.options.in_prefixes(path_ref) || self.options.excluded_by(path_ref).is_some() {
+            return false;
+        }
+
+        self.filter.explain(path_ref).is_included()
+    }
+
+    /// filter a file listing without needing any contents
+    pub fn filter_paths<'a>(&self, paths: &'a [String]) -> Vec<&'a str> {
+        let mut included: Vec<&str> = paths
+            .iter()
+            .map(|p| p.as_str())
+            .filter(|p| self.should_include(p))
+            .collect();
+        included.sort_unstable();
+        included
+    }
+
+    /// write (path, content) pairs in the same format as `Ingester::ingest`,
+    /// returning the number of files written
+    pub fn ingest<W: Write>(
+        &self,
+        files: &[(String, String)],
+        output: &mut W,
+    ) -> std::io::Result<usize> {
+        let mut selected: Vec<(&str, String)> = files
+            .iter()
+            .filter(|(path, content)| {
+                self.should_include(path) && self.options.size_in_range(content.len() as u64)
+            })
+            .filter_map(|(path, content)| {
+                let content = self.transforms.apply(path, content.clone())?;
+                Some((path.as_str(), self.options.cap_lines(content)))
+            })
+            .collect();
+        selected.sort_by(|a, b| a.0.cmp(b.0));
+
+        let contents: HashMap<&str, &String> = files
+            .iter()
+            .map(|(path, content)| (path.as_str(), content))
+            .collect();
+        let mut omitted = Vec::new();
+        let selected = crate::importance::keep_important(
+            selected,
+            self.options.max_files,
+            |(path, _)| Path::new(*path),
+            |path| contents.get(path).map(|content| content.to_string()),
+            &mut omitted,
+        );
+
+        let paths: Vec<&str> = selected.iter().map(|(path, _)| *path).collect();
+        write!(output, "{}", crate::generate_tree_from_paths(&paths))?;
+        crate::importance::write_omitted(
+            &omitted,
+            self.options.max_files.unwrap_or_default(),
+            output,
+        )?;
+
+        let mut limit = TotalLimit::new(&self.options);
+        let mut written = 0;
+        let mut skipped = Vec::new();
+        for (path, content) in &selected {
+            if limit.reached() {
+                skipped.push(*path);
+                continue;
+            }
+            let file = crate::IngestedFile {
+                path: path.into(),
+                size: content.len(),
+                tokens: crate::estimate_tokens(content),
+            };
+            if !limit.admit(&file) {
+                skipped.push(*path);
+                continue;
+            }
+            // no history in memory, so `Full` has nothing over `Basic`
+            let detail = (self.options.header_detail != crate::HeaderDetail::Path)
+                .then(|| crate::FileDetail::new(path, content));
+            writeln!(output, "{}", crate::format_header(path, detail.as_ref()))?;
+            writeln!(output, "{}", crate::escape_content(content))?;
+            writeln!(output)?;
+            written += 1;
+        }
+        limit.write_notice(&skipped, output)?;
+
+        Ok(written)
+    }
+
+    pub fn ingest_to_string(&self, files: &[(String, String)]) -> String {
+        let mut output = Vec::new();
+        // writing into a Vec cannot fail
+        let _ = self.ingest(files, &mut output);
+        String::from_utf8_lossy(&output).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FilterPreset;
+
+    #[test]
+    fn test_memory_ingest_applies_filters() {
+        let ingester = MemoryIngester::new(IngestOptions::with_preset(FilterPreset::Standard));
+        let files = vec![
+            ("src/main.rs".to_string(), "fn main() {}".to_string()),
+            ("Cargo.lock".to_string(), "# lock".to_string()),
+            ("node_modules/x/index.js".to_string(), "x".to_string()),
+        ];
+
+        let output = ingester.ingest_to_string(&files);
+        assert!(output.contains("=== src/main.rs ===\nfn main() {}\n"));
+        assert!(!output.contains("Cargo.lock"));
+        assert!(!output.contains("node_modules"));
+    }
+
+    #[test]
+    fn test_memory_ingest_size_and_line_limits() {
+        let options = IngestOptions::builder()
+            .preset(FilterPreset::Raw)
+            .min_file_size(10)
+            .max_lines(2)
+            .build()
+            .unwrap();
+        let ingester = MemoryIngester::new(options);
+        let files = vec![
+            ("mod.rs".to_string(), "pub use a;".to_string()),
+            ("tiny.rs".to_string(), "mod a;".to_string()),
+            ("long.rs".to_string(), "one\ntwo\nthree\nfour\n".to_string()),
+        ];
+
+        let output = ingester.ingest_to_string(&files);
+        assert!(output.contains("=== mod.rs ===\npub use a;\n"));
+        assert!(!output.contains("tiny.rs"));
+        assert!(output.contains("=== long.rs ===\none\ntwo\n[... 2 more lines truncated]\n"));
+    }
+
+    #[test]
+    fn test_memory_ingest_total_size_limit() {
+        let options = IngestOptions::builder()
+            .preset(FilterPreset::Raw)
+            .max_total_size(20)
+            .build()
+            .unwrap();
+        let ingester = MemoryIngester::new(options);
+        let files = vec![
+            ("a.rs".to_string(), "fn a() {}".to_string()),
+            ("b.rs".to_string(), "fn b() { long() }".to_string()),
+            ("c.rs".to_string(), "c".to_string()),
+        ];
+
+        let mut output = Vec::new();
+        assert_eq!(ingester.ingest(&files, &mut output).unwrap(), 1);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("=== a.rs ===\nfn a() {}\n"));
+        // stops at the first file that does not fit, smaller ones after it too
+        assert!(!output.contains("=== c.rs ==="));
+        assert!(output.ends_with(
+            "[... output truncated at 20 bytes, 2 more files skipped:\nb.rs\nc.rs\n]\n"
+        ));
+    }
+
+    #[test]
+    fn test_docs_and_tests_presets() {
+        let files = vec![
+            ("README.md".to_string(), "# Title".to_string()),
+            (
+                "src/lib.rs".to_string(),
+                "/// adds\npub fn add() {}\n".to_string(),
+            ),
+            ("src/util.rs".to_string(), "fn helper() {}\n".to_string()),
+            (
+                "tests/api.rs".to_string(),
+                "#[test]\nfn works() {}\n".to_string(),
+            ),
+            ("web/app.spec.ts".to_string(), "it()".to_string()),
+        ];
+
+        let docs = MemoryIngester::new(IngestOptions::with_preset(FilterPreset::DocsOnly));
+        let output = docs.ingest_to_string(&files);
+        assert!(output.contains("=== README.md ===\n# Title\n"));
+        assert!(output.contains("=== src/lib.rs ===\nadds\n"));
+        assert!(!output.contains("src/util.rs"));
+        assert!(!output.contains("app.spec.ts"));
+
+        let tests = MemoryIngester::new(IngestOptions::with_preset(FilterPreset::TestsOnly));
+        let output = tests.ingest_to_string(&files);
+        assert!(output.contains("=== tests/api.rs ==="));
+        assert!(output.contains("=== web/app.spec.ts ==="));
+        assert!(!output.contains("README.md"));
+        assert!(!output.contains("src/lib.rs"));
+    }
+
+    #[test]
+    fn test_header_lines_in_content_are_escaped() {
+        let ingester = MemoryIngester::new(IngestOptions::with_preset(FilterPreset::Raw));
+        let body = "=== fake.rs ===\n\\=== already escaped\nplain\n";
+        let files = vec![
+            ("out.txt".to_string(), body.to_string()),
+            ("b.rs".to_string(), "fn b() {}".to_string()),
+        ];
+
+        let output = ingester.ingest_to_string(&files);
+        assert!(output.contains("\\=== fake.rs ===\n\\\\=== already escaped\n"));
+        assert_eq!(crate::count_files(&output), 2);
+
+        let split = crate::split_files(&output);
+        assert_eq!(
+            split.iter().map(|(path, _)| *path).collect::<Vec<_>>(),
+            ["b.rs", "out.txt"]
+        );
+        assert_eq!(
+            crate::unescape_content(split[1].1).trim_end(),
+            body.trim_end()
+        );
+    }
+}
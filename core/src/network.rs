@@ -0,0 +1,255 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// wait before the first retry, doubled for each one after it
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(8);
+
+/// Outbound network settings shared by every clone/fetch
+#[derive(Debug, Clone, Default)]
+pub struct NetworkConfig {
+    /// Proxy URL, e.g. `http://proxy.corp:3128`
+    pub proxy: Option<String>,
+    /// Hosts that bypass the proxy (suffix match, `*` disables the proxy)
+    pub no_proxy: Vec<String>,
+    /// Extra PEM bundle trusted in addition to the system roots
+    pub ca_bundle: Option<PathBuf>,
+    /// Refuse every clone, fetch and ref listing, only cached checkouts
+    /// and local repositories are read
+    pub offline: bool,
+}
+
+impl NetworkConfig {
+    /// read HTTPS_PROXY/HTTP_PROXY/ALL_PROXY, NO_PROXY, GITHEM_CA_BUNDLE and
+    /// GITHEM_OFFLINE
+    pub fn from_env() -> Self {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| std::env::var(name).ok())
+                .filter(|value| !value.trim().is_empty())
+        };
+
+        Self {
+            proxy: var(&[
+                "HTTPS_PROXY",
+                "https_proxy",
+                "HTTP_PROXY",
+                "http_proxy",
+                "ALL_PROXY",
+                "all_proxy",
+            ]),
+            no_proxy: var(&["NO_PROXY", "no_proxy"])
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(|host| host.trim().to_string())
+                        .filter(|host| !host.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            ca_bundle: var(&["GITHEM_CA_BUNDLE"]).map(PathBuf::from),
+            offline: var(&["GITHEM_OFFLINE"]).is_some_and(|value| value != "0" && value != "false"),
+        }
+    }
+
+    /// proxy to use for `url`, honoring no_proxy
+    pub fn proxy_for(&self, url: &str) -> Option<&str> {
+        let proxy = self.proxy.as_deref()?;
+
+        let host = url
+            .split("://")
+            .nth(1)
+            .and_then(|rest| rest.split(['/', ':']).next())
+            .unwrap_or_default();
+
+        let bypass = self.no_proxy.iter().any(|entry| {
+            let entry = entry.trim_start_matches('.');
+            entry == "*" || host == entry || host.ends_with(&format!(".{entry}"))
+        });
+
+        if bypass {
+            None
+        } else {
+            Some(proxy)
+        }
+    }
+}
+
+static NETWORK: RwLock<Option<NetworkConfig>> = RwLock::new(None);
+
+/// install the network configuration for this process
+/// the CA bundle is registered with libgit2 globally and cannot be removed
+pub fn configure_network(config: NetworkConfig) -> Result<()> {
+    if let Some(ref bundle) = config.ca_bundle {
+        if !bundle.is_file() {
+            return Err(anyhow::anyhow!("CA bundle not found: {}", bundle.display()));
+        }
+        // safety: only mutates libgit2's global TLS settings, no other threads
+        // are expected to be mid-handshake while the process is configured
+        unsafe { git2::opts::set_ssl_cert_file(bundle) }
+            .with_context(|| format!("Failed to load CA bundle {}", bundle.display()))?;
+    }
+
+    *NETWORK.write().unwrap_or_else(|e| e.into_inner()) = Some(config);
+    Ok(())
+}
+
+/// current configuration, falling back to the environment on first use
+pub fn network_config() -> NetworkConfig {
+    if let Some(config) = NETWORK.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return config.clone();
+    }
+
+    let config = NetworkConfig::from_env();
+    if let Err(e) = configure_network(config.clone()) {
+        eprintln!("Warning: {e:#}");
+        let fallback = NetworkConfig {
+            ca_bundle: None,
+            ..config
+        };
+        *NETWORK.write().unwrap_or_else(|e| e.into_inner()) = Some(fallback.clone());
+        return fallback;
+    }
+    config
+}
+
+/// an error instead of connecting to `url` when the network is off
+pub fn ensure_online(url: &str) -> Result<()> {
+    if network_config().offline {
+        anyhow::bail!("Offline, not connecting to {url}");
+    }
+    Ok(())
+}
+
+/// libgit2 proxy options for a remote url
+pub fn proxy_options(url: &str) -> git2::ProxyOptions<'static> {
+    let mut options = git2::ProxyOptions::new();
+    match network_config().proxy_for(url) {
+        Some(proxy) => {
+            options.url(proxy);
+        }
+        // still let libgit2 pick up http.proxy from git config
+        None => {
+            options.auto();
+        }
+    }
+    options
+}
+
+/// run `operation`, and again up to `retries` times while it fails with a
+/// transient network error, backing off exponentially in between
+pub fn retry<T>(retries: u32, mut operation: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        match operation() {
+            Err(e) if attempt < retries && is_transient(&e) => {
+                attempt += 1;
+                eprintln!(
+                    "Warning: {e:#}, retrying in {}ms ({attempt}/{retries})",
+                    delay.as_millis()
+                );
+                std::thread::sleep(delay);
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// connection, TLS and server side failures, which may well pass, as
+/// opposed to missing repositories, refs or credentials
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    let Some(error) = error.chain().find_map(|e| e.downcast_ref::<git2::Error>()) else {
+        return false;
+    };
+    match error.class() {
+        git2::ErrorClass::Net | git2::ErrorClass::Ssl | git2::ErrorClass::Os => true,
+        git2::ErrorClass::Http => {
+            error.code() != git2::ErrorCode::Auth && !error.message().contains("status code: 4")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{ErrorClass, ErrorCode};
+
+    fn git_error(code: ErrorCode, class: ErrorClass, message: &str) -> anyhow::Error {
+        git2::Error::new(code, class, message).into()
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&git_error(
+            ErrorCode::GenericError,
+            ErrorClass::Net,
+            "failed to connect"
+        )));
+        assert!(is_transient(&git_error(
+            ErrorCode::GenericError,
+            ErrorClass::Ssl,
+            "handshake failed"
+        )));
+        assert!(is_transient(
+            &git_error(
+                ErrorCode::GenericError,
+                ErrorClass::Http,
+                "unexpected http status code: 502"
+            )
+            .context("Failed to clone")
+        ));
+
+        assert!(!is_transient(&git_error(
+            ErrorCode::GenericError,
+            ErrorClass::Http,
+            "unexpected http status code: 404"
+        )));
+        assert!(!is_transient(&git_error(
+            ErrorCode::Auth,
+            ErrorClass::Http,
+            "authentication required"
+        )));
+        assert!(!is_transient(&git_error(
+            ErrorCode::NotFound,
+            ErrorClass::Reference,
+            "reference not found"
+        )));
+        assert!(!is_transient(&anyhow::anyhow!("Invalid URL")));
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut attempts = 0;
+        let result: Result<()> = retry(3, || {
+            attempts += 1;
+            Err(git_error(
+                ErrorCode::Auth,
+                ErrorClass::Http,
+                "authentication required",
+            ))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let mut attempts = 0;
+        let result = retry(1, || {
+            attempts += 1;
+            if attempts == 1 {
+                Err(git_error(
+                    ErrorCode::GenericError,
+                    ErrorClass::Net,
+                    "connection reset",
+                ))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+    }
+}
@@ -0,0 +1,679 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// What to ingest and how. Build it with [`IngestOptions::builder`], which
+/// checks that the settings fit together, or start from `Default` and set
+/// fields; it is non-exhaustive so new options do not break callers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct IngestOptions {
+    pub include_patterns: Vec<String>,
+    pub exclude_patterns: Vec<String>,
+    /// only files with one of these extensions (`rs`, `.toml`), on top of
+    /// the include patterns rather than as an alternative to them
+    pub extensions: Vec<String>,
+    pub max_file_size: usize,
+    /// files smaller than this many bytes are skipped, 0 keeps everything
+    pub min_file_size: usize,
+    /// longer files are cut off after this many lines
+    pub max_lines: Option<usize>,
+    /// ingestion stops before the file contents written would exceed this
+    /// many bytes, the files left out are listed at the end
+    pub max_total_size: Option<usize>,
+    /// the same as `max_total_size` for estimated tokens
+    pub max_total_tokens: Option<usize>,
+    /// only this many files, the most important ones, are written (see
+    /// `rank_by_importance`), the others are counted per directory
+    pub max_files: Option<usize>,
+    pub include_untracked: bool,
+    pub branch: Option<String>,
+    /// several branches of one clone, written one after the other in
+    /// `# Branch` sections, instead of `branch`
+    pub branches: Vec<String>,
+    pub path_prefix: Option<String>,
+    /// more subtrees next to `path_prefix`, files below any of them are
+    /// ingested
+    pub paths: Vec<String>,
+    /// subtrees left out entirely, the tree walk does not descend into them
+    pub exclude_paths: Vec<String>,
+    pub filter_preset: Option<crate::FilterPreset>,
+    pub apply_default_filters: bool,
+    /// inline the targets of symlinks that resolve to files inside the
+    /// repository, links are otherwise recorded as `[symlink -> target]`
+    pub follow_symlinks: bool,
+    pub pattern_syntax: crate::PatternSyntax,
+    /// content transforms, run in their canonical order (see `Transform`)
+    pub transforms: Vec<crate::Transform>,
+    /// metadata written next to each path in the `=== path ===` markers
+    pub header_detail: crate::HeaderDetail,
+    /// directories whose files add up to more than this many tokens are
+    /// written as a summary (files, public symbols, tokens) instead
+    pub summarize_dirs: Option<usize>,
+    /// READMEs and architecture docs whatever the filters, first, and a
+    /// leading `# Documentation` section with the doc comments of the code
+    pub docs_first: bool,
+    /// a leading `# Ownership` section mapping paths to their owners, from
+    /// CODEOWNERS, MAINTAINERS and manifest authors
+    pub ownership: bool,
+    /// CI workflows, Dockerfiles and Makefiles whatever the filters, in a
+    /// `# Build & CI` section instead of among the other files
+    pub build_ci: bool,
+    /// the tests of included sources (foo_test.go, tests/foo.rs,
+    /// foo.spec.ts), whatever the preset
+    pub with_tests: bool,
+    /// the sources of included tests, for test first selections
+    pub with_sources: bool,
+    /// clones and fetches failing on a network error are attempted this
+    /// many more times, with exponential backoff
+    pub retries: u32,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        Self {
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            extensions: Vec::new(),
+            max_file_size: 1048576,
+            min_file_size: 0,
+            max_lines: None,
+            max_total_size: None,
+            max_total_tokens: None,
+            max_files: None,
+            include_untracked: false,
+            branch: None,
+            branches: Vec::new(),
+            path_prefix: None,
+            paths: Vec::new(),
+            exclude_paths: Vec::new(),
+            filter_preset: None,
+            apply_default_filters: true,
+            follow_symlinks: false,
+            pattern_syntax: crate::PatternSyntax::Glob,
+            transforms: crate::Transform::defaults(),
+            header_detail: crate::HeaderDetail::Path,
+            summarize_dirs: None,
+            docs_first: false,
+            ownership: false,
+            build_ci: false,
+            with_tests: false,
+            with_sources: false,
+            retries: 2,
+        }
+    }
+}
+
+impl IngestOptions {
+    pub fn builder() -> IngestOptionsBuilder {
+        IngestOptionsBuilder::default()
+    }
+
+    pub fn with_preset(preset: crate::FilterPreset) -> Self {
+        Self {
+            filter_preset: Some(preset),
+            apply_default_filters: false,
+            ..Default::default()
+        }
+    }
+
+    pub fn extension_allowed(&self, path: &Path) -> bool {
+        crate::filtering::extension_allowed(&self.extensions, path)
+    }
+
+    /// `path_prefix` and `paths` without trailing slashes, empty for the
+    /// whole repository
+    pub fn prefixes(&self) -> Vec<&str> {
+        let prefixes = self.path_prefix.iter().chain(&self.paths);
+        prefixes
+            .map(|prefix| prefix.trim_end_matches('/'))
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+
+    /// whether `path` lies below one of the prefixes, always without any
+    pub fn in_prefixes(&self, path: &Path) -> bool {
+        let prefixes = self.prefixes();
+        prefixes.is_empty() || prefixes.iter().any(|prefix| path.starts_with(prefix))
+    }
+
+    /// the entry of `exclude_paths` that `path` lies below, if any
+    pub fn excluded_by(&self, path: &Path) -> Option<&str> {
+        self.exclude_paths
+            .iter()
+            .map(|excluded| excluded.trim_end_matches('/'))
+            .find(|excluded| !excluded.is_empty() && path.starts_with(excluded))
+    }
+
+    /// whether a file of `size` bytes is within min_file_size and max_file_size
+    pub fn size_in_range(&self, size: u64) -> bool {
+        size >= self.min_file_size as u64 && size <= self.max_file_size as u64
+    }
+
+    /// cut `content` down to max_lines, noting how much was dropped
+    pub fn cap_lines(&self, content: String) -> String {
+        let cut = match self.max_lines {
+            None => return content,
+            Some(0) => 0,
+            Some(max_lines) => match content.match_indices('\n').nth(max_lines - 1) {
+                Some((newline, _)) => newline + 1,
+                None => return content,
+            },
+        };
+
+        let remaining = content[cut..].lines().count();
+        if remaining == 0 {
+            return content;
+        }
+
+        let mut capped = content[..cut].to_string();
+        capped.push_str(&format!("[... {remaining} more lines truncated]"));
+        capped
+    }
+
+    pub fn get_effective_excludes(&self) -> Vec<String> {
+        let mut excludes = self.exclude_patterns.clone();
+
+        if let Some(preset) = self.filter_preset {
+            excludes.extend(crate::get_excludes_for_preset(preset));
+        } else if self.apply_default_filters {
+            excludes.extend(crate::get_default_excludes());
+        }
+
+        excludes.sort();
+        excludes.dedup();
+        excludes
+    }
+}
+
+/// Fluent construction of [`IngestOptions`], starting from the defaults
+#[derive(Debug, Clone, Default)]
+pub struct IngestOptionsBuilder {
+    options: IngestOptions,
+    /// set explicitly, as opposed to the default of `IngestOptions`
+    default_filters: Option<bool>,
+}
+
+impl IngestOptionsBuilder {
+    /// filter with `preset` instead of the default filters
+    pub fn preset(mut self, preset: crate::FilterPreset) -> Self {
+        self.options.filter_preset = Some(preset);
+        self
+    }
+
+    /// apply the standard excludes when no preset is chosen
+    pub fn default_filters(mut self, enabled: bool) -> Self {
+        self.default_filters = Some(enabled);
+        self
+    }
+
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.options.include_patterns.push(pattern.into());
+        self
+    }
+
+    pub fn includes<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.options
+            .include_patterns
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.options.exclude_patterns.push(pattern.into());
+        self
+    }
+
+    pub fn excludes<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.options
+            .exclude_patterns
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn extension(mut self, extension: impl Into<String>) -> Self {
+        self.options.extensions.push(extension.into());
+        self
+    }
+
+    pub fn extensions<S: Into<String>>(mut self, extensions: impl IntoIterator<Item = S>) -> Self {
+        self.options
+            .extensions
+            .extend(extensions.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn max_file_size(mut self, bytes: usize) -> Self {
+        self.options.max_file_size = bytes;
+        self
+    }
+
+    pub fn min_file_size(mut self, bytes: usize) -> Self {
+        self.options.min_file_size = bytes;
+        self
+    }
+
+    pub fn max_lines(mut self, lines: impl Into<Option<usize>>) -> Self {
+        self.options.max_lines = lines.into();
+        self
+    }
+
+    pub fn max_total_size(mut self, bytes: impl Into<Option<usize>>) -> Self {
+        self.options.max_total_size = bytes.into();
+        self
+    }
+
+    pub fn max_total_tokens(mut self, tokens: impl Into<Option<usize>>) -> Self {
+        self.options.max_total_tokens = tokens.into();
+        self
+    }
+
+    pub fn max_files(mut self, files: impl Into<Option<usize>>) -> Self {
+        self.options.max_files = files.into();
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.options.retries = retries;
+        self
+    }
+
+    pub fn include_untracked(mut self, enabled: bool) -> Self {
+        self.options.include_untracked = enabled;
+        self
+    }
+
+    pub fn branch(mut self, branch: impl Into<String>) -> Self {
+        self.options.branch = Some(branch.into());
+        self
+    }
+
+    /// ingest each of `branches` into its own section, see `IngestOptions::branches`
+    pub fn branches<S: Into<String>>(mut self, branches: impl IntoIterator<Item = S>) -> Self {
+        self.options
+            .branches
+            .extend(branches.into_iter().map(Into::into));
+        self
+    }
+
+    /// only ingest files below this directory
+    pub fn path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.options.path_prefix = Some(prefix.into());
+        self
+    }
+
+    /// also ingest the files below these directories, see `IngestOptions::paths`
+    pub fn paths<S: Into<String>>(mut self, paths: impl IntoIterator<Item = S>) -> Self {
+        self.options.paths.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// leave out everything below these directories
+    pub fn exclude_paths<S: Into<String>>(mut self, paths: impl IntoIterator<Item = S>) -> Self {
+        self.options
+            .exclude_paths
+            .extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn follow_symlinks(mut self, enabled: bool) -> Self {
+        self.options.follow_symlinks = enabled;
+        self
+    }
+
+    pub fn pattern_syntax(mut self, syntax: crate::PatternSyntax) -> Self {
+        self.options.pattern_syntax = syntax;
+        self
+    }
+
+    /// add a transform to the defaults
+    pub fn transform(mut self, transform: crate::Transform) -> Self {
+        self.options.transforms.push(transform);
+        self
+    }
+
+    pub fn without_transform(mut self, transform: crate::Transform) -> Self {
+        self.options.transforms.retain(|t| *t != transform);
+        self
+    }
+
+    pub fn header_detail(mut self, detail: crate::HeaderDetail) -> Self {
+        self.options.header_detail = detail;
+        self
+    }
+
+    pub fn docs_first(mut self, enabled: bool) -> Self {
+        self.options.docs_first = enabled;
+        self
+    }
+
+    pub fn ownership(mut self, enabled: bool) -> Self {
+        self.options.ownership = enabled;
+        self
+    }
+
+    pub fn build_ci(mut self, enabled: bool) -> Self {
+        self.options.build_ci = enabled;
+        self
+    }
+
+    pub fn with_tests(mut self, enabled: bool) -> Self {
+        self.options.with_tests = enabled;
+        self
+    }
+
+    pub fn with_sources(mut self, enabled: bool) -> Self {
+        self.options.with_sources = enabled;
+        self
+    }
+
+    /// summarize directories above `max_tokens`, see `IngestOptions::summarize_dirs`
+    pub fn summarize_dirs(mut self, max_tokens: impl Into<Option<usize>>) -> Self {
+        self.options.summarize_dirs = max_tokens.into();
+        self
+    }
+
+    /// the options, or why they do not fit together
+    pub fn build(self) -> anyhow::Result<IngestOptions> {
+        let mut options = self.options;
+
+        match (options.filter_preset, self.default_filters) {
+            (Some(preset), Some(true)) => anyhow::bail!(
+                "preset {} replaces the default filters, set one or the other",
+                preset.as_str()
+            ),
+            (Some(_), _) => options.apply_default_filters = false,
+            (None, Some(enabled)) => options.apply_default_filters = enabled,
+            (None, None) => {}
+        }
+
+        if options.max_file_size == 0 {
+            anyhow::bail!("max_file_size must be positive");
+        }
+        if options.min_file_size > options.max_file_size {
+            anyhow::bail!(
+                "min_file_size ({}) exceeds max_file_size ({})",
+                options.min_file_size,
+                options.max_file_size
+            );
+        }
+        if options.max_lines == Some(0) {
+            anyhow::bail!("max_lines must be positive");
+        }
+        if options.max_files == Some(0) {
+            anyhow::bail!("max_files must be positive");
+        }
+        if options.summarize_dirs == Some(0) {
+            anyhow::bail!("summarize_dirs must be positive");
+        }
+
+        let patterns = options
+            .include_patterns
+            .iter()
+            .chain(&options.exclude_patterns);
+        if patterns.clone().any(|p| p.trim().is_empty()) {
+            anyhow::bail!("patterns must not be empty");
+        }
+        if options
+            .extensions
+            .iter()
+            .any(|e| e.trim_start_matches('.').is_empty())
+        {
+            anyhow::bail!("extensions must not be empty");
+        }
+
+        // branches are not checked here, local checkouts accept any revision,
+        // but several of them are always fetched
+        if !options.branches.is_empty() && options.branch.is_some() {
+            anyhow::bail!("set either branch or branches, not both");
+        }
+        for branch in &options.branches {
+            crate::validate_ref_name(branch).map_err(|e| anyhow::anyhow!("Invalid branch: {e}"))?;
+        }
+        let mut seen = std::collections::HashSet::new();
+        options
+            .branches
+            .retain(|branch| seen.insert(branch.clone()));
+        for prefix in options.path_prefix.iter().chain(&options.paths) {
+            crate::validate_path_prefix(prefix)
+                .map_err(|e| anyhow::anyhow!("Invalid path prefix: {e}"))?;
+        }
+        for excluded in &options.exclude_paths {
+            crate::validate_path_prefix(excluded)
+                .map_err(|e| anyhow::anyhow!("Invalid exclude path: {e}"))?;
+        }
+
+        if options.filter_preset == Some(crate::FilterPreset::DocsOnly)
+            && options
+                .transforms
+                .contains(&crate::Transform::StripComments)
+        {
+            anyhow::bail!(
+                "strip-comments would remove the doc comments the docs-only preset keeps"
+            );
+        }
+        options.transforms.sort();
+        options.transforms.dedup();
+
+        Ok(options)
+    }
+}
+
+/// A file written by an ingest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestedFile {
+    pub path: std::path::PathBuf,
+    /// bytes of content written, after transforms and the line cap
+    pub size: usize,
+    pub tokens: usize,
+}
+
+/// What an ingest wrote, in output order
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestReport {
+    pub files: Vec<IngestedFile>,
+    /// files left out once `max_total_size` or `max_total_tokens` was reached
+    #[serde(default)]
+    pub skipped: Vec<std::path::PathBuf>,
+    /// files left out by `max_files`
+    #[serde(default)]
+    pub omitted: Vec<std::path::PathBuf>,
+}
+
+impl IngestReport {
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.files.iter().map(|f| f.size).sum()
+    }
+
+    pub fn total_tokens(&self) -> usize {
+        self.files.iter().map(|f| f.tokens).sum()
+    }
+
+    /// the `Repository structure:` listing the api returns next to the content
+    pub fn tree(&self) -> String {
+        let paths: Vec<&Path> = self.files.iter().map(|file| file.path.as_path()).collect();
+        format!(
+            "Repository structure:\n{}",
+            crate::render_tree(&paths, None)
+        )
+    }
+}
+
+/// running totals against `max_total_size` and `max_total_tokens`; the
+/// first file that does not fit stops the ingest, it and every file after
+/// it are skipped
+#[derive(Debug)]
+pub(crate) struct TotalLimit {
+    max_size: Option<usize>,
+    max_tokens: Option<usize>,
+    size: usize,
+    tokens: usize,
+    /// the limit that was hit, as the notice names it
+    reached: Option<String>,
+}
+
+impl TotalLimit {
+    pub(crate) fn new(options: &IngestOptions) -> Self {
+        Self {
+            max_size: options.max_total_size,
+            max_tokens: options.max_total_tokens,
+            size: 0,
+            tokens: 0,
+            reached: None,
+        }
+    }
+
+    pub(crate) fn reached(&self) -> bool {
+        self.reached.is_some()
+    }
+
+    /// whether `file` may be written, counting it when it may
+    pub(crate) fn admit(&mut self, file: &IngestedFile) -> bool {
+        if self.reached.is_some() {
+            return false;
+        }
+        if let Some(max) = self.max_size.filter(|max| self.size + file.size > *max) {
+            self.reached = Some(format!("{max} bytes"));
+            return false;
+        }
+        if let Some(max) = self
+            .max_tokens
+            .filter(|max| self.tokens + file.tokens > *max)
+        {
+            self.reached = Some(format!("{max} tokens"));
+            return false;
+        }
+        self.size += file.size;
+        self.tokens += file.tokens;
+        true
+    }
+
+    /// the notice closing a truncated output
+    pub(crate) fn write_notice<W: std::io::Write, P: AsRef<Path>>(
+        &self,
+        skipped: &[P],
+        output: &mut W,
+    ) -> std::io::Result<()> {
+        let Some(limit) = &self.reached else {
+            return Ok(());
+        };
+        writeln!(
+            output,
+            "[... output truncated at {limit}, {} more files skipped:",
+            skipped.len()
+        )?;
+        for path in skipped {
+            writeln!(output, "{}", path.as_ref().display())?;
+        }
+        writeln!(output, "]")
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterStats {
+    pub total_files: usize,
+    pub included_files: usize,
+    pub excluded_files: usize,
+    pub total_size: u64,
+    pub included_size: u64,
+    pub excluded_size: u64,
+    pub excluded_by_filter: usize,
+}
+
+impl FilterStats {
+    pub fn inclusion_rate(&self) -> f64 {
+        if self.total_files == 0 {
+            0.0
+        } else {
+            self.included_files as f64 / self.total_files as f64
+        }
+    }
+
+    pub fn size_reduction(&self) -> f64 {
+        if self.total_size == 0 {
+            0.0
+        } else {
+            self.excluded_size as f64 / self.total_size as f64
+        }
+    }
+}
+
+pub trait IngestionCallback: Send + Sync {
+    fn on_progress(&mut self, _stage: &str, _message: &str) {}
+    fn on_file(&mut self, _path: &Path, _content: &str) {}
+    fn on_complete(&mut self, _files: usize, _bytes: usize) {}
+    fn on_error(&mut self, _error: &str) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FilterPreset, Transform};
+
+    #[test]
+    fn test_builder() {
+        let options = IngestOptions::builder()
+            .preset(FilterPreset::Standard)
+            .include("src/")
+            .max_file_size(1_000_000)
+            .transform(Transform::RedactSecrets)
+            .build()
+            .unwrap();
+        assert_eq!(options.filter_preset, Some(FilterPreset::Standard));
+        assert!(!options.apply_default_filters);
+        assert_eq!(options.include_patterns, ["src/"]);
+        assert_eq!(
+            options.transforms,
+            [Transform::CompressLicense, Transform::RedactSecrets]
+        );
+
+        let options = IngestOptions::builder()
+            .branches(["main", "dev", "main"])
+            .build()
+            .unwrap();
+        assert_eq!(options.branches, ["main", "dev"]);
+
+        let options = IngestOptions::builder()
+            .path_prefix("src/core/")
+            .paths(["proto"])
+            .build()
+            .unwrap();
+        assert_eq!(options.prefixes(), ["src/core", "proto"]);
+        assert!(options.in_prefixes(Path::new("proto/a.proto")));
+        assert!(!options.in_prefixes(Path::new("src/corelib/a.rs")));
+
+        let options = IngestOptions::builder()
+            .exclude_paths(["vendor/legacy/"])
+            .build()
+            .unwrap();
+        assert_eq!(
+            options.excluded_by(Path::new("vendor/legacy/a/b.c")),
+            Some("vendor/legacy")
+        );
+        assert_eq!(options.excluded_by(Path::new("vendor/legacy.c")), None);
+
+        let conflicts = [
+            IngestOptions::builder()
+                .preset(FilterPreset::Raw)
+                .default_filters(true),
+            IngestOptions::builder().min_file_size(10).max_file_size(5),
+            IngestOptions::builder().max_lines(0),
+            IngestOptions::builder().path_prefix("../etc"),
+            IngestOptions::builder().paths(["src", "/etc"]),
+            IngestOptions::builder().exclude_paths(["vendor/../.."]),
+            IngestOptions::builder().exclude(" "),
+            IngestOptions::builder().branch("main").branches(["dev"]),
+            IngestOptions::builder().branches(["main", "a..b"]),
+            IngestOptions::builder()
+                .preset(FilterPreset::DocsOnly)
+                .transform(Transform::StripComments),
+        ];
+        for builder in conflicts {
+            assert!(builder.clone().build().is_err(), "{builder:?}");
+        }
+    }
+}
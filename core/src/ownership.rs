@@ -0,0 +1,248 @@
+/// where a CODEOWNERS file may live, in the order GitHub looks
+pub const CODEOWNERS_PATHS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// files that name the people responsible for the whole repository
+pub const MAINTAINERS_PATHS: &[&str] = &["MAINTAINERS", "MAINTAINERS.md", "MAINTAINERS.txt"];
+
+/// owners of the paths matching `pattern` according to `source`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnershipRule {
+    pub pattern: String,
+    pub owners: Vec<String>,
+    pub source: String,
+}
+
+/// `pattern @owner ...` lines, comments and owner-less lines dropped
+pub fn parse_codeowners(content: &str, source: &str) -> Vec<OwnershipRule> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?;
+            let owners: Vec<String> = parts.map(str::to_string).collect();
+            (!owners.is_empty()).then(|| OwnershipRule {
+                pattern: pattern.to_string(),
+                owners,
+                source: source.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// the `M:` maintainers of each `F:` pattern in a Linux style MAINTAINERS
+/// file, or every name in a plain list as the owners of the whole tree
+pub fn parse_maintainers(content: &str, source: &str) -> Vec<OwnershipRule> {
+    let sectioned = content.lines().any(|line| line.starts_with("F:"));
+    if !sectioned {
+        let owners: Vec<String> = content
+            .lines()
+            .map(|line| line.trim().trim_start_matches(['-', '*']).trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.ends_with(':'))
+            .map(str::to_string)
+            .collect();
+        return rule("*", owners, source).into_iter().collect();
+    }
+
+    let mut rules = Vec::new();
+    // a blank line ends a section
+    for section in content.split("\n\n") {
+        let field = |tag: &str| -> Vec<String> {
+            section
+                .lines()
+                .filter_map(|line| line.strip_prefix(tag))
+                .map(|value| value.trim().to_string())
+                .collect()
+        };
+        let owners = field("M:");
+        for pattern in field("F:") {
+            rules.extend(rule(&pattern, owners.clone(), source));
+        }
+    }
+    rules
+}
+
+fn rule(pattern: &str, owners: Vec<String>, source: &str) -> Option<OwnershipRule> {
+    (!owners.is_empty()).then(|| OwnershipRule {
+        pattern: pattern.to_string(),
+        owners,
+        source: source.to_string(),
+    })
+}
+
+/// the directory of a manifest as an ownership pattern, `*` at the root
+fn manifest_dir(path: &str) -> String {
+    match path.rsplit_once('/') {
+        Some((dir, _)) => format!("{dir}/"),
+        None => "*".to_string(),
+    }
+}
+
+/// `authors` of the `[package]` or `[workspace.package]` table of a Cargo.toml
+pub fn cargo_authors(content: &str, path: &str) -> Option<OwnershipRule> {
+    let mut table = "";
+    let mut list: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if let Some(open) = list.as_mut() {
+            open.push_str(trimmed);
+            if trimmed.contains(']') {
+                break;
+            }
+            continue;
+        }
+        if trimmed.starts_with('[') {
+            table = trimmed;
+            continue;
+        }
+        if !matches!(table, "[package]" | "[workspace.package]") {
+            continue;
+        }
+        if let Some(value) = trimmed.strip_prefix("authors").map(str::trim_start) {
+            let Some(value) = value.strip_prefix('=') else {
+                continue;
+            };
+            list = Some(value.trim().to_string());
+            if value.contains(']') {
+                break;
+            }
+        }
+    }
+
+    let list = list?;
+    let owners: Vec<String> = list
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .map(str::to_string)
+        .collect();
+    rule(&manifest_dir(path), owners, path)
+}
+
+/// `author` and `contributors` of a package.json
+pub fn package_json_authors(content: &str, path: &str) -> Option<OwnershipRule> {
+    let package: serde_json::Value = serde_json::from_str(content).ok()?;
+    let person = |value: &serde_json::Value| -> Option<String> {
+        match value {
+            serde_json::Value::String(name) => Some(name.clone()),
+            serde_json::Value::Object(fields) => {
+                let name = fields.get("name")?.as_str()?;
+                Some(match fields.get("email").and_then(|e| e.as_str()) {
+                    Some(email) => format!("{name} <{email}>"),
+                    None => name.to_string(),
+                })
+            }
+            _ => None,
+        }
+    };
+
+    let mut owners: Vec<String> = package.get("author").and_then(person).into_iter().collect();
+    if let Some(contributors) = package.get("contributors").and_then(|c| c.as_array()) {
+        owners.extend(contributors.iter().filter_map(person));
+    }
+    rule(&manifest_dir(path), owners, path)
+}
+
+/// the rules found in `files`, (path, content) pairs of the ownership
+/// files and manifests of a repository
+pub fn collect_rules<'a>(
+    files: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Vec<OwnershipRule> {
+    let mut rules = Vec::new();
+    let mut codeowners_seen = false;
+    for (path, content) in files {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        if CODEOWNERS_PATHS.contains(&path) {
+            // GitHub only reads the first CODEOWNERS it finds
+            if !codeowners_seen {
+                rules.extend(parse_codeowners(content, path));
+                codeowners_seen = true;
+            }
+        } else if MAINTAINERS_PATHS.contains(&path) {
+            rules.extend(parse_maintainers(content, path));
+        } else if name == "Cargo.toml" {
+            rules.extend(cargo_authors(content, path));
+        } else if name == "package.json" {
+            rules.extend(package_json_authors(content, path));
+        }
+    }
+    rules
+}
+
+/// whether `path` is read by `collect_rules`
+pub fn is_ownership_source(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    CODEOWNERS_PATHS.contains(&path)
+        || MAINTAINERS_PATHS.contains(&path)
+        || name == "Cargo.toml"
+        || (name == "package.json" && !path.contains("node_modules/"))
+}
+
+/// the `# Ownership` section written ahead of the file tree
+pub fn render_ownership(rules: &[OwnershipRule]) -> Option<String> {
+    if rules.is_empty() {
+        return None;
+    }
+    let width = rules.iter().map(|r| r.pattern.len()).max().unwrap_or(0);
+    let mut section = String::from("# Ownership\n\n");
+    for rule in rules {
+        section.push_str(&format!(
+            "{:width$}  {}  ({})\n",
+            rule.pattern,
+            rule.owners.join(", "),
+            rule.source
+        ));
+    }
+    section.push('\n');
+    Some(section)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ownership_rules() {
+        let codeowners = "# owners\n* @org/core\n/api/ @alice @bob # http\nunowned/\n";
+        let cargo =
+            "[package]\nname = \"x\"\nauthors = [\n  \"Jane <jane@x.org>\",\n  \"Joe\",\n]\n";
+        let package =
+            r#"{"author": {"name": "Ann", "email": "ann@x.org"}, "contributors": ["Bo"]}"#;
+        let maintainers =
+            "CORE\nM:\tJane <jane@x.org>\nF:\tcore/\n\nCLI\nM:\tJoe\nF:\tcli/\nF:\tdocs/cli.md\n";
+
+        let rules = collect_rules([
+            (".github/CODEOWNERS", codeowners),
+            ("CODEOWNERS", "* @ignored"),
+            ("cli/Cargo.toml", cargo),
+            ("web/package.json", package),
+            ("MAINTAINERS", maintainers),
+        ]);
+        let summary: Vec<(&str, String)> = rules
+            .iter()
+            .map(|r| (r.pattern.as_str(), r.owners.join(" ")))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("*", "@org/core".to_string()),
+                ("/api/", "@alice @bob".to_string()),
+                ("cli/", "Jane <jane@x.org> Joe".to_string()),
+                ("web/", "Ann <ann@x.org> Bo".to_string()),
+                ("core/", "Jane <jane@x.org>".to_string()),
+                ("cli/", "Joe".to_string()),
+                ("docs/cli.md", "Joe".to_string()),
+            ]
+        );
+
+        // members inheriting the workspace authors add nothing
+        assert_eq!(
+            cargo_authors("[package]\nauthors.workspace = true\n", "a/Cargo.toml"),
+            None
+        );
+        assert!(render_ownership(&rules)
+            .unwrap()
+            .starts_with("# Ownership\n\n*            @org/core  (.github/CODEOWNERS)\n"));
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::{BTreeMap, HashSet};
+
+/// directories holding tests, and the layout directories that separate a
+/// source from its tests (src/foo.rs and tests/foo.rs, src/main/java and
+/// src/test/java)
+const TEST_DIRS: &[&str] = &["test", "tests", "__tests__", "spec", "specs"];
+const LAYOUT_DIRS: &[&str] = &[
+    "src",
+    "lib",
+    "main",
+    "test",
+    "tests",
+    "__tests__",
+    "spec",
+    "specs",
+];
+
+/// stems too generic to pair on: a tests/mod.rs is a helper, not the test
+/// of every mod.rs
+const GENERIC_STEMS: &[&str] = &["mod", "__init__", "conftest", "setup"];
+
+/// what a test and its source have in common
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct PairKey {
+    stem: String,
+    family: String,
+}
+
+struct Parsed<'a> {
+    path: &'a str,
+    key: PairKey,
+    /// the directory with layout directories dropped
+    dir: Vec<&'a str>,
+}
+
+fn family(extension: &str) -> String {
+    match extension {
+        "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" => "js".to_string(),
+        ext => ext.to_string(),
+    }
+}
+
+/// the stem of a test file name, `foo` for foo_test.go, foo.spec.ts,
+/// test_foo.py or FooTest.java, None when the name carries no test marker
+fn test_stem(name: &str, extension: &str) -> Option<String> {
+    let stem = name.strip_suffix(extension)?.strip_suffix('.')?;
+    for marker in [".test", ".spec", "_test", "_tests", "_spec"] {
+        if let Some(stem) = stem.strip_suffix(marker) {
+            return Some(stem.to_string());
+        }
+    }
+    if let Some(stem) = stem.strip_prefix("test_") {
+        return Some(stem.to_string());
+    }
+    for marker in ["Test", "Tests", "Spec"] {
+        match stem.strip_suffix(marker) {
+            Some(stem) if !stem.is_empty() => return Some(stem.to_string()),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// (is a test, parsed) for files with an extension
+fn parse(path: &str) -> Option<(bool, Parsed<'_>)> {
+    let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let (_, extension) = name.rsplit_once('.')?;
+    let components: Vec<&str> = dir.split('/').filter(|c| !c.is_empty()).collect();
+    let in_test_dir = components.iter().any(|c| TEST_DIRS.contains(c));
+
+    let marked = test_stem(name, extension);
+    let is_test = marked.is_some() || in_test_dir;
+    let stem = marked.unwrap_or_else(|| name[..name.len() - extension.len() - 1].to_string());
+    if GENERIC_STEMS.contains(&stem.as_str()) {
+        return None;
+    }
+
+    Some((
+        is_test,
+        Parsed {
+            path,
+            key: PairKey {
+                stem,
+                family: family(extension),
+            },
+            dir: components
+                .into_iter()
+                .filter(|c| !LAYOUT_DIRS.contains(c))
+                .collect(),
+        },
+    ))
+}
+
+/// whether `path` is a test by the naming and directory conventions of the
+/// tests-only preset
+pub fn is_test_file(path: &str) -> bool {
+    parse(path).is_some_and(|(is_test, _)| is_test)
+}
+
+/// files among `candidates` that `with_tests` (the tests of selected sources)
+/// and `with_sources` (the sources of selected tests) add to `selected`, each
+/// with the selected file it pairs with. A test pairs with a source of the
+/// same name whose directory is at or below its own, layout directories
+/// like src/ and tests/ aside.
+pub fn paired_files<'a>(
+    selected: impl IntoIterator<Item = &'a str>,
+    candidates: impl IntoIterator<Item = &'a str>,
+    with_tests: bool,
+    with_sources: bool,
+) -> Vec<(String, String)> {
+    let selected: Vec<&str> = selected.into_iter().collect();
+    let already: HashSet<&str> = selected.iter().copied().collect();
+
+    let mut by_key: BTreeMap<(bool, PairKey), Vec<Parsed>> = BTreeMap::new();
+    for (is_test, parsed) in candidates
+        .into_iter()
+        .filter(|c| !already.contains(c))
+        .filter_map(parse)
+    {
+        by_key
+            .entry((is_test, parsed.key.clone()))
+            .or_default()
+            .push(parsed);
+    }
+
+    let mut paired = Vec::new();
+    let mut seen = HashSet::new();
+    for (is_test, file) in selected.into_iter().filter_map(parse) {
+        if (is_test && !with_sources) || (!is_test && !with_tests) {
+            continue;
+        }
+        let Some(partners) = by_key.get(&(!is_test, file.key.clone())) else {
+            continue;
+        };
+        for partner in partners {
+            let (test, source) = if is_test {
+                (&file, partner)
+            } else {
+                (partner, &file)
+            };
+            if source.dir.starts_with(&test.dir) && seen.insert(partner.path) {
+                paired.push((partner.path.to_string(), file.path.to_string()));
+            }
+        }
+    }
+    paired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pair_tests_and_sources() {
+        let files = [
+            "core/src/parser.rs",
+            "core/tests/parser.rs",
+            "core/tests/mod.rs",
+            "core/src/mod.rs",
+            "pkg/server.go",
+            "pkg/server_test.go",
+            "web/src/app.tsx",
+            "web/src/__tests__/app.test.ts",
+            "lib/util.py",
+            "tests/test_util.py",
+            "other/tests/test_util.py",
+            "src/main/java/x/Foo.java",
+            "src/test/java/x/FooTest.java",
+        ];
+        let sources = [
+            "core/src/parser.rs",
+            "core/src/mod.rs",
+            "pkg/server.go",
+            "web/src/app.tsx",
+            "lib/util.py",
+        ];
+
+        let paired = paired_files(sources, files, true, false);
+        let tests: Vec<&str> = paired.iter().map(|(test, _)| test.as_str()).collect();
+        assert_eq!(
+            tests,
+            [
+                "core/tests/parser.rs",
+                "pkg/server_test.go",
+                "web/src/__tests__/app.test.ts",
+                "tests/test_util.py"
+            ]
+        );
+
+        let paired = paired_files(["src/test/java/x/FooTest.java"], files, false, true);
+        assert_eq!(
+            paired,
+            [(
+                "src/main/java/x/Foo.java".to_string(),
+                "src/test/java/x/FooTest.java".to_string()
+            )]
+        );
+        assert!(paired_files(["pkg/server.go"], files, false, true).is_empty());
+
+        assert!(is_test_file("a/b_test.go") && is_test_file("tests/helpers.rs"));
+        assert!(!is_test_file("src/testing.rs") && !is_test_file("src/Test.java"));
+    }
+}
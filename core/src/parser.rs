@@ -24,298 +24,258 @@ pub enum GitHubUrlType {
     GitLabTree,
     GitLabBlob,
     GitLabMergeRequest,
+    /// `owner/repo@<rev>`, the repository pinned to an exact revision
+    Pinned,
 }
 
-pub fn parse_github_url(url: &str) -> Option<ParsedGitHubUrl> {
+/// split `repo@rev` into the repository name and the pinned revision
+pub fn split_revision(repo: &str) -> (&str, Option<&str>) {
+    match repo.split_once('@') {
+        Some((name, rev)) if !name.is_empty() && !rev.is_empty() => (name, Some(rev)),
+        _ => (repo, None),
+    }
+}
+
+/// a hosting service: the domain its urls start with and how the path
+/// after it splits into repository and route; new hosts are an entry in
+/// `GITHUB_HOSTS` or `GITLAB_HOSTS`
+struct Host {
+    domain: &'static str,
+    /// also matched without a scheme, like `github.com/owner/repo`
+    schemeless: bool,
+    parse: fn(&[&str]) -> Option<ParsedGitHubUrl>,
+}
+
+const GITHUB_HOSTS: [Host; 3] = [
+    Host {
+        domain: "gist.github.com",
+        schemeless: false,
+        parse: parse_gist_path,
+    },
+    Host {
+        domain: "raw.githubusercontent.com",
+        schemeless: false,
+        parse: parse_raw_path,
+    },
+    Host {
+        domain: "github.com",
+        schemeless: true,
+        parse: parse_github_path,
+    },
+];
+
+const GITLAB_HOSTS: [Host; 1] = [Host {
+    domain: "gitlab.com",
+    schemeless: true,
+    parse: parse_gitlab_path,
+}];
+
+/// `url` parsed by the first of `hosts` it is on
+fn parse_on(hosts: &[Host], url: &str) -> Option<ParsedGitHubUrl> {
     let url = url.trim().trim_end_matches('/');
+    let unschemed = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"));
+
+    let parsed = hosts.iter().find_map(|host| {
+        let rest = unschemed.or(host.schemeless.then_some(url))?;
+        let path = rest.strip_prefix(host.domain)?.strip_prefix('/')?;
+        (host.parse)(&path.split('/').collect::<Vec<_>>())
+    })?;
+    // `owner//tree/main` has no repository to point at
+    (!parsed.owner.is_empty() && !parsed.repo.is_empty()).then_some(parsed)
+}
 
-    if url.contains("gist.github.com") {
-        return parse_gist_url(url);
+/// where a url points inside its repository
+struct Route {
+    url_type: GitHubUrlType,
+    branch: Option<String>,
+    path: Option<String>,
+}
+
+impl Route {
+    fn new(url_type: GitHubUrlType) -> Self {
+        Self {
+            url_type,
+            branch: None,
+            path: None,
+        }
     }
 
-    if url.contains("raw.githubusercontent.com") {
-        return parse_raw_url(url);
+    fn at(mut self, branch: impl Into<String>) -> Self {
+        self.branch = Some(branch.into());
+        self
     }
 
-    if let Some(path) = url
-        .strip_prefix("https://github.com/")
-        .or_else(|| url.strip_prefix("http://github.com/"))
-        .or_else(|| url.strip_prefix("github.com/"))
-    {
-        let parts: Vec<&str> = path.split('/').collect();
-        if parts.len() >= 2 {
-            let owner = parts[0].to_string();
-            let repo = parts[1].to_string();
-
-            if parts.len() == 2 {
-                return Some(ParsedGitHubUrl {
-                    owner: owner.clone(),
-                    repo: repo.clone(),
-                    branch: None,
-                    path: None,
-                    url_type: GitHubUrlType::Repository,
-                    canonical_url: format!("https://github.com/{}/{}", owner, repo),
-                });
-            }
+    /// `parts` joined as the path, none when empty
+    fn below(mut self, parts: &[&str]) -> Self {
+        self.path = (!parts.is_empty()).then(|| parts.join("/"));
+        self
+    }
 
-            if parts.len() >= 4 {
-                match parts[2] {
-                    "tree" | "blob" => {
-                        let all_parts = &parts[3..];
-                        if all_parts.is_empty() {
-                            return None;
-                        }
-
-                        let mut branch_end_idx = all_parts.len();
-                        for (i, part) in all_parts.iter().enumerate() {
-                            if part.contains('.') && !part.ends_with(".git") {
-                                branch_end_idx = i;
-                                break;
-                            }
-                            if matches!(
-                                *part,
-                                "src"
-                                    | "lib"
-                                    | "test"
-                                    | "tests"
-                                    | "docs"
-                                    | "bin"
-                                    | "pkg"
-                                    | "cmd"
-                                    | "internal"
-                                    | "api"
-                                    | "web"
-                                    | "client"
-                                    | "server"
-                                    | "assets"
-                                    | "public"
-                            ) {
-                                branch_end_idx = i;
-                                break;
-                            }
-                        }
-
-                        let branch = all_parts[..branch_end_idx].join("/");
-                        let path = if branch_end_idx < all_parts.len() {
-                            Some(all_parts[branch_end_idx..].join("/"))
-                        } else {
-                            None
-                        };
-
-                        return Some(ParsedGitHubUrl {
-                            owner: owner.clone(),
-                            repo: repo.clone(),
-                            branch: Some(branch),
-                            path,
-                            url_type: if parts[2] == "tree" {
-                                GitHubUrlType::Tree
-                            } else {
-                                GitHubUrlType::Blob
-                            },
-                            canonical_url: format!("https://github.com/{}/{}", owner, repo),
-                        });
-                    }
-                    "commit" => {
-                        return Some(ParsedGitHubUrl {
-                            owner: owner.clone(),
-                            repo: repo.clone(),
-                            branch: Some(parts[3].to_string()),
-                            path: None,
-                            url_type: GitHubUrlType::Commit,
-                            canonical_url: format!("https://github.com/{}/{}", owner, repo),
-                        });
-                    }
-                    "compare" => {
-                        let compare_spec = parts[3..].join("/");
-                        return Some(ParsedGitHubUrl {
-                            owner: owner.clone(),
-                            repo: repo.clone(),
-                            branch: Some(compare_spec),
-                            path: None,
-                            url_type: GitHubUrlType::Compare,
-                            canonical_url: format!("https://github.com/{}/{}", owner, repo),
-                        });
-                    }
-                    _ => {}
-                }
-            }
+    fn parsed(self, owner: &str, repo: &str, canonical_url: String) -> ParsedGitHubUrl {
+        ParsedGitHubUrl {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            branch: self.branch,
+            path: self.path,
+            url_type: self.url_type,
+            canonical_url,
         }
     }
-
-    None
 }
 
-fn parse_gist_url(url: &str) -> Option<ParsedGitHubUrl> {
-    if let Some(path) = url
-        .strip_prefix("https://gist.github.com/")
-        .or_else(|| url.strip_prefix("http://gist.github.com/"))
-    {
-        let parts: Vec<&str> = path.split('/').collect();
-
-        if parts.len() == 1 {
-            return Some(ParsedGitHubUrl {
-                owner: "anonymous".to_string(),
-                repo: parts[0].to_string(),
-                branch: None,
-                path: None,
-                url_type: GitHubUrlType::Gist,
-                canonical_url: format!("https://gist.github.com/{}", parts[0]),
-            });
+/// a route from the parts of a url after the route's name
+type RouteParser = fn(&[&str]) -> Option<Route>;
+
+/// the routes below `github.com/owner/repo/`, by their first part, given
+/// the parts after it (at least one)
+const GITHUB_ROUTES: [(&str, RouteParser); 5] = [
+    ("tree", |args| {
+        Some(branch_then_path(Route::new(GitHubUrlType::Tree), args))
+    }),
+    ("blob", |args| {
+        Some(branch_then_path(Route::new(GitHubUrlType::Blob), args))
+    }),
+    ("commit", |args| {
+        Some(Route::new(GitHubUrlType::Commit).at(args[0]))
+    }),
+    // a release is its tag, pinned like `owner/repo@tag`
+    ("releases", |args| match args {
+        ["tag", tag @ ..] if !tag.is_empty() => {
+            Some(Route::new(GitHubUrlType::Pinned).at(tag.join("/")))
         }
+        _ => None,
+    }),
+    ("compare", |args| {
+        Some(Route::new(GitHubUrlType::Compare).at(args.join("/")))
+    }),
+];
+
+/// directories that usually start the path of a tree or blob url, a
+/// branch name may itself contain slashes
+const PATH_ROOTS: [&str; 15] = [
+    "src", "lib", "test", "tests", "docs", "bin", "pkg", "cmd", "internal", "api", "web", "client",
+    "server", "assets", "public",
+];
+
+/// `branch/path` where the branch ends before the first part that looks
+/// like a file name or a common top level directory
+fn branch_then_path(route: Route, parts: &[&str]) -> Route {
+    let branch_end = parts
+        .iter()
+        .position(|part| {
+            (part.contains('.') && !part.ends_with(".git")) || PATH_ROOTS.contains(part)
+        })
+        .unwrap_or(parts.len());
+    route
+        .at(parts[..branch_end].join("/"))
+        .below(&parts[branch_end..])
+}
 
-        if parts.len() >= 2 {
-            return Some(ParsedGitHubUrl {
-                owner: parts[0].to_string(),
-                repo: parts[1].to_string(),
-                branch: None,
-                path: None,
-                url_type: GitHubUrlType::Gist,
-                canonical_url: format!("https://gist.github.com/{}/{}", parts[0], parts[1]),
-            });
+fn parse_github_path(parts: &[&str]) -> Option<ParsedGitHubUrl> {
+    let [owner, repo, rest @ ..] = parts else {
+        return None;
+    };
+    let github = |repo: &str, route: Route| {
+        route.parsed(owner, repo, format!("https://github.com/{owner}/{repo}"))
+    };
+
+    match rest {
+        [] => Some(match split_revision(repo) {
+            (name, Some(revision)) => github(name, Route::new(GitHubUrlType::Pinned).at(revision)),
+            _ => github(repo, Route::new(GitHubUrlType::Repository)),
+        }),
+        [action, args @ ..] if !args.is_empty() => {
+            let (_, route) = GITHUB_ROUTES.iter().find(|(name, _)| name == action)?;
+            Some(github(repo, route(args)?))
         }
+        _ => None,
     }
-
-    None
 }
 
-fn parse_raw_url(url: &str) -> Option<ParsedGitHubUrl> {
-    let path = url
-        .strip_prefix("https://raw.githubusercontent.com/")
-        .or_else(|| url.strip_prefix("http://raw.githubusercontent.com/"))?;
-
-    let parts: Vec<&str> = path.split('/').collect();
-    if parts.len() >= 3 {
-        let owner = parts[0].to_string();
-        let repo = parts[1].to_string();
-        let branch = parts[2].to_string();
-        let path = if parts.len() > 3 {
-            Some(parts[3..].join("/"))
-        } else {
-            None
-        };
-
-        return Some(ParsedGitHubUrl {
-            owner: owner.clone(),
-            repo: repo.clone(),
-            branch: Some(branch),
-            path,
-            url_type: GitHubUrlType::Raw,
-            canonical_url: format!("https://github.com/{}/{}", owner, repo),
-        });
+fn parse_gist_path(parts: &[&str]) -> Option<ParsedGitHubUrl> {
+    let route = Route::new(GitHubUrlType::Gist);
+    match parts {
+        [id] => Some(route.parsed("anonymous", id, format!("https://gist.github.com/{id}"))),
+        [owner, id, ..] => {
+            Some(route.parsed(owner, id, format!("https://gist.github.com/{owner}/{id}")))
+        }
+        [] => None,
     }
-
-    None
 }
 
-fn parse_gitlab_url(url: &str) -> Option<ParsedGitHubUrl> {
-    let url = url.trim().trim_end_matches('/');
-
-    let path = url
-        .strip_prefix("https://gitlab.com/")
-        .or_else(|| url.strip_prefix("http://gitlab.com/"))
-        .or_else(|| url.strip_prefix("gitlab.com/"))?;
-
-    // gitlab URLs can have subgroups: owner/subgroup1/subgroup2/project
-    // we need to find where the project name ends
-    let parts: Vec<&str> = path.split('/').collect();
+fn parse_raw_path(parts: &[&str]) -> Option<ParsedGitHubUrl> {
+    let [owner, repo, branch, path @ ..] = parts else {
+        return None;
+    };
+    let route = Route::new(GitHubUrlType::Raw).at(*branch).below(path);
+    Some(route.parsed(owner, repo, format!("https://github.com/{owner}/{repo}")))
+}
 
+/// the routes after the `/-/` of a gitlab url, by their first part, given
+/// the parts after it
+const GITLAB_ROUTES: [(&str, RouteParser); 3] = [
+    // /-/tree/branch or /-/tree/branch/path
+    ("tree", |args| match args {
+        [branch, path @ ..] => Some(
+            Route::new(GitHubUrlType::GitLabTree)
+                .at(*branch)
+                .below(path),
+        ),
+        [] => None,
+    }),
+    // /-/blob/branch/path/to/file
+    ("blob", |args| match args {
+        [branch, path @ ..] if !path.is_empty() => Some(
+            Route::new(GitHubUrlType::GitLabBlob)
+                .at(*branch)
+                .below(path),
+        ),
+        _ => None,
+    }),
+    // /-/merge_requests/123
+    ("merge_requests", |args| {
+        let number = args.first()?;
+        Some(Route::new(GitHubUrlType::GitLabMergeRequest).at(*number))
+    }),
+];
+
+fn parse_gitlab_path(parts: &[&str]) -> Option<ParsedGitHubUrl> {
     if parts.len() < 2 {
         return None;
     }
 
-    // find the "/-/" separator which indicates the end of the project path
-    let separator_idx = parts.iter().position(|&p| p == "-");
+    // gitlab projects can sit in subgroups, owner/subgroup1/subgroup2/project;
+    // a "-" part ends the project path and starts the route (tree, blob, ...)
+    let (project, route) = match parts.iter().position(|&part| part == "-") {
+        Some(separator) => {
+            let [action, args @ ..] = &parts[separator + 1..] else {
+                return None;
+            };
+            let (_, route) = GITLAB_ROUTES.iter().find(|(name, _)| name == action)?;
+            (&parts[..separator], route(args)?)
+        }
+        None => (parts, Route::new(GitHubUrlType::GitLabRepository)),
+    };
 
-    if let Some(sep_idx) = separator_idx {
-        // URL has /-/ separator (tree, blob, merge_requests, etc.)
-        let project_parts = &parts[0..sep_idx];
-        let action_parts = &parts[sep_idx + 1..];
+    // a project is at least namespace/project
+    let [owner, .., repo] = project else {
+        return None;
+    };
+    Some(route.parsed(
+        owner,
+        repo,
+        format!("https://gitlab.com/{}", project.join("/")),
+    ))
+}
 
-        if project_parts.is_empty() || action_parts.is_empty() {
-            return None;
-        }
+pub fn parse_github_url(url: &str) -> Option<ParsedGitHubUrl> {
+    parse_on(&GITHUB_HOSTS, url)
+}
 
-        let owner = project_parts[0].to_string();
-        let repo = project_parts[project_parts.len() - 1].to_string();
-        let full_path = project_parts.join("/");
-
-        match action_parts[0] {
-            "tree" => {
-                // /-/tree/branch or /-/tree/branch/path
-                if action_parts.len() < 2 {
-                    return None;
-                }
-
-                let branch = action_parts[1].to_string();
-                let path = if action_parts.len() > 2 {
-                    Some(action_parts[2..].join("/"))
-                } else {
-                    None
-                };
-
-                return Some(ParsedGitHubUrl {
-                    owner,
-                    repo,
-                    branch: Some(branch),
-                    path,
-                    url_type: GitHubUrlType::GitLabTree,
-                    canonical_url: format!("https://gitlab.com/{}", full_path),
-                });
-            }
-            "blob" => {
-                // /-/blob/branch/path/to/file
-                if action_parts.len() < 3 {
-                    return None;
-                }
-
-                let branch = action_parts[1].to_string();
-                let path = Some(action_parts[2..].join("/"));
-
-                return Some(ParsedGitHubUrl {
-                    owner,
-                    repo,
-                    branch: Some(branch),
-                    path,
-                    url_type: GitHubUrlType::GitLabBlob,
-                    canonical_url: format!("https://gitlab.com/{}", full_path),
-                });
-            }
-            "merge_requests" => {
-                // /-/merge_requests/123
-                if action_parts.len() < 2 {
-                    return None;
-                }
-
-                let mr_number = action_parts[1].to_string();
-
-                return Some(ParsedGitHubUrl {
-                    owner,
-                    repo,
-                    branch: Some(mr_number),
-                    path: None,
-                    url_type: GitHubUrlType::GitLabMergeRequest,
-                    canonical_url: format!("https://gitlab.com/{}", full_path),
-                });
-            }
-            _ => {
-                return None;
-            }
-        }
-    } else {
-        // no /-/ separator, it's a simple repository URL
-        let owner = parts[0].to_string();
-        let repo = parts[parts.len() - 1].to_string();
-        let full_path = parts.join("/");
-
-        return Some(ParsedGitHubUrl {
-            owner,
-            repo,
-            branch: None,
-            path: None,
-            url_type: GitHubUrlType::GitLabRepository,
-            canonical_url: format!("https://gitlab.com/{}", full_path),
-        });
-    }
+pub fn parse_gitlab_url(url: &str) -> Option<ParsedGitHubUrl> {
+    parse_on(&GITLAB_HOSTS, url)
 }
 
 pub fn normalize_source_url(
@@ -358,3 +318,328 @@ pub fn validate_github_name(name: &str) -> bool {
         && !name.starts_with(['-', '.'])
         && !name.ends_with(['-', '.'])
 }
+
+/// branch and tag names that are safe to put in a refspec or on a command line,
+/// a subset of `git check-ref-format` plus a few refspec metacharacters
+pub fn validate_ref_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 255 {
+        return Err("ref name must be 1 to 255 characters".to_string());
+    }
+    if name.starts_with(['-', '+', '/']) || name.ends_with(['/', '.']) {
+        return Err(format!(
+            "ref name '{name}' has an invalid first or last character"
+        ));
+    }
+    if name.chars().any(|c| c.is_control() || c.is_whitespace()) {
+        return Err("ref name must not contain whitespace or control characters".to_string());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| matches!(c, '~' | '^' | ':' | '?' | '*' | '[' | '\\'))
+    {
+        return Err(format!("ref name must not contain '{c}'"));
+    }
+    if name.contains("..") || name.contains("@{") || name.contains("//") || name == "@" {
+        return Err(format!("ref name '{name}' is not a valid git ref"));
+    }
+    if name
+        .split('/')
+        .any(|part| part.starts_with('.') || part.ends_with(".lock"))
+    {
+        return Err(format!("ref name '{name}' is not a valid git ref"));
+    }
+    Ok(())
+}
+
+/// `base...head` or `base..head`, with both sides valid ref names
+pub fn parse_compare_spec(spec: &str) -> Result<(String, String), String> {
+    let (base, head) = spec
+        .split_once("...")
+        .or_else(|| spec.split_once(".."))
+        .ok_or_else(|| format!("compare spec '{spec}' must look like base...head"))?;
+    validate_ref_name(base)?;
+    validate_ref_name(head)?;
+    Ok((base.to_string(), head.to_string()))
+}
+
+/// repository-relative directory such as `src/core`, never escaping the checkout
+pub fn validate_path_prefix(path: &str) -> Result<(), String> {
+    if path.is_empty() || path.len() > 1024 {
+        return Err("must be 1 to 1024 characters".to_string());
+    }
+    if path.starts_with(['/', '-']) || path.contains('\\') {
+        return Err(format!("'{path}' must be relative to the repository root"));
+    }
+    if path.chars().any(char::is_control) {
+        return Err("must not contain control characters".to_string());
+    }
+    if path.split('/').any(|part| part == "..") {
+        return Err(format!("'{path}' must not contain '..'"));
+    }
+    Ok(())
+}
+
+/// a repository-relative file that githem may write, e.g. from a patch or a
+/// dump: never inside `.git`, where git would run or trust what it finds,
+/// and never drive-prefixed like `C:x`, which windows resolves outside `dir`
+pub fn validate_write_path(path: &str) -> Result<(), String> {
+    validate_path_prefix(path)?;
+    let first = path.split('/').next().unwrap_or_default();
+    if first.len() >= 2 && first.as_bytes()[0].is_ascii_alphabetic() && first.as_bytes()[1] == b':'
+    {
+        return Err(format!("'{path}' must not start with a drive letter"));
+    }
+    // case-insensitive filesystems and windows also resolve `.GIT` and `.git.`
+    if path.split('/').any(|part| {
+        part.trim_end_matches(['.', ' '])
+            .eq_ignore_ascii_case(".git")
+    }) {
+        return Err(format!("'{path}' must not be inside .git"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_parse_hosts() {
+        use GitHubUrlType::*;
+        const GITHUB: &str = "https://github.com/o/r";
+        const GITLAB: &str = "https://gitlab.com/g/p";
+
+        let cases = [
+            ("github.com/o/r", Repository, None, None, GITHUB),
+            (
+                "https://github.com/o/r/tree/feature/x/src/lib",
+                Tree,
+                Some("feature/x"),
+                Some("src/lib"),
+                GITHUB,
+            ),
+            (
+                "https://github.com/o/r/blob/main/Cargo.toml",
+                Blob,
+                Some("main"),
+                Some("Cargo.toml"),
+                GITHUB,
+            ),
+            (
+                "https://github.com/o/r/commit/abc123",
+                Commit,
+                Some("abc123"),
+                None,
+                GITHUB,
+            ),
+            (
+                "https://github.com/o/r/compare/a...b",
+                Compare,
+                Some("a...b"),
+                None,
+                GITHUB,
+            ),
+            (
+                "https://raw.githubusercontent.com/o/r/main/a/b.rs",
+                Raw,
+                Some("main"),
+                Some("a/b.rs"),
+                GITHUB,
+            ),
+            (
+                "https://gist.github.com/o/123",
+                Gist,
+                None,
+                None,
+                "https://gist.github.com/o/123",
+            ),
+            (
+                "gitlab.com/g/sub/p",
+                GitLabRepository,
+                None,
+                None,
+                "https://gitlab.com/g/sub/p",
+            ),
+            (
+                "https://gitlab.com/g/p/-/tree/dev/docs",
+                GitLabTree,
+                Some("dev"),
+                Some("docs"),
+                GITLAB,
+            ),
+            (
+                "https://gitlab.com/g/p/-/merge_requests/7",
+                GitLabMergeRequest,
+                Some("7"),
+                None,
+                GITLAB,
+            ),
+        ];
+        for (url, url_type, branch, path, canonical) in cases {
+            let parsed = parse_github_url(url)
+                .or_else(|| parse_gitlab_url(url))
+                .unwrap();
+            assert_eq!(parsed.url_type, url_type, "{url}");
+            assert_eq!(parsed.branch.as_deref(), branch, "{url}");
+            assert_eq!(parsed.path.as_deref(), path, "{url}");
+            assert_eq!(parsed.canonical_url, canonical, "{url}");
+        }
+        let invalid = [
+            "https://github.com/o",
+            "https://github.com/o/r/tree",
+            "gist.github.com/o/1",
+            "https://gitlab.com/g/p/-/blob/main",
+            "https://github.com/o//tree/main",
+            "https://gitlab.com/p/-/tree/main",
+        ];
+        for url in invalid {
+            assert!(
+                parse_github_url(url)
+                    .or_else(|| parse_gitlab_url(url))
+                    .is_none(),
+                "{url}"
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_parse_never_panics(url in "\\PC*") {
+            let _ = parse_github_url(&url);
+            let _ = parse_gitlab_url(&url);
+            let _ = normalize_source_url(&url, None, None);
+        }
+
+        #[test]
+        fn prop_canonical_url_parses(
+            url in "(https?://)?(gist\\.github|github|gitlab|raw\\.githubusercontent)\\.com(/(-|tree|blob|tag|releases|[a-z@.]{0,4})){0,6}/{0,2}"
+        ) {
+            let parse = |url: &str| parse_github_url(url).or_else(|| parse_gitlab_url(url));
+            if let Some(parsed) = parse(&url) {
+                prop_assert!(!parsed.owner.is_empty() && !parsed.repo.is_empty());
+                prop_assert!(parse(&parsed.canonical_url).is_some(), "{} -> {}", url, parsed.canonical_url);
+            }
+        }
+
+        #[test]
+        fn prop_repository_roundtrip(owner in "[a-z0-9][a-z0-9-]{0,12}", repo in "[a-z0-9][a-z0-9_-]{0,12}") {
+            let parsed = parse_github_url(&format!("https://github.com/{owner}/{repo}/")).unwrap();
+            prop_assert_eq!(parsed.url_type, GitHubUrlType::Repository);
+            prop_assert_eq!(parsed.canonical_url, format!("https://github.com/{owner}/{repo}"));
+
+            let parsed = parse_gitlab_url(&format!("gitlab.com/{owner}/group/{repo}")).unwrap();
+            prop_assert_eq!((parsed.owner, parsed.repo), (owner.clone(), repo.clone()));
+            prop_assert_eq!(parsed.canonical_url, format!("https://gitlab.com/{owner}/group/{repo}"));
+        }
+
+        #[test]
+        fn prop_tree_roundtrip(
+            branch in "[a-z]{1,8}(/[a-z]{1,8})?",
+            path in "src(/[a-z]{1,8}){0,3}/[a-z]{1,8}\\.rs",
+        ) {
+            prop_assume!(!branch.split('/').any(|part| PATH_ROOTS.contains(&part)));
+            let parsed = parse_github_url(&format!("https://github.com/o/r/blob/{branch}/{path}")).unwrap();
+            prop_assert_eq!(parsed.branch, Some(branch));
+            prop_assert_eq!(parsed.path, Some(path));
+        }
+    }
+
+    #[test]
+    fn test_validate_ref_name() {
+        for name in ["main", "feature/login", "v1.2.3", "release-2024_01"] {
+            assert!(validate_ref_name(name).is_ok(), "{name}");
+        }
+        for name in [
+            "",
+            "-upload-pack=evil",
+            "+refs/heads/x",
+            "a..b",
+            "main:refs/heads/other",
+            "refs/*",
+            "HEAD@{1}",
+            "bad\nname",
+            "with space",
+            "dir/.hidden",
+            "branch.lock",
+            "trailing/",
+        ] {
+            assert!(validate_ref_name(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_pinned() {
+        for source in [
+            "https://github.com/owner/repo@v1.2.3",
+            "https://github.com/owner/repo/releases/tag/v1.2.3",
+        ] {
+            let parsed = parse_github_url(source).unwrap();
+            assert_eq!(parsed.url_type, GitHubUrlType::Pinned, "{source}");
+            assert_eq!(parsed.repo, "repo");
+            assert_eq!(parsed.branch.as_deref(), Some("v1.2.3"));
+        }
+        assert_eq!(
+            split_revision("repo@release/2.0"),
+            ("repo", Some("release/2.0"))
+        );
+        assert_eq!(split_revision("repo@"), ("repo@", None));
+    }
+
+    #[test]
+    fn test_parse_compare_spec() {
+        assert_eq!(
+            parse_compare_spec("main...feature/x"),
+            Ok(("main".to_string(), "feature/x".to_string()))
+        );
+        assert_eq!(
+            parse_compare_spec("v1.0..v2.0"),
+            Ok(("v1.0".to_string(), "v2.0".to_string()))
+        );
+        assert!(parse_compare_spec("main").is_err());
+        assert!(parse_compare_spec("main...").is_err());
+        assert!(parse_compare_spec("main...--upload-pack=x").is_err());
+    }
+
+    #[test]
+    fn test_validate_path_prefix() {
+        for path in ["src", "crates/core/", "docs/v1..2"] {
+            assert!(validate_path_prefix(path).is_ok(), "{path}");
+        }
+        for path in [
+            "",
+            "/etc",
+            "../secrets",
+            "src/../../x",
+            "-rf",
+            "a\\b",
+            "src\0",
+        ] {
+            assert!(validate_path_prefix(path).is_err(), "{path:?}");
+        }
+    }
+
+    #[test]
+    fn test_validate_write_path() {
+        for path in [
+            "src/main.rs",
+            ".github/workflows/ci.yml",
+            "docs/.gitignore",
+            "ab:c",
+        ] {
+            assert!(validate_write_path(path).is_ok(), "{path}");
+        }
+        for path in [
+            ".git/config",
+            ".git/hooks/pre-commit",
+            "sub/.git/HEAD",
+            ".GIT/config",
+            ".git./x",
+            "C:x",
+            "c:/x",
+            "../x",
+        ] {
+            assert!(validate_write_path(path).is_err(), "{path:?}");
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use crate::transform::split_line_number;
+use anyhow::{bail, Result};
+
+/// footer of the patch output mode, asking for an answer `githem apply` can use
+pub const PATCH_INSTRUCTIONS: &str = "\
+# Response format
+
+Answer with changes as a unified diff, one `--- a/path` / `+++ b/path` pair
+per file and `@@ -old,count +new,count @@` hunks with at least three lines of
+context. The `  42 | ` prefixes above are line numbers, not file content: use
+them for the hunk headers and leave them out of the diff lines. Use
+/dev/null as the old path for new files and as the new path for deleted files.
+";
+
+/// one line of a hunk body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hunk {
+    /// 1-based line the hunk starts at in the old file, 0 for an empty file
+    pub old_start: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    /// the lines the hunk expects to find
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// the changes to one file; a None path is `/dev/null`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// the file the patch applies to
+    pub fn path(&self) -> Option<&str> {
+        self.new_path.as_deref().or(self.old_path.as_deref())
+    }
+
+    pub fn is_creation(&self) -> bool {
+        self.old_path.is_none()
+    }
+
+    pub fn is_deletion(&self) -> bool {
+        self.new_path.is_none()
+    }
+}
+
+/// `a/src/lib.rs` as `src/lib.rs`, None for /dev/null
+fn diff_path(text: &str) -> Option<String> {
+    // `--- a/file.rs\t2024-01-01 ...` from diff -u
+    let path = text.split('\t').next().unwrap_or(text).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// start line of the old side of an `@@ -12,5 +12,7 @@` header
+fn hunk_start(header: &str) -> Option<usize> {
+    let old = header.strip_prefix("@@ -")?.split([' ', ',']).next()?;
+    old.parse().ok()
+}
+
+/// drop line numbers a model copied from numbered output, but only when
+/// every old line of the hunk has one, so real `1 | x` content survives
+fn strip_line_numbers(hunk: &mut Hunk) {
+    let old = hunk.old_lines();
+    let numbered = !old.is_empty() && old.iter().all(|line| split_line_number(line).is_some());
+    if !numbered {
+        return;
+    }
+    for line in &mut hunk.lines {
+        let (HunkLine::Context(text) | HunkLine::Remove(text) | HunkLine::Add(text)) = line;
+        if let Some((_, rest)) = split_line_number(text) {
+            *text = rest.to_string();
+        }
+    }
+}
+
+/// parse a unified diff, as produced by git or written by a model; code
+/// fences, prose around the diff and wrong hunk line counts are tolerated
+pub fn parse_patch(text: &str) -> Result<Vec<FilePatch>> {
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut lines = text.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(old) = line.strip_prefix("--- ") {
+            let Some(new) = lines.peek().and_then(|next| next.strip_prefix("+++ ")) else {
+                continue;
+            };
+            let new = new.to_string();
+            lines.next();
+            patches.push(FilePatch {
+                old_path: diff_path(old),
+                new_path: diff_path(&new),
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some(start) = hunk_start(line) else {
+            continue;
+        };
+        let Some(patch) = patches.last_mut() else {
+            bail!("hunk `{line}` comes before any --- / +++ file header");
+        };
+
+        let mut hunk = Hunk {
+            old_start: start,
+            lines: Vec::new(),
+        };
+        while let Some(&next) = lines.peek() {
+            let body = if next.starts_with("--- ")
+                && lines.clone().nth(1).is_some_and(|l| l.starts_with("+++ "))
+            {
+                None
+            } else if let Some(text) = next.strip_prefix('+') {
+                Some(HunkLine::Add(text.to_string()))
+            } else if let Some(text) = next.strip_prefix('-') {
+                Some(HunkLine::Remove(text.to_string()))
+            } else if let Some(text) = next.strip_prefix(' ') {
+                Some(HunkLine::Context(text.to_string()))
+            } else if next.is_empty() {
+                // editors and models drop the space of empty context lines
+                Some(HunkLine::Context(String::new()))
+            } else if next.starts_with('\\') {
+                // `\ No newline at end of file`
+                lines.next();
+                continue;
+            } else {
+                None
+            };
+            match body {
+                Some(body) => {
+                    hunk.lines.push(body);
+                    lines.next();
+                }
+                None => break,
+            }
+        }
+        // trailing blank lines are usually the gap before the next file
+        while hunk.lines.last() == Some(&HunkLine::Context(String::new())) {
+            hunk.lines.pop();
+        }
+        strip_line_numbers(&mut hunk);
+        patch.hunks.push(hunk);
+    }
+
+    patches.retain(|patch| !patch.hunks.is_empty() || patch.is_deletion());
+    if patches.is_empty() {
+        bail!("no unified diff found");
+    }
+    Ok(patches)
+}
+
+/// where `old` occurs in `lines`, preferring the spot closest to `expected`;
+/// exact matches win over ones that only differ in whitespace
+fn find_block(lines: &[&str], old: &[&str], expected: usize) -> Option<usize> {
+    if old.is_empty() {
+        return Some(expected.min(lines.len()));
+    }
+    if old.len() > lines.len() {
+        return None;
+    }
+
+    let last = lines.len() - old.len();
+    let mut candidates: Vec<usize> = (0..=last).collect();
+    candidates.sort_by_key(|&at| at.abs_diff(expected));
+
+    let exact = |at: &usize| lines[*at..*at + old.len()] == *old;
+    let loose = |at: &usize| {
+        lines[*at..*at + old.len()]
+            .iter()
+            .zip(old)
+            .all(|(line, want)| line.split_whitespace().eq(want.split_whitespace()))
+    };
+    candidates
+        .iter()
+        .copied()
+        .find(exact)
+        .or_else(|| candidates.iter().copied().find(loose))
+}
+
+/// `original` with the hunks applied, in order; hunks may have drifted
+/// from the line numbers in their headers
+pub fn apply_hunks(original: &str, hunks: &[Hunk]) -> Result<String> {
+    let mut lines: Vec<String> = original.lines().map(str::to_string).collect();
+    // lines added minus lines removed by the hunks applied so far
+    let mut shift: isize = 0;
+
+    for (i, hunk) in hunks.iter().enumerate() {
+        let old = hunk.old_lines();
+        let expected = (hunk.old_start.saturating_sub(1) as isize + shift).max(0) as usize;
+        let current: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let Some(at) = find_block(&current, &old, expected) else {
+            bail!(
+                "hunk {} (at line {}) does not match the file",
+                i + 1,
+                hunk.old_start
+            );
+        };
+
+        let mut replacement = Vec::new();
+        let mut cursor = at;
+        for line in &hunk.lines {
+            match line {
+                // keep the file's own version of loosely matched lines
+                HunkLine::Context(_) => {
+                    replacement.push(lines[cursor].clone());
+                    cursor += 1;
+                }
+                HunkLine::Remove(_) => cursor += 1,
+                HunkLine::Add(text) => replacement.push(text.clone()),
+            }
+        }
+        shift += replacement.len() as isize - old.len() as isize;
+        lines.splice(at..at + old.len(), replacement);
+    }
+
+    let mut patched = lines.join("\n");
+    if !lines.is_empty() && (original.ends_with('\n') || original.is_empty()) {
+        patched.push('\n');
+    }
+    Ok(patched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_apply_patch() {
+        let original = "fn main() {\n    let x = 1;\n    println!(\"{x}\");\n}\n";
+        // the hunk header is off by two lines, like models tend to write them
+        let diff = "Here is the fix:\n```diff\n--- a/src/main.rs\n+++ b/src/main.rs\n\
+                    @@ -3,3 +3,3 @@\n fn main() {\n-    let x = 1;\n+    let x = 2;\n     println!(\"{x}\");\n```\n";
+
+        let patches = parse_patch(diff).unwrap();
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].path(), Some("src/main.rs"));
+        assert_eq!(
+            apply_hunks(original, &patches[0].hunks).unwrap(),
+            "fn main() {\n    let x = 2;\n    println!(\"{x}\");\n}\n"
+        );
+
+        // line numbers copied from --line-numbers output, whitespace drift
+        let numbered = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -2,1 +2,1 @@\n-   2 |   let x = 1;\n+   2 |     let x = 3;\n";
+        let patches = parse_patch(numbered).unwrap();
+        assert!(apply_hunks(original, &patches[0].hunks)
+            .unwrap()
+            .contains("    let x = 3;\n"));
+
+        let created =
+            parse_patch("--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n+a\n+b\n").unwrap();
+        assert!(created[0].is_creation());
+        assert_eq!(apply_hunks("", &created[0].hunks).unwrap(), "a\nb\n");
+
+        let unrelated = parse_patch("--- a/x\n+++ b/x\n@@ -1 +1 @@\n-nope\n+yes\n").unwrap();
+        assert!(apply_hunks(original, &unrelated[0].hunks).is_err());
+        assert!(parse_patch("no diff here").is_err());
+    }
+}
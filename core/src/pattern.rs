@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+
+/// How include and exclude patterns are interpreted
+///
+/// With `Glob` a pattern is matched like a `.gitignore` line:
+/// - `*` and `?` stay within one path segment, `**` spans directories,
+///   `[abc]` / `[a-z]` / `[!a]` match one character
+/// - a pattern without `/` matches a file or directory name at any depth
+/// - a leading `/`, or any `/` inside the pattern, anchors it at the
+///   repository root
+/// - a trailing `/` only matches directories (and so everything below them)
+/// - a `(?i)` prefix makes the match case-insensitive
+///
+/// A matching directory matches every path below it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PatternSyntax {
+    #[default]
+    Glob,
+    /// prefix/suffix matching of earlier releases, kept for existing scripts
+    Legacy,
+}
+
+impl PatternSyntax {
+    /// exclude semantics: does `pattern` cover `path`
+    pub fn excludes(self, pattern: &str, path: &str) -> bool {
+        match self {
+            PatternSyntax::Glob => pattern_matches(pattern, path),
+            PatternSyntax::Legacy => crate::glob_match(pattern, path),
+        }
+    }
+
+    /// whether `pattern` excludes every path below the directory `dir`, so
+    /// a tree walk need not descend into it
+    pub fn excludes_dir(self, pattern: &str, dir: &str) -> bool {
+        match self {
+            // `dir/` stands for any child: it matches when `dir` or a parent
+            // does as a directory, or when the pattern ends in `*` or `**`
+            // right after `dir/`, which matches every name there as well
+            PatternSyntax::Glob => pattern_matches(pattern, &format!("{dir}/")),
+            PatternSyntax::Legacy => legacy_excludes_dir(pattern, dir),
+        }
+    }
+
+    /// include semantics, which in legacy mode differ from exclude ones
+    pub fn includes(self, pattern: &str, path: &str) -> bool {
+        match self {
+            PatternSyntax::Glob => pattern_matches(pattern, path),
+            PatternSyntax::Legacy => legacy_include_matches(pattern, path),
+        }
+    }
+}
+
+/// `crate::glob_match` holding for any path below `dir`
+fn legacy_excludes_dir(pattern: &str, dir: &str) -> bool {
+    if pattern.starts_with("*.") {
+        return false;
+    }
+    if let Some(prefix) = pattern.strip_suffix("/*") {
+        return dir.starts_with(prefix);
+    }
+    if pattern.contains('*') {
+        let parts: Vec<&str> = pattern.split('*').collect();
+        if parts.len() == 2 {
+            return parts[1].is_empty() && dir.starts_with(parts[0]);
+        }
+    }
+    dir == pattern || dir.starts_with(&format!("{pattern}/"))
+}
+
+fn legacy_include_matches(pattern: &str, path: &str) -> bool {
+    if let Some(dir_prefix) = pattern.strip_suffix('/') {
+        path.starts_with(dir_prefix) && path.len() > dir_prefix.len()
+    } else if !pattern.contains('/') {
+        let filename = path.rsplit('/').next().unwrap_or(path);
+        crate::glob_match(pattern, filename)
+    } else {
+        crate::glob_match(pattern, path)
+    }
+}
+
+/// match a repository-relative `/` separated path with `Glob` semantics
+pub fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (case_insensitive, pattern) = match pattern.strip_prefix("(?i)") {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let (anchored, pattern) = match pattern.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (pattern.contains('/'), pattern),
+    };
+    if pattern.is_empty() {
+        return false;
+    }
+
+    let (pattern, path) = if case_insensitive {
+        (pattern.to_lowercase(), path.to_lowercase())
+    } else {
+        (pattern.to_string(), path.to_string())
+    };
+    let tokens = tokenize(&pattern);
+
+    if anchored {
+        // the path itself or one of the directories above it
+        let mut parents = path.match_indices('/').map(|(i, _)| &path[..i]);
+        parents.any(|dir| wildcard(&tokens, dir)) || (!dir_only && wildcard(&tokens, &path))
+    } else {
+        let mut segments = path.split('/').peekable();
+        while let Some(segment) = segments.next() {
+            let is_dir = segments.peek().is_some();
+            if (is_dir || !dir_only) && wildcard(&tokens, segment) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+enum Token {
+    Literal(char),
+    /// `?`
+    Any,
+    /// `*`
+    Star,
+    /// `**`
+    GlobStar,
+    /// `**/`, which also matches no directory at all
+    GlobStarSlash,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    tokens.push(Token::GlobStarSlash);
+                    i += 3;
+                } else {
+                    tokens.push(Token::GlobStar);
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => tokens.push(Token::Star),
+            '?' => tokens.push(Token::Any),
+            '[' => {
+                if let Some((class, len)) = parse_class(&chars[i + 1..]) {
+                    tokens.push(class);
+                    i += len + 1;
+                    continue;
+                }
+                tokens.push(Token::Literal('['));
+            }
+            c => tokens.push(Token::Literal(c)),
+        }
+        i += 1;
+    }
+
+    tokens
+}
+
+/// `[...]` after the opening bracket, with the number of chars consumed
+/// (an unterminated class is a literal `[`)
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut ranges = Vec::new();
+    let mut first = true;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == ']' && !first {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&end| end != ']') {
+            ranges.push((c, chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((c, c));
+            i += 1;
+        }
+        first = false;
+    }
+
+    None
+}
+
+/// dynamic programming over text positions, linear in pattern length
+/// so hostile patterns cannot blow up
+fn wildcard(tokens: &[Token], text: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let n = text.len();
+
+    // reachable[j]: the tokens so far can consume exactly text[..j]
+    let mut reachable = vec![false; n + 1];
+    reachable[0] = true;
+
+    for token in tokens {
+        let mut next = vec![false; n + 1];
+        match token {
+            Token::Star => {
+                for j in 0..=n {
+                    next[j] = reachable[j] || (j > 0 && next[j - 1] && text[j - 1] != '/');
+                }
+            }
+            Token::GlobStar => {
+                for j in 0..=n {
+                    next[j] = reachable[j] || (j > 0 && next[j - 1]);
+                }
+            }
+            Token::GlobStarSlash => {
+                let mut seen = false;
+                for j in 0..=n {
+                    next[j] = reachable[j] || (seen && text[j - 1] == '/');
+                    seen |= reachable[j];
+                }
+            }
+            single => {
+                for j in 0..n {
+                    next[j + 1] = reachable[j] && matches_char(single, text[j]);
+                }
+            }
+        }
+        reachable = next;
+    }
+
+    reachable[n]
+}
+
+fn matches_char(token: &Token, c: char) -> bool {
+    match token {
+        Token::Literal(literal) => *literal == c,
+        Token::Any => c != '/',
+        Token::Class { negated, ranges } => {
+            c != '/' && ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+        }
+        Token::Star | Token::GlobStar | Token::GlobStarSlash => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_modifiers() {
+        // unanchored names match at any depth
+        assert!(pattern_matches("*.rs", "src/main.rs"));
+        assert!(pattern_matches(
+            "node_modules",
+            "web/node_modules/x/index.js"
+        ));
+        // a slash anchors the pattern at the root
+        assert!(pattern_matches("bin/*", "bin/tool"));
+        assert!(!pattern_matches("bin/*", "cabin/tool"));
+        assert!(!pattern_matches("bin/*", "binary.rs"));
+        assert!(pattern_matches("/README.md", "README.md"));
+        assert!(!pattern_matches("/README.md", "docs/README.md"));
+        // directory-only
+        assert!(pattern_matches("src/", "src/main.rs"));
+        assert!(pattern_matches("src/", "crates/core/src/lib.rs"));
+        assert!(!pattern_matches("build/", "build"));
+        // case-insensitive
+        assert!(!pattern_matches("README*", "readme.md"));
+        assert!(pattern_matches("(?i)README*", "readme.md"));
+        // wildcards
+        assert!(pattern_matches("src/**/*.rs", "src/a/b/c.rs"));
+        assert!(pattern_matches("src/**/*.rs", "src/c.rs"));
+        assert!(!pattern_matches("src/*.rs", "src/a/c.rs"));
+        assert!(pattern_matches("[Dd]esktop.ini", "Desktop.ini"));
+        assert!(pattern_matches("file?.txt", "file1.txt"));
+        assert!(!pattern_matches("[!a]*", "abc"));
+    }
+
+    #[test]
+    fn test_excludes_dir() {
+        let glob = PatternSyntax::Glob;
+        assert!(glob.excludes_dir("node_modules/*", "node_modules"));
+        assert!(glob.excludes_dir("node_modules", "web/node_modules"));
+        assert!(glob.excludes_dir("target/", "target/debug"));
+        assert!(glob.excludes_dir("docs/**", "docs"));
+        assert!(!glob.excludes_dir("docs/*.md", "docs"));
+        assert!(!glob.excludes_dir("*.rs", "src"));
+        assert!(!glob.excludes_dir("node_modules/*", "web/node_modules"));
+
+        let legacy = PatternSyntax::Legacy;
+        assert!(legacy.excludes_dir("vendor/*", "vendor"));
+        assert!(legacy.excludes_dir("build", "build/out"));
+        assert!(!legacy.excludes_dir("*.min.js", "dist"));
+        assert!(!legacy.excludes_dir("test*.rs", "tests"));
+    }
+
+    #[test]
+    fn test_legacy_syntax() {
+        // the old prefix matching, kept behind PatternSyntax::Legacy
+        assert!(PatternSyntax::Legacy.excludes("bin/*", "binary.rs"));
+        assert!(!PatternSyntax::Glob.excludes("bin/*", "binary.rs"));
+        assert!(PatternSyntax::Legacy.includes("src/", "src/main.rs"));
+        assert!(!PatternSyntax::Legacy.includes("src/", "crates/src/main.rs"));
+    }
+}
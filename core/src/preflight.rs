@@ -0,0 +1,205 @@
+use crate::RemoteRef;
+use std::fmt;
+
+/// suggestions offered for a missing ref
+const MAX_SUGGESTIONS: usize = 3;
+
+/// a requested branch or tag the remote does not advertise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefNotFound {
+    pub name: String,
+    /// the advertised branches and tags closest to `name`, best first
+    pub suggestions: Vec<String>,
+}
+
+impl fmt::Display for RefNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "No branch or tag named '{}' in the repository",
+            self.name
+        )?;
+        if !self.suggestions.is_empty() {
+            let quoted: Vec<String> = self.suggestions.iter().map(|s| format!("'{s}'")).collect();
+            write!(f, ", did you mean {}?", quoted.join(" or "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RefNotFound {}
+
+/// something that may be an abbreviated or full commit SHA, which the
+/// remote cannot confirm without a fetch
+fn is_commit_like(revision: &str) -> bool {
+    (7..=40).contains(&revision.len()) && revision.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// branch and tag names among `refs`, shortened
+fn ref_names(refs: &[RemoteRef]) -> Vec<&str> {
+    let mut names: Vec<&str> = refs
+        .iter()
+        .filter(|r| !r.name.ends_with("^{}"))
+        .filter_map(|r| {
+            r.name
+                .strip_prefix("refs/heads/")
+                .or_else(|| r.name.strip_prefix("refs/tags/"))
+        })
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// Ok when `revision` is a branch or tag in `refs`, or looks like a commit
+pub fn check_ref(refs: &[RemoteRef], revision: &str) -> Result<(), RefNotFound> {
+    let short = revision
+        .strip_prefix("refs/heads/")
+        .or_else(|| revision.strip_prefix("refs/tags/"))
+        .unwrap_or(revision);
+    let names = ref_names(refs);
+    if revision == "HEAD" || names.contains(&short) || is_commit_like(revision) {
+        return Ok(());
+    }
+
+    Err(RefNotFound {
+        name: revision.to_string(),
+        suggestions: closest(&names, short),
+    })
+}
+
+/// up to `MAX_SUGGESTIONS` names that are a small edit away from `wanted`,
+/// or contain it
+fn closest(names: &[&str], wanted: &str) -> Vec<String> {
+    let wanted_lower = wanted.to_lowercase();
+    let budget = wanted.chars().count() / 3 + 1;
+
+    let mut ranked: Vec<(usize, &str)> = names
+        .iter()
+        .filter_map(|&name| {
+            let lower = name.to_lowercase();
+            let distance = edit_distance(&lower, &wanted_lower);
+            if distance <= budget {
+                Some((distance, name))
+            } else if lower.contains(&wanted_lower) {
+                // ranked behind every near miss
+                Some((budget + 1 + lower.len() - wanted_lower.len(), name))
+            } else {
+                None
+            }
+        })
+        .collect();
+    ranked.sort();
+    ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// levenshtein distance over chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(feature = "git")]
+/// fail before cloning `url` when one of `revisions` is not on the remote,
+/// at the cost of listing its refs
+pub fn preflight_refs<'a>(
+    url: &str,
+    revisions: impl IntoIterator<Item = &'a str>,
+) -> anyhow::Result<()> {
+    let revisions: Vec<&str> = revisions.into_iter().collect();
+    if revisions.is_empty() {
+        return Ok(());
+    }
+
+    let refs = crate::list_remote_refs(url)?;
+    for revision in revisions {
+        check_ref(&refs, revision)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(names: &[&str]) -> Vec<RemoteRef> {
+        names
+            .iter()
+            .map(|name| RemoteRef {
+                name: name.to_string(),
+                oid: "0".repeat(40),
+                target: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_ref() {
+        let refs = remote(&[
+            "HEAD",
+            "refs/heads/main",
+            "refs/heads/develop",
+            "refs/heads/release/1.2",
+            "refs/tags/v1.2.0",
+            "refs/tags/v1.2.0^{}",
+            "refs/pull/7/head",
+        ]);
+
+        assert!(check_ref(&refs, "main").is_ok());
+        assert!(check_ref(&refs, "release/1.2").is_ok());
+        assert!(check_ref(&refs, "v1.2.0").is_ok());
+        assert!(check_ref(&refs, "refs/heads/develop").is_ok());
+        assert!(check_ref(&refs, "a1b2c3d").is_ok());
+        assert!(check_ref(&refs, &"f".repeat(40)).is_ok());
+
+        let missing = check_ref(&refs, "mian").unwrap_err();
+        assert_eq!(missing.suggestions, vec!["main"]);
+        assert_eq!(
+            missing.to_string(),
+            "No branch or tag named 'mian' in the repository, did you mean 'main'?"
+        );
+
+        assert_eq!(
+            check_ref(&refs, "v1.2").unwrap_err().suggestions,
+            vec!["v1.2.0"]
+        );
+        assert_eq!(
+            check_ref(&refs, "release").unwrap_err().suggestions,
+            vec!["release/1.2"]
+        );
+        assert!(check_ref(&refs, "pull/7/head").is_err());
+        assert!(check_ref(&refs, "nothing-like-it")
+            .unwrap_err()
+            .suggestions
+            .is_empty());
+        assert!(check_ref(&refs, "abc").is_err());
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("main", "main"), 0);
+        assert_eq!(edit_distance("mian", "main"), 2);
+        assert_eq!(edit_distance("master", "main"), 4);
+        assert_eq!(edit_distance("develop", "devel"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
@@ -0,0 +1,39 @@
+use std::sync::OnceLock;
+
+/// long running phases the CLI can draw progress for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressPhase {
+    /// objects received from the remote
+    Clone,
+    /// files scanned while building the cache index
+    Index,
+    /// files written to the output
+    Write,
+}
+
+type ProgressHandler = fn(ProgressPhase, u64, u64);
+
+static PROGRESS_HANDLER: OnceLock<ProgressHandler> = OnceLock::new();
+
+/// register a handler receiving `(phase, position, total)` updates
+/// (the CLI draws progress bars, servers leave this unset)
+pub fn set_progress_handler(handler: ProgressHandler) {
+    let _ = PROGRESS_HANDLER.set(handler);
+}
+
+#[cfg(feature = "git")]
+/// forward an update, false when nobody is listening
+pub(crate) fn report(phase: ProgressPhase, position: u64, total: u64) -> bool {
+    match PROGRESS_HANDLER.get() {
+        Some(handler) => {
+            handler(phase, position, total);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(feature = "git")]
+pub(crate) fn has_handler() -> bool {
+    PROGRESS_HANDLER.get().is_some()
+}
@@ -0,0 +1,267 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Turns text into fixed-size vectors for similarity ranking
+pub trait EmbeddingBackend {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Local, dependency-free backend that is lexical matching, not a model:
+/// words and identifier parts are feature-hashed into a vector, so files rank
+/// by the vocabulary they share with the query and synonyms or paraphrases
+/// do not match. Needs no network or model files; use an embeddings endpoint
+/// for semantic ranking.
+pub struct LexicalEmbedder {
+    dimensions: usize,
+}
+
+impl LexicalEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(16),
+        }
+    }
+
+    fn hash_token(token: &str) -> u64 {
+        // fnv-1a, stable across runs unlike DefaultHasher
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in token.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+
+    fn tokens(text: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        for word in text.split(|c: char| !c.is_alphanumeric() && c != '_') {
+            if word.len() < 2 {
+                continue;
+            }
+            let lower = word.to_lowercase();
+            // also index snake_case parts so `refresh_token` matches "token refresh"
+            if lower.contains('_') {
+                tokens.extend(
+                    lower
+                        .split('_')
+                        .filter(|p| p.len() >= 2)
+                        .map(|p| p.to_string()),
+                );
+            }
+            tokens.push(lower);
+        }
+        tokens
+    }
+}
+
+impl Default for LexicalEmbedder {
+    fn default() -> Self {
+        Self::new(512)
+    }
+}
+
+impl EmbeddingBackend for LexicalEmbedder {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts
+            .iter()
+            .map(|text| {
+                let mut vector = vec![0f32; self.dimensions];
+                for token in Self::tokens(text) {
+                    let hash = Self::hash_token(&token);
+                    let idx = (hash % self.dimensions as u64) as usize;
+                    let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+                    vector[idx] += sign;
+                }
+                normalize(&mut vector);
+                vector
+            })
+            .collect())
+    }
+}
+
+/// Backend for any OpenAI-compatible `/embeddings` endpoint
+#[cfg(feature = "embeddings-api")]
+pub struct OpenAiEmbedder {
+    pub endpoint: String,
+    pub model: String,
+    pub api_key: Option<String>,
+}
+
+#[cfg(feature = "embeddings-api")]
+impl OpenAiEmbedder {
+    const BATCH_SIZE: usize = 64;
+
+    pub fn new(endpoint: &str, model: &str, api_key: Option<String>) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+}
+
+#[cfg(feature = "embeddings-api")]
+impl EmbeddingBackend for OpenAiEmbedder {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        #[derive(Deserialize)]
+        struct EmbeddingData {
+            embedding: Vec<f32>,
+        }
+
+        #[derive(Deserialize)]
+        struct EmbeddingResponse {
+            data: Vec<EmbeddingData>,
+        }
+
+        let url = format!("{}/embeddings", self.endpoint);
+        let mut vectors = Vec::with_capacity(texts.len());
+
+        for batch in texts.chunks(Self::BATCH_SIZE) {
+            let mut request = ureq::post(&url);
+            if let Some(ref key) = self.api_key {
+                request = request.header("Authorization", &format!("Bearer {key}"));
+            }
+
+            let response: EmbeddingResponse = request
+                .send_json(serde_json::json!({
+                    "model": self.model,
+                    "input": batch,
+                }))?
+                .body_mut()
+                .read_json()?;
+
+            if response.data.len() != batch.len() {
+                return Err(anyhow::anyhow!(
+                    "Embedding API returned {} vectors for {} inputs",
+                    response.data.len(),
+                    batch.len()
+                ));
+            }
+
+            for mut data in response.data {
+                normalize(&mut data.embedding);
+                vectors.push(data.embedding);
+            }
+        }
+
+        Ok(vectors)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelevanceOptions {
+    /// Natural language description of what the caller is looking for
+    pub query: String,
+    /// Maximum number of files to keep
+    pub top_k: usize,
+    /// Maximum estimated tokens across the selected files
+    pub token_budget: usize,
+    /// Lines per embedded chunk; a file scores as its best chunk
+    pub chunk_lines: usize,
+}
+
+impl Default for RelevanceOptions {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            top_k: 20,
+            token_budget: 100_000,
+            chunk_lines: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedFile {
+    pub path: PathBuf,
+    pub score: f32,
+    pub tokens: usize,
+}
+
+/// rank files by their best-matching chunk, most relevant first
+pub fn rank_files(
+    files: &[(PathBuf, String)],
+    options: &RelevanceOptions,
+    backend: &dyn EmbeddingBackend,
+) -> Result<Vec<RankedFile>> {
+    if options.query.trim().is_empty() {
+        return Err(anyhow::anyhow!("Relevance query is empty"));
+    }
+
+    let query_vector = backend
+        .embed(std::slice::from_ref(&options.query))?
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("Embedding backend returned no query vector"))?;
+
+    // the path is part of every chunk so file names contribute to relevance
+    let mut chunks = Vec::new();
+    let mut owners = Vec::new();
+    for (idx, (path, content)) in files.iter().enumerate() {
+        let lines: Vec<&str> = content.lines().collect();
+        if lines.is_empty() {
+            chunks.push(path.to_string_lossy().to_string());
+            owners.push(idx);
+            continue;
+        }
+        for chunk in lines.chunks(options.chunk_lines.max(1)) {
+            chunks.push(format!("{}\n{}", path.display(), chunk.join("\n")));
+            owners.push(idx);
+        }
+    }
+
+    let vectors = backend.embed(&chunks)?;
+    let mut scores = vec![f32::MIN; files.len()];
+    for (vector, owner) in vectors.iter().zip(owners) {
+        let score = cosine(&query_vector, vector);
+        if score > scores[owner] {
+            scores[owner] = score;
+        }
+    }
+
+    let mut ranked: Vec<RankedFile> = files
+        .iter()
+        .zip(scores)
+        .map(|((path, content), score)| RankedFile {
+            path: path.clone(),
+            score,
+            tokens: crate::estimate_tokens(content),
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(ranked)
+}
+
+/// keep the best files until top_k or the token budget is reached
+pub fn select_within_budget(
+    ranked: Vec<RankedFile>,
+    options: &RelevanceOptions,
+) -> Vec<RankedFile> {
+    let mut selected = Vec::new();
+    let mut used = 0;
+
+    for file in ranked {
+        if selected.len() >= options.top_k {
+            break;
+        }
+        if used + file.tokens > options.token_budget {
+            continue;
+        }
+        used += file.tokens;
+        selected.push(file);
+    }
+
+    selected
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
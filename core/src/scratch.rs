@@ -0,0 +1,242 @@
+use crate::cache::dir_size;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where throwaway clones go and how large they may get
+#[derive(Debug, Clone, Default)]
+pub struct ScratchConfig {
+    /// Parent of the per-clone directories, defaults to the system temp dir
+    pub root: Option<PathBuf>,
+    /// Bytes a single clone may download before it is aborted, 0 for no limit
+    pub max_bytes: u64,
+}
+
+static SCRATCH: OnceLock<ScratchConfig> = OnceLock::new();
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// install the scratch configuration for this process, first call wins
+pub fn configure_scratch(config: ScratchConfig) {
+    let _ = SCRATCH.set(config);
+}
+
+fn root() -> PathBuf {
+    SCRATCH
+        .get()
+        .and_then(|config| config.root.clone())
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+pub(crate) fn max_bytes() -> u64 {
+    SCRATCH.get().map_or(0, |config| config.max_bytes)
+}
+
+/// true once a transfer has gone past the configured limit
+pub(crate) fn over_quota(received_bytes: usize) -> bool {
+    let limit = max_bytes();
+    limit > 0 && received_bytes as u64 > limit
+}
+
+/// turn the error of a transfer aborted by `over_quota` into a readable one
+pub(crate) fn fetch_error(error: git2::Error) -> anyhow::Error {
+    let limit = max_bytes();
+    if limit > 0 && error.code() == git2::ErrorCode::User {
+        anyhow::anyhow!(
+            "Repository exceeds the clone size limit of {} MB",
+            limit / (1024 * 1024)
+        )
+    } else {
+        error.into()
+    }
+}
+
+/// private directory for one clone, removed with everything in it on drop
+#[derive(Debug)]
+pub struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    /// `githem-<kind>-<pid>-<id>` under the scratch root
+    pub fn new(kind: &str) -> Result<Self> {
+        let root = root();
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create scratch root {}", root.display()))?;
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = root.join(format!("githem-{kind}-{}-{id}", std::process::id()));
+        std::fs::create_dir(&path)
+            .with_context(|| format!("Failed to create scratch directory {}", path.display()))?;
+
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// scratch directories end in a numeric id (older releases used a timestamp),
+/// which keeps `githem-cache` and friends out of the sweep
+fn is_scratch_name(name: &str) -> bool {
+    name.strip_prefix("githem-")
+        .and_then(|rest| rest.rsplit('-').next())
+        .is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// the process that created a `githem-<kind>-<pid>-<id>` directory, None
+/// for older names without one
+fn owner_pid(name: &str) -> Option<u32> {
+    let mut parts = name.strip_prefix("githem-")?.rsplit('-');
+    parts.next()?;
+    let pid = parts.next()?;
+    // a kind on its own is no pid: `githem-compare-42`
+    parts.next()?;
+    pid.parse().ok()
+}
+
+/// whether `pid` is a running process, true when that cannot be told
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return true;
+    };
+    // signal 0 only checks the process exists, EPERM means it does but
+    // belongs to someone else
+    let exists = unsafe { libc::kill(pid, 0) } == 0;
+    exists || std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+#[cfg(not(unix))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+/// latest modification of the directory or anything below it, a running
+/// clone keeps writing into subdirectories without touching the top level
+fn newest_mtime(path: &Path) -> Option<SystemTime> {
+    let mut newest = std::fs::symlink_metadata(path)
+        .and_then(|m| m.modified())
+        .ok()?;
+    for entry in std::fs::read_dir(path).into_iter().flatten().flatten() {
+        let modified = match entry.file_type() {
+            Ok(t) if t.is_dir() => newest_mtime(&entry.path()),
+            _ => entry.metadata().and_then(|m| m.modified()).ok(),
+        };
+        newest = newest.max(modified.unwrap_or(newest));
+    }
+    Some(newest)
+}
+
+/// no running process owns `dir` and nothing in it changed since `cutoff`
+fn is_orphaned(dir: &Path, cutoff: SystemTime) -> bool {
+    let owner = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(owner_pid);
+    !owner.is_some_and(|pid| pid == std::process::id() || is_alive(pid))
+        && newest_mtime(dir).is_some_and(|modified| modified < cutoff)
+}
+
+/// remove scratch directories left behind by crashed or killed processes:
+/// those whose process is gone (or that predate pids in the name) and where
+/// nothing was modified within `min_age`, as another host or pid namespace
+/// sharing the root may still be using them; returns how many were removed
+/// and the bytes they held
+pub fn sweep_scratch(min_age: Duration) -> (usize, u64) {
+    let cutoff = SystemTime::now().checked_sub(min_age).unwrap_or(UNIX_EPOCH);
+
+    scratch_dirs()
+        .filter(|entry| is_orphaned(&entry.path(), cutoff))
+        .map(|entry| (entry.path(), dir_size(&entry.path())))
+        .filter(|(path, _)| std::fs::remove_dir_all(path).is_ok())
+        .fold((0, 0), |(dirs, bytes), (_, size)| (dirs + 1, bytes + size))
+}
+
+/// bytes held by the scratch directories of every process sharing the root
+pub fn scratch_usage() -> u64 {
+    scratch_dirs().map(|entry| dir_size(&entry.path())).sum()
+}
+
+fn scratch_dirs() -> impl Iterator<Item = std::fs::DirEntry> {
+    std::fs::read_dir(root())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|entry| entry.file_name().to_str().is_some_and(is_scratch_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_scratch_name() {
+        for name in [
+            "githem-1712345678901",
+            "githem-compare-42",
+            "githem-clone-981-3",
+        ] {
+            assert!(is_scratch_name(name), "{name}");
+        }
+        for name in [
+            "githem-cache",
+            "githem-shares",
+            "githem-",
+            "other-123",
+            "githem-at-x1",
+        ] {
+            assert!(!is_scratch_name(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_owner_pid() {
+        assert_eq!(owner_pid("githem-clone-981-3"), Some(981));
+        assert_eq!(owner_pid("githem-hook-12-0"), Some(12));
+        for name in [
+            "githem-compare-42",
+            "githem-1712345678901",
+            "githem-clone-x-3",
+        ] {
+            assert_eq!(owner_pid(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_is_orphaned() {
+        let later = SystemTime::now() + Duration::from_secs(3600);
+        let dir = ScratchDir::new("test").unwrap();
+        assert!(!is_orphaned(dir.path(), later));
+
+        // above any pid_max, so no such process
+        let root = tempfile::tempdir().unwrap();
+        let dead = root.path().join("githem-test-1073741822-0");
+        std::fs::create_dir(&dead).unwrap();
+        std::fs::write(dead.join("file"), "x").unwrap();
+        assert!(is_orphaned(&dead, later));
+        assert!(!is_orphaned(
+            &dead,
+            SystemTime::now() - Duration::from_secs(3600)
+        ));
+    }
+
+    #[test]
+    fn test_scratch_dir_removed_on_drop() {
+        let dir = ScratchDir::new("test").unwrap();
+        let path = dir.path().to_path_buf();
+        std::fs::write(path.join("file"), "x").unwrap();
+        assert!(path.is_dir());
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+
+/// Options for searching ingested output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchOptions {
+    pub query: String,
+    /// Treat the query as a regular expression instead of a literal
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Lines of context before and after each match
+    pub context_lines: usize,
+    /// Stop after this many matches in total
+    pub max_matches: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            regex: false,
+            case_sensitive: false,
+            context_lines: 2,
+            max_matches: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub line_number: usize,
+    pub line: String,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMatches {
+    pub path: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// grep every file section of an ingestion output
+pub fn search_output(content: &str, options: &SearchOptions) -> Result<Vec<FileMatches>> {
+    if options.query.is_empty() {
+        return Err(anyhow::anyhow!("Search query is empty"));
+    }
+
+    let pattern = if options.regex {
+        options.query.clone()
+    } else {
+        regex::escape(&options.query)
+    };
+
+    let matcher = RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .size_limit(1 << 20)
+        .build()?;
+
+    let mut results = Vec::new();
+    let mut total = 0;
+
+    for (path, body) in crate::split_files(content) {
+        if total >= options.max_matches {
+            break;
+        }
+
+        let lines: Vec<&str> = body.lines().map(crate::unescape_line).collect();
+        let mut matches = Vec::new();
+
+        for (idx, line) in lines.iter().enumerate() {
+            if !matcher.is_match(line) {
+                continue;
+            }
+
+            let before_start = idx.saturating_sub(options.context_lines);
+            let after_end = (idx + 1 + options.context_lines).min(lines.len());
+
+            matches.push(SearchMatch {
+                line_number: idx + 1,
+                line: line.to_string(),
+                context_before: lines[before_start..idx]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+                context_after: lines[idx + 1..after_end]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            });
+
+            total += 1;
+            if total >= options.max_matches {
+                break;
+            }
+        }
+
+        if !matches.is_empty() {
+            results.push(FileMatches {
+                path: path.to_string(),
+                matches,
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "# File Structure\n\nTotal files: 2\n\n  a.rs\n  b.rs\n\n\
+=== a.rs ===\nfn main() {\n    refresh_token();\n}\n\n\
+=== b.rs ===\nfn refresh_token() {}\n\n";
+
+    #[test]
+    fn test_literal_search() {
+        let options = SearchOptions {
+            query: "REFRESH_TOKEN".to_string(),
+            context_lines: 1,
+            ..Default::default()
+        };
+        let results = search_output(OUTPUT, &options).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].path, "a.rs");
+        assert_eq!(results[0].matches[0].line_number, 2);
+        assert_eq!(results[0].matches[0].context_before, vec!["fn main() {"]);
+        assert_eq!(results[0].matches[0].context_after, vec!["}"]);
+    }
+
+    #[test]
+    fn test_regex_search() {
+        let options = SearchOptions {
+            query: r"^fn \w+\(\)".to_string(),
+            regex: true,
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let results = search_output(OUTPUT, &options).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].matches[0].line, "fn refresh_token() {}");
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{IngestReport, Ingester};
+use anyhow::{Context, Result};
+use std::io::{self, BufWriter, Write};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+/// chunk size handed from the ingestion thread to the async writer
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// `Write` end of a channel, each write becomes one chunk
+struct ChannelWriter(mpsc::Sender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(buf.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "output closed"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// run `Ingester::ingest_auto` on a blocking thread and stream its output
+/// into `output` as it is produced; ingestion stops early if `output`
+/// fails. The ingester is handed back for further use.
+pub async fn ingest_async<W>(
+    mut ingester: Ingester,
+    output: &mut W,
+) -> Result<(Ingester, IngestReport)>
+where
+    W: AsyncWrite + Unpin,
+{
+    let (sender, mut receiver) = mpsc::channel::<Vec<u8>>(16);
+
+    let task = tokio::task::spawn_blocking(move || {
+        let mut writer = BufWriter::with_capacity(CHUNK_SIZE, ChannelWriter(sender));
+        let report = ingester.ingest_auto(&mut writer);
+        let report = report.and_then(|report| {
+            writer.flush()?;
+            Ok(report)
+        });
+        (ingester, report)
+    });
+
+    let mut written = Ok(());
+    while let Some(chunk) = receiver.recv().await {
+        if let Err(e) = output.write_all(&chunk).await {
+            written = Err(e);
+            break;
+        }
+    }
+    // a dropped receiver makes the ingestion thread fail its next write
+    drop(receiver);
+
+    let (ingester, report) = task.await.context("Ingestion task panicked")?;
+    written.context("Failed to write ingestion output")?;
+    output.flush().await?;
+    Ok((ingester, report?))
+}
@@ -0,0 +1,279 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// directories whose files a model rarely needs to read the code
+const PERIPHERAL_DIRS: &[&str] = &[
+    "tests",
+    "test",
+    "__tests__",
+    "spec",
+    "testdata",
+    "fixtures",
+    "examples",
+    "benches",
+    "docs",
+    "vendor",
+    "third_party",
+    "assets",
+    "static",
+];
+
+/// formats that hold data rather than code
+const DATA_EXTENSIONS: &[&str] = &[
+    "json", "csv", "tsv", "svg", "xml", "txt", "sql", "snap", "yaml", "yml", "html",
+];
+
+/// looked for, lowercased, in the comments among the first lines of a file
+const GENERATED_MARKERS: &[&str] = &[
+    "@generated",
+    "do not edit",
+    "auto-generated",
+    "autogenerated",
+    "code generated by",
+];
+
+/// directories deeper than this are not suggested one by one
+const MAX_DIR_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExcludeSuggestion {
+    /// exclude pattern, gitignore style like `--exclude`
+    pub pattern: String,
+    pub reason: &'static str,
+    /// files and estimated tokens it removes on top of the suggestions
+    /// before it
+    pub files: usize,
+    pub tokens: usize,
+}
+
+struct Candidate {
+    pattern: String,
+    reason: &'static str,
+    /// indices into the files
+    members: Vec<usize>,
+}
+
+struct Plan<'a> {
+    files: Vec<(&'a str, usize)>,
+    excluded: Vec<bool>,
+    remaining: usize,
+    suggestions: Vec<ExcludeSuggestion>,
+}
+
+impl Plan<'_> {
+    fn savings(&self, candidate: &Candidate) -> (usize, usize) {
+        candidate
+            .members
+            .iter()
+            .filter(|&&i| !self.excluded[i])
+            .fold((0, 0), |(files, tokens), &i| {
+                (files + 1, tokens + self.files[i].1)
+            })
+    }
+
+    fn take(&mut self, candidate: &Candidate) {
+        let (files, tokens) = self.savings(candidate);
+        if files == 0 {
+            return;
+        }
+        for &i in &candidate.members {
+            self.excluded[i] = true;
+        }
+        self.remaining -= tokens;
+        self.suggestions.push(ExcludeSuggestion {
+            pattern: candidate.pattern.clone(),
+            reason: candidate.reason,
+            files,
+            tokens,
+        });
+    }
+
+    /// takes candidates, earlier ones first on ties, until the budget is
+    /// met: the smallest one that is enough on its own, otherwise the
+    /// largest that leaves something
+    fn reduce(&mut self, budget: usize, mut candidates: Vec<Candidate>) {
+        while self.remaining > budget {
+            let over = self.remaining - budget;
+            let sized: Vec<(usize, usize)> = candidates
+                .iter()
+                .enumerate()
+                .map(|(i, candidate)| (i, self.savings(candidate).1))
+                .filter(|&(_, tokens)| tokens > 0 && tokens < self.remaining)
+                .collect();
+            let enough = sized
+                .iter()
+                .filter(|(_, tokens)| *tokens >= over)
+                .min_by_key(|(_, tokens)| *tokens);
+            let largest = || {
+                sized
+                    .iter()
+                    .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+            };
+            let Some(&(best, _)) = enough.or_else(largest) else {
+                return;
+            };
+            let candidate = candidates.remove(best);
+            self.take(&candidate);
+        }
+    }
+
+    fn matching(&self, pattern: String, reason: &'static str) -> Candidate {
+        let members = self
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(_, (path, _))| crate::pattern_matches(&pattern, path))
+            .map(|(i, _)| i)
+            .collect();
+        Candidate {
+            pattern,
+            reason,
+            members,
+        }
+    }
+}
+
+fn generated(content: &str) -> Option<&'static str> {
+    let marked = content
+        .lines()
+        .take(5)
+        .map(|line| line.trim_start().to_lowercase())
+        .filter(|line| {
+            ["//", "#", "/*", "*", "--", "<!--"]
+                .iter()
+                .any(|c| line.starts_with(c))
+        })
+        .any(|line| GENERATED_MARKERS.iter().any(|marker| line.contains(marker)));
+    if marked {
+        return Some("generated");
+    }
+    let lines = content.lines().count().max(1);
+    (content.len() > 2000 && content.len() / lines > 500).then_some("minified")
+}
+
+/// exclude patterns that bring the files of an output under `budget`
+/// estimated tokens: generated and minified files whatever the budget,
+/// then test, docs and vendored directories, data formats and large
+/// files, and other directories as a last resort
+pub fn suggest_excludes(content: &str, budget: usize) -> Vec<ExcludeSuggestion> {
+    let sections = crate::split_files(content);
+    let files: Vec<(&str, usize)> = sections
+        .iter()
+        .map(|(path, body)| (*path, crate::estimate_tokens(body)))
+        .collect();
+    let mut plan = Plan {
+        remaining: files.iter().map(|(_, tokens)| tokens).sum(),
+        excluded: vec![false; files.len()],
+        files,
+        suggestions: Vec::new(),
+    };
+
+    for (i, (path, body)) in sections.iter().enumerate() {
+        if let Some(reason) = generated(body) {
+            plan.take(&Candidate {
+                pattern: format!("/{path}"),
+                reason,
+                members: vec![i],
+            });
+        }
+    }
+
+    let peripheral = PERIPHERAL_DIRS
+        .iter()
+        .map(|dir| plan.matching(format!("{dir}/"), "tests, docs or vendored code"))
+        .chain(
+            DATA_EXTENSIONS
+                .iter()
+                .map(|ext| plan.matching(format!("*.{ext}"), "data files")),
+        )
+        .collect();
+    plan.reduce(budget, peripheral);
+
+    let large = plan
+        .files
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, tokens))| *tokens > budget / 4)
+        .map(|(i, (path, _))| Candidate {
+            pattern: format!("/{path}"),
+            reason: "large file",
+            members: vec![i],
+        })
+        .collect();
+    plan.reduce(budget, large);
+
+    let dirs: BTreeSet<&str> = plan
+        .files
+        .iter()
+        .flat_map(|(path, _)| path.match_indices('/').map(|(end, _)| &path[..end]))
+        .filter(|dir| dir.matches('/').count() < MAX_DIR_DEPTH)
+        .collect();
+    let directories = dirs
+        .into_iter()
+        .map(|dir| Candidate {
+            pattern: format!("/{dir}/"),
+            reason: "directory",
+            members: plan
+                .files
+                .iter()
+                .enumerate()
+                .filter(|(_, (path, _))| {
+                    path.strip_prefix(dir)
+                        .is_some_and(|rest| rest.starts_with('/'))
+                })
+                .map(|(i, _)| i)
+                .collect(),
+        })
+        .collect();
+    plan.reduce(budget, directories);
+
+    plan.suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(files: &[(&str, String)]) -> String {
+        files
+            .iter()
+            .map(|(path, body)| format!("=== {path} ===\n{body}\n"))
+            .collect()
+    }
+
+    #[test]
+    fn test_suggest_excludes() {
+        let code = "fn main() { let x = compute(1, 2); println!(\"{x}\"); }\n".repeat(40);
+        let config = "header = \"// @generated, do not edit\"\n".to_string();
+        let content = output(&[
+            ("src/main.rs", code.clone()),
+            (
+                "src/schema.rs",
+                format!("// @generated by build.rs\n{code}"),
+            ),
+            ("tests/it.rs", code.clone()),
+            ("build.toml", config),
+            (
+                "data/big.json",
+                "{\"key\": [1, 2, 3, 4, 5, 6, 7, 8]}\n".repeat(200),
+            ),
+        ]);
+
+        let under = suggest_excludes(&content, 1_000_000);
+        assert_eq!(under.len(), 1);
+        assert_eq!(
+            (under[0].pattern.as_str(), under[0].reason),
+            ("/src/schema.rs", "generated")
+        );
+
+        let main = crate::estimate_tokens(&code);
+        let suggestions = suggest_excludes(&content, main * 2 + 100);
+        let patterns: Vec<&str> = suggestions.iter().map(|s| s.pattern.as_str()).collect();
+        assert_eq!(patterns, ["/src/schema.rs", "*.json"]);
+
+        let tight = suggest_excludes(&content, main + 100);
+        let patterns: Vec<&str> = tight.iter().map(|s| s.pattern.as_str()).collect();
+        assert_eq!(patterns, ["/src/schema.rs", "*.json", "tests/"]);
+        assert!(tight.iter().all(|s| s.files == 1 && s.tokens > 0));
+    }
+}
@@ -0,0 +1,211 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// at most this many symbols are listed per file
+const MAX_SYMBOLS: usize = 12;
+
+/// what a directory summary says about one of its files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSummary {
+    pub path: String,
+    pub size: usize,
+    pub tokens: usize,
+    pub symbols: Vec<String>,
+}
+
+impl FileSummary {
+    pub fn new(path: &str, content: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            size: content.len(),
+            tokens: crate::estimate_tokens(content),
+            symbols: exported_symbols(path, content),
+        }
+    }
+}
+
+/// the directory `path` is summarized under: the first directory below
+/// the prefix it lies in, None for files directly in it, which are always
+/// kept in full
+pub fn summary_group(path: &Path, prefixes: &[&str]) -> Option<String> {
+    let path = path.to_string_lossy();
+    let prefixes: Vec<&str> = prefixes
+        .iter()
+        .map(|p| p.trim_end_matches('/'))
+        .filter(|p| !p.is_empty())
+        .collect();
+    let prefix = prefixes.iter().copied().find(|prefix| {
+        path.strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('/'))
+    });
+    let rest = match prefix {
+        Some(prefix) => &path[prefix.len() + 1..],
+        None if prefixes.is_empty() => &path,
+        None => return None,
+    };
+    let (dir, _) = rest.split_once('/')?;
+    Some(match prefix {
+        Some(prefix) => format!("{prefix}/{dir}"),
+        None => dir.to_string(),
+    })
+}
+
+/// groups whose files add up to more than `max_tokens`, estimated from
+/// their sizes so nothing has to be read to decide
+pub fn oversized_groups<'a>(
+    files: impl IntoIterator<Item = (&'a Path, u64)>,
+    prefixes: &[&str],
+    max_tokens: usize,
+) -> Vec<String> {
+    let mut sizes: HashMap<String, u64> = HashMap::new();
+    for (path, size) in files {
+        if let Some(group) = summary_group(path, prefixes) {
+            *sizes.entry(group).or_default() += size;
+        }
+    }
+    let mut groups: Vec<String> = sizes
+        .into_iter()
+        .filter(|(_, size)| *size / 4 > max_tokens as u64)
+        .map(|(group, _)| group)
+        .collect();
+    groups.sort();
+    groups
+}
+
+/// the section written instead of a directory's files
+pub fn render_summary(dir: &str, files: &[FileSummary]) -> String {
+    let tokens: usize = files.iter().map(|f| f.tokens).sum();
+
+    let mut summary = format!(
+        "[directory summary: {} files, {} tokens left out]\n\n",
+        files.len(),
+        tokens
+    );
+    for file in files {
+        let name = file
+            .path
+            .strip_prefix(dir)
+            .unwrap_or(&file.path)
+            .trim_start_matches('/');
+        summary.push_str(&format!(
+            "{name} ({}, {} tokens)\n",
+            crate::header::human_size(file.size),
+            file.tokens
+        ));
+        if !file.symbols.is_empty() {
+            summary.push_str(&format!("  {}\n", file.symbols.join(", ")));
+        }
+    }
+    summary
+}
+
+/// header detail of a summary section, `=== api/ (summary, 210.4 KB, 48213 tokens) ===`
+pub fn summary_detail(files: &[FileSummary]) -> crate::FileDetail {
+    crate::FileDetail {
+        language: Some("summary"),
+        size: files.iter().map(|f| f.size).sum(),
+        tokens: files.iter().map(|f| f.tokens).sum(),
+        blob: None,
+        modified: None,
+    }
+}
+
+fn symbol_patterns() -> &'static [(&'static [&'static str], Regex)] {
+    static PATTERNS: OnceLock<Vec<(&'static [&'static str], Regex)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let pattern = |extensions: &'static [&'static str], pattern: &str| {
+            (extensions, Regex::new(pattern).expect("symbol pattern compiles"))
+        };
+        vec![
+            pattern(
+                &["rs"],
+                r"(?m)^\s*pub(?:\([^)]*\))?\s+(?:async\s+|unsafe\s+|const\s+)*(fn|struct|enum|trait|type|mod|const|static)\s+([A-Za-z_]\w*)",
+            ),
+            pattern(&["go"], r"(?m)^(func|type)\s+(?:\([^)]*\)\s*)?([A-Z]\w*)"),
+            pattern(&["py"], r"(?m)^(def|class)\s+([A-Za-z]\w*)"),
+            pattern(
+                &["js", "jsx", "mjs", "ts", "tsx"],
+                r"(?m)^export\s+(?:default\s+)?(?:async\s+)?(function|class|const|let|interface|type|enum)\s+([A-Za-z_$][\w$]*)",
+            ),
+            pattern(
+                &["java", "kt", "cs", "scala", "swift"],
+                r"(?m)^\s*(?:public|open)\s+(?:(?:static|final|abstract|sealed|data)\s+)*(class|interface|enum|record|struct|object|fun)\s+([A-Za-z_]\w*)",
+            ),
+        ]
+    })
+}
+
+/// `fn name`, `struct Name`, ... for the public items of a source file
+pub fn exported_symbols(path: &str, content: &str) -> Vec<String> {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .unwrap_or_default();
+    let Some((_, pattern)) = symbol_patterns()
+        .iter()
+        .find(|(exts, _)| exts.contains(&extension))
+    else {
+        return Vec::new();
+    };
+
+    let mut symbols: Vec<String> = pattern
+        .captures_iter(content)
+        .map(|c| format!("{} {}", &c[1], &c[2]))
+        .collect();
+    if symbols.len() > MAX_SYMBOLS {
+        let more = symbols.len() - MAX_SYMBOLS;
+        symbols.truncate(MAX_SYMBOLS);
+        symbols.push(format!("+{more} more"));
+    }
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory_summary() {
+        let rust = "pub struct Config;\nfn private() {}\npub(crate) async fn load() {}\n";
+        assert_eq!(
+            exported_symbols("src/lib.rs", rust),
+            ["struct Config", "fn load"]
+        );
+        assert_eq!(
+            exported_symbols("a.ts", "export default function App() {}\nconst x = 1;\n"),
+            ["function App"]
+        );
+
+        assert_eq!(
+            summary_group(Path::new("api/src/http.rs"), &[]).as_deref(),
+            Some("api")
+        );
+        assert_eq!(summary_group(Path::new("README.md"), &[]), None);
+        assert_eq!(
+            summary_group(Path::new("core/src/lib.rs"), &["core/"]).as_deref(),
+            Some("core/src")
+        );
+        assert_eq!(
+            summary_group(Path::new("proto/v1/a.proto"), &["core", "proto"]).as_deref(),
+            Some("proto/v1")
+        );
+        assert_eq!(
+            summary_group(Path::new("cli/src/main.rs"), &["core", "proto"]),
+            None
+        );
+
+        let files = [
+            (Path::new("big/a.rs"), 90_000),
+            (Path::new("small/b.rs"), 100),
+            (Path::new("c.rs"), 90_000),
+        ];
+        assert_eq!(oversized_groups(files, &[], 20_000), ["big"]);
+
+        let summary = render_summary("big", &[FileSummary::new("big/a.rs", rust)]);
+        assert!(summary.starts_with("[directory summary: 1 files"));
+        assert!(summary.contains("\n\na.rs (65 B, "));
+        assert!(summary.contains("  struct Config, fn load\n"));
+    }
+}
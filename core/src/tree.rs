@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Default)]
+struct Dir {
+    dirs: BTreeMap<String, Dir>,
+    files: Vec<String>,
+}
+
+impl Dir {
+    fn insert(&mut self, path: &str) {
+        let mut parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        let Some(file) = parts.pop() else {
+            return;
+        };
+        let dir = parts.into_iter().fold(self, |dir, part| {
+            dir.dirs.entry(part.to_string()).or_default()
+        });
+        dir.files.push(file.to_string());
+    }
+
+    fn file_count(&self) -> usize {
+        self.files.len() + self.dirs.values().map(Dir::file_count).sum::<usize>()
+    }
+
+    fn render(&self, prefix: &str, depth: usize, max_depth: Option<usize>, output: &mut String) {
+        let entries = self.dirs.iter().map(|(name, dir)| (name, Some(dir)));
+        let entries: Vec<_> = entries
+            .chain(self.files.iter().map(|name| (name, None)))
+            .collect();
+
+        for (i, (name, dir)) in entries.iter().enumerate() {
+            let last = i + 1 == entries.len();
+            let connector = if last { "└── " } else { "├── " };
+            match dir {
+                Some(dir) if max_depth.is_some_and(|max| depth + 1 >= max) => {
+                    let count = dir.file_count();
+                    let noun = if count == 1 { "file" } else { "files" };
+                    output.push_str(&format!("{prefix}{connector}{name}/ ({count} {noun})\n"));
+                }
+                Some(dir) => {
+                    output.push_str(&format!("{prefix}{connector}{name}/\n"));
+                    let child_prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+                    dir.render(&child_prefix, depth + 1, max_depth, output);
+                }
+                None => output.push_str(&format!("{prefix}{connector}{name}\n")),
+            }
+        }
+    }
+}
+
+/// `├──`/`└──` tree of repository-relative paths, directories before files
+/// and both sorted by name. Directories at `max_depth` are collapsed into
+/// one line with their file count.
+pub fn render_tree<P: AsRef<Path>>(paths: &[P], max_depth: Option<usize>) -> String {
+    let mut root = Dir::default();
+    for path in paths {
+        root.insert(&path.as_ref().to_string_lossy());
+    }
+    sort_files(&mut root);
+
+    let mut output = String::new();
+    root.render("", 0, max_depth, &mut output);
+    output
+}
+
+fn sort_files(dir: &mut Dir) {
+    dir.files.sort();
+    dir.dirs.values_mut().for_each(sort_files);
+}
+
+/// the file paths of a `render_tree` listing; collapsed directories and
+/// lines that are not part of a tree are skipped
+pub fn parse_tree(tree: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut dirs: Vec<String> = Vec::new();
+
+    for line in tree.lines() {
+        let chars: Vec<char> = line.chars().collect();
+        let Some(at) = chars
+            .windows(4)
+            .position(|w| matches!(w, ['├' | '└', '─', '─', ' ']))
+        else {
+            continue;
+        };
+        if at % 4 != 0 {
+            continue;
+        }
+        let depth = at / 4;
+        let name: String = chars[at + 4..].iter().collect();
+
+        dirs.truncate(depth);
+        if dirs.len() < depth {
+            continue;
+        }
+        match name.strip_suffix('/') {
+            Some(dir) => dirs.push(dir.to_string()),
+            // collapsed directories list no files
+            None if name.ends_with(')') && name.contains("/ (") => {}
+            None => paths.push(
+                dirs.iter()
+                    .chain([&name])
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join("/"),
+            ),
+        }
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_tree() {
+        let paths = [
+            "src/main.rs",
+            "README.md",
+            "src/a/b/deep.rs",
+            "src/a/x.rs",
+            "Cargo.toml",
+        ];
+        let tree = render_tree(&paths, None);
+        assert_eq!(
+            tree,
+            "├── src/\n\
+             │   ├── a/\n\
+             │   │   ├── b/\n\
+             │   │   │   └── deep.rs\n\
+             │   │   └── x.rs\n\
+             │   └── main.rs\n\
+             ├── Cargo.toml\n\
+             └── README.md\n"
+        );
+
+        let mut parsed = parse_tree(&tree);
+        parsed.sort();
+        let mut expected = paths.map(String::from).to_vec();
+        expected.sort();
+        assert_eq!(parsed, expected);
+
+        assert_eq!(
+            render_tree(&paths, Some(1)),
+            "├── src/ (3 files)\n├── Cargo.toml\n└── README.md\n"
+        );
+    }
+}
@@ -1,7 +1,293 @@
 use crate::types::*;
+use std::collections::{HashMap, HashSet};
+use futures::StreamExt;
 use gloo_net::http::Request;
+use gloo_net::websocket::{futures::WebSocket, Message};
 
 const API_BASE: &str = "/api";
+const GITHUB_API: &str = "https://api.github.com";
+const GITHUB_RAW: &str = "https://raw.githubusercontent.com";
+
+#[derive(serde::Deserialize)]
+struct GitHubTree {
+    tree: Vec<GitHubTreeEntry>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct GitHubRepository {
+    default_branch: String,
+}
+
+/// the default branch GitHub reports for a repository
+async fn github_default_branch(owner: &str, repo: &str) -> Result<String, String> {
+    let response = Request::get(&format!("{}/repos/{}/{}", GITHUB_API, owner, repo))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch repository: {}", e))?;
+    if !response.ok() {
+        return Err(format!("GitHub API error: {}", response.status()));
+    }
+    let repository: GitHubRepository = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse repository: {}", e))?;
+    Ok(repository.default_branch)
+}
+
+#[derive(serde::Deserialize)]
+struct GitHubTreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    size: Option<usize>,
+}
+
+/// Ingest a public GitHub repository entirely in the browser using the REST API
+/// and githem-core's in-memory ingester, without going through our server.
+pub async fn ingest_in_browser(
+    owner: &str,
+    repo: &str,
+    branch: &str,
+    options: githem_core::IngestOptions,
+) -> Result<IngestionResult, String> {
+    let default_branch = github_default_branch(owner, repo).await?;
+    let branch = if branch == "HEAD" { default_branch.as_str() } else { branch };
+
+    let tree_url = format!(
+        "{}/repos/{}/{}/git/trees/{}?recursive=1",
+        GITHUB_API, owner, repo, branch
+    );
+    let response = Request::get(&tree_url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch tree: {}", e))?;
+
+    if !response.ok() {
+        return Err(format!("GitHub API error: {}", response.status()));
+    }
+
+    let tree: GitHubTree = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse tree: {}", e))?;
+
+    // large repositories come back truncated; let the server do those properly
+    if tree.truncated {
+        return Err("Repository too large for in-browser ingestion".to_string());
+    }
+
+    let ingester = githem_core::MemoryIngester::new(options);
+    let paths: Vec<String> = tree
+        .tree
+        .iter()
+        .filter(|e| e.kind == "blob" && e.size.unwrap_or(0) <= ingester.options.max_file_size)
+        .map(|e| e.path.clone())
+        .collect();
+
+    let mut files = Vec::new();
+    for path in ingester.filter_paths(&paths) {
+        let raw_url = format!("{}/{}/{}/{}/{}", GITHUB_RAW, owner, repo, branch, path);
+        let content = match Request::get(&raw_url).send().await {
+            Ok(r) if r.ok() => r.text().await.unwrap_or_else(|_| "[binary file]".to_string()),
+            _ => continue,
+        };
+        files.push((path.to_string(), content));
+    }
+
+    let content = ingester.ingest_to_string(&files);
+    let total_size = content.len();
+
+    Ok(IngestionResult {
+        id: format!("local-{}-{}-{}", owner, repo, branch),
+        summary: IngestionSummary {
+            repository: format!("https://github.com/{}/{}", owner, repo),
+            branch: branch.to_string(),
+            subpath: ingester.options.path_prefix.clone(),
+            files_analyzed: files.len(),
+            total_size,
+            estimated_tokens: githem_core::estimate_tokens(&content),
+        },
+        tree: githem_core::generate_tree(&content),
+        content,
+        metadata: RepositoryMetadata {
+            url: format!("https://github.com/{}/{}", owner, repo),
+            branches: if branch == default_branch {
+                vec![default_branch.clone()]
+            } else {
+                vec![default_branch.clone(), branch.to_string()]
+            },
+            default_branch,
+            size: Some(total_size as u64),
+            last_commit: None,
+            remote_url: Some(format!("https://github.com/{}/{}", owner, repo)),
+        },
+    })
+}
+
+/// messages sent by the server's /ws endpoint
+#[derive(serde::Deserialize)]
+#[serde(tag = "type")]
+enum WsMessage {
+    Progress { stage: String, message: String },
+    File { path: String, content: String },
+    Complete {
+        files: usize,
+        bytes: usize,
+        #[serde(default)]
+        branch: Option<String>,
+        #[serde(default)]
+        metadata: Option<RepositoryMetadata>,
+    },
+    Error { message: String },
+    FilterStats { stats: githem_core::FilterStats },
+}
+
+pub(crate) fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+pub(crate) fn decode_query_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// githem-core preset for the names the api accepts, None for unknown names
+pub fn parse_preset(preset: &str) -> Option<githem_core::FilterPreset> {
+    match preset.to_lowercase().as_str() {
+        "raw" => Some(githem_core::FilterPreset::Raw),
+        "standard" => Some(githem_core::FilterPreset::Standard),
+        "code-only" | "code_only" | "codeonly" => Some(githem_core::FilterPreset::CodeOnly),
+        "minimal" => Some(githem_core::FilterPreset::Minimal),
+        "docs-only" | "docs_only" | "docsonly" | "docs" => Some(githem_core::FilterPreset::DocsOnly),
+        "tests-only" | "tests_only" | "testsonly" | "tests" => Some(githem_core::FilterPreset::TestsOnly),
+        _ => None,
+    }
+}
+
+/// the /ws endpoint on the same host the page was served from
+fn websocket_url(query: &str) -> Result<String, String> {
+    let location = web_sys::window()
+        .ok_or_else(|| "No window available".to_string())?
+        .location();
+    let protocol = location.protocol().map_err(|_| "Failed to read page protocol".to_string())?;
+    let host = location.host().map_err(|_| "Failed to read page host".to_string())?;
+    let scheme = if protocol == "https:" { "wss" } else { "ws" };
+    Ok(format!("{}://{}/ws?{}", scheme, host, query))
+}
+
+/// Ingest on the server over the websocket, reporting every message as it
+/// arrives. Dropping the future closes the socket, which cancels the ingestion.
+pub async fn ingest_over_websocket(
+    owner: &str,
+    repo: &str,
+    filters: &RepositoryQuery,
+    mut on_progress: impl FnMut(&IngestProgress),
+) -> Result<IngestionResult, String> {
+    let repository = format!("https://github.com/{}/{}", owner, repo);
+    let mut query = format!("url={}", encode_query_value(&repository));
+    // the websocket takes the same parameter names as the routes
+    let filters = filters.to_string();
+    if !filters.is_empty() {
+        query.push('&');
+        query.push_str(&filters);
+    }
+
+    let mut socket = WebSocket::open(&websocket_url(&query)?)
+        .map_err(|e| format!("Failed to open websocket: {}", e))?;
+
+    let mut progress = IngestProgress::default();
+    let mut content = String::new();
+
+    while let Some(message) = socket.next().await {
+        let text = match message {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Bytes(_)) => continue,
+            Err(e) => return Err(format!("WebSocket error: {}", e)),
+        };
+
+        let message: WsMessage = serde_json::from_str(&text)
+            .map_err(|e| format!("Failed to parse server message: {}", e))?;
+
+        match message {
+            WsMessage::Progress { stage, message } => {
+                progress.stage = stage;
+                progress.message = message;
+            }
+            WsMessage::File { path, content: file } => {
+                content.push_str(&format!("=== {} ===\n", path));
+                content.push_str(&file);
+                progress.received_bytes += file.len();
+                progress.files.push((path, file.len()));
+            }
+            WsMessage::FilterStats { stats } => {
+                progress.filter_stats = Some(stats);
+            }
+            WsMessage::Error { message } => return Err(message),
+            WsMessage::Complete { files, bytes, branch: ingested, metadata } => {
+                let branch = ingested.or(branch.map(str::to_string)).unwrap_or_else(|| "HEAD".to_string());
+                let metadata = metadata.unwrap_or_else(|| RepositoryMetadata {
+                    url: repository.clone(),
+                    default_branch: branch.clone(),
+                    branches: vec![branch.clone()],
+                    size: Some(bytes as u64),
+                    last_commit: None,
+                    remote_url: None,
+                });
+                return Ok(IngestionResult {
+                    id: format!("ws-{}-{}-{}", owner, repo, branch),
+                    summary: IngestionSummary {
+                        repository: repository.clone(),
+                        branch: branch.clone(),
+                        subpath: subpath.map(|s| s.to_string()),
+                        files_analyzed: files,
+                        total_size: bytes,
+                        estimated_tokens: githem_core::estimate_tokens(&content),
+                    },
+                    tree: githem_core::generate_tree(&content),
+                    content,
+                    metadata,
+                });
+            }
+        }
+
+        on_progress(&progress);
+    }
+
+    Err("Connection closed before the ingestion finished".to_string())
+}
 
 pub async fn ingest_repository(request: IngestRequest) -> Result<IngestionResult, String> {
     let response = Request::post(&format!("{}/ingest", API_BASE))
@@ -29,6 +315,47 @@ pub async fn ingest_repository(request: IngestRequest) -> Result<IngestionResult
     get_ingestion_result(id).await
 }
 
+/// pin the current view to a commit on the server and return a short link
+pub async fn create_share(
+    owner: &str,
+    repo: &str,
+    branch: &str,
+    subpath: Option<&str>,
+    include: &[String],
+    exclude: &[String],
+) -> Result<String, String> {
+    let body = serde_json::json!({
+        "owner": owner,
+        "repo": repo,
+        "branch": (!branch.is_empty() && branch != "HEAD").then_some(branch),
+        "path": subpath,
+        "include": (!include.is_empty()).then(|| include.join(",")),
+        "exclude": (!exclude.is_empty()).then(|| exclude.join(",")),
+    });
+
+    let response = Request::post(&format!("{}/share", API_BASE))
+        .json(&body)
+        .map_err(|e| format!("Failed to create request: {}", e))?
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+
+    if !response.ok() {
+        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("API error: {}", error_text));
+    }
+
+    let value = response
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("Failed to parse response: {}", e))?;
+
+    value["url"]
+        .as_str()
+        .map(|url| url.to_string())
+        .ok_or_else(|| "Missing url in response".to_string())
+}
+
 pub async fn get_ingestion_result(id: &str) -> Result<IngestionResult, String> {
     let response = Request::get(&format!("{}/result/{}", API_BASE, id))
         .send()
@@ -93,28 +420,342 @@ pub async fn download_content(id: &str) -> Result<String, String> {
         .map_err(|e| format!("Failed to read content: {}", e))
 }
 
-// Parse file tree from the ingestion result
-pub fn parse_file_tree(tree_text: &str) -> Option<FileNode> {
-    // This is a simplified parser - you'd want to make this more robust
-    let lines: Vec<&str> = tree_text.lines().collect();
-    
-    // Find the root directory line
-    let root_line = lines.iter()
-        .find(|line| line.contains("└── ") && line.ends_with("/"))?;
-    
-    let root_name = root_line
-        .split("└── ")
-        .last()?
-        .trim_end_matches('/');
-    
-    Some(FileNode {
+pub async fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    let clipboard = web_sys::window()
+        .ok_or_else(|| "No window available".to_string())?
+        .navigator()
+        .clipboard();
+    wasm_bindgen_futures::JsFuture::from(clipboard.write_text(text))
+        .await
+        .map(|_| ())
+        .map_err(|_| "Clipboard access was denied".to_string())
+}
+
+/// save `text` as a file through a temporary object url
+pub fn download_text(filename: &str, text: &str, mime: &str) -> Result<(), String> {
+    use wasm_bindgen::JsCast;
+
+    let window = web_sys::window().ok_or_else(|| "No window available".to_string())?;
+    let document = window
+        .document()
+        .ok_or_else(|| "No document available".to_string())?;
+
+    let parts = js_sys::Array::of1(&wasm_bindgen::JsValue::from_str(text));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)
+        .map_err(|_| "Failed to create download".to_string())?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)
+        .map_err(|_| "Failed to create download".to_string())?;
+
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(|_| "Failed to create download".to_string())?
+        .unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+
+    let _ = web_sys::Url::revoke_object_url(&url);
+    Ok(())
+}
+
+/// the ingestion output as markdown, one fenced block per file
+pub fn to_markdown(title: &str, content: &str) -> String {
+    let mut markdown = format!("# {}\n\n", title);
+    for (path, body) in githem_core::split_files(content) {
+        let language = path.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+        // a fence longer than any backtick run inside the file
+        let longest = body
+            .split(|c| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest.max(2) + 1);
+        markdown.push_str(&format!(
+            "## {}\n\n{}{}\n{}\n{}\n\n",
+            path,
+            fence,
+            language,
+            body.trim_end(),
+            fence
+        ));
+    }
+    markdown
+}
+
+/// raw diff text for `compare/{spec}` or `pull/{number}`
+pub async fn fetch_diff(owner: &str, repo: &str, kind: &str, spec: &str) -> Result<String, String> {
+    let response = Request::get(&format!("{}/{}/{}/{}/{}", API_BASE, owner, repo, kind, spec))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch diff: {}", e))?;
+
+    if !response.ok() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("API error: {}", error_text));
+    }
+
+    response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read diff: {}", e))
+}
+
+/// split the server's diff output into files; the summary header before the
+/// first `diff --git` is dropped
+pub fn parse_diff(diff: &str) -> Vec<DiffFile> {
+    let mut files: Vec<DiffFile> = Vec::new();
+
+    for line in diff.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            // "a/path b/path", the new side names the file
+            let path = rest
+                .split_once(" b/")
+                .map(|(_, b)| b)
+                .unwrap_or(rest)
+                .to_string();
+            files.push(DiffFile {
+                path,
+                additions: 0,
+                deletions: 0,
+                lines: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        let (kind, text) = if line.starts_with("@@") {
+            (DiffLineKind::Hunk, line)
+        } else if file.lines.is_empty() {
+            // index, mode, rename and ---/+++ lines before the first hunk
+            continue;
+        } else if let Some(text) = line.strip_prefix('+') {
+            file.additions += 1;
+            (DiffLineKind::Added, text)
+        } else if let Some(text) = line.strip_prefix('-') {
+            file.deletions += 1;
+            (DiffLineKind::Removed, text)
+        } else if let Some(text) = line.strip_prefix(' ') {
+            (DiffLineKind::Context, text)
+        } else {
+            (DiffLineKind::Context, line)
+        };
+
+        file.lines.push(DiffLine {
+            kind,
+            text: text.to_string(),
+        });
+    }
+
+    files
+}
+
+/// the diff wrapped in instructions, ready to paste into a chat
+pub fn diff_prompt(title: &str, diff: &str) -> String {
+    format!(
+        "Review the following changes in {}. Point out bugs, risky changes and missing tests.\n\n```diff\n{}\n```\n",
+        title,
+        diff.trim_end()
+    )
+}
+
+/// Apply include/exclude patterns to an already fetched ingestion. Also
+/// returns the include patterns that matched no file, which usually means the
+/// server's preset dropped those files and a server-side ingest is needed.
+pub fn refilter(
+    full: &IngestionResult,
+    include: &[String],
+    exclude: &[String],
+) -> (IngestionResult, Vec<String>) {
+    if include.is_empty() && exclude.is_empty() {
+        return (full.clone(), Vec::new());
+    }
+
+    // the server already applied its preset, only the user's patterns remain
+    // patterns that do not build (blank ones) filter nothing and are reported
+    let matcher = |include: &[String]| {
+        githem_core::IngestOptions::builder()
+            .preset(githem_core::FilterPreset::Raw)
+            .includes(include)
+            .excludes(exclude)
+            .max_file_size(usize::MAX)
+            .build()
+            .ok()
+            .map(githem_core::MemoryIngester::new)
+    };
+
+    let files = githem_core::split_files(&full.content);
+    let paths: Vec<String> = files.iter().map(|(path, _)| path.to_string()).collect();
+
+    let unmatched = include
+        .iter()
+        .filter(|pattern| {
+            matcher(std::slice::from_ref(*pattern))
+                .is_none_or(|ingester| ingester.filter_paths(&paths).is_empty())
+        })
+        .cloned()
+        .collect();
+
+    let Some(ingester) = matcher(include) else {
+        return (full.clone(), unmatched);
+    };
+    (rebuild(full, |path| ingester.should_include(path)), unmatched)
+}
+
+/// the ingestion without the given files, for files unchecked in the tree
+pub fn without_files(full: &IngestionResult, skipped: &HashSet<String>) -> IngestionResult {
+    if skipped.is_empty() {
+        return full.clone();
+    }
+    rebuild(full, |path| !skipped.contains(path))
+}
+
+/// keep the files `keep` accepts, recomputing the tree and summary
+fn rebuild(full: &IngestionResult, keep: impl Fn(&str) -> bool) -> IngestionResult {
+    let files = githem_core::split_files(&full.content);
+    let selected: Vec<&(&str, &str)> = files.iter().filter(|(path, _)| keep(path)).collect();
+
+    let selected_paths: Vec<&str> = selected.iter().map(|(path, _)| *path).collect();
+    let mut content = githem_core::generate_tree_from_paths(&selected_paths);
+    for (path, body) in &selected {
+        content.push_str(&format!("=== {} ===\n", path));
+        content.push_str(body);
+    }
+
+    let mut result = full.clone();
+    result.summary.files_analyzed = selected.len();
+    result.summary.total_size = content.len();
+    result.summary.estimated_tokens = githem_core::estimate_tokens(&content);
+    result.tree = githem_core::generate_tree(&content);
+    result.content = content;
+    result
+}
+
+/// byte range of every file body in an ingestion output, keyed by path
+pub fn file_offsets(content: &str) -> HashMap<String, (usize, usize)> {
+    let base = content.as_ptr() as usize;
+    githem_core::split_files(content)
+        .into_iter()
+        .map(|(path, body)| {
+            let start = body.as_ptr() as usize - base;
+            (path.to_string(), (start, start + body.len()))
+        })
+        .collect()
+}
+
+/// paths listed in the `# File Structure` section at the top of the output
+fn structure_paths(content: &str) -> Option<Vec<String>> {
+    let mut lines = content.lines().skip_while(|line| line.trim() != "# File Structure");
+    lines.next()?;
+
+    let section: Vec<&str> = lines.take_while(|line| !line.starts_with("=== ")).collect();
+    Some(githem_core::parse_tree(&section.join("\n")))
+}
+
+fn insert_path(root: &mut FileNode, path: &str, size: Option<usize>) {
+    let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+    let mut node = root;
+
+    for (i, part) in parts.iter().enumerate() {
+        let is_file = i == parts.len() - 1;
+        let index = match node
+            .children
+            .iter()
+            .position(|c| c.name == *part && c.is_directory == !is_file)
+        {
+            Some(index) => index,
+            None => {
+                node.children.push(FileNode {
+                    name: part.to_string(),
+                    path: parts[..=i].join("/"),
+                    is_directory: !is_file,
+                    size: if is_file { size } else { None },
+                    children: vec![],
+                    content: None,
+                    is_expanded: false,
+                    is_included: true,
+                });
+                node.children.len() - 1
+            }
+        };
+        node = &mut node.children[index];
+    }
+}
+
+/// directories first, then files, both by name; directory sizes are the sum of their files
+fn finish_tree(node: &mut FileNode) -> usize {
+    if !node.is_directory {
+        return node.size.unwrap_or(0);
+    }
+
+    node.children
+        .sort_by(|a, b| b.is_directory.cmp(&a.is_directory).then_with(|| a.name.cmp(&b.name)));
+    let size = node.children.iter_mut().map(finish_tree).sum();
+    node.size = Some(size);
+    size
+}
+
+/// clear `is_included` on skipped files and on directories with nothing left,
+/// returns whether anything under `node` is still included
+pub fn mark_skipped(node: &mut FileNode, skipped: &HashSet<String>) -> bool {
+    node.is_included = if node.is_directory {
+        node.children
+            .iter_mut()
+            .fold(false, |any, child| mark_skipped(child, skipped) || any)
+    } else {
+        !skipped.contains(&node.path)
+    };
+    node.is_included
+}
+
+/// every file path at or below `node`
+pub fn file_paths(node: &FileNode) -> Vec<String> {
+    if !node.is_directory {
+        return vec![node.path.clone()];
+    }
+    node.children.iter().flat_map(file_paths).collect()
+}
+
+/// Build the nested file tree of an ingestion output. Paths come from the
+/// `# File Structure` section, or from the file headers when it is missing.
+pub fn parse_file_tree(root_name: &str, content: &str) -> Option<FileNode> {
+    let offsets = file_offsets(content);
+    let paths = structure_paths(content)
+        .filter(|paths| !paths.is_empty())
+        .unwrap_or_else(|| {
+            githem_core::split_files(content)
+                .into_iter()
+                .map(|(path, _)| path.to_string())
+                .collect()
+        });
+
+    if paths.is_empty() {
+        return None;
+    }
+
+    let mut root = FileNode {
         name: root_name.to_string(),
-        path: "/".to_string(),
+        path: String::new(),
         is_directory: true,
         size: None,
-        children: vec![], // Would need to parse children recursively
+        children: vec![],
         content: None,
         is_expanded: true,
         is_included: true,
-    })
+    };
+
+    for path in &paths {
+        let size = offsets.get(path).map(|(start, end)| end - start);
+        insert_path(&mut root, path, size);
+    }
+    finish_tree(&mut root);
+
+    Some(root)
 }
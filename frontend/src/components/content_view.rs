@@ -1,28 +1,38 @@
 use dioxus::prelude::*;
 use crate::types::*;
 
+/// must match the `leading-5` line height of the rendered lines
+const LINE_HEIGHT_PX: usize = 20;
+/// lines rendered above and below the viewport
+const OVERSCAN_LINES: usize = 50;
+const SCROLL_CONTAINER_ID: &str = "content-view-scroll";
+
 #[component]
 pub fn ContentView(state: Signal<RepositoryState>) -> Element {
-    let content = if let Some(_selected) = &state().selected_file {
-        // Get content for selected file
-        state().ingestion.as_ref().map(|i| i.content.clone())
-    } else if let Some(ingestion) = &state().ingestion {
-        Some(ingestion.content.clone())
-    } else {
-        None
-    };
-    
+    // only the selected file's slice of the output, never the whole blob
+    let selected = state().selected_file.clone().and_then(|path| {
+        let current = state();
+        let (start, end) = *current.file_offsets.get(&path)?;
+        let content = current.full_ingestion.as_ref()?.content.get(start..end)?.to_string();
+        Some((path, content))
+    });
+    // (scroll top, height) of the container, only read by VirtualLines
+    let viewport = use_signal(|| (0usize, 0usize));
+
     rsx! {
         div {
+            id: SCROLL_CONTAINER_ID,
             class: "h-full overflow-auto bg-white dark:bg-gray-900",
-            
-            if let Some(content) = content {
-                pre {
-                    class: "p-4 text-sm font-mono text-gray-800 dark:text-gray-200",
-                    code {
-                        "{content}"
-                    }
+            onmounted: move |_| measure_viewport(viewport),
+            onscroll: move |_| measure_viewport(viewport),
+
+            if let Some((path, content)) = selected {
+                div {
+                    class: "sticky top-0 z-10 px-4 py-2 text-sm font-medium bg-gray-50 dark:bg-gray-800
+                           border-b border-gray-200 dark:border-gray-700 text-gray-700 dark:text-gray-300",
+                    "{path}"
                 }
+                FileBody { key: "{path}", path: path.clone(), content: content, viewport: viewport }
             } else {
                 div {
                     class: "flex items-center justify-center h-full text-gray-500 dark:text-gray-400",
@@ -32,3 +42,81 @@ pub fn ContentView(state: Signal<RepositoryState>) -> Element {
         }
     }
 }
+
+#[component]
+fn FileBody(
+    path: ReadOnlySignal<String>,
+    content: ReadOnlySignal<String>,
+    viewport: Signal<(usize, usize)>,
+) -> Element {
+    let highlighted = use_memo(move || crate::highlight::highlight(&path.read(), &content.read()));
+
+    if let Some(html) = highlighted() {
+        return rsx! {
+            pre {
+                class: "p-4 text-sm leading-5 font-mono",
+                code {
+                    class: "hljs",
+                    dangerous_inner_html: "{html}",
+                }
+            }
+        };
+    }
+
+    rsx! {
+        VirtualLines { content: content, viewport: viewport }
+    }
+}
+
+/// plain text where only the lines near the viewport are in the dom
+#[component]
+fn VirtualLines(content: ReadOnlySignal<String>, viewport: Signal<(usize, usize)>) -> Element {
+    // byte offset of every line start, plus the end of the content
+    let line_starts = use_memo(move || {
+        let content = content.read();
+        let mut starts: Vec<usize> = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .filter(|&i| i < content.len())
+            .collect();
+        starts.push(content.len());
+        starts
+    });
+    let starts = line_starts.read();
+    let total_lines = starts.len() - 1;
+    let (scroll_top, height) = viewport();
+    let first = (scroll_top / LINE_HEIGHT_PX).saturating_sub(OVERSCAN_LINES).min(total_lines);
+    // before the first scroll event the viewport size is unknown
+    let visible = height.max(1000) / LINE_HEIGHT_PX + 2 * OVERSCAN_LINES;
+    let last = (first + visible).min(total_lines);
+
+    let text = content.read()[starts[first]..starts[last]].to_string();
+    let total_height = total_lines * LINE_HEIGHT_PX;
+    let offset = first * LINE_HEIGHT_PX;
+
+    rsx! {
+        div {
+            class: "relative",
+            style: "height: {total_height}px",
+
+            pre {
+                class: "absolute left-0 right-0 px-4 text-sm leading-5 font-mono text-gray-800 dark:text-gray-200",
+                style: "top: {offset}px",
+                code {
+                    "{text}"
+                }
+            }
+        }
+    }
+}
+
+fn measure_viewport(mut viewport: Signal<(usize, usize)>) {
+    let element = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id(SCROLL_CONTAINER_ID));
+    if let Some(element) = element {
+        viewport.set((
+            element.scroll_top().max(0) as usize,
+            element.client_height().max(0) as usize,
+        ));
+    }
+}
@@ -1,11 +1,14 @@
 use dioxus::prelude::*;
 use crate::types::*;
-use crate::components::{format_size, format_tokens};
+use crate::components::{format_size, TokenBudget};
 
 #[component]
 pub fn ControlPanel(state: Signal<RepositoryState>) -> Element {
     let mut include_input = use_signal(String::new);
     let mut exclude_input = use_signal(String::new);
+    let mut app_state = use_context::<Signal<AppState>>();
+    // the output last copied, so refiltering resets the button
+    let mut copied = use_signal(|| None::<String>);
     
     rsx! {
         div {
@@ -21,7 +24,7 @@ pub fn ControlPanel(state: Signal<RepositoryState>) -> Element {
                         value: "{state().branch}",
                         onchange: move |evt| {
                             state.write().branch = evt.value();
-                            // TODO: Reload with new branch
+                            reingest_on_server(state, app_state, false);
                         },
                         class: "px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg
                                bg-white dark:bg-gray-700 text-gray-900 dark:text-white",
@@ -36,6 +39,26 @@ pub fn ControlPanel(state: Signal<RepositoryState>) -> Element {
                     }
                 }
                 
+                // Preset selector, changing it needs the server's unfiltered view
+                select {
+                    value: "{state().preset.clone().unwrap_or_default()}",
+                    onchange: move |evt| {
+                        let preset = evt.value();
+                        state.write().preset = (!preset.is_empty()).then_some(preset);
+                        reingest_on_server(state, app_state, false);
+                    },
+                    class: "px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg
+                           bg-white dark:bg-gray-700 text-gray-900 dark:text-white",
+
+                    for (value, label) in [("", "Standard"), ("code-only", "Code only"), ("minimal", "Minimal"), ("docs-only", "Docs only"), ("tests-only", "Tests only"), ("raw", "Raw")] {
+                        option {
+                            value: "{value}",
+                            selected: state().preset.as_deref().unwrap_or_default() == value,
+                            "{label}"
+                        }
+                    }
+                }
+                
                 // View mode selector
                 div {
                     class: "flex bg-white dark:bg-gray-700 rounded-lg border border-gray-300 dark:border-gray-600",
@@ -87,8 +110,9 @@ pub fn ControlPanel(state: Signal<RepositoryState>) -> Element {
                                 .map(|s| s.trim().to_string())
                                 .filter(|s| !s.is_empty())
                                 .collect();
-                            state.write().include_patterns = patterns;
-                            // TODO: Apply filters
+                            let mut state = state.write();
+                            state.include_patterns = patterns;
+                            state.refilter();
                         },
                         class: "px-3 py-1 text-sm bg-blue-600 text-white rounded hover:bg-blue-700",
                         "Apply"
@@ -120,13 +144,28 @@ pub fn ControlPanel(state: Signal<RepositoryState>) -> Element {
                                 .map(|s| s.trim().to_string())
                                 .filter(|s| !s.is_empty())
                                 .collect();
-                            state.write().exclude_patterns = patterns;
-                            // TODO: Apply filters
+                            let mut state = state.write();
+                            state.exclude_patterns = patterns;
+                            state.refilter();
                         },
                         class: "px-3 py-1 text-sm bg-blue-600 text-white rounded hover:bg-blue-700",
                         "Apply"
                     }
                 }
+
+                // the fetched output was already filtered by the server's preset
+                if !state().unmatched_patterns.is_empty() {
+                    span {
+                        class: "text-sm text-amber-600 dark:text-amber-400",
+                        "No fetched files match {state().unmatched_patterns.join(\", \")}"
+                    }
+
+                    button {
+                        onclick: move |_| reingest_on_server(state, app_state, true),
+                        class: "px-3 py-1 text-sm bg-amber-600 text-white rounded hover:bg-amber-700",
+                        "Re-ingest on server"
+                    }
+                }
             }
             
             // Third row: Stats and actions
@@ -145,10 +184,16 @@ pub fn ControlPanel(state: Signal<RepositoryState>) -> Element {
                         span {
                             "💾 {format_size(ingestion.summary.total_size)}"
                         }
-                        
-                        span {
-                            "🔤 ~{format_tokens(ingestion.summary.estimated_tokens)} tokens"
+
+                        if let Some(commit) = &ingestion.metadata.last_commit {
+                            span {
+                                class: "truncate max-w-xs",
+                                title: "{commit}",
+                                "🔖 {commit}"
+                            }
                         }
+                        
+                        TokenBudget { state: state }
                     }
                     
                     // Actions
@@ -156,29 +201,67 @@ pub fn ControlPanel(state: Signal<RepositoryState>) -> Element {
                         class: "flex items-center gap-2",
                         
                         button {
-                            onclick: move |_| {
-                                // TODO: Download content
-                            },
+                            onclick: move |_| download(state, app_state, "txt"),
                             class: "px-4 py-2 text-sm bg-gray-200 dark:bg-gray-700 rounded-lg
                                    hover:bg-gray-300 dark:hover:bg-gray-600 transition-colors",
-                            "📥 Download"
+                            "📥 .txt"
                         }
                         
                         button {
-                            onclick: move |_| {
-                                // TODO: Copy to clipboard
-                            },
+                            onclick: move |_| download(state, app_state, "md"),
                             class: "px-4 py-2 text-sm bg-gray-200 dark:bg-gray-700 rounded-lg
                                    hover:bg-gray-300 dark:hover:bg-gray-600 transition-colors",
-                            "📋 Copy"
+                            "📥 .md"
                         }
                         
-                        a {
-                            href: "/api/result/{ingestion.id}",
-                            target: "_blank",
+                        button {
+                            onclick: move |_| {
+                                let content = state().ingestion.map(|i| i.content).unwrap_or_default();
+                                spawn(async move {
+                                    match crate::api::copy_to_clipboard(&content).await {
+                                        Ok(()) => copied.set(Some(content)),
+                                        Err(e) => app_state.write().error = Some(e),
+                                    }
+                                });
+                            },
                             class: "px-4 py-2 text-sm bg-gray-200 dark:bg-gray-700 rounded-lg
                                    hover:bg-gray-300 dark:hover:bg-gray-600 transition-colors",
-                            "🔗 API"
+                            if copied.read().as_deref() == Some(ingestion.content.as_str()) { "✅ Copied" } else { "📋 Copy" }
+                        }
+                        
+                        if let Some(url) = state().share_url {
+                            a {
+                                href: "{url}",
+                                target: "_blank",
+                                class: "px-4 py-2 text-sm text-blue-600 dark:text-blue-400 hover:underline",
+                                "{url}"
+                            }
+                        } else {
+                            button {
+                                onclick: move |_| {
+                                    spawn(async move {
+                                        let current = state();
+                                        let include = current.include_list();
+                                        let exclude = current.exclude_list();
+                                        match crate::api::create_share(
+                                            &current.owner,
+                                            &current.repo,
+                                            &current.branch,
+                                            current.subpath.as_deref(),
+                                            &include,
+                                            &exclude,
+                                        )
+                                        .await
+                                        {
+                                            Ok(url) => state.write().share_url = Some(url),
+                                            Err(e) => app_state.write().error = Some(e),
+                                        }
+                                    });
+                                },
+                                class: "px-4 py-2 text-sm bg-gray-200 dark:bg-gray-700 rounded-lg
+                                       hover:bg-gray-300 dark:hover:bg-gray-600 transition-colors",
+                                "🔗 Share"
+                            }
                         }
                     }
                 }
@@ -187,6 +270,76 @@ pub fn ControlPanel(state: Signal<RepositoryState>) -> Element {
     }
 }
 
+/// save the filtered output, as is or as markdown
+fn download(state: Signal<RepositoryState>, mut app_state: Signal<AppState>, extension: &str) {
+    let current = state();
+    let Some(ingestion) = &current.ingestion else {
+        return;
+    };
+
+    let (text, mime) = match extension {
+        "md" => (
+            crate::api::to_markdown(
+                &format!("{}/{}", current.owner, current.repo),
+                &ingestion.content,
+            ),
+            "text/markdown",
+        ),
+        _ => (ingestion.content.clone(), "text/plain"),
+    };
+
+    if let Err(e) = crate::api::download_text(&current.download_name(extension), &text, mime) {
+        app_state.write().error = Some(e);
+    }
+}
+
+/// Fetch again on the server: with the patterns applied server-side, for files
+/// the in-browser refilter cannot bring back, or without them after a branch
+/// or preset change so later pattern tweaks stay client-side.
+fn reingest_on_server(
+    mut state: Signal<RepositoryState>,
+    mut app_state: Signal<AppState>,
+    with_patterns: bool,
+) {
+    if let Some(task) = state.write().ingest_task.take() {
+        task.cancel();
+    }
+
+    let task = spawn(async move {
+        let current = state();
+        state.write().progress = Some(IngestProgress::default());
+
+        let mut filters = current.query();
+        if !with_patterns {
+            filters.include.clear();
+            filters.exclude.clear();
+        }
+
+        let result = crate::api::ingest_over_websocket(
+            &current.owner,
+            &current.repo,
+            &filters,
+            move |progress| state.write().progress = Some(progress.clone()),
+        )
+        .await;
+
+        let mut state = state.write();
+        state.progress = None;
+        state.ingest_task = None;
+        match result {
+            Ok(ingestion) => {
+                state.set_ingestion(ingestion);
+                if with_patterns {
+                    // whatever the server returned is what these patterns select
+                    state.unmatched_patterns.clear();
+                }
+            }
+            Err(e) => app_state.write().error = Some(e),
+        }
+    });
+    state.write().ingest_task = Some(task);
+}
+
 #[component]
 fn ViewModeButton(mode: ViewMode, current: ViewMode, state: Signal<RepositoryState>) -> Element {
     let label = match mode {
@@ -0,0 +1,130 @@
+// src/components/diff_view.rs
+use dioxus::prelude::*;
+use crate::types::*;
+
+#[component]
+pub fn DiffFileView(file: DiffFile, layout: DiffLayout) -> Element {
+    let mut expanded = use_signal(|| true);
+
+    rsx! {
+        div {
+            class: "mb-4 border border-gray-200 dark:border-gray-700 rounded-lg overflow-hidden",
+
+            button {
+                onclick: move |_| expanded.toggle(),
+                class: "w-full flex items-center justify-between px-4 py-2 text-sm font-medium
+                       bg-gray-50 dark:bg-gray-800 text-gray-700 dark:text-gray-300
+                       hover:bg-gray-100 dark:hover:bg-gray-700",
+
+                span {
+                    if expanded() { "▾ " } else { "▸ " }
+                    "{file.path}"
+                }
+                span {
+                    span { class: "text-green-600 mr-2", "+{file.additions}" }
+                    span { class: "text-red-600", "-{file.deletions}" }
+                }
+            }
+
+            if expanded() {
+                match layout {
+                    DiffLayout::Unified => rsx! { UnifiedLines { lines: file.lines.clone() } },
+                    DiffLayout::SideBySide => rsx! { SideBySideLines { lines: file.lines.clone() } },
+                }
+            }
+        }
+    }
+}
+
+fn line_class(kind: Option<DiffLineKind>) -> &'static str {
+    match kind {
+        Some(DiffLineKind::Hunk) => "bg-blue-50 dark:bg-blue-900/30 text-blue-700 dark:text-blue-300",
+        Some(DiffLineKind::Added) => "bg-green-50 dark:bg-green-900/30 text-green-800 dark:text-green-300",
+        Some(DiffLineKind::Removed) => "bg-red-50 dark:bg-red-900/30 text-red-800 dark:text-red-300",
+        Some(DiffLineKind::Context) => "text-gray-800 dark:text-gray-200",
+        None => "bg-gray-50 dark:bg-gray-800",
+    }
+}
+
+#[component]
+fn UnifiedLines(lines: Vec<DiffLine>) -> Element {
+    let rows = lines.iter().map(|line| {
+        let marker = match line.kind {
+            DiffLineKind::Added => "+",
+            DiffLineKind::Removed => "-",
+            DiffLineKind::Context => " ",
+            DiffLineKind::Hunk => "",
+        };
+        (line_class(Some(line.kind)), format!("{}{}", marker, line.text))
+    });
+
+    rsx! {
+        pre {
+            class: "text-sm font-mono overflow-x-auto",
+            for (class, text) in rows {
+                div {
+                    class: "px-4 whitespace-pre {class}",
+                    "{text}"
+                }
+            }
+        }
+    }
+}
+
+/// removed lines on the left, the added lines that replace them on the right
+fn side_by_side(lines: &[DiffLine]) -> Vec<(Option<&DiffLine>, Option<&DiffLine>)> {
+    let mut rows = Vec::new();
+    let mut removed: Vec<&DiffLine> = Vec::new();
+    let mut added: Vec<&DiffLine> = Vec::new();
+
+    let flush = |rows: &mut Vec<_>, removed: &mut Vec<&DiffLine>, added: &mut Vec<&DiffLine>| {
+        for i in 0..removed.len().max(added.len()) {
+            rows.push((removed.get(i).copied(), added.get(i).copied()));
+        }
+        removed.clear();
+        added.clear();
+    };
+
+    for line in lines {
+        match line.kind {
+            DiffLineKind::Removed => {
+                if !added.is_empty() {
+                    flush(&mut rows, &mut removed, &mut added);
+                }
+                removed.push(line);
+            }
+            DiffLineKind::Added => added.push(line),
+            DiffLineKind::Context | DiffLineKind::Hunk => {
+                flush(&mut rows, &mut removed, &mut added);
+                rows.push((Some(line), Some(line)));
+            }
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+
+    rows
+}
+
+#[component]
+fn SideBySideLines(lines: Vec<DiffLine>) -> Element {
+    let cells = side_by_side(&lines).into_iter().flat_map(|(left, right)| {
+        [left, right].map(|side| {
+            (
+                line_class(side.map(|l| l.kind)),
+                side.map(|l| l.text.clone()).unwrap_or_default(),
+            )
+        })
+    });
+
+    rsx! {
+        div {
+            class: "grid grid-cols-2 text-sm font-mono overflow-x-auto",
+            for (class, text) in cells {
+                div {
+                    class: "px-4 whitespace-pre border-r border-gray-200 dark:border-gray-700 {class}",
+                    "{text}"
+                }
+            }
+        }
+    }
+}
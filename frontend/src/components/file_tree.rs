@@ -1,6 +1,7 @@
 // src/components/file_tree.rs
 use dioxus::prelude::*;
 use crate::types::*;
+use crate::components::{format_size, get_file_icon};
 
 #[component]
 pub fn FileTreeView(state: Signal<RepositoryState>) -> Element {
@@ -30,30 +31,73 @@ fn FileTreeNode(
     state: Signal<RepositoryState>,
     depth: usize,
 ) -> Element {
-    // Implementation similar to the original Dioxus code
+    // expansion is view state, so it lives with the node's component
+    let mut expanded = use_signal(|| node.is_expanded);
+    let is_selected = !node.is_directory && state().selected_file.as_deref() == Some(node.path.as_str());
+    let icon = match (node.is_directory, expanded()) {
+        (true, true) => "📂",
+        (true, false) => "📁",
+        (false, _) => get_file_icon(&node.name),
+    };
+    let size = node.size.map(format_size).unwrap_or_default();
+    let path = node.path.clone();
+    let is_directory = node.is_directory;
+    let is_included = node.is_included;
+    let paths = crate::api::file_paths(&node);
+
     rsx! {
         div {
             class: "select-none",
             style: "padding-left: {depth * 20}px",
             
             div {
-                class: "flex items-center py-1 px-2 hover:bg-gray-100 dark:hover:bg-gray-800 rounded cursor-pointer",
+                class: if is_selected {
+                    "flex items-center py-1 px-2 rounded cursor-pointer bg-blue-100 dark:bg-blue-900"
+                } else {
+                    "flex items-center py-1 px-2 hover:bg-gray-100 dark:hover:bg-gray-800 rounded cursor-pointer"
+                },
                 onclick: move |_| {
-                    if !node.is_directory {
-                        state.write().selected_file = Some(node.path.clone());
+                    if is_directory {
+                        expanded.toggle();
+                    } else {
+                        state.write().selected_file = Some(path.clone());
                     }
                 },
                 
-                if node.is_directory {
-                    span { class: "mr-1", "📁" }
-                } else {
-                    span { class: "mr-1", "📄" }
+                input {
+                    r#type: "checkbox",
+                    class: "mr-2",
+                    checked: is_included,
+                    onclick: move |evt| evt.stop_propagation(),
+                    onchange: move |_| state.write().set_files_checked(paths.clone(), !is_included),
                 }
+
+                span { class: "mr-1", "{icon}" }
                 
                 span {
-                    class: "text-sm",
+                    class: if is_included {
+                        "text-sm flex-1 truncate"
+                    } else {
+                        "text-sm flex-1 truncate text-gray-400 dark:text-gray-500 line-through"
+                    },
                     "{node.name}"
                 }
+
+                span {
+                    class: "text-xs text-gray-500 dark:text-gray-400 ml-2",
+                    "{size}"
+                }
+            }
+        }
+
+        if is_directory && expanded() {
+            for child in node.children.iter() {
+                FileTreeNode {
+                    key: "{child.path}",
+                    node: child.clone(),
+                    state: state,
+                    depth: depth + 1,
+                }
             }
         }
     }
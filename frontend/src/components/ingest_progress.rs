@@ -0,0 +1,92 @@
+// src/components/ingest_progress.rs
+use dioxus::prelude::*;
+use crate::types::*;
+use crate::components::{format_size, get_file_icon};
+
+/// files shown in the arrival list, newest first
+const RECENT_FILES: usize = 12;
+
+#[component]
+pub fn IngestProgressView(progress: IngestProgress, on_cancel: EventHandler<()>) -> Element {
+    let stage = if progress.stage.is_empty() {
+        "connecting".to_string()
+    } else {
+        progress.stage.clone()
+    };
+    let recent: Vec<(String, usize)> = progress
+        .files
+        .iter()
+        .rev()
+        .take(RECENT_FILES)
+        .cloned()
+        .collect();
+
+    rsx! {
+        div {
+            class: "h-full flex items-center justify-center bg-white dark:bg-gray-900",
+
+            div {
+                class: "w-full max-w-xl p-6 rounded-lg border border-gray-200 dark:border-gray-700 shadow",
+
+                div {
+                    class: "flex items-center justify-between mb-4",
+
+                    div {
+                        class: "flex items-center gap-3",
+
+                        div {
+                            class: "animate-spin rounded-full h-5 w-5 border-b-2 border-blue-600"
+                        }
+
+                        span {
+                            class: "font-medium text-gray-900 dark:text-white capitalize",
+                            "{stage}"
+                        }
+                    }
+
+                    button {
+                        onclick: move |_| on_cancel.call(()),
+                        class: "px-3 py-1 text-sm bg-gray-200 dark:bg-gray-700 rounded
+                               hover:bg-gray-300 dark:hover:bg-gray-600 transition-colors",
+                        "Cancel"
+                    }
+                }
+
+                if !progress.message.is_empty() {
+                    p {
+                        class: "text-sm text-gray-600 dark:text-gray-400 mb-4",
+                        "{progress.message}"
+                    }
+                }
+
+                div {
+                    class: "flex gap-6 text-sm text-gray-600 dark:text-gray-400 mb-4",
+
+                    span { "📁 {progress.files.len()} files received" }
+                    span { "💾 {format_size(progress.received_bytes)}" }
+                }
+
+                if let Some(stats) = &progress.filter_stats {
+                    div {
+                        class: "text-sm text-gray-600 dark:text-gray-400 mb-4",
+                        "Filters kept {stats.included_files} of {stats.total_files} files "
+                        "({stats.excluded_files} excluded)"
+                    }
+                }
+
+                ul {
+                    class: "text-sm font-mono text-gray-700 dark:text-gray-300 space-y-1",
+
+                    for (path, size) in recent {
+                        li {
+                            key: "{path}",
+                            class: "flex justify-between",
+                            span { "{get_file_icon(&path)} {path}" }
+                            span { class: "text-gray-500", "{format_size(size)}" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
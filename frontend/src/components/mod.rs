@@ -2,11 +2,17 @@ pub mod control_panel;
 pub mod file_tree;
 pub mod content_view;
 pub mod raw_view;
+pub mod ingest_progress;
+pub mod diff_view;
+pub mod token_budget;
 
 pub use control_panel::ControlPanel;
 pub use file_tree::FileTreeView;
 pub use content_view::ContentView;
 pub use raw_view::RawView;
+pub use ingest_progress::IngestProgressView;
+pub use diff_view::DiffFileView;
+pub use token_budget::TokenBudget;
 
 // Helper functions
 pub fn format_size(bytes: usize) -> String {
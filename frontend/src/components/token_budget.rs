@@ -0,0 +1,58 @@
+// src/components/token_budget.rs
+use dioxus::prelude::*;
+use crate::types::*;
+use crate::components::format_tokens;
+
+/// running token total of the output against a model's context window
+#[component]
+pub fn TokenBudget(state: Signal<RepositoryState>) -> Element {
+    let tokens = state()
+        .ingestion
+        .as_ref()
+        .map(|i| i.summary.estimated_tokens)
+        .unwrap_or(0);
+    let window = state().context_window;
+    let percent = tokens * 100 / window.max(1);
+    let bar_class = match percent {
+        0..=74 => "bg-green-500",
+        75..=99 => "bg-amber-500",
+        _ => "bg-red-500",
+    };
+
+    rsx! {
+        div {
+            class: "flex items-center gap-2",
+
+            span {
+                "🔤 ~{format_tokens(tokens)} / {format_tokens(window)} tokens"
+            }
+
+            div {
+                class: "w-32 h-2 rounded bg-gray-200 dark:bg-gray-700 overflow-hidden",
+                title: "{percent}% of the context window",
+                div {
+                    class: "h-full {bar_class}",
+                    style: "width: {percent.min(100)}%",
+                }
+            }
+
+            select {
+                onchange: move |evt| {
+                    if let Ok(window) = evt.value().parse() {
+                        state.write().context_window = window;
+                    }
+                },
+                class: "px-2 py-1 text-sm border border-gray-300 dark:border-gray-600 rounded
+                       bg-white dark:bg-gray-700 text-gray-900 dark:text-white",
+
+                for (size, label) in CONTEXT_WINDOWS {
+                    option {
+                        value: "{size}",
+                        selected: *size == window,
+                        "{label}"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+// src/highlight.rs
+use wasm_bindgen::prelude::*;
+
+/// files above this are shown as plain text, highlighting them blocks the page
+pub const MAX_HIGHLIGHT_BYTES: usize = 200 * 1024;
+
+#[wasm_bindgen]
+extern "C" {
+    // highlight.js is loaded from a <script> tag in App; `catch` keeps a
+    // script failing to load from turning into a panic
+    #[wasm_bindgen(catch, js_namespace = hljs, js_name = getLanguage)]
+    fn get_language(name: &str) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(catch, js_namespace = hljs, js_name = highlight)]
+    fn highlight_js(code: &str, options: &JsValue) -> Result<JsValue, JsValue>;
+}
+
+/// highlight.js language name for a path, None when it has no grammar for it
+fn language_for(path: &str) -> Option<String> {
+    let filename = path.rsplit('/').next().unwrap_or(path).to_lowercase();
+    let candidate = match filename.rsplit_once('.') {
+        Some((_, ext)) => ext.to_string(),
+        // Dockerfile, Makefile, ...
+        None => filename,
+    };
+
+    get_language(&candidate)
+        .ok()
+        .filter(|language| !language.is_undefined())
+        .map(|_| candidate)
+}
+
+/// highlighted html for a file, None for plain-text rendering
+pub fn highlight(path: &str, code: &str) -> Option<String> {
+    if code.len() > MAX_HIGHLIGHT_BYTES {
+        return None;
+    }
+
+    let language = language_for(path)?;
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"language".into(), &language.into()).ok()?;
+    js_sys::Reflect::set(&options, &"ignoreIllegals".into(), &true.into()).ok()?;
+
+    let result = highlight_js(code, &options).ok()?;
+    js_sys::Reflect::get(&result, &"value".into())
+        .ok()?
+        .as_string()
+}
@@ -3,9 +3,11 @@ use dioxus::prelude::*;
 mod components;
 mod views;
 mod api;
+mod highlight;
 mod types;
 
-use views::{Repository, RepositoryBranch, RepositoryPath, Home, Layout};
+use types::RepositoryQuery;
+use views::{Repository, RepositoryBranch, RepositoryPath, RepositoryCompare, RepositoryPull, Home, Layout};
 
 #[derive(Debug, Clone, Routable, PartialEq)]
 #[rustfmt::skip]
@@ -15,18 +17,27 @@ enum Route {
         Home {},
         
         // GitHub-like routes
-        #[route("/:owner/:repo")]
-        Repository { owner: String, repo: String },
+        #[route("/:owner/:repo?:..query")]
+        Repository { owner: String, repo: String, query: RepositoryQuery },
         
-        #[route("/:owner/:repo/tree/:branch")]
-        RepositoryBranch { owner: String, repo: String, branch: String },
+        #[route("/:owner/:repo/tree/:branch?:..query")]
+        RepositoryBranch { owner: String, repo: String, branch: String, query: RepositoryQuery },
         
-        #[route("/:owner/:repo/tree/:branch/*path")]
-        RepositoryPath { owner: String, repo: String, branch: String, path: String },
+        #[route("/:owner/:repo/tree/:branch/*path?:..query")]
+        RepositoryPath { owner: String, repo: String, branch: String, path: String, query: RepositoryQuery },
+
+        #[route("/:owner/:repo/compare/:spec")]
+        RepositoryCompare { owner: String, repo: String, spec: String },
+
+        #[route("/:owner/:repo/pull/:number")]
+        RepositoryPull { owner: String, repo: String, number: String },
 }
 
 const FAVICON: Asset = asset!("/assets/favicon.ico");
 const TAILWIND_CSS: Asset = asset!("/assets/tailwind.css");
+// vendored rather than loaded from a CDN, see README.md to update them
+const HIGHLIGHT_JS: Asset = asset!("/assets/vendor/highlight.min.js");
+const HIGHLIGHT_CSS: Asset = asset!("/assets/vendor/github-dark.min.css");
 
 fn main() {
     dioxus::launch(App);
@@ -40,6 +51,8 @@ fn App() -> Element {
     rsx! {
         document::Link { rel: "icon", href: FAVICON }
         document::Link { rel: "stylesheet", href: TAILWIND_CSS }
+        document::Link { rel: "stylesheet", href: HIGHLIGHT_CSS }
+        document::Script { src: HIGHLIGHT_JS }
         
         Router::<Route> {}
     }
@@ -1,5 +1,6 @@
+use dioxus::prelude::Task;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct AppState {
@@ -46,6 +47,11 @@ pub struct RepositoryMetadata {
     pub default_branch: String,
     pub branches: Vec<String>,
     pub size: Option<u64>,
+    /// short id and summary of the head commit
+    #[serde(default)]
+    pub last_commit: Option<String>,
+    #[serde(default)]
+    pub remote_url: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -66,13 +72,265 @@ pub struct RepositoryState {
     pub repo: String,
     pub branch: String,
     pub subpath: Option<String>,
+    /// filter preset name, None for the default (standard)
+    pub preset: Option<String>,
+    /// what the server (or the in-browser ingester) returned, before client-side filters
+    pub full_ingestion: Option<IngestionResult>,
+    /// `full_ingestion` with the include/exclude patterns applied and the
+    /// unchecked files left out; what gets copied, downloaded and counted
     pub ingestion: Option<IngestionResult>,
+    /// files matching the patterns, unchecked ones marked not included
     pub file_tree: Option<FileNode>,
+    /// byte range of each file's body inside the full ingestion content
+    pub file_offsets: HashMap<String, (usize, usize)>,
     pub selected_file: Option<String>,
     pub include_patterns: HashSet<String>,
     pub exclude_patterns: HashSet<String>,
+    /// include patterns that matched nothing in `full_ingestion`
+    pub unmatched_patterns: Vec<String>,
+    /// files unchecked in the tree
+    pub unchecked_files: HashSet<String>,
+    /// context window of the model the output is meant for, in tokens
+    pub context_window: usize,
     pub search_query: String,
     pub view_mode: ViewMode,
+    /// short link created by the share button
+    pub share_url: Option<String>,
+    /// set while a server-side ingestion is streaming in
+    pub progress: Option<IngestProgress>,
+    pub ingest_task: Option<Task>,
+}
+
+impl RepositoryState {
+    /// state for a freshly opened repository route
+    pub fn new(owner: String, repo: String, query: &RepositoryQuery) -> Self {
+        Self {
+            owner,
+            repo,
+            branch: query.branch.clone().unwrap_or_default(),
+            subpath: query.path.clone(),
+            preset: query.preset.clone(),
+            full_ingestion: None,
+            ingestion: None,
+            file_tree: None,
+            file_offsets: HashMap::new(),
+            selected_file: None,
+            include_patterns: query.include.iter().cloned().collect(),
+            exclude_patterns: query.exclude.iter().cloned().collect(),
+            unmatched_patterns: Vec::new(),
+            unchecked_files: HashSet::new(),
+            context_window: 128_000,
+            search_query: String::new(),
+            view_mode: ViewMode::Split,
+            share_url: None,
+            progress: None,
+            ingest_task: None,
+        }
+    }
+
+    /// the current filters as route query parameters
+    pub fn query(&self) -> RepositoryQuery {
+        RepositoryQuery {
+            branch: self.requested_branch(),
+            path: self.subpath.clone(),
+            include: self.include_list(),
+            exclude: self.exclude_list(),
+            preset: self.preset.clone(),
+        }
+    }
+
+    pub fn include_list(&self) -> Vec<String> {
+        let mut patterns: Vec<String> = self.include_patterns.iter().cloned().collect();
+        patterns.sort();
+        patterns
+    }
+
+    pub fn exclude_list(&self) -> Vec<String> {
+        let mut patterns: Vec<String> = self.exclude_patterns.iter().cloned().collect();
+        patterns.sort();
+        patterns
+    }
+
+    /// the branch to ask the server for, None for the default branch
+    pub fn requested_branch(&self) -> Option<String> {
+        (!self.branch.is_empty() && self.branch != "HEAD").then(|| self.branch.clone())
+    }
+
+    /// `owner-repo-branch.<extension>`, safe to use as a file name
+    pub fn download_name(&self, extension: &str) -> String {
+        let branch = if self.branch.is_empty() {
+            "HEAD"
+        } else {
+            &self.branch
+        };
+        format!("{}-{}-{}.{}", self.owner, self.repo, branch, extension)
+            .replace(['/', '\\'], "-")
+    }
+
+    pub fn set_ingestion(&mut self, full: IngestionResult) {
+        self.file_offsets = crate::api::file_offsets(&full.content);
+        self.full_ingestion = Some(full);
+        self.refilter();
+    }
+
+    /// check or uncheck files, then recompute the output
+    pub fn set_files_checked(&mut self, paths: Vec<String>, checked: bool) {
+        for path in paths {
+            if checked {
+                self.unchecked_files.remove(&path);
+            } else {
+                self.unchecked_files.insert(path);
+            }
+        }
+        self.refilter();
+    }
+
+    /// apply the current patterns to `full_ingestion` without another fetch
+    pub fn refilter(&mut self) {
+        let Some(full) = &self.full_ingestion else {
+            return;
+        };
+
+        let (matched, unmatched) =
+            crate::api::refilter(full, &self.include_list(), &self.exclude_list());
+
+        let mut tree = crate::api::parse_file_tree(&self.repo, &matched.content);
+        if let Some(tree) = &mut tree {
+            crate::api::mark_skipped(tree, &self.unchecked_files);
+        }
+        if let Some(selected) = &self.selected_file {
+            let visible = githem_core::split_files(&matched.content)
+                .iter()
+                .any(|(path, _)| path == selected);
+            if !visible {
+                self.selected_file = None;
+            }
+        }
+
+        self.file_tree = tree;
+        self.ingestion = Some(crate::api::without_files(&matched, &self.unchecked_files));
+        self.unmatched_patterns = unmatched;
+        // a share link would not reflect the new filters
+        self.share_url = None;
+    }
+}
+
+/// Filter state carried in the query string of the repository routes, so a
+/// link opens the same filtered view. Patterns are comma-separated like the
+/// api's query parameters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RepositoryQuery {
+    pub branch: Option<String>,
+    pub path: Option<String>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub preset: Option<String>,
+}
+
+impl From<&str> for RepositoryQuery {
+    fn from(query: &str) -> Self {
+        let mut parsed = Self::default();
+        let patterns = |value: &str| -> Vec<String> {
+            value
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect()
+        };
+
+        for pair in query.trim_start_matches('?').split('&') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let value = crate::api::decode_query_value(value);
+            if value.is_empty() {
+                continue;
+            }
+            match key {
+                "branch" => parsed.branch = Some(value),
+                "path" => parsed.path = Some(value),
+                "include" => parsed.include = patterns(&value),
+                "exclude" => parsed.exclude = patterns(&value),
+                "preset" => parsed.preset = Some(value),
+                _ => {}
+            }
+        }
+
+        parsed
+    }
+}
+
+impl std::fmt::Display for RepositoryQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let include = self.include.join(",");
+        let exclude = self.exclude.join(",");
+        let pairs = [
+            ("branch", self.branch.as_deref().unwrap_or_default()),
+            ("path", self.path.as_deref().unwrap_or_default()),
+            ("include", include.as_str()),
+            ("exclude", exclude.as_str()),
+            ("preset", self.preset.as_deref().unwrap_or_default()),
+        ];
+
+        let mut first = true;
+        for (key, value) in pairs {
+            if value.is_empty() {
+                continue;
+            }
+            let separator = if first { "" } else { "&" };
+            write!(f, "{}{}={}", separator, key, crate::api::encode_query_value(value))?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// context sizes offered by the token budget gauge
+pub const CONTEXT_WINDOWS: &[(usize, &str)] = &[
+    (8_000, "8k"),
+    (32_000, "32k"),
+    (128_000, "128k"),
+    (200_000, "200k"),
+];
+
+/// live state of an ingestion streamed over the websocket
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IngestProgress {
+    pub stage: String,
+    pub message: String,
+    /// files received so far with their size in bytes
+    pub files: Vec<(String, usize)>,
+    pub received_bytes: usize,
+    pub filter_stats: Option<githem_core::FilterStats>,
+}
+
+/// one file of a unified diff
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffFile {
+    pub path: String,
+    pub additions: usize,
+    pub deletions: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffLineKind {
+    Hunk,
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffLayout {
+    Unified,
+    SideBySide,
 }
 
 #[derive(Clone, Debug, PartialEq, Copy)]
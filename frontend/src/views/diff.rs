@@ -0,0 +1,111 @@
+use dioxus::prelude::*;
+use crate::{api, types::*, components::*};
+
+#[component]
+pub fn RepositoryCompare(owner: String, repo: String, spec: String) -> Element {
+    rsx! {
+        DiffPage { owner: owner, repo: repo, kind: "compare", spec: spec }
+    }
+}
+
+#[component]
+pub fn RepositoryPull(owner: String, repo: String, number: String) -> Element {
+    rsx! {
+        DiffPage { owner: owner, repo: repo, kind: "pull", spec: number }
+    }
+}
+
+#[component]
+fn DiffPage(owner: String, repo: String, kind: &'static str, spec: String) -> Element {
+    let mut app_state = use_context::<Signal<AppState>>();
+    let mut diff = use_signal(|| None::<String>);
+    let mut layout = use_signal(|| DiffLayout::Unified);
+
+    let title = match kind {
+        "pull" => format!("{}/{} pull request #{}", owner, repo, spec),
+        _ => format!("{}/{} {}", owner, repo, spec),
+    };
+
+    use_effect(use_reactive!(|(owner, repo, spec)| {
+        spawn(async move {
+            app_state.write().loading = true;
+            match api::fetch_diff(&owner, &repo, kind, &spec).await {
+                Ok(text) => diff.set(Some(text)),
+                Err(e) => app_state.write().error = Some(e),
+            }
+            app_state.write().loading = false;
+        });
+    }));
+
+    let files = use_memo(move || diff.read().as_deref().map(api::parse_diff).unwrap_or_default());
+    let additions: usize = files.read().iter().map(|f| f.additions).sum();
+    let deletions: usize = files.read().iter().map(|f| f.deletions).sum();
+
+    let copy_title = title.clone();
+    let copy_prompt = move |_| {
+        let Some(text) = diff() else {
+            return;
+        };
+        let prompt = api::diff_prompt(&copy_title, &text);
+        spawn(async move {
+            if let Err(e) = api::copy_to_clipboard(&prompt).await {
+                app_state.write().error = Some(e);
+            }
+        });
+    };
+
+    rsx! {
+        div {
+            class: "max-w-7xl mx-auto p-4",
+
+            div {
+                class: "flex items-center justify-between mb-4",
+
+                div {
+                    h1 {
+                        class: "text-lg font-semibold text-gray-900 dark:text-white",
+                        "{title}"
+                    }
+                    p {
+                        class: "text-sm text-gray-600 dark:text-gray-400",
+                        "{files.read().len()} files changed, "
+                        span { class: "text-green-600", "+{additions}" }
+                        " "
+                        span { class: "text-red-600", "-{deletions}" }
+                    }
+                }
+
+                div {
+                    class: "flex items-center gap-2",
+
+                    div {
+                        class: "flex bg-white dark:bg-gray-700 rounded-lg border border-gray-300 dark:border-gray-600",
+                        for (mode, label) in [(DiffLayout::Unified, "Unified"), (DiffLayout::SideBySide, "Split")] {
+                            button {
+                                onclick: move |_| layout.set(mode),
+                                class: if layout() == mode {
+                                    "px-3 py-1.5 text-sm font-medium transition-colors bg-blue-600 text-white"
+                                } else {
+                                    "px-3 py-1.5 text-sm font-medium transition-colors text-gray-700 dark:text-gray-300 hover:bg-gray-100 dark:hover:bg-gray-600"
+                                },
+                                "{label}"
+                            }
+                        }
+                    }
+
+                    button {
+                        onclick: copy_prompt,
+                        disabled: diff.read().is_none(),
+                        class: "px-4 py-2 text-sm bg-gray-200 dark:bg-gray-700 rounded-lg
+                               hover:bg-gray-300 dark:hover:bg-gray-600 transition-colors",
+                        "📋 Copy as prompt"
+                    }
+                }
+            }
+
+            for file in files() {
+                DiffFileView { key: "{file.path}", file: file.clone(), layout: layout() }
+            }
+        }
+    }
+}
@@ -12,7 +12,7 @@ pub fn Home() -> Element {
         let url = url_input();
         if !url.is_empty() {
             if let Some((owner, repo)) = parse_github_url(&url) {
-                navigator.push(Route::Repository { owner, repo });
+                navigator.push(Route::Repository { owner, repo, query: Default::default() });
             }
         }
     };
@@ -59,7 +59,7 @@ pub fn Home() -> Element {
                                         let url = url_input();
                                         if !url.is_empty() {
                                             if let Some((owner, repo)) = parse_github_url(&url) {
-                                                navigator.push(Route::Repository { owner, repo });
+                                                navigator.push(Route::Repository { owner, repo, query: Default::default() });
                                             }
                                         }
                                     }
@@ -227,7 +227,8 @@ fn QuickExample(owner: &'static str, repo: &'static str) -> Element {
             onclick: move |_| {
                 navigator.push(Route::Repository {
                     owner: owner.to_string(),
-                    repo: repo.to_string()
+                    repo: repo.to_string(),
+                    query: Default::default(),
                 });
             },
             class: "text-left px-3 py-2 rounded border border-gray-200 dark:border-gray-700
@@ -278,7 +279,8 @@ fn RecentRepos() -> Element {
                         onclick: move |_| {
                             navigator.push(Route::Repository {
                                 owner: owner.to_string(),
-                                repo: repo.to_string()
+                                repo: repo.to_string(),
+                                query: Default::default(),
                             });
                         },
                         class: "inline-flex items-center px-3 py-1.5 text-sm rounded-full
@@ -60,28 +60,28 @@ fn Header() -> Element {
                         
                         // Breadcrumbs based on current route
                         match &route {
-                            Route::Repository { owner, repo } => rsx! {
+                            Route::Repository { owner, repo, .. } => rsx! {
                                 span { class: "text-gray-400", "/" }
                                 Link {
-                                    to: Route::Repository { owner: owner.clone(), repo: repo.clone() },
+                                    to: Route::Repository { owner: owner.clone(), repo: repo.clone(), query: Default::default() },
                                     class: "hover:text-gray-300",
                                     "{owner}/{repo}"
                                 }
                             },
-                            Route::RepositoryBranch { owner, repo, branch } => rsx! {
+                            Route::RepositoryBranch { owner, repo, branch, .. } => rsx! {
                                 span { class: "text-gray-400", "/" }
                                 Link {
-                                    to: Route::Repository { owner: owner.clone(), repo: repo.clone() },
+                                    to: Route::Repository { owner: owner.clone(), repo: repo.clone(), query: Default::default() },
                                     class: "hover:text-gray-300",
                                     "{owner}/{repo}"
                                 }
                                 span { class: "text-gray-400", "/" }
                                 span { "{branch}" }
                             },
-                            Route::RepositoryPath { owner, repo, branch, path } => rsx! {
+                            Route::RepositoryPath { owner, repo, branch, path, .. } => rsx! {
                                 span { class: "text-gray-400", "/" }
                                 Link {
-                                    to: Route::Repository { owner: owner.clone(), repo: repo.clone() },
+                                    to: Route::Repository { owner: owner.clone(), repo: repo.clone(), query: Default::default() },
                                     class: "hover:text-gray-300",
                                     "{owner}/{repo}"
                                 }
@@ -90,7 +90,8 @@ fn Header() -> Element {
                                     to: Route::RepositoryBranch { 
                                         owner: owner.clone(), 
                                         repo: repo.clone(), 
-                                        branch: branch.clone() 
+                                        branch: branch.clone(),
+                                        query: Default::default(),
                                     },
                                     class: "hover:text-gray-300",
                                     "{branch}"
@@ -98,6 +99,26 @@ fn Header() -> Element {
                                 span { class: "text-gray-400", "/" }
                                 span { class: "text-sm", "{path}" }
                             },
+                            Route::RepositoryCompare { owner, repo, spec } => rsx! {
+                                span { class: "text-gray-400", "/" }
+                                Link {
+                                    to: Route::Repository { owner: owner.clone(), repo: repo.clone(), query: Default::default() },
+                                    class: "hover:text-gray-300",
+                                    "{owner}/{repo}"
+                                }
+                                span { class: "text-gray-400", "/" }
+                                span { class: "text-sm", "compare {spec}" }
+                            },
+                            Route::RepositoryPull { owner, repo, number } => rsx! {
+                                span { class: "text-gray-400", "/" }
+                                Link {
+                                    to: Route::Repository { owner: owner.clone(), repo: repo.clone(), query: Default::default() },
+                                    class: "hover:text-gray-300",
+                                    "{owner}/{repo}"
+                                }
+                                span { class: "text-gray-400", "/" }
+                                span { class: "text-sm", "#{number}" }
+                            },
                             _ => rsx! {}
                         }
                     }
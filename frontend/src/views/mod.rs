@@ -6,3 +6,6 @@ pub use home::Home;
 
 mod repository;
 pub use repository::{Repository, RepositoryBranch, RepositoryPath};
+
+mod diff;
+pub use diff::{RepositoryCompare, RepositoryPull};
@@ -1,47 +1,69 @@
 use dioxus::prelude::*;
-use crate::{api, types::*, components::*};
+use crate::{api, types::*, components::*, Route};
 
 #[component]
-pub fn Repository(owner: String, repo: String) -> Element {
-    let state = use_signal(|| RepositoryState {
-        owner: owner.clone(),
-        repo: repo.clone(),
-        branch: String::new(),
-        subpath: None,
-        ingestion: None,
-        file_tree: None,
-        selected_file: None,
-        include_patterns: Default::default(),
-        exclude_patterns: Default::default(),
-        search_query: String::new(),
-        view_mode: ViewMode::Split,
-    });
+pub fn Repository(owner: String, repo: String, query: RepositoryQuery) -> Element {
+    let mut state = use_signal(|| RepositoryState::new(owner.clone(), repo.clone(), &query));
     
-    let app_state = use_context::<Signal<AppState>>();
+    let mut app_state = use_context::<Signal<AppState>>();
     
     // Load repository on mount
     use_effect(move || {
         to_owned![state, app_state];
-        spawn(async move {
+        let task = spawn(async move {
             // Set loading
             app_state.write().loading = true;
             
-            // Create ingestion request
-            let request = IngestRequest {
-                url: format!("https://github.com/{}/{}", state().owner, state().repo),
-                branch: None,
-                subpath: None,
-                include_patterns: vec![],
-                exclude_patterns: vec![],
-                max_file_size: 10 * 1024 * 1024,
+            // patterns are applied client-side, so the full set is fetched once
+            let current = state();
+            let filters = RepositoryQuery {
+                include: Vec::new(),
+                exclude: Vec::new(),
+                ..current.query()
             };
-            
-            match api::ingest_repository(request).await {
+            let preset = filters
+                .preset
+                .as_deref()
+                .and_then(api::parse_preset)
+                .unwrap_or(githem_core::FilterPreset::Standard);
+
+            // public repos can be processed in the browser; fall back to the server
+            let mut options = githem_core::IngestOptions::builder().preset(preset);
+            if let Some(path) = &filters.path {
+                options = options.path_prefix(path.clone());
+            }
+            let local = match options.build() {
+                Ok(options) => {
+                    api::ingest_in_browser(
+                        &current.owner,
+                        &current.repo,
+                        filters.branch.as_deref().unwrap_or("HEAD"),
+                        options,
+                    )
+                    .await
+                }
+                Err(e) => Err(e.to_string()),
+            };
+
+            let result = match local {
+                Ok(ingestion) => Ok(ingestion),
+                Err(_) => {
+                    // the server streams its progress, which replaces the overlay
+                    app_state.write().loading = false;
+                    state.write().progress = Some(IngestProgress::default());
+                    let result = api::ingest_over_websocket(&current.owner, &current.repo, &filters, move |progress| {
+                        state.write().progress = Some(progress.clone());
+                    })
+                    .await;
+                    state.write().progress = None;
+                    result
+                }
+            };
+
+            match result {
                 Ok(ingestion) => {
-                    let file_tree = api::parse_file_tree(&ingestion.tree);
-                    state.write().ingestion = Some(ingestion.clone());
-                    state.write().file_tree = file_tree;
                     state.write().branch = ingestion.summary.branch.clone();
+                    state.write().set_ingestion(ingestion);
                 }
                 Err(e) => {
                     app_state.write().error = Some(e);
@@ -50,6 +72,18 @@ pub fn Repository(owner: String, repo: String) -> Element {
             
             app_state.write().loading = false;
         });
+        state.write().ingest_task = Some(task);
+    });
+
+    // keep the query string in step with the filters, so the url can be shared
+    let navigator = use_navigator();
+    use_effect(move || {
+        let query = state.read().query();
+        let current = router().current::<Route>();
+        let next = with_query(&current, query);
+        if next != current {
+            navigator.replace(next);
+        }
     });
     
     rsx! {
@@ -61,49 +95,96 @@ pub fn Repository(owner: String, repo: String) -> Element {
             div {
                 class: "flex-1 overflow-hidden",
                 
-                match state().view_mode {
-                    ViewMode::Tree => rsx! {
-                        FileTreeView { state: state }
-                    },
-                    ViewMode::Content => rsx! {
-                        ContentView { state: state }
-                    },
-                    ViewMode::Split => rsx! {
-                        div {
-                            class: "grid grid-cols-3 h-full",
-                            
-                            div {
-                                class: "col-span-1 border-r border-gray-200 dark:border-gray-700",
-                                FileTreeView { state: state }
+                if let Some(progress) = state().progress {
+                    IngestProgressView {
+                        progress: progress,
+                        on_cancel: move |_| {
+                            if let Some(task) = state.write().ingest_task.take() {
+                                task.cancel();
                             }
-                            
+                            state.write().progress = None;
+                            app_state.write().loading = false;
+                        },
+                    }
+                } else {
+                    match state().view_mode {
+                        ViewMode::Tree => rsx! {
+                            FileTreeView { state: state }
+                        },
+                        ViewMode::Content => rsx! {
+                            ContentView { state: state }
+                        },
+                        ViewMode::Split => rsx! {
                             div {
-                                class: "col-span-2",
-                                ContentView { state: state }
+                                class: "grid grid-cols-3 h-full",
+                            
+                                div {
+                                    class: "col-span-1 border-r border-gray-200 dark:border-gray-700",
+                                    FileTreeView { state: state }
+                                }
+                            
+                                div {
+                                    class: "col-span-2",
+                                    ContentView { state: state }
+                                }
                             }
-                        }
-                    },
-                    ViewMode::Raw => rsx! {
-                        RawView { state: state }
-                    },
+                        },
+                        ViewMode::Raw => rsx! {
+                            RawView { state: state }
+                        },
+                    }
                 }
             }
         }
     }
 }
 
+/// `route` with `query` as its filters; branch and path already in the route
+/// are not repeated in the query string
+fn with_query(route: &Route, mut query: RepositoryQuery) -> Route {
+    match route.clone() {
+        Route::Repository { owner, repo, .. } => Route::Repository { owner, repo, query },
+        Route::RepositoryBranch { owner, repo, branch, .. } => {
+            if query.branch.as_ref() == Some(&branch) {
+                query.branch = None;
+            }
+            Route::RepositoryBranch { owner, repo, branch, query }
+        }
+        Route::RepositoryPath { owner, repo, branch, path, .. } => {
+            if query.branch.as_ref() == Some(&branch) {
+                query.branch = None;
+            }
+            if query.path.as_ref() == Some(&path) {
+                query.path = None;
+            }
+            Route::RepositoryPath { owner, repo, branch, path, query }
+        }
+        other => other,
+    }
+}
+
 #[component]
-pub fn RepositoryBranch(owner: String, repo: String, branch: String) -> Element {
-    // Similar to Repository but with branch pre-selected
+pub fn RepositoryBranch(owner: String, repo: String, branch: String, query: RepositoryQuery) -> Element {
     rsx! {
-        Repository { owner: owner, repo: repo }
+        Repository {
+            owner: owner,
+            repo: repo,
+            query: RepositoryQuery { branch: query.branch.clone().or(Some(branch)), ..query },
+        }
     }
 }
 
 #[component]
-pub fn RepositoryPath(owner: String, repo: String, branch: String, path: String) -> Element {
-    // Similar to Repository but with path pre-selected
+pub fn RepositoryPath(owner: String, repo: String, branch: String, path: String, query: RepositoryQuery) -> Element {
     rsx! {
-        Repository { owner: owner, repo: repo }
+        Repository {
+            owner: owner,
+            repo: repo,
+            query: RepositoryQuery {
+                branch: query.branch.clone().or(Some(branch)),
+                path: query.path.clone().or(Some(path)),
+                ..query
+            },
+        }
     }
 }